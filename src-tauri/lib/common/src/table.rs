@@ -0,0 +1,39 @@
+//! 表格数据的列类型，导入时推断，建表时使用
+
+use serde::{Deserialize, Serialize};
+
+/// 列类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    /// 整数
+    Integer,
+    /// 小数
+    Real,
+    /// 日期，以`YYYY-MM-DD`或`YYYY-MM-DD HH:MM:SS`格式的文本保存，可直接比较大小
+    Date,
+    /// 文本
+    Text,
+}
+
+impl ColumnType {
+    /// 建表时声明的类型
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            ColumnType::Integer => "INTEGER",
+            ColumnType::Real => "REAL",
+            ColumnType::Date => "DATE",
+            ColumnType::Text => "TEXT",
+        }
+    }
+
+    /// 根据声明的类型转换，无法识别的类型视为文本
+    pub fn from_sql_type(sql_type: &str) -> Self {
+        match sql_type.to_uppercase().as_str() {
+            "INTEGER" => ColumnType::Integer,
+            "REAL" => ColumnType::Real,
+            "DATE" => ColumnType::Date,
+            _ => ColumnType::Text,
+        }
+    }
+}
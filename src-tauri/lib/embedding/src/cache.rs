@@ -0,0 +1,431 @@
+//! 嵌入向量的磁盘缓存
+//!
+//! 以模型标识和文本的SHA-256作为key，每个向量保存为data/embedding_cache下的一个文件，
+//! 内容为小端序的f32数组。缓存总大小超过上限时按最近访问时间淘汰，访问时间记录在文件的修改时间中，
+//! 应用重启后依然有效。
+//!
+//! 缓存目录的索引在设置配置时由后台线程扫描建立，扫描完成前按文件是否存在判断是否命中。
+//! 锁只保护内存中的索引，读写缓存文件时不持有锁。读写缓存文件是阻塞IO，异步调用方需在阻塞线程中调用。
+
+use crate::Result;
+use common::data_dir;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 嵌入缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingCacheOptions {
+    /// 是否启用缓存
+    pub enabled: bool,
+    /// 缓存大小上限，单位MB
+    pub max_size_mb: u64,
+}
+
+impl Default for EmbeddingCacheOptions {
+    fn default() -> Self {
+        EmbeddingCacheOptions {
+            enabled: true,
+            max_size_mb: 512,
+        }
+    }
+}
+
+impl EmbeddingCacheOptions {
+    fn max_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+}
+
+/// 缓存条目
+struct Entry {
+    /// 文件大小
+    size: u64,
+    /// 最近访问时间，毫秒时间戳
+    last_access: u64,
+}
+
+/// 索引的状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IndexState {
+    /// 未扫描
+    Unloaded,
+    /// 后台线程正在扫描
+    Loading,
+    /// 已扫描，索引包含缓存目录下的全部文件
+    Loaded,
+}
+
+struct Cache {
+    options: EmbeddingCacheOptions,
+    /// 缓存目录
+    dir: PathBuf,
+    /// 缓存目录下的文件索引，key为相对于缓存目录的路径
+    entries: HashMap<String, Entry>,
+    /// 缓存文件的总大小
+    total_size: u64,
+    state: IndexState,
+    /// 清空缓存的次数，清空前开始的扫描结果作废
+    generation: u64,
+}
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| {
+    Mutex::new(Cache::new(
+        EmbeddingCacheOptions::default(),
+        data_dir!("embedding_cache"),
+    ))
+});
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn sha256(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 缓存文件相对于缓存目录的路径：模型/哈希前两位/哈希
+fn entry_key(model_id: &str, text: &str) -> String {
+    let hash = sha256(text);
+    format!("{}/{}/{}", &sha256(model_id)[..16], &hash[..2], hash)
+}
+
+/// 设置缓存配置，缓存大小上限降低时立即淘汰超出的部分，启用时在后台建立索引
+pub(crate) fn set_options(options: EmbeddingCacheOptions) {
+    let evicted = {
+        let mut cache = CACHE.lock().unwrap();
+        cache.options = options;
+        if cache.options.enabled {
+            start_loading(&mut cache);
+        }
+        cache.evict()
+    };
+    remove_files(evicted);
+}
+
+pub(crate) fn enabled() -> bool {
+    CACHE.lock().unwrap().options.enabled
+}
+
+/// 查询缓存，返回值与texts一一对应，未命中时为None
+pub(crate) fn get_many(model_id: &str, texts: &[String]) -> Vec<Option<Vec<f32>>> {
+    // 需要读取的缓存文件，索引建立前按文件是否存在判断
+    let (dir, keys) = {
+        let mut cache = CACHE.lock().unwrap();
+        if !cache.options.enabled {
+            return vec![None; texts.len()];
+        }
+        start_loading(&mut cache);
+        let keys = texts
+            .iter()
+            .map(|text| {
+                let key = entry_key(model_id, text);
+                let indexed = cache.state != IndexState::Loaded || cache.entries.contains_key(&key);
+                indexed.then_some(key)
+            })
+            .collect::<Vec<_>>();
+        (cache.dir.clone(), keys)
+    };
+
+    let vectors = keys
+        .iter()
+        .map(|key| {
+            let path = dir.join(key.as_ref()?);
+            let vector = read_vector(&path)?;
+            // 文件的修改时间即最近访问时间
+            if let Ok(file) = fs::File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            Some(vector)
+        })
+        .collect::<Vec<_>>();
+
+    let mut cache = CACHE.lock().unwrap();
+    let now = now();
+    for (key, vector) in keys.iter().zip(&vectors) {
+        let Some(key) = key else {
+            continue;
+        };
+        match vector {
+            Some(vector) => cache.touch(key, (vector.len() * 4) as u64, now),
+            // 文件已损坏或被删除
+            None => cache.remove(key),
+        }
+    }
+    vectors
+}
+
+/// 写入缓存，超出大小上限时淘汰最久未访问的缓存
+pub(crate) fn put_many(model_id: &str, texts: &[String], vectors: &[Vec<f32>]) {
+    let dir = {
+        let mut cache = CACHE.lock().unwrap();
+        if !cache.options.enabled {
+            return;
+        }
+        start_loading(&mut cache);
+        cache.dir.clone()
+    };
+
+    // 已写入的缓存文件及其大小
+    let mut written = vec![];
+    for (text, vector) in texts.iter().zip(vectors) {
+        let key = entry_key(model_id, text);
+        let path = dir.join(&key);
+        let bytes = vector
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        if let Some(parent) = path.parent()
+            && fs::create_dir_all(parent).is_err()
+        {
+            continue;
+        }
+        if fs::write(&path, &bytes).is_err() {
+            continue;
+        }
+        written.push((key, bytes.len() as u64));
+    }
+
+    let evicted = {
+        let mut cache = CACHE.lock().unwrap();
+        let now = now();
+        for (key, size) in written {
+            cache.insert(key, size, now);
+        }
+        cache.evict()
+    };
+    remove_files(evicted);
+}
+
+/// 清空缓存
+pub(crate) fn clear() -> Result<()> {
+    let mut cache = CACHE.lock()?;
+    if cache.dir.exists() {
+        fs::remove_dir_all(&cache.dir)?;
+    }
+    cache.entries.clear();
+    cache.total_size = 0;
+    cache.generation += 1;
+    // 缓存目录已清空，正在进行的扫描结果作废
+    cache.state = IndexState::Loaded;
+    Ok(())
+}
+
+/// 在后台线程中扫描缓存目录建立索引，扫描期间不持有锁
+fn start_loading(cache: &mut Cache) {
+    if cache.state != IndexState::Unloaded {
+        return;
+    }
+    cache.state = IndexState::Loading;
+    let dir = cache.dir.clone();
+    let generation = cache.generation;
+    let result = thread::Builder::new()
+        .name("embedding-cache-index".to_string())
+        .spawn(move || {
+            let entries = scan(&dir);
+            let evicted = {
+                let mut cache = CACHE.lock().unwrap();
+                if cache.generation != generation {
+                    return;
+                }
+                cache.merge(entries)
+            };
+            remove_files(evicted);
+        });
+    if let Err(e) = result {
+        log::error!("Failed to start embedding cache index thread: {}", e);
+        cache.state = IndexState::Unloaded;
+    }
+}
+
+/// 扫描缓存目录，文件的修改时间即最近访问时间
+fn scan(dir: &Path) -> HashMap<String, Entry> {
+    let mut entries = HashMap::new();
+    // 目录结构固定为三层：模型/哈希前两位/哈希
+    let files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .flat_map(|model| fs::read_dir(model.path()).into_iter().flatten().flatten())
+        .flat_map(|prefix| fs::read_dir(prefix.path()).into_iter().flatten().flatten());
+    for file in files {
+        let Ok(metadata) = file.metadata() else {
+            continue;
+        };
+        let Ok(key) = file.path().strip_prefix(dir).map(|p| p.to_path_buf()) else {
+            continue;
+        };
+        let last_access = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        entries.insert(
+            key.to_string_lossy().replace('\\', "/"),
+            Entry {
+                size: metadata.len(),
+                last_access,
+            },
+        );
+    }
+    entries
+}
+
+/// 删除淘汰的缓存文件，在释放锁后调用
+fn remove_files(paths: Vec<PathBuf>) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn read_vector(path: &Path) -> Option<Vec<f32>> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.is_empty() || bytes.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+impl Cache {
+    fn new(options: EmbeddingCacheOptions, dir: PathBuf) -> Self {
+        Cache {
+            options,
+            dir,
+            entries: HashMap::new(),
+            total_size: 0,
+            state: IndexState::Unloaded,
+            generation: 0,
+        }
+    }
+
+    /// 合并扫描结果，扫描期间写入或访问过的条目以内存中的为准，返回需要删除的淘汰文件
+    fn merge(&mut self, entries: HashMap<String, Entry>) -> Vec<PathBuf> {
+        for (key, entry) in entries {
+            if !self.entries.contains_key(&key) {
+                self.total_size += entry.size;
+                self.entries.insert(key, entry);
+            }
+        }
+        self.state = IndexState::Loaded;
+        self.evict()
+    }
+
+    fn insert(&mut self, key: String, size: u64, now: u64) {
+        self.remove(&key);
+        self.total_size += size;
+        self.entries.insert(
+            key,
+            Entry {
+                size,
+                last_access: now,
+            },
+        );
+    }
+
+    /// 更新最近访问时间，文件的修改时间由调用方在锁外写入
+    fn touch(&mut self, key: &str, size: u64, now: u64) {
+        match self.entries.get_mut(key) {
+            Some(entry) => entry.last_access = now,
+            // 索引建立前命中的文件
+            None => self.insert(key.to_string(), size, now),
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_size -= entry.size;
+        }
+    }
+
+    /// 超出大小上限时按最近访问时间淘汰，淘汰到上限的90%以免频繁淘汰，返回需要删除的文件
+    ///
+    /// 索引建立前只统计了部分文件，等扫描完成后再淘汰
+    fn evict(&mut self) -> Vec<PathBuf> {
+        let mut evicted = vec![];
+        let max_bytes = self.options.max_bytes();
+        if self.state != IndexState::Loaded || self.total_size <= max_bytes {
+            return evicted;
+        }
+        let target = max_bytes / 10 * 9;
+        let mut keys = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_access, key.clone()))
+            .collect::<Vec<_>>();
+        keys.sort();
+        for (_, key) in keys {
+            if self.total_size <= target {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                evicted.push(self.dir.join(&key));
+                self.total_size -= entry.size;
+            }
+        }
+        evicted
+    }
+}
+
+#[test]
+fn test_evict_least_recently_used() {
+    const MB: u64 = 1024 * 1024;
+    let mut cache = Cache::new(
+        EmbeddingCacheOptions {
+            enabled: true,
+            max_size_mb: 10,
+        },
+        std::env::temp_dir().join("embedding_cache_evict_test"),
+    );
+    cache.state = IndexState::Loaded;
+    cache.insert("a".to_string(), 4 * MB, 1);
+    cache.insert("b".to_string(), 4 * MB, 3);
+    cache.insert("c".to_string(), 4 * MB, 2);
+    let evicted = cache.evict();
+    // 淘汰到上限的90%，最久未访问的a、c被淘汰
+    assert_eq!(evicted, vec![cache.dir.join("a"), cache.dir.join("c")]);
+    assert_eq!(cache.entries.keys().collect::<Vec<_>>(), vec!["b"]);
+    assert_eq!(cache.total_size, 4 * MB);
+
+    // 扫描完成前不淘汰，合并后按最近访问时间淘汰
+    let mut cache = Cache::new(cache.options.clone(), cache.dir.clone());
+    cache.insert("new".to_string(), 6 * MB, 10);
+    assert!(cache.evict().is_empty());
+    assert_eq!(cache.total_size, 6 * MB);
+    let evicted = cache.merge(HashMap::from([
+        (
+            "new".to_string(),
+            Entry {
+                size: 6 * MB,
+                last_access: 1,
+            },
+        ),
+        (
+            "old".to_string(),
+            Entry {
+                size: 6 * MB,
+                last_access: 1,
+            },
+        ),
+    ]));
+    assert_eq!(evicted, vec![cache.dir.join("old")]);
+    assert_eq!(cache.entries.keys().collect::<Vec<_>>(), vec!["new"]);
+    assert_eq!(cache.total_size, 6 * MB);
+}
@@ -1,15 +1,39 @@
 use common::resources_dir;
 use fastembed::{
-    ImageEmbedding, ImageInitOptionsUserDefined, InitOptionsUserDefined,
-    RerankInitOptionsUserDefined, TextEmbedding, TextRerank, TokenizerFiles,
-    UserDefinedEmbeddingModel, UserDefinedImageEmbeddingModel, UserDefinedRerankingModel,
+    ImageEmbedding, ImageInitOptionsUserDefined, InitOptionsUserDefined, TextEmbedding,
+    TokenizerFiles, UserDefinedEmbeddingModel, UserDefinedImageEmbeddingModel,
 };
 use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::sync::{LazyLock, Mutex};
+use std::time::UNIX_EPOCH;
 use std::{env, fs};
 
+mod cache;
+mod pool;
+mod rerank;
+
+pub use cache::EmbeddingCacheOptions;
+pub use pool::EmbeddingPoolOptions;
+pub use rerank::{
+    ApiReranker, BUNDLED_RERANK_MODELS, DEFAULT_RERANK_MODEL, OnnxReranker, RerankBackend,
+    RerankModel, RerankScore, Reranker,
+};
+
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// 默认的文本嵌入模型，位于resources/model目录下
+pub const DEFAULT_TEXT_MODEL: &str = "bge-small-zh-v1_5";
+/// 默认文本嵌入模型的输出维度
+pub const DEFAULT_TEXT_DIMENSION: usize = 512;
+/// CLIP文本模型，与图片模型共享向量空间，用于以文搜图
+pub const TEXT_TO_IMAGE_MODEL: &str = "clip-ViT-B-32-text";
+/// CLIP模型的输出维度
+pub const IMAGE_DIMENSION: usize = 512;
+/// 内置的文本嵌入模型，[`TextEmbeddingModel::Bundled`]只能使用其中的模型
+pub const BUNDLED_TEXT_MODELS: &[&str] = &[DEFAULT_TEXT_MODEL, TEXT_TO_IMAGE_MODEL];
+
 pub enum EmbeddingInput {
     Text(String),
     Image(DynamicImage),
@@ -18,55 +42,39 @@ pub trait Embedding {
     fn embedding(input: EmbeddingInput) -> Result<Vec<f32>>;
 }
 
-#[allow(unused)]
-static TEXT_TO_IMAGE_MODEL: LazyLock<TextEmbedding> = LazyLock::new(|| {
-    let current_dir = env::current_exe().unwrap();
-    let model_path = &resources_dir!("model", "clip-ViT-B-32-text")
-        .to_string_lossy()
-        .into_owned(); //"resources/model/clip-ViT-B-32-text";
-    let tokenizer_files = TokenizerFiles {
-        tokenizer_file: fs::read(format!("{}/tokenizer.json", model_path))
-            .expect("tokenizer file load fail"),
-        config_file: fs::read(format!("{}/config.json", model_path))
-            .expect("config file load fail"),
-        special_tokens_map_file: fs::read(format!("{}/special_tokens_map.json", model_path))
-            .expect("special tokens map file load fail"),
-        tokenizer_config_file: fs::read(format!("{}/tokenizer_config.json", model_path))
-            .expect("tokenizer config file load fail"),
-    };
-    let model = UserDefinedEmbeddingModel::new(
-        fs::read(format!("{}/model.onnx", model_path)).expect("model load fail"),
-        tokenizer_files,
-    );
-    let model = TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::default())
-        .expect("Failed to initialize text embedding model");
-    model
-});
-
 static TEXT_MODEL: LazyLock<Mutex<TextEmbedding>> = LazyLock::new(|| {
-    let current_dir = env::current_exe().unwrap();
-    let model_path = &resources_dir!("model", "bge-small-zh-v1_5")
+    let model_path = &resources_dir!("model", DEFAULT_TEXT_MODEL)
         .to_string_lossy()
         .into_owned(); //"resources/model/bge-small-zh-v1_5";
+    let model = load_text_model(model_path).expect("Failed to initialize text embedding model");
+    Mutex::new(model)
+});
+
+/// 内置模型的目录，name必须是bundled中的模型，避免配置中的名称指向resources/model以外的目录
+fn bundled_model_path(bundled: &[&str], name: &str) -> Result<String> {
+    if !bundled.contains(&name) {
+        return Err(format!("unknown bundled model: {}", name).into());
+    }
+    Ok(resources_dir!("model", name).to_string_lossy().into_owned())
+}
+
+/// 从模型目录加载ONNX文本嵌入模型
+///
+/// 目录中需要包含：model.onnx、tokenizer.json、config.json、special_tokens_map.json、tokenizer_config.json
+fn load_text_model(model_path: &str) -> Result<TextEmbedding> {
     let tokenizer_files = TokenizerFiles {
-        tokenizer_file: fs::read(format!("{}/tokenizer.json", model_path))
-            .expect("tokenizer file load fail"),
-        config_file: fs::read(format!("{}/config.json", model_path))
-            .expect("config file load fail"),
-        special_tokens_map_file: fs::read(format!("{}/special_tokens_map.json", model_path))
-            .expect("special tokens map file load fail"),
-        tokenizer_config_file: fs::read(format!("{}/tokenizer_config.json", model_path))
-            .expect("tokenizer config file load fail"),
+        tokenizer_file: fs::read(format!("{}/tokenizer.json", model_path))?,
+        config_file: fs::read(format!("{}/config.json", model_path))?,
+        special_tokens_map_file: fs::read(format!("{}/special_tokens_map.json", model_path))?,
+        tokenizer_config_file: fs::read(format!("{}/tokenizer_config.json", model_path))?,
     };
     let model = UserDefinedEmbeddingModel::new(
-        fs::read(format!("{}/model.onnx", model_path)).expect("model load fail"),
+        fs::read(format!("{}/model.onnx", model_path))?,
         tokenizer_files,
     );
-
-    let model = TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::default())
-        .expect("Failed to initialize text embedding model");
-    Mutex::new(model)
-});
+    let model = TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::default())?;
+    Ok(model)
+}
 
 static IMAGE_MODEL: LazyLock<Mutex<ImageEmbedding>> = LazyLock::new(|| {
     let current_dir = env::current_exe().unwrap();
@@ -84,37 +92,9 @@ static IMAGE_MODEL: LazyLock<Mutex<ImageEmbedding>> = LazyLock::new(|| {
     Mutex::new(model)
 });
 
-static TEXT_RERANK_MODEL: LazyLock<Mutex<TextRerank>> = LazyLock::new(|| {
-    let current_dir = env::current_exe().unwrap();
-    let model_path = &resources_dir!("model", "jina-reranker-v2-base-multilingual")
-        .to_string_lossy()
-        .into_owned(); // "resources/model/jina-reranker-v2-base-multilingual";
-
-    let tokenizer_files = TokenizerFiles {
-        tokenizer_file: fs::read(format!("{}/tokenizer.json", model_path))
-            .expect("tokenizer file load fail"),
-        config_file: fs::read(format!("{}/config.json", model_path))
-            .expect("config file load fail"),
-        special_tokens_map_file: fs::read(format!("{}/special_tokens_map.json", model_path))
-            .expect("special tokens map file load fail"),
-        tokenizer_config_file: fs::read(format!("{}/tokenizer_config.json", model_path))
-            .expect("tokenizer config file load fail"),
-    };
-
-    let model = UserDefinedRerankingModel::new(
-        fs::read(format!("{}/model.onnx", model_path)).expect("model load fail"),
-        tokenizer_files,
-    );
-    let model =
-        TextRerank::try_new_from_user_defined(model, RerankInitOptionsUserDefined::default())
-            .expect("Failed to initialize text rerank model");
-    Mutex::new(model)
-});
-
 pub async fn init() {
     // let _ = TEXT_MODEL;
     // let _ = IMAGE_MODEL;
-    // let _ = TEXT_RERANK_MODEL;
 
     let dylib_dir = resources_dir!("dylib");
     #[cfg(target_os = "windows")]
@@ -136,7 +116,13 @@ impl Embedding for Embeddings {
     fn embedding(input: EmbeddingInput) -> Result<Vec<f32>> {
         match input {
             EmbeddingInput::Text(text) => {
-                let embeddings = TEXT_MODEL.lock()?.embed(vec![text], None)?;
+                let model_id = TextEmbeddingModel::default().cache_id();
+                let texts = vec![text];
+                if let Some(Some(vector)) = cache::get_many(&model_id, &texts).pop() {
+                    return Ok(vector);
+                }
+                let embeddings = TEXT_MODEL.lock()?.embed(texts.clone(), None)?;
+                cache::put_many(&model_id, &texts, &embeddings);
                 Ok(embeddings[0].clone())
             }
             EmbeddingInput::Image(image) => {
@@ -147,18 +133,272 @@ impl Embedding for Embeddings {
     }
 }
 
-pub struct Reranker;
-impl Reranker {
-    pub fn rerank<S: AsRef<str> + Send + Sync>(
-        question: S,
-        documents: Vec<S>,
-    ) -> Result<Vec<(usize, f32)>> {
-        let results = TEXT_RERANK_MODEL
-            .lock()?
-            .rerank(question, documents, false, None)?;
-        Ok(results
+/// 文本嵌入模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    rename_all_fields = "snake_case",
+    tag = "type"
+)]
+pub enum TextEmbeddingModel {
+    /// 内置的ONNX模型，name为resources/model下的目录名
+    Bundled { name: String },
+    /// 用户提供的ONNX模型，dir为模型所在目录，目录结构和内置模型一致
+    Onnx { dir: String },
+    /// OpenAI兼容的`/embeddings`接口
+    OpenAi {
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+    },
+}
+
+impl Default for TextEmbeddingModel {
+    fn default() -> Self {
+        TextEmbeddingModel::Bundled {
+            name: DEFAULT_TEXT_MODEL.to_string(),
+        }
+    }
+}
+
+impl TextEmbeddingModel {
+    /// 模型唯一标识
+    pub fn id(&self) -> String {
+        match self {
+            TextEmbeddingModel::Bundled { name } => format!("bundled:{}", name),
+            TextEmbeddingModel::Onnx { dir } => format!("onnx:{}", dir),
+            TextEmbeddingModel::OpenAi {
+                base_url, model, ..
+            } => format!("openai:{}:{}", base_url.trim_end_matches('/'), model),
+        }
+    }
+
+    /// 嵌入缓存使用的模型标识
+    ///
+    /// 用户提供的模型加上模型文件的修改时间及大小，替换模型文件后不会读到旧模型生成的向量
+    fn cache_id(&self) -> String {
+        match self {
+            TextEmbeddingModel::Onnx { dir } => {
+                let version = fs::metadata(Path::new(dir).join("model.onnx"))
+                    .ok()
+                    .map(|metadata| {
+                        let mtime = metadata
+                            .modified()
+                            .ok()
+                            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                            .map(|d| d.as_millis())
+                            .unwrap_or_default();
+                        format!("{}-{}", mtime, metadata.len())
+                    })
+                    .unwrap_or_default();
+                format!("{}:{}", self.id(), version)
+            }
+            _ => self.id(),
+        }
+    }
+
+    /// 以文搜图使用的CLIP文本模型
+    pub fn text_to_image() -> Self {
+        TextEmbeddingModel::Bundled {
+            name: TEXT_TO_IMAGE_MODEL.to_string(),
+        }
+    }
+
+    /// 是否为默认模型
+    pub fn is_default(&self) -> bool {
+        self == &TextEmbeddingModel::default()
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAiEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embeddings {
+    /// 使用指定的模型批量将文本转换为向量
+    ///
+    /// 优先使用缓存，未命中的文本按线程池配置的批大小拆分，本地模型在嵌入线程池中执行，
+    /// 远程模型按批依次请求。返回的向量顺序与texts一致
+    pub async fn embed_texts(
+        model: &TextEmbeddingModel,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        if !cache::enabled() {
+            return Self::embed_texts_uncached(model, texts).await;
+        }
+        // 读写缓存文件是阻塞IO，在阻塞线程中执行
+        let model_id = model.cache_id();
+        let mut embeddings = {
+            let model_id = model_id.clone();
+            let texts = texts.clone();
+            tokio::task::spawn_blocking(move || cache::get_many(&model_id, &texts)).await?
+        };
+        let (missed_indexes, missed_texts): (Vec<_>, Vec<_>) = texts
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| embeddings[*index].is_none())
+            .unzip();
+        if !missed_texts.is_empty() {
+            let vectors = Self::embed_texts_uncached(model, missed_texts.clone()).await?;
+            let cached = vectors.clone();
+            tokio::task::spawn_blocking(move || cache::put_many(&model_id, &missed_texts, &cached))
+                .await?;
+            for (index, vector) in missed_indexes.into_iter().zip(vectors) {
+                embeddings[index] = Some(vector);
+            }
+        }
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    /// 不使用缓存将文本转换为向量，用于校验模型是否可用等必须实际调用模型的场景
+    pub async fn embed_texts_uncached(
+        model: &TextEmbeddingModel,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>> {
+        match model {
+            TextEmbeddingModel::Bundled { name } => {
+                let model_path = bundled_model_path(BUNDLED_TEXT_MODELS, name)?;
+                pool::embed(&model_path, texts).await
+            }
+            TextEmbeddingModel::Onnx { dir } => pool::embed(dir, texts).await,
+            TextEmbeddingModel::OpenAi {
+                base_url,
+                api_key,
+                model,
+            } => {
+                let mut embeddings = Vec::with_capacity(texts.len());
+                for batch in texts.chunks(pool::batch_size()) {
+                    embeddings.extend(
+                        Self::embed_texts_with_openai(
+                            base_url,
+                            api_key.as_deref(),
+                            model,
+                            batch.to_vec(),
+                        )
+                        .await?,
+                    );
+                }
+                Ok(embeddings)
+            }
+        }
+    }
+
+    /// 使用CLIP图片模型批量将图片转换为向量，返回值与paths一一对应
+    ///
+    /// 图片按线程池配置的批大小分批读取及嵌入，在阻塞线程中执行。
+    /// 无法读取的图片记录日志后跳过，对应的返回值为None，不影响其他图片
+    pub async fn embed_images(paths: Vec<String>) -> Result<Vec<Option<Vec<f32>>>> {
+        if paths.is_empty() {
+            return Ok(vec![]);
+        }
+        let batch_size = pool::batch_size();
+        let embeddings = tokio::task::spawn_blocking(move || {
+            let mut embeddings = Vec::with_capacity(paths.len());
+            for batch in paths.chunks(batch_size) {
+                let images = batch
+                    .iter()
+                    .map(|path| {
+                        image::open(path)
+                            .inspect_err(|e| log::warn!("Failed to open image {}: {}", path, e))
+                            .ok()
+                    })
+                    .collect::<Vec<_>>();
+                let opened = images.iter().flatten().cloned().collect::<Vec<_>>();
+                let mut vectors = if opened.is_empty() {
+                    vec![]
+                } else {
+                    let mut model = IMAGE_MODEL.lock().map_err(|e| e.to_string())?;
+                    model.embed_images(opened).map_err(|e| e.to_string())?
+                }
+                .into_iter();
+                embeddings.extend(
+                    images
+                        .iter()
+                        .map(|image| image.as_ref().and_then(|_| vectors.next())),
+                );
+            }
+            Ok::<_, String>(embeddings)
+        })
+        .await??;
+        Ok(embeddings)
+    }
+
+    /// 设置嵌入线程池配置
+    pub fn set_pool_options(options: EmbeddingPoolOptions) {
+        pool::set_options(options);
+    }
+
+    /// 设置嵌入缓存配置
+    pub fn set_cache_options(options: EmbeddingCacheOptions) {
+        cache::set_options(options);
+    }
+
+    /// 清空嵌入缓存
+    pub fn clear_cache() -> Result<()> {
+        cache::clear()
+    }
+
+    async fn embed_texts_with_openai(
+        base_url: &str,
+        api_key: Option<&str>,
+        model: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", base_url.trim_end_matches('/'));
+        let mut request = HTTP_CLIENT.post(url).json(&OpenAiEmbeddingRequest {
+            model,
+            input: &texts,
+        });
+        if let Some(api_key) = api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await?.error_for_status()?;
+        let mut response = response.json::<OpenAiEmbeddingResponse>().await?;
+        if response.data.len() != texts.len() {
+            return Err(format!(
+                "embedding count mismatch, expected {}, got {}",
+                texts.len(),
+                response.data.len()
+            )
+            .into());
+        }
+        response.data.sort_by_key(|item| item.index);
+        Ok(response
+            .data
             .into_iter()
-            .map(|result| (result.index, result.score))
-            .collect::<Vec<(usize, f32)>>())
+            .map(|item| item.embedding)
+            .collect())
     }
 }
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .read_timeout(std::time::Duration::from_secs(300))
+        .build()
+        .unwrap()
+});
+
+#[test]
+fn test_bundled_model_path() {
+    assert!(bundled_model_path(BUNDLED_TEXT_MODELS, DEFAULT_TEXT_MODEL).is_ok());
+    assert!(bundled_model_path(BUNDLED_TEXT_MODELS, TEXT_TO_IMAGE_MODEL).is_ok());
+    assert!(bundled_model_path(BUNDLED_TEXT_MODELS, "../../etc").is_err());
+    assert!(bundled_model_path(BUNDLED_TEXT_MODELS, "bge-small-zh-v1_5/..").is_err());
+}
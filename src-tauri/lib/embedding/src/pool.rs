@@ -0,0 +1,174 @@
+//! 本地嵌入模型的工作线程池
+//!
+//! ONNX模型推理是CPU密集的同步调用，放在独立的工作线程中执行，避免阻塞异步运行时。
+//! 每个工作线程持有自己的模型实例，多个线程可同时推理；待嵌入的文本按批大小拆分后分发给各线程。
+
+use crate::{Result, load_text_model};
+use fastembed::TextEmbedding;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, RwLock, mpsc};
+use std::thread;
+use tokio::sync::oneshot;
+
+/// 嵌入线程池配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingPoolOptions {
+    /// 每批嵌入的文本数量，远程模型每次请求的文本数量同样受该值限制
+    pub batch_size: usize,
+    /// 工作线程数量，每个线程加载一份模型实例，线程越多占用的内存越多
+    pub threads: usize,
+}
+
+impl Default for EmbeddingPoolOptions {
+    fn default() -> Self {
+        EmbeddingPoolOptions {
+            batch_size: 32,
+            threads: 2,
+        }
+    }
+}
+
+/// 嵌入任务
+struct Job {
+    /// 模型目录
+    model_path: String,
+    texts: Vec<String>,
+    reply: oneshot::Sender<std::result::Result<Vec<Vec<f32>>, String>>,
+}
+
+struct Pool {
+    sender: mpsc::Sender<Job>,
+    options: EmbeddingPoolOptions,
+}
+
+static POOL: LazyLock<RwLock<Pool>> =
+    LazyLock::new(|| RwLock::new(Pool::new(EmbeddingPoolOptions::default())));
+
+impl Pool {
+    fn new(options: EmbeddingPoolOptions) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..options.threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("embedding-{}", index))
+                .spawn(move || worker(receiver))
+                .expect("Failed to spawn embedding worker");
+        }
+        Pool { sender, options }
+    }
+}
+
+/// 设置线程池配置，立即生效
+///
+/// 原线程池的工作线程完成已提交的任务后退出
+pub(crate) fn set_options(options: EmbeddingPoolOptions) {
+    *POOL.write().unwrap() = Pool::new(options);
+}
+
+/// 当前的批大小
+pub(crate) fn batch_size() -> usize {
+    POOL.read().unwrap().options.batch_size.max(1)
+}
+
+/// 使用模型目录下的本地模型将文本转换为向量
+///
+/// 文本按批大小拆分后提交到线程池，返回的向量顺序与texts一致
+pub(crate) async fn embed(model_path: &str, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+    let (sender, batch_size) = {
+        let pool = POOL.read()?;
+        (pool.sender.clone(), pool.options.batch_size.max(1))
+    };
+    let (jobs, replies) = split_jobs(model_path, &texts, batch_size);
+    for job in jobs {
+        sender.send(job).map_err(|_| "embedding pool closed")?;
+    }
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for receiver in replies {
+        embeddings.extend(receiver.await??);
+    }
+    Ok(embeddings)
+}
+
+/// 将文本按批大小拆分为任务，返回任务及对应的结果接收端，接收端的顺序与文本顺序一致
+fn split_jobs(
+    model_path: &str,
+    texts: &[String],
+    batch_size: usize,
+) -> (
+    Vec<Job>,
+    Vec<oneshot::Receiver<std::result::Result<Vec<Vec<f32>>, String>>>,
+) {
+    texts
+        .chunks(batch_size)
+        .map(|batch| {
+            let (reply, receiver) = oneshot::channel();
+            let job = Job {
+                model_path: model_path.to_string(),
+                texts: batch.to_vec(),
+                reply,
+            };
+            (job, receiver)
+        })
+        .unzip()
+}
+
+fn worker(receiver: Arc<Mutex<mpsc::Receiver<Job>>>) {
+    // 本线程的模型实例，按模型目录缓存
+    let mut models: HashMap<String, TextEmbedding> = HashMap::new();
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        // 线程池已替换且任务已处理完
+        let Ok(job) = job else {
+            return;
+        };
+        let result = embed_with(&mut models, &job.model_path, job.texts).map_err(|e| e.to_string());
+        let _ = job.reply.send(result);
+    }
+}
+
+fn embed_with(
+    models: &mut HashMap<String, TextEmbedding>,
+    model_path: &str,
+    texts: Vec<String>,
+) -> Result<Vec<Vec<f32>>> {
+    if !models.contains_key(model_path) {
+        models.insert(model_path.to_string(), load_text_model(model_path)?);
+    }
+    let model = models.get_mut(model_path).unwrap();
+    Ok(model.embed(texts, None)?)
+}
+
+#[test]
+fn test_split_jobs() {
+    let texts = (0..5).map(|i| i.to_string()).collect::<Vec<_>>();
+    let (jobs, replies) = split_jobs("model", &texts, 2);
+    assert_eq!(
+        jobs.iter().map(|job| job.texts.clone()).collect::<Vec<_>>(),
+        vec![vec!["0", "1"], vec!["2", "3"], vec!["4"]]
+    );
+    assert!(jobs.iter().all(|job| job.model_path == "model"));
+    // 任务以任意顺序完成，结果仍按文本顺序合并
+    for job in jobs.into_iter().rev() {
+        let vectors = job
+            .texts
+            .iter()
+            .map(|text| vec![text.parse::<f32>().unwrap()])
+            .collect();
+        job.reply.send(Ok(vectors)).unwrap();
+    }
+    let embeddings = replies
+        .into_iter()
+        .flat_map(|receiver| receiver.blocking_recv().unwrap().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        embeddings,
+        (0..5).map(|i| vec![i as f32]).collect::<Vec<_>>()
+    );
+    assert!(split_jobs("model", &[], 2).0.is_empty());
+}
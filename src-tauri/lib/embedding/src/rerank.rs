@@ -0,0 +1,253 @@
+//! 重排
+//!
+//! 重排模型计算检索文本与每个候选文档的相关度，支持三种后端：内置的ONNX交叉编码器、
+//! 用户提供的ONNX交叉编码器，以及OpenAI风格（Jina、Cohere兼容）的`/rerank`接口。
+//! 各后端返回的分数均归一化到0~1，可以使用同一个最低分数过滤。
+
+use crate::{HTTP_CLIENT, Result, bundled_model_path};
+use fastembed::{
+    RerankInitOptionsUserDefined, TextRerank, TokenizerFiles, UserDefinedRerankingModel,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// 默认的重排模型，位于resources/model目录下
+pub const DEFAULT_RERANK_MODEL: &str = "jina-reranker-v2-base-multilingual";
+/// 内置的重排模型，[`RerankModel::Bundled`]只能使用其中的模型
+pub const BUNDLED_RERANK_MODELS: &[&str] = &[DEFAULT_RERANK_MODEL];
+
+/// 重排结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RerankScore {
+    /// 文档在候选列表中的下标
+    pub index: usize,
+    /// 相关度，0~1
+    pub score: f32,
+}
+
+/// 重排模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    rename_all_fields = "snake_case",
+    tag = "type"
+)]
+pub enum RerankModel {
+    /// 内置的ONNX交叉编码器，name为resources/model下的目录名
+    Bundled { name: String },
+    /// 用户提供的ONNX交叉编码器，dir为模型所在目录，目录结构和内置模型一致
+    Onnx { dir: String },
+    /// OpenAI风格的`/rerank`接口
+    Api {
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+    },
+}
+
+impl Default for RerankModel {
+    fn default() -> Self {
+        RerankModel::Bundled {
+            name: DEFAULT_RERANK_MODEL.to_string(),
+        }
+    }
+}
+
+/// 重排后端
+pub trait RerankBackend {
+    /// 计算每个文档与检索文本的相关度，返回按分数从高到低排序的结果
+    fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+    ) -> impl Future<Output = Result<Vec<RerankScore>>> + Send;
+}
+
+/// 本地ONNX交叉编码器
+pub struct OnnxReranker {
+    /// 模型目录
+    pub model_path: String,
+}
+
+/// 本地重排模型，按模型目录缓存
+///
+/// 每个模型有单独的锁，加载和推理时只锁定该模型，不影响其它模型
+static ONNX_MODELS: LazyLock<Mutex<HashMap<String, Arc<Mutex<Option<TextRerank>>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 从模型目录加载ONNX重排模型，目录结构同[`crate::load_text_model`]
+fn load_rerank_model(model_path: &str) -> Result<TextRerank> {
+    let tokenizer_files = TokenizerFiles {
+        tokenizer_file: fs::read(format!("{}/tokenizer.json", model_path))?,
+        config_file: fs::read(format!("{}/config.json", model_path))?,
+        special_tokens_map_file: fs::read(format!("{}/special_tokens_map.json", model_path))?,
+        tokenizer_config_file: fs::read(format!("{}/tokenizer_config.json", model_path))?,
+    };
+    let model = UserDefinedRerankingModel::new(
+        fs::read(format!("{}/model.onnx", model_path))?,
+        tokenizer_files,
+    );
+    let model =
+        TextRerank::try_new_from_user_defined(model, RerankInitOptionsUserDefined::default())?;
+    Ok(model)
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1. / (1. + (-x).exp())
+}
+
+impl OnnxReranker {
+    fn rerank_blocking(&self, query: &str, documents: Vec<String>) -> Result<Vec<RerankScore>> {
+        let model = ONNX_MODELS
+            .lock()?
+            .entry(self.model_path.clone())
+            .or_default()
+            .clone();
+        let mut model = model.lock().map_err(|e| e.to_string())?;
+        // 加载失败时保持未加载，下次调用时重新加载
+        if model.is_none() {
+            *model = Some(load_rerank_model(&self.model_path)?);
+        }
+        let model = model.as_mut().unwrap();
+        let documents = documents.iter().map(String::as_str).collect::<Vec<_>>();
+        let results = model.rerank(query, documents, false, None)?;
+        // 交叉编码器输出的是logit，转换为0~1的相关度
+        Ok(results
+            .into_iter()
+            .map(|result| RerankScore {
+                index: result.index,
+                score: sigmoid(result.score),
+            })
+            .collect())
+    }
+}
+
+impl RerankBackend for OnnxReranker {
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<RerankScore>> {
+        let reranker = OnnxReranker {
+            model_path: self.model_path.clone(),
+        };
+        let query = query.to_string();
+        // 模型推理是CPU密集的同步调用，在阻塞线程中执行
+        let scores = tokio::task::spawn_blocking(move || {
+            reranker
+                .rerank_blocking(&query, documents)
+                .map_err(|e| e.to_string())
+        })
+        .await??;
+        Ok(scores)
+    }
+}
+
+/// OpenAI风格的`/rerank`接口
+pub struct ApiReranker {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+#[derive(Serialize)]
+struct ApiRerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiRerankResponse {
+    results: Vec<ApiRerankResult>,
+}
+
+#[derive(Deserialize)]
+struct ApiRerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+impl RerankBackend for ApiReranker {
+    async fn rerank(&self, query: &str, documents: Vec<String>) -> Result<Vec<RerankScore>> {
+        let url = format!("{}/rerank", self.base_url.trim_end_matches('/'));
+        let mut request = HTTP_CLIENT.post(url).json(&ApiRerankRequest {
+            model: &self.model,
+            query,
+            documents: &documents,
+        });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        let response = response
+            .json::<ApiRerankResponse>()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut scores = response
+            .results
+            .into_iter()
+            .filter(|result| result.index < documents.len())
+            .map(|result| RerankScore {
+                index: result.index,
+                score: result.relevance_score,
+            })
+            .collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(scores)
+    }
+}
+
+pub struct Reranker;
+impl Reranker {
+    /// 使用指定的重排模型计算每个文档与检索文本的相关度，返回按分数从高到低排序的结果
+    pub async fn rerank(
+        model: &RerankModel,
+        query: &str,
+        documents: Vec<String>,
+    ) -> Result<Vec<RerankScore>> {
+        if documents.is_empty() {
+            return Ok(vec![]);
+        }
+        match model {
+            RerankModel::Bundled { name } => {
+                OnnxReranker {
+                    model_path: bundled_model_path(BUNDLED_RERANK_MODELS, name)?,
+                }
+                .rerank(query, documents)
+                .await
+            }
+            RerankModel::Onnx { dir } => {
+                OnnxReranker {
+                    model_path: dir.clone(),
+                }
+                .rerank(query, documents)
+                .await
+            }
+            RerankModel::Api {
+                base_url,
+                api_key,
+                model,
+            } => {
+                ApiReranker {
+                    base_url: base_url.clone(),
+                    api_key: api_key.clone(),
+                    model: model.clone(),
+                }
+                .rerank(query, documents)
+                .await
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sigmoid() {
+    assert_eq!(sigmoid(0.), 0.5);
+    assert!(sigmoid(10.) > 0.99 && sigmoid(10.) < 1.);
+    assert!(sigmoid(-10.) < 0.01 && sigmoid(-10.) > 0.);
+    // 单调递增，保持交叉编码器的排序
+    assert!(sigmoid(-1.) < sigmoid(1.));
+}
@@ -10,12 +10,18 @@ use futures_util::TryStreamExt;
 use itertools::Itertools;
 use lancedb::arrow::arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
 use lancedb::database::CreateTableMode;
+use lancedb::index::scalar::{FtsIndexBuilder, FullTextSearchQuery};
+use lancedb::index::vector::{IvfHnswSqIndexBuilder, IvfPqIndexBuilder};
+use lancedb::index::{Index, IndexType};
 use lancedb::query::{ExecutableQuery, QueryBase, Select};
-use lancedb::table::{CompactionOptions, OptimizeAction};
+use lancedb::table::{CompactionOptions, NewColumnTransform, OptimizeAction, OptimizeOptions};
 use lancedb::{Connection, DistanceType, Table, connect};
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::sync::{Arc, LazyLock};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
 use std::{fs, io};
 
 #[derive(Debug, thiserror::Error)]
@@ -36,6 +42,37 @@ type Result<T> = std::result::Result<T, Error>;
 static TABLE_INSERT_COUNT: LazyLock<DashMap<String, i32>> = LazyLock::new(|| DashMap::new());
 /// 表优化阈值，超过这个值时，执行一次优化
 const TABLE_OPTIMIZE_THRESHOLD: i32 = 20;
+/// RRF融合常数，值越大，排名靠后的结果的权重越接近排名靠前的结果
+const RRF_K: f32 = 60.;
+/// 混合检索时，每一路召回的候选数量相对于limit的倍数
+const HYBRID_CANDIDATE_FACTOR: usize = 2;
+/// 向量索引配置
+static VECTOR_INDEX_OPTIONS: LazyLock<RwLock<VectorIndexOptions>> =
+    LazyLock::new(|| RwLock::new(VectorIndexOptions::default()));
+/// 未建立索引的行数超过已索引行数的该比例时，重建向量索引，否则增量合并
+const VECTOR_INDEX_REBUILD_RATIO: f32 = 0.2;
+/// 已升级结构的表
+static UPGRADED_TABLES: LazyLock<DashMap<String, ()>> = LazyLock::new(|| DashMap::new());
+/// 表结构升级锁，同一张表同时只有一个升级，避免重复补齐列或同时重新编号
+static UPGRADE_LOCKS: LazyLock<DashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+    LazyLock::new(|| DashMap::new());
+/// 元数据列及其类型，旧版本创建的表缺少这些列，打开表时补齐
+const METADATA_COLUMNS: &[(&str, &str)] = &[
+    ("source", "VARCHAR"),
+    ("page", "BIGINT"),
+    ("tags", "VARCHAR"),
+    ("heading_path", "VARCHAR"),
+];
+/// 标题路径的分隔符
+const HEADING_PATH_SEPARATOR: &str = " > ";
+/// 向量索引检索的默认分区数量
+const DEFAULT_NPROBES: usize = 20;
+/// 上一次分配的记录id
+static LAST_RECORD_ID: AtomicI64 = AtomicI64::new(0);
+/// 记录id中序号所占的位数，高位为毫秒时间戳
+const RECORD_ID_SEQUENCE_BITS: u32 = 12;
+/// 重新编号时临时表的后缀，重新编号后的数据先完整写入临时表，再覆盖原表
+const RENUMBER_TABLE_SUFFIX: &str = "__renumber";
 
 pub struct Database {
     connection: Connection,
@@ -51,19 +88,19 @@ impl Database {
 
     /// 创建一张空表
     ///
-    /// 创建表时，LanceDB不支持在空表上建立索引，而是需要在达到一定数据量后需要手动建立索引，
-    /// 但是目前测试来看，创建索引会很慢，所以暂时不创建索引，即使没有索引，再100万以下的数据量查询性能也在维持在1秒以内，
-    /// 对于大部分场景应该是够的。
+    /// LanceDB不支持在空表上建立向量索引，向量索引在表的行数达到[`VectorIndexOptions::min_rows`]后，
+    /// 由[`Database::optimize_table`]自动建立，在此之前使用暴力检索。
     /// - table_name：表名
-    pub async fn create_empty_table(&self, table_name: &str) -> Result<Table> {
+    /// - dimension：向量维度，需与知识库使用的嵌入模型输出维度一致
+    pub async fn create_empty_table(&self, table_name: &str, dimension: usize) -> Result<Table> {
         let table_names = self.connection.table_names().execute().await?;
         if table_names.contains(&table_name.to_string()) {
-            let table = self.connection.open_table(table_name).execute().await?;
+            let table = self.open_table(table_name).await?;
             Ok(table)
         } else {
             let table = self
                 .connection
-                .create_empty_table(table_name, self.default_schema().await?)
+                .create_empty_table(table_name, self.default_schema(dimension).await?)
                 .mode(CreateTableMode::ExistOk(Box::new(|x| x)))
                 .execute()
                 .await?;
@@ -72,16 +109,18 @@ impl Database {
     }
 
     /// 默认的schema
-    async fn default_schema(&self) -> Result<Arc<Schema>> {
+    async fn default_schema(&self, dimension: usize) -> Result<Arc<Schema>> {
         let schema = Arc::new(Schema::new(vec![
             // 唯一标识
             Field::new("id", DataType::Int64, false),
             Field::new("prev", DataType::Int64, true),
             Field::new("next", DataType::Int64, true),
-            // 特征向量：目前使用Clip模型，文本和图片的输出维度均为512
+            // 批次内的序号，从1开始
+            Field::new("seq", DataType::Int64, false),
+            // 特征向量：维度由嵌入模型决定，默认模型的输出维度为512
             Field::new(
                 "vector",
-                DataType::new_fixed_size_list(DataType::Float32, 512, false),
+                DataType::new_fixed_size_list(DataType::Float32, dimension as i32, false),
                 false,
             ),
             // 原始内容：文本 或 图片地址 或 base64编码的图片
@@ -96,6 +135,14 @@ impl Database {
             Field::new("batch_id", DataType::Utf8, false),
             // 创建时间：毫秒时间戳
             Field::new("create_time", DataType::Int64, false),
+            // 元数据：来源，通常为文件名
+            Field::new("source", DataType::Utf8, true),
+            // 元数据：页码，从1开始
+            Field::new("page", DataType::Int64, true),
+            // 元数据：标签，格式为`,标签1,标签2,`，便于按标签过滤
+            Field::new("tags", DataType::Utf8, true),
+            // 元数据：标题路径，格式为`一级标题 > 二级标题`
+            Field::new("heading_path", DataType::Utf8, true),
         ]));
         Ok(schema)
     }
@@ -105,6 +152,199 @@ impl Database {
         Ok(())
     }
 
+    /// 打开表，旧版本创建的表缺少元数据列时自动补齐，缺少序号列时重新编号
+    async fn open_table(&self, table_name: &str) -> Result<Table> {
+        let table = self.connection.open_table(table_name).execute().await?;
+        let key = format!("{}:{}", self.connection.uri(), table_name);
+        if UPGRADED_TABLES.contains_key(&key) {
+            return Ok(table);
+        }
+        let lock = UPGRADE_LOCKS.entry(key.clone()).or_default().clone();
+        let _guard = lock.lock().await;
+        // 等待锁期间表可能已由其他调用升级，重新打开以读取最新的结构
+        let table = self.connection.open_table(table_name).execute().await?;
+        if UPGRADED_TABLES.contains_key(&key) {
+            return Ok(table);
+        }
+        let schema = table.schema().await?;
+        let missing = METADATA_COLUMNS
+            .iter()
+            .filter(|(name, _)| schema.field_with_name(name).is_err())
+            .map(|(name, data_type)| (name.to_string(), format!("CAST(NULL AS {})", data_type)))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            table
+                .add_columns(NewColumnTransform::SqlExpressions(missing), None)
+                .await?;
+        }
+        if schema.field_with_name("seq").is_err() {
+            self.renumber_table(&table).await?;
+            UPGRADED_TABLES.insert(key, ());
+            return Ok(self.connection.open_table(table_name).execute().await?);
+        }
+        UPGRADED_TABLES.insert(key, ());
+        Ok(table)
+    }
+
+    /// 升级所有表的结构，旧版本创建的表在此补齐元数据列并重新编号
+    ///
+    /// 单张表升级失败时记录日志并继续升级其他表，未在此完成升级的表，在下次打开时重试
+    pub async fn upgrade_tables(&self) -> Result<()> {
+        for table_name in self.table_names().await? {
+            if table_name.ends_with(RENUMBER_TABLE_SUFFIX) {
+                continue;
+            }
+            if let Err(e) = self.open_table(&table_name).await {
+                log::error!("Upgrade table {} error: {}", table_name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 重新编号旧版本创建的表
+    ///
+    /// 旧版本每次写入时id都从1开始，不同批次的id会重复，`prev`/`next`也只在单次写入内有效。
+    /// 在此为所有记录分配全局唯一的id，按批次内的写入顺序重新链接前后记录并生成序号。
+    ///
+    /// 重新编号后的数据先完整写入临时表，再用临时表的数据覆盖原表，覆盖是一次提交，
+    /// 中途失败时原表保持不变，下次打开时重新编号。
+    async fn renumber_table(&self, table: &Table) -> Result<()> {
+        let schema = table.schema().await?;
+        let total = table.count_rows(None).await?;
+        let batches = if total == 0 {
+            vec![]
+        } else {
+            table
+                .query()
+                .limit(total)
+                .execute()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        };
+
+        // 所有记录：(批次id, 创建时间, 原id, batch下标, 行下标)
+        let mut rows = vec![];
+        for (b, batch) in batches.iter().enumerate() {
+            let ids = int64_column(batch, "id")?;
+            let create_times = int64_column(batch, "create_time")?;
+            let batch_ids = batch
+                .column_by_name("batch_id")
+                .and_then(|x| x.as_any().downcast_ref::<StringArray>())
+                .ok_or(Error::Unknown)?;
+            for i in 0..batch.num_rows() {
+                rows.push((
+                    batch_ids.value(i),
+                    create_times.value(i),
+                    ids.value(i),
+                    b,
+                    i,
+                ));
+            }
+        }
+        // 同一批次的记录按写入顺序排列
+        rows.sort();
+
+        // 每条记录新的(id, prev, next, seq)
+        let mut links = batches
+            .iter()
+            .map(|batch| vec![(0, None, None, 0); batch.num_rows()])
+            .collect::<Vec<_>>();
+        let new_ids = next_record_ids(rows.len());
+        for (_, group) in &(0..rows.len()).chunk_by(|&k| rows[k].0) {
+            let group = group.collect::<Vec<_>>();
+            for (n, &k) in group.iter().enumerate() {
+                let (_, _, _, b, i) = rows[k];
+                let prev = if n > 0 { Some(new_ids[k - 1]) } else { None };
+                let next = if n + 1 < group.len() {
+                    Some(new_ids[k + 1])
+                } else {
+                    None
+                };
+                links[b][i] = (new_ids[k], prev, next, n as i64 + 1);
+            }
+        }
+
+        let mut fields = vec![];
+        for field in schema.fields() {
+            fields.push(field.clone());
+            if field.name() == "next" {
+                fields.push(Arc::new(Field::new("seq", DataType::Int64, false)));
+            }
+        }
+        let new_schema = Arc::new(Schema::new(fields));
+
+        let mut new_batches = vec![];
+        for (batch, links) in batches.iter().zip(links) {
+            let columns = new_schema
+                .fields()
+                .iter()
+                .map(|field| {
+                    let column: ArrayRef = match field.name().as_str() {
+                        "id" => Arc::new(Int64Array::from_iter_values(links.iter().map(|x| x.0))),
+                        "prev" => Arc::new(Int64Array::from_iter(links.iter().map(|x| x.1))),
+                        "next" => Arc::new(Int64Array::from_iter(links.iter().map(|x| x.2))),
+                        "seq" => Arc::new(Int64Array::from_iter_values(links.iter().map(|x| x.3))),
+                        name => batch.column_by_name(name).ok_or(Error::Unknown)?.clone(),
+                    };
+                    Ok(column)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            new_batches.push(Ok(RecordBatch::try_new(new_schema.clone(), columns)?));
+        }
+
+        // 上次中断时遗留的临时表，其中的数据可能不完整，丢弃后重新写入
+        let temp_name = format!("{}{}", table.name(), RENUMBER_TABLE_SUFFIX);
+        if self.table_names().await?.contains(&temp_name) {
+            self.drop_table(&temp_name).await?;
+        }
+        let temp_table = self
+            .connection
+            .create_table(
+                &temp_name,
+                Box::new(RecordBatchIterator::new(new_batches, new_schema.clone())),
+            )
+            .execute()
+            .await?;
+        let temp_batches = if total == 0 {
+            vec![]
+        } else {
+            temp_table
+                .query()
+                .limit(total)
+                .execute()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+        };
+        if temp_batches.iter().map(|x| x.num_rows()).sum::<usize>() != total {
+            return Err(Error::InvalidParameter(format!(
+                "renumber table {} failed: row count mismatch",
+                table.name()
+            )));
+        }
+        let table = self
+            .connection
+            .create_table(
+                table.name(),
+                Box::new(RecordBatchIterator::new(
+                    temp_batches.into_iter().map(Ok),
+                    new_schema.clone(),
+                )),
+            )
+            .mode(CreateTableMode::Overwrite)
+            .execute()
+            .await?;
+        self.drop_table(&temp_name).await?;
+
+        // 覆盖后原有的索引失效，重新建立
+        if total > 0 {
+            self.ensure_fts_index(&table).await?;
+            self.ensure_vector_index(&table).await?;
+        }
+        Ok(())
+    }
+
     /// 添加记录
     /// - table_name：表名
     /// - records：批量数据
@@ -113,12 +353,31 @@ impl Database {
         table_name: &str,
         records: Vec<AddRecordRequest>,
     ) -> Result<()> {
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
         let schema = table.schema().await?;
 
-        let batch = self.convert_records(schema, records)?;
+        let tails = self
+            .batch_tails(
+                &table,
+                records.iter().map(|x| x.batch_id.as_str()).collect(),
+            )
+            .await?;
+        let (batch, links) = self.convert_records(schema, records, &tails)?;
         table.add(batch).execute().await?;
 
+        // 批次在之前的写入中已有数据，将原最后一条记录链接到本次写入的第一条记录
+        for (id, next) in links {
+            table
+                .update()
+                .only_if(Filter::eq(ID, &id).into_sql())
+                .column("next", next.literal())
+                .execute()
+                .await?;
+        }
+
+        // 首次写入数据后建立全文索引
+        self.ensure_fts_index(&table).await?;
+
         // 满足阈值时执行一次优化
         self.optimize_table(&table).await?;
 
@@ -158,15 +417,90 @@ impl Database {
             delete_unverified: None,
             error_if_tagged_old_versions: None,
         };
+        // 将新增数据合并到已有索引中
+        let index = OptimizeAction::Index(OptimizeOptions::default());
         table.optimize(compact).await?;
         table.optimize(prune).await?;
+        // 需要新建或重建向量索引时，建立索引的同时会索引全部数据，否则增量合并
+        if !self.ensure_vector_index(table).await? {
+            table.optimize(index).await?;
+        }
+        Ok(())
+    }
+
+    /// 行数达到阈值时建立向量索引，未索引的数据过多时重建
+    ///
+    /// 返回是否新建或重建了向量索引
+    async fn ensure_vector_index(&self, table: &Table) -> Result<bool> {
+        let options = VECTOR_INDEX_OPTIONS.read().unwrap().clone();
+        if options.min_rows == 0 {
+            return Ok(false);
+        }
+        let rows = table.count_rows(None).await?;
+        if rows < options.min_rows {
+            return Ok(false);
+        }
+
+        let indices = table.list_indices().await?;
+        let vector_index = indices
+            .iter()
+            .find(|index| index.columns.iter().any(|c| c == "vector"));
+        if let Some(vector_index) = vector_index {
+            let same_type = vector_index.index_type == options.index_type.index_type();
+            let stats = table.index_stats(&vector_index.name).await?;
+            let need_rebuild = match stats {
+                Some(stats) => {
+                    stats.num_unindexed_rows as f32
+                        > stats.num_indexed_rows as f32 * VECTOR_INDEX_REBUILD_RATIO
+                }
+                None => true,
+            };
+            if same_type && !need_rebuild {
+                return Ok(false);
+            }
+        }
+
+        let index = match options.index_type {
+            VectorIndexType::IvfPq => {
+                Index::IvfPq(IvfPqIndexBuilder::default().distance_type(DistanceType::Cosine))
+            }
+            VectorIndexType::Hnsw => Index::IvfHnswSq(
+                IvfHnswSqIndexBuilder::default().distance_type(DistanceType::Cosine),
+            ),
+        };
+        table
+            .create_index(&["vector"], index)
+            .replace(true)
+            .execute()
+            .await?;
+        Ok(true)
+    }
+
+    /// 在content列上建立全文索引，已存在时跳过
+    ///
+    /// 默认分词器按空格和标点切分，对中文无效，这里使用ngram分词，中英文均可匹配。
+    async fn ensure_fts_index(&self, table: &Table) -> Result<()> {
+        let indices = table.list_indices().await?;
+        if indices
+            .iter()
+            .any(|index| index.columns.iter().any(|c| c == "content"))
+        {
+            return Ok(());
+        }
+        table
+            .create_index(
+                &["content"],
+                Index::FTS(FtsIndexBuilder::default().base_tokenizer("ngram".to_string())),
+            )
+            .execute()
+            .await?;
         Ok(())
     }
 
     /// 检查指定的id是否存在
     /// - table_name：表名
     /// - id：唯一标识
-    async fn exists(&self, table_name: &str, id: &str) -> Result<bool> {
+    async fn exists(&self, table_name: &str, id: i64) -> Result<bool> {
         let exists = self.exists_ids(table_name, vec![id]).await?.len() > 0;
         Ok(exists)
     }
@@ -174,44 +508,30 @@ impl Database {
     /// 给定一组id列表，返回表中已存在的id列表
     /// - table_name：表名
     /// - ids：唯一标识
-    async fn exists_ids(&self, table_name: &str, ids: Vec<&str>) -> Result<Vec<String>> {
+    async fn exists_ids(&self, table_name: &str, ids: Vec<i64>) -> Result<Vec<i64>> {
         if ids.is_empty() {
             return Ok(vec![]);
         }
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
+        let limit = ids.len();
         let records = table
             .query()
-            .only_if(&format!(
-                "id in ({})",
-                ids.iter()
-                    .map(|x| format!("'{}'", x))
-                    .collect::<Vec<_>>()
-                    .join(",")
-            ))
+            .only_if(Filter::is_in(ID, ids).into_sql())
+            .limit(limit)
             .select(Select::columns(&["id"]))
             .execute()
             .await?
             .try_collect::<Vec<_>>()
             .await?;
 
-        // 提取结果
-        let record = if let Some(result) = records.get(0) {
-            result
-        } else {
-            return Ok(vec![]);
-        };
-
-        // 结果数量
-        let rows = record.num_rows();
-        // id 列格式
-        let ids = record.column_by_name("id").unwrap();
-
-        // 提取id
+        // 提取id，结果可能分布在多个RecordBatch中
         let mut exists_ids = Vec::new();
-        for i in 0..rows {
-            let id = ids.as_any().downcast_ref::<StringArray>().unwrap();
-            let id = id.value(i);
-            exists_ids.push(id.to_string());
+        for record in records {
+            let ids = record
+                .column_by_name("id")
+                .and_then(|ids| ids.as_any().downcast_ref::<Int64Array>())
+                .ok_or(Error::Unknown)?;
+            exists_ids.extend(ids.values().iter().copied());
         }
 
         Ok(exists_ids)
@@ -219,26 +539,105 @@ impl Database {
 
     /// 删除记录
     ///
-    /// 从表中删除指定id的数据，如果id不存在则忽略，仍然返回成功
+    /// 从表中删除指定id的数据，如果id不存在则忽略，仍然返回成功。
+    /// 删除后将被删除记录的前后记录重新链接，保持批次内的链式结构完整。
     /// - table_name：表名
     /// - ids：唯一标识
-    pub async fn delete_records(&self, table_name: &str, ids: Vec<String>) -> Result<()> {
+    pub async fn delete_records(&self, table_name: &str, ids: Vec<i64>) -> Result<()> {
         if ids.is_empty() {
             return Ok(());
         }
-        let table = self.connection.open_table(table_name).execute().await?;
-        table
-            .delete(&format!(
-                "id in ({})",
-                ids.iter()
-                    .map(|x| format!("'{}'", x))
-                    .collect::<Vec<_>>()
-                    .join(",")
-            ))
+        let table = self.open_table(table_name).await?;
+
+        // 被删除记录的前后记录
+        let limit = ids.len();
+        let records = table
+            .query()
+            .only_if(Filter::is_in(ID, ids.clone()).into_sql())
+            .select(Select::columns(&["id", "prev", "next"]))
+            .limit(limit)
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
             .await?;
+        let mut links = HashMap::new();
+        for record in records {
+            let ids = int64_column(&record, "id")?;
+            let prevs = int64_column(&record, "prev")?;
+            let nexts = int64_column(&record, "next")?;
+            for i in 0..record.num_rows() {
+                let prev = (!prevs.is_null(i)).then(|| prevs.value(i));
+                let next = (!nexts.is_null(i)).then(|| nexts.value(i));
+                links.insert(ids.value(i), (prev, next));
+            }
+        }
+
+        table.delete(&Filter::is_in(ID, ids).into_sql()).await?;
+
+        for (prev, next) in relink(&links) {
+            if let Some(prev) = prev {
+                table
+                    .update()
+                    .only_if(Filter::eq(ID, &prev).into_sql())
+                    .column("next", next.map_or("NULL".to_string(), |x| x.literal()))
+                    .execute()
+                    .await?;
+            }
+            if let Some(next) = next {
+                table
+                    .update()
+                    .only_if(Filter::eq(ID, &next).into_sql())
+                    .column("prev", prev.map_or("NULL".to_string(), |x| x.literal()))
+                    .execute()
+                    .await?;
+            }
+        }
         Ok(())
     }
 
+    /// 按顺序列出批次内的全部记录，不返回特征向量
+    /// - table_name：表名
+    /// - batch_id：批次ID
+    pub async fn list_records(
+        &self,
+        table_name: &str,
+        batch_id: &str,
+    ) -> Result<Vec<SearchResult>> {
+        let table = self.open_table(table_name).await?;
+        let filter = Filter::eq(BATCH_ID, batch_id).into_sql();
+        let count = table.count_rows(Some(filter.clone())).await?;
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let record_batches = table
+            .query()
+            .only_if(filter)
+            .select(Select::columns(&[
+                "id",
+                "prev",
+                "next",
+                "seq",
+                "batch_id",
+                "content",
+                "content_type",
+                "content_ref",
+                "payload",
+                "create_time",
+                "source",
+                "page",
+                "tags",
+                "heading_path",
+            ]))
+            .limit(count)
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut records = Self::convert_record_batch_to_search_result(record_batches)?;
+        records.sort_by_key(|x| x.seq);
+        Ok(records)
+    }
+
     /// 删除记录
     ///
     /// 从表中删除指定id的数据，如果id不存在则忽略，仍然返回成功
@@ -252,16 +651,9 @@ impl Database {
         if batch_ids.is_empty() {
             return Ok(());
         }
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
         table
-            .delete(&format!(
-                "batch_id in ({})",
-                batch_ids
-                    .iter()
-                    .map(|x| format!("'{}'", x))
-                    .collect::<Vec<_>>()
-                    .join(",")
-            ))
+            .delete(&Filter::is_in(BATCH_ID, batch_ids).into_sql())
             .await?;
         Ok(())
     }
@@ -271,20 +663,22 @@ impl Database {
     /// 更新指定id的数据，如果id不存在则返回错误
     /// - table_name：表名
     /// - id：唯一标识
+    /// - content：原始内容，如果为None则不更新，更新时应同时传入重新计算的特征向量
     /// - vector：特征向量，如果为None则不更新
     /// - payload：自定义数据，如果为None则不更新
     pub async fn update_record(
         &self,
         table_name: &str,
-        id: String,
+        id: i64,
+        content: Option<String>,
         vector: Option<Vec<f32>>,
         payload: Option<String>,
     ) -> Result<()> {
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
 
-        if vector.is_none() && payload.is_none() {
+        if content.is_none() && vector.is_none() && payload.is_none() {
             // 不存在对应的id，则返回错误
-            if !self.exists(table_name, &id).await? {
+            if !self.exists(table_name, id).await? {
                 return Err(Error::InvalidParameter(format!(
                     "data not found with id: {}",
                     id
@@ -293,8 +687,12 @@ impl Database {
             return Ok(());
         }
 
-        let mut builder = table.update().only_if(&format!("id = '{}'", id));
+        let mut builder = table.update().only_if(Filter::eq(ID, &id).into_sql());
 
+        // 更新原始内容
+        if let Some(content) = content {
+            builder = builder.column("content", content.as_str().literal());
+        }
         // 更新特征向量
         if let Some(vector) = vector {
             builder = builder.column(
@@ -311,7 +709,7 @@ impl Database {
         }
         // 更新自定义数据
         if let Some(payload) = payload {
-            builder = builder.column("payload", payload);
+            builder = builder.column("payload", payload.as_str().literal());
         }
         let update_rows = builder.execute().await?.rows_updated;
         if update_rows == 0 {
@@ -323,14 +721,49 @@ impl Database {
         Ok(())
     }
 
+    /// 更新批次内所有记录的标签
+    /// - table_name：表名
+    /// - batch_id：批次ID
+    /// - tags：标签，为空时清空标签
+    pub async fn update_tags_with_batch_id(
+        &self,
+        table_name: &str,
+        batch_id: &str,
+        tags: &[String],
+    ) -> Result<()> {
+        let table = self.open_table(table_name).await?;
+        let tags = match encode_tags(tags) {
+            Some(tags) => tags.as_str().literal(),
+            None => "NULL".to_string(),
+        };
+        table
+            .update()
+            .only_if(Filter::eq(BATCH_ID, batch_id).into_sql())
+            .column("tags", tags)
+            .execute()
+            .await?;
+        Ok(())
+    }
+
     /// 将记录转换为RecordBatch
-    /// - schema：表的schema
+    ///
+    /// 每条记录分配全局唯一的id，同一批次的记录按顺序编号并链接前后记录。
+    /// 批次在表中已有数据时，接在该批次最后一条记录之后。
+    ///
+    /// - schema：表的schema，按列名填充数据
     /// - records：原始数据
+    /// - tails：各批次在表中的最后一条记录，`批次id -> (id, seq)`
+    ///
+    /// 返回RecordBatch，以及需要更新`next`的已有记录，`(已有记录id, 新记录id)`
     fn convert_records(
         &self,
         schema: SchemaRef,
         records: Vec<AddRecordRequest>,
-    ) -> Result<Box<RecordBatchIterator<Vec<std::result::Result<RecordBatch, ArrowError>>>>> {
+        tails: &HashMap<String, (i64, i64)>,
+    ) -> Result<(
+        Box<RecordBatchIterator<Vec<std::result::Result<RecordBatch, ArrowError>>>>,
+        Vec<(i64, i64)>,
+    )> {
         // 校验records
         if records.is_empty() {
             return Err(Error::InvalidParameter("records is empty".to_string()));
@@ -344,124 +777,282 @@ impl Database {
                 "vector length must be same".to_string(),
             ));
         }
-        let mut p = (None, 1, Some(1));
-        let db_records = records
-            .iter()
-            .enumerate()
-            .map(|(index, record)| {
-                let r = Record {
-                    id: p.1,
-                    prev: p.0,
-                    next: p.2,
-                    vector: record.vector.clone(),
-                    content: record.content.clone(),
-                    content_type: record.content_type.clone(),
-                    content_ref: if let Some(ref content_ref) = record.content_ref {
-                        Some(serde_json::to_string(content_ref).unwrap())
-                    } else {
-                        None
-                    },
-                    payload: record.payload.clone(),
-                    batch_id: record.batch_id.clone(),
-                };
-                if index == records.len() - 1 {
-                    p.2 = None;
+
+        let ids = next_record_ids(records.len());
+        let mut db_records: Vec<Record> = Vec::with_capacity(records.len());
+        let mut links = vec![];
+        // 各批次的最后一条记录：(id, seq, 在本次写入中的下标)
+        let mut last: HashMap<String, (i64, i64, Option<usize>)> = HashMap::new();
+        for (index, (record, id)) in records.into_iter().zip(ids).enumerate() {
+            let (prev, seq) = match last.get(&record.batch_id) {
+                Some((prev_id, prev_seq, prev_index)) => {
+                    match prev_index {
+                        Some(prev_index) => db_records[*prev_index].next = Some(id),
+                        None => links.push((*prev_id, id)),
+                    }
+                    (Some(*prev_id), prev_seq + 1)
                 }
-                p = (Some(r.id), r.next.unwrap(), Some(r.next.unwrap() + 1));
-                r
-            })
-            .collect::<Vec<_>>();
+                None => match tails.get(&record.batch_id) {
+                    Some((tail_id, tail_seq)) => {
+                        links.push((*tail_id, id));
+                        (Some(*tail_id), tail_seq + 1)
+                    }
+                    None => (None, 1),
+                },
+            };
+            last.insert(record.batch_id.clone(), (id, seq, Some(index)));
+            db_records.push(Record {
+                id,
+                prev,
+                next: None,
+                seq,
+                vector: record.vector,
+                content: record.content,
+                content_type: record.content_type,
+                content_ref: if let Some(ref content_ref) = record.content_ref {
+                    Some(serde_json::to_string(content_ref).unwrap())
+                } else {
+                    None
+                },
+                payload: record.payload,
+                batch_id: record.batch_id,
+                metadata: record.metadata,
+            });
+        }
 
-        let batch = RecordBatch::try_new(
-            schema,
-            vec![
-                // id
-                Arc::new(Int64Array::from(
-                    db_records.iter().map(|x| x.id).collect::<Vec<_>>(),
-                )),
-                // prev
-                Arc::new(Int64Array::from(
-                    db_records.iter().map(|x| x.prev).collect::<Vec<_>>(),
-                )),
-                // next
-                Arc::new(Int64Array::from(
-                    db_records.iter().map(|x| x.next).collect::<Vec<_>>(),
-                )),
-                // vector
-                Arc::new(
-                    FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+        let create_time = chrono::Local::now().timestamp_millis();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let column: ArrayRef = match field.name().as_str() {
+                    // id
+                    "id" => Arc::new(Int64Array::from(
+                        db_records.iter().map(|x| x.id).collect::<Vec<_>>(),
+                    )),
+                    // prev
+                    "prev" => Arc::new(Int64Array::from(
+                        db_records.iter().map(|x| x.prev).collect::<Vec<_>>(),
+                    )),
+                    // next
+                    "next" => Arc::new(Int64Array::from(
+                        db_records.iter().map(|x| x.next).collect::<Vec<_>>(),
+                    )),
+                    // seq
+                    "seq" => Arc::new(Int64Array::from(
+                        db_records.iter().map(|x| x.seq).collect::<Vec<_>>(),
+                    )),
+                    // vector
+                    "vector" => Arc::new(FixedSizeListArray::from_iter_primitive::<
+                        Float32Type,
+                        _,
+                        _,
+                    >(
                         db_records
                             .iter()
                             .map(|x| Some(x.vector.iter().map(|v| Some(*v)).collect::<Vec<_>>()))
                             .collect::<Vec<_>>(),
                         first_vector_len,
-                    ),
-                ),
-                // content
-                Arc::new(StringArray::from(
-                    db_records
-                        .iter()
-                        .map(|x| x.content.as_str())
-                        .collect::<Vec<_>>(),
-                )),
-                // content_type
-                Arc::new(StringArray::from(
-                    db_records
-                        .iter()
-                        .map(|x| x.content_type.as_str())
-                        .collect::<Vec<_>>(),
-                )),
-                // content_ref
-                Arc::new(StringArray::from(
-                    db_records
-                        .iter()
-                        .map(|x| x.content_ref.clone())
-                        .collect::<Vec<_>>(),
-                )),
-                // payload
-                Arc::new(StringArray::from(
-                    db_records
-                        .iter()
-                        .map(|x| x.payload.clone())
-                        .collect::<Vec<_>>(),
-                )),
-                // batch_id
-                Arc::new(StringArray::from(
-                    db_records
-                        .iter()
-                        .map(|x| x.batch_id.as_str())
-                        .collect::<Vec<_>>(),
-                )),
-                // create_time
-                Arc::new(Int64Array::from(
-                    db_records
-                        .iter()
-                        .map(|_| chrono::Local::now().timestamp_millis())
-                        .collect::<Vec<_>>(),
-                )),
-            ],
-        )?;
+                    )),
+                    // content
+                    "content" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.content.as_str())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // content_type
+                    "content_type" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.content_type.as_str())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // content_ref
+                    "content_ref" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.content_ref.clone())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // payload
+                    "payload" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.payload.clone())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // batch_id
+                    "batch_id" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.batch_id.as_str())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // create_time
+                    "create_time" => {
+                        Arc::new(Int64Array::from(vec![create_time; db_records.len()]))
+                    }
+                    // source
+                    "source" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.metadata.source.clone())
+                            .collect::<Vec<_>>(),
+                    )),
+                    // page
+                    "page" => Arc::new(Int64Array::from(
+                        db_records
+                            .iter()
+                            .map(|x| x.metadata.page)
+                            .collect::<Vec<_>>(),
+                    )),
+                    // tags
+                    "tags" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| encode_tags(&x.metadata.tags))
+                            .collect::<Vec<_>>(),
+                    )),
+                    // heading_path
+                    "heading_path" => Arc::new(StringArray::from(
+                        db_records
+                            .iter()
+                            .map(|x| encode_heading_path(&x.metadata.heading_path))
+                            .collect::<Vec<_>>(),
+                    )),
+                    name => {
+                        return Err(Error::InvalidParameter(format!("unknown column: {}", name)));
+                    }
+                };
+                Ok(column)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let batch = RecordBatch::try_new(schema, columns)?;
         let schema = batch.schema().clone();
         let batch = Ok(batch);
         let batch = Box::new(RecordBatchIterator::new(vec![batch], schema));
-        Ok(batch)
+        Ok((batch, links))
+    }
+
+    /// 查询各批次在表中的最后一条记录
+    /// - table：表
+    /// - batch_ids：批次ID
+    ///
+    /// 返回`批次id -> (id, seq)`，表中没有数据的批次不返回
+    async fn batch_tails(
+        &self,
+        table: &Table,
+        batch_ids: Vec<&str>,
+    ) -> Result<HashMap<String, (i64, i64)>> {
+        let batch_ids = batch_ids.into_iter().unique().collect::<Vec<_>>();
+        let limit = batch_ids.len();
+        let records = table
+            .query()
+            .only_if(
+                Filter::and([Filter::is_in(BATCH_ID, batch_ids), Filter::is_null(NEXT)]).into_sql(),
+            )
+            .select(Select::columns(&["id", "seq", "batch_id"]))
+            .limit(limit)
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let mut tails = HashMap::new();
+        for record in records {
+            let ids = int64_column(&record, "id")?;
+            let seqs = int64_column(&record, "seq")?;
+            let batch_ids = record
+                .column_by_name("batch_id")
+                .and_then(|x| x.as_any().downcast_ref::<StringArray>())
+                .ok_or(Error::Unknown)?;
+            for i in 0..record.num_rows() {
+                tails.insert(
+                    batch_ids.value(i).to_string(),
+                    (ids.value(i), seqs.value(i)),
+                );
+            }
+        }
+        Ok(tails)
     }
 
     /// 搜索
     /// - table_name：表名
     /// - id：唯一标识，如果指定，则只搜索该记录
     /// - vector：特征向量
+    /// - query：关键词文本，用于全文检索
+    /// - mode：检索方式，默认为向量检索
     /// - limit：搜索结果数量，默认10
     /// - offset：搜索结果偏移量
-    /// - min_score：最小分数，如果指定，则只返回分数大于等于该值的记录，取值范围`[0,1]`
+    /// - min_score：最小分数，如果指定，则只返回分数大于等于该值的记录，取值范围`[0,1]`，仅对向量检索生效
     pub async fn search(&self, search_request: SearchRequest) -> Result<Vec<SearchResult>> {
-        let table_name = search_request.table_name;
-        let table = self
-            .connection
-            .open_table(table_name.clone())
-            .execute()
-            .await?;
-        let is_vector_search = search_request.vector.is_some();
+        let table_name = search_request.table_name.clone();
+        let table = self.open_table(&table_name).await?;
+
+        let records = match search_request.mode {
+            SearchMode::Vector => self.vector_search(&table, &search_request).await?,
+            SearchMode::FullText => self.full_text_search(&table, &search_request).await?,
+            SearchMode::Hybrid => self.hybrid_search(&table, &search_request).await?,
+        };
+
+        if search_request.context_size.is_none() || search_request.context_size.unwrap() == 0 {
+            return Ok(records);
+        }
+
+        let mut result = vec![];
+        let group = records
+            .into_iter()
+            .into_group_map_by(|x| x.batch_id.clone());
+        for (batch_id, records) in group.into_iter() {
+            let list = self
+                .extend_context(
+                    &table_name,
+                    &batch_id,
+                    &records,
+                    search_request.context_size.unwrap_or(0),
+                )
+                .await?;
+            result.extend(list);
+        }
+
+        result.sort_by(|a, b| {
+            return if a.score == b.score {
+                a.id.cmp(&b.id)
+            } else {
+                b.score.partial_cmp(&a.score).unwrap()
+            };
+        });
+
+        Ok(result)
+    }
+
+    /// 构建id、batch_id及元数据的过滤条件
+    fn build_filter(search_request: &SearchRequest) -> Option<String> {
+        let mut filters = vec![];
+        if let Some(id) = search_request.id {
+            filters.push(Filter::eq(ID, &id));
+        }
+        if let Some(batch_id) = &search_request.batch_id {
+            filters.push(Filter::eq(BATCH_ID, batch_id.as_str()));
+        }
+        if let Some(filter) = &search_request.filter {
+            filters.push(filter.to_filter());
+        }
+        if filters.is_empty() {
+            None
+        } else {
+            Some(Filter::and(filters).into_sql())
+        }
+    }
+
+    /// 向量检索，未指定向量时按过滤条件查询
+    async fn vector_search(
+        &self,
+        table: &Table,
+        search_request: &SearchRequest,
+    ) -> Result<Vec<SearchResult>> {
         let min_score = if let Some(min_score) = search_request.min_score {
             Some((1. - min_score) * 2.)
         } else {
@@ -475,26 +1066,29 @@ impl Database {
         // 这应该是LanceDB的一个bug，不使用向量查询和使用向量查询时的offset表现不一致
         // 使用向量查询时：offset需要加上limit，否则限制的limit数量不会返回
         // 不使用向量查询时：offset和limit表现符合预期
-        if is_vector_search {
+        if search_request.vector.is_some() {
             query = query.offset(offset).limit(offset + limit);
         }
 
-        if let Some(id) = search_request.id {
-            query = query.only_if(format!("id = '{}'", id));
-        }
-        if let Some(batch_id) = search_request.batch_id {
-            query = query.only_if(format!("batch_id = '{}'", batch_id));
+        if let Some(filter) = Self::build_filter(search_request) {
+            query = query.only_if(filter);
         }
-        let record_batches = match search_request.vector {
+        let record_batches = match &search_request.vector {
             None => query.execute().await?.try_collect::<Vec<_>>().await?,
             Some(vector) => {
-                query
+                let mut query = query
                     // 临近搜索
-                    .nearest_to(vector)?
+                    .nearest_to(vector.clone())?
                     .column("vector")
                     // 使用余弦相似度
                     .distance_type(DistanceType::Cosine)
                     .distance_range(None, min_score)
+                    // 以下参数仅在建立向量索引后生效
+                    .nprobes(search_request.nprobes.unwrap_or(DEFAULT_NPROBES));
+                if let Some(refine_factor) = search_request.refine_factor {
+                    query = query.refine_factor(refine_factor);
+                }
+                query
                     .with_row_id()
                     .execute()
                     .await?
@@ -503,43 +1097,83 @@ impl Database {
             }
         };
 
-        let records =
-            Self::convert_record_batch_to_search_result(record_batches, is_vector_search)?;
+        Self::convert_record_batch_to_search_result(record_batches)
+    }
+
+    /// 全文检索（BM25）
+    async fn full_text_search(
+        &self,
+        table: &Table,
+        search_request: &SearchRequest,
+    ) -> Result<Vec<SearchResult>> {
+        let text = match &search_request.query {
+            Some(text) if !text.trim().is_empty() => text.clone(),
+            _ => {
+                return Err(Error::InvalidParameter(
+                    "query is required for full text search".to_string(),
+                ));
+            }
+        };
+        self.ensure_fts_index(table).await?;
 
-        if search_request.context_size.is_none() || search_request.context_size.unwrap() == 0 {
-            return Ok(records);
+        let limit = search_request.limit.unwrap_or(10);
+        let offset = search_request.offset.unwrap_or(0);
+        let mut query = table
+            .query()
+            .full_text_search(FullTextSearchQuery::new(text))
+            .limit(limit)
+            .offset(offset);
+        if let Some(filter) = Self::build_filter(search_request) {
+            query = query.only_if(filter);
         }
+        let record_batches = query.execute().await?.try_collect::<Vec<_>>().await?;
 
-        let mut result = vec![];
-        let group = records
-            .into_iter()
-            .into_group_map_by(|x| x.batch_id.clone());
-        for (batch_id, records) in group.into_iter() {
-            let list = self
-                .extend_context(
-                    &table_name,
-                    &batch_id,
-                    &records,
-                    search_request.context_size.unwrap_or(0),
-                )
-                .await?;
-            result.extend(list);
-        }
+        Self::convert_record_batch_to_search_result(record_batches)
+    }
 
-        result.sort_by(|a, b| {
-            return if a.score == b.score {
-                a.id.cmp(&b.id)
+    /// 混合检索
+    ///
+    /// 分别执行向量检索和全文检索，再使用RRF（Reciprocal Rank Fusion）按排名融合两路结果，
+    /// 融合后的分数为RRF分数，不再是`[0,1]`区间的相似度。
+    /// 向量或关键词缺失其一时，退化为单路检索。
+    async fn hybrid_search(
+        &self,
+        table: &Table,
+        search_request: &SearchRequest,
+    ) -> Result<Vec<SearchResult>> {
+        let has_query = search_request
+            .query
+            .as_ref()
+            .is_some_and(|q| !q.trim().is_empty());
+        if search_request.vector.is_none() || !has_query {
+            return if has_query {
+                self.full_text_search(table, search_request).await
             } else {
-                b.score.partial_cmp(&a.score).unwrap()
+                self.vector_search(table, search_request).await
             };
-        });
+        }
 
-        Ok(result)
+        let limit = search_request.limit.unwrap_or(10);
+        let offset = search_request.offset.unwrap_or(0);
+
+        // 每一路召回更多的候选，融合后再截取
+        let mut candidate_request = search_request.clone();
+        candidate_request.offset = Some(0);
+        candidate_request.limit = Some((offset + limit) * HYBRID_CANDIDATE_FACTOR);
+
+        let vector_records = self.vector_search(table, &candidate_request).await?;
+        let text_records = self.full_text_search(table, &candidate_request).await?;
+
+        let fused = reciprocal_rank_fusion(vec![vector_records, text_records], RRF_K);
+
+        Ok(fused.into_iter().skip(offset).take(limit).collect())
     }
 
+    /// 将查询结果转换为SearchResult
+    ///
+    /// 向量检索的分数由`_distance`列换算，全文检索的分数取`_score`列（BM25分数）
     fn convert_record_batch_to_search_result(
         record_batches: Vec<RecordBatch>,
-        is_vector_search: bool,
     ) -> Result<Vec<SearchResult>> {
         let mut records = Vec::new();
 
@@ -552,6 +1186,7 @@ impl Database {
             let ids = record.column_by_name("id").unwrap();
             let prev_s = record.column_by_name("prev").unwrap();
             let next_s = record.column_by_name("next").unwrap();
+            let seq_s = record.column_by_name("seq").unwrap();
             // batch_ids 列格式
             let batch_ids = record.column_by_name("batch_id").unwrap();
             let contents = record.column_by_name("content").unwrap();
@@ -561,17 +1196,27 @@ impl Database {
             let payloads = record.column_by_name("payload").unwrap();
             // create_times 列格式
             let create_times = record.column_by_name("create_time").unwrap();
+            // 元数据列，查询时未选择的列为空
+            let sources = record.column_by_name("source");
+            let pages = record.column_by_name("page");
+            let tags_s = record.column_by_name("tags");
+            let heading_paths = record.column_by_name("heading_path");
 
             // distance 列格式
             let default_distances = &(Arc::new(Float32Array::new_null(rows)) as ArrayRef);
+            let is_vector_search = record.column_by_name("_distance").is_some();
             let distances = record
                 .column_by_name("_distance")
                 .unwrap_or(default_distances);
+            // score 列格式
+            let is_full_text_search = record.column_by_name("_score").is_some();
+            let scores = record.column_by_name("_score").unwrap_or(default_distances);
 
             for i in 0..rows {
                 let id = ids.as_any().downcast_ref::<Int64Array>().unwrap();
                 let prev = prev_s.as_any().downcast_ref::<Int64Array>().unwrap();
                 let next = next_s.as_any().downcast_ref::<Int64Array>().unwrap();
+                let seq = seq_s.as_any().downcast_ref::<Int64Array>().unwrap();
                 let batch_id = batch_ids.as_any().downcast_ref::<StringArray>().unwrap();
                 let content = contents.as_any().downcast_ref::<StringArray>().unwrap();
                 let content_type = content_types
@@ -582,10 +1227,20 @@ impl Database {
                 let payload = payloads.as_any().downcast_ref::<StringArray>().unwrap();
                 let create_time = create_times.as_any().downcast_ref::<Int64Array>().unwrap();
                 let distance = distances.as_any().downcast_ref::<Float32Array>().unwrap();
+                let fts_score = scores.as_any().downcast_ref::<Float32Array>().unwrap();
 
                 let id = id.value(i);
-                let prev = prev.value(i);
-                let next = next.value(i);
+                let prev = if prev.is_null(i) {
+                    None
+                } else {
+                    Some(prev.value(i))
+                };
+                let next = if next.is_null(i) {
+                    None
+                } else {
+                    Some(next.value(i))
+                };
+                let seq = seq.value(i);
                 let batch_id = batch_id.value(i);
                 let content = content.value(i);
                 let content_type = content_type.value(i);
@@ -594,14 +1249,31 @@ impl Database {
                 let create_time = create_time.value(i);
                 let score = if is_vector_search {
                     Some(1. - distance.value(i) / 2.)
+                } else if is_full_text_search {
+                    Some(fts_score.value(i))
                 } else {
                     None
                 };
 
+                let metadata = RecordMetadata {
+                    source: string_value(sources, i),
+                    page: pages.and_then(|pages| {
+                        let pages = pages.as_any().downcast_ref::<Int64Array>().unwrap();
+                        if pages.is_null(i) {
+                            None
+                        } else {
+                            Some(pages.value(i))
+                        }
+                    }),
+                    tags: decode_tags(string_value(tags_s, i)),
+                    heading_path: decode_heading_path(string_value(heading_paths, i)),
+                };
+
                 records.push(SearchResult {
                     id,
-                    prev: Some(prev),
-                    next: Some(next),
+                    prev,
+                    next,
+                    seq,
                     payload: Some(payload.to_string()),
                     score,
                     batch_id: batch_id.to_string(),
@@ -609,6 +1281,7 @@ impl Database {
                     content_type: content_type.to_string(),
                     content_ref: serde_json::from_str(content_ref).unwrap(),
                     create_time,
+                    metadata,
                 });
             }
         }
@@ -616,7 +1289,7 @@ impl Database {
         Ok(records)
     }
 
-    /// 扩展上下文。数据使用链式存储，在此向前和向后寻找context_size条数据用于扩展上下文
+    /// 扩展上下文。数据使用链式存储，在此沿`prev`和`next`向前和向后寻找context_size条数据用于扩展上下文
     async fn extend_context(
         &self,
         table_name: &str,
@@ -624,47 +1297,68 @@ impl Database {
         records: &Vec<SearchResult>,
         context_size: usize,
     ) -> Result<Vec<SearchResult>> {
-        let table = self.connection.open_table(table_name).execute().await?;
-        let context_records_batches = table
-            .query()
-            .only_if(format!(
-                "batch_id = '{}' and id in ({})",
-                batch_id,
-                records
-                    .iter()
-                    .flat_map(|r| {
-                        let mut ids = vec![];
-                        for i in 1..=context_size {
-                            ids.push((r.id - i as i64).to_string());
-                            ids.push((r.id + i as i64).to_string());
-                        }
-                        ids
-                    })
-                    .join(",")
-            ))
-            .execute()
-            .await?
-            .try_collect::<Vec<_>>()
-            .await?;
+        let table = self.open_table(table_name).await?;
 
-        let context_records =
-            Self::convert_record_batch_to_search_result(context_records_batches, false)?;
+        // 已查询到的记录
+        let mut mapping: HashMap<i64, SearchResult> =
+            HashMap::from_iter(records.iter().map(|r| (r.id, r.clone())));
+        // 每条记录向前和向后已找到的记录id
+        let mut prevs: Vec<Vec<i64>> = vec![vec![]; records.len()];
+        let mut nexts: Vec<Vec<i64>> = vec![vec![]; records.len()];
+        // 每条记录下一步要查找的前后记录id
+        let mut frontier = records.iter().map(|r| (r.prev, r.next)).collect::<Vec<_>>();
+
+        for _ in 0..context_size {
+            let ids = frontier
+                .iter()
+                .flat_map(|(prev, next)| [*prev, *next])
+                .flatten()
+                .filter(|id| !mapping.contains_key(id))
+                .unique()
+                .collect::<Vec<_>>();
+            if !ids.is_empty() {
+                let limit = ids.len();
+                let context_records_batches = table
+                    .query()
+                    .only_if(
+                        Filter::and([
+                            Filter::eq(BATCH_ID, batch_id.as_str()),
+                            Filter::is_in(ID, ids),
+                        ])
+                        .into_sql(),
+                    )
+                    .limit(limit)
+                    .execute()
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                let context_records =
+                    Self::convert_record_batch_to_search_result(context_records_batches)?;
+                mapping.extend(context_records.into_iter().map(|r| (r.id, r)));
+            }
 
-        let mapping: HashMap<i64, &SearchResult> =
-            HashMap::from_iter(context_records.iter().map(|r| (r.id, r)));
+            for (i, (prev, next)) in frontier.iter_mut().enumerate() {
+                *prev = prev.and_then(|id| mapping.get(&id)).and_then(|r| {
+                    prevs[i].push(r.id);
+                    r.prev
+                });
+                *next = next.and_then(|id| mapping.get(&id)).and_then(|r| {
+                    nexts[i].push(r.id);
+                    r.next
+                });
+            }
+        }
 
         let list = records
-            .into_iter()
-            .map(|record| {
-                let id = record.id;
+            .iter()
+            .enumerate()
+            .map(|(i, record)| {
                 let mut record = record.clone();
-                for i in 1..=context_size {
-                    if let Some(prev) = mapping.get(&(id - i as i64)) {
-                        record.content = format!("{}{}", prev.content, record.content);
-                    }
-                    if let Some(next) = mapping.get(&(id + i as i64)) {
-                        record.content = format!("{}{}", record.content, next.content);
-                    }
+                for id in &prevs[i] {
+                    record.content = format!("{}{}", mapping[id].content, record.content);
+                }
+                for id in &nexts[i] {
+                    record.content = format!("{}{}", record.content, mapping[id].content);
                 }
                 record
             })
@@ -672,16 +1366,17 @@ impl Database {
 
         Ok(list)
     }
+
     /// 获取表的总行数
     /// - table_name：表名
     pub async fn total_rows(&self, table_name: &str) -> Result<usize> {
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
         let count = table.count_rows(None).await?;
         Ok(count)
     }
 
     pub async fn disk_usage(&self, table_name: &str) -> Result<usize> {
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
         let uri = table.dataset_uri();
         // 统计uri目录占用空间大小
         let usage = fs::metadata(uri)?.len();
@@ -699,7 +1394,7 @@ impl Database {
         let table_names = self.connection.table_names().execute().await?;
         let mut tables = Vec::new();
         for table_name in &table_names {
-            let table = self.connection.open_table(table_name).execute().await?;
+            let table = self.open_table(table_name).await?;
             let count = table.count_rows(None).await?;
             tables.push(TableInfo {
                 table_name: table_name.clone(),
@@ -716,7 +1411,7 @@ impl Database {
     }
 
     pub async fn table_info(&self, table_name: &str) -> Result<TableInfo> {
-        let table = self.connection.open_table(table_name).execute().await?;
+        let table = self.open_table(table_name).await?;
         let count = table.count_rows(None).await?;
         Ok(TableInfo {
             table_name: table_name.to_string(),
@@ -731,6 +1426,8 @@ pub struct Record {
     id: i64,
     prev: Option<i64>,
     next: Option<i64>,
+    /// 批次内的序号，从1开始
+    seq: i64,
     /// 特征向量
     pub vector: Vec<f32>,
     /// 原始内容
@@ -743,6 +1440,8 @@ pub struct Record {
     pub payload: Option<String>,
     /// 批次id
     pub batch_id: String,
+    /// 元数据
+    pub metadata: RecordMetadata,
 }
 
 #[derive(Debug, Clone)]
@@ -759,6 +1458,317 @@ pub struct AddRecordRequest {
     pub content_ref: Option<ContentRef>,
     /// 自定义数据
     pub payload: Option<String>,
+    /// 元数据，可用于检索时过滤
+    pub metadata: RecordMetadata,
+}
+
+/// 分段的元数据
+///
+/// 导入时间即记录的`create_time`，不单独存储
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordMetadata {
+    /// 来源，通常为文件名
+    pub source: Option<String>,
+    /// 页码，从1开始
+    pub page: Option<i64>,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 标题路径，从一级标题开始
+    pub heading_path: Vec<String>,
+}
+
+/// 元数据过滤条件，转换为LanceDB的过滤表达式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum MetadataFilter {
+    /// 同时满足所有条件，为空时不过滤
+    And { filters: Vec<MetadataFilter> },
+    /// 满足任一条件，为空时不匹配任何数据
+    Or { filters: Vec<MetadataFilter> },
+    /// 不满足条件
+    Not { filter: Box<MetadataFilter> },
+    /// 来源为其中之一
+    Source { sources: Vec<String> },
+    /// 批次为其中之一
+    Batch { batch_ids: Vec<String> },
+    /// 包含标签
+    Tag { tag: String },
+    /// 页码范围，包含边界
+    Page { from: Option<i64>, to: Option<i64> },
+    /// 导入时间范围，毫秒时间戳，包含边界
+    ImportTime { from: Option<i64>, to: Option<i64> },
+    /// 标题路径以指定的标题开头，如`第一章`或`第一章 > 第二节`，按完整的标题匹配，`第一章`不匹配`第一章节`
+    HeadingPrefix { prefix: String },
+}
+
+impl MetadataFilter {
+    /// 转换为过滤表达式，字符串值均已转义
+    pub fn to_sql(&self) -> String {
+        self.to_filter().into_sql()
+    }
+
+    fn to_filter(&self) -> Filter {
+        match self {
+            MetadataFilter::And { filters } => Filter::and(filters.iter().map(|f| f.to_filter())),
+            MetadataFilter::Or { filters } => Filter::or(filters.iter().map(|f| f.to_filter())),
+            MetadataFilter::Not { filter } => Filter::not(filter.to_filter()),
+            MetadataFilter::Source { sources } => {
+                Filter::is_in(SOURCE, sources.iter().map(String::as_str))
+            }
+            MetadataFilter::Batch { batch_ids } => {
+                Filter::is_in(BATCH_ID, batch_ids.iter().map(String::as_str))
+            }
+            MetadataFilter::Tag { tag } => {
+                Filter::contains(TAGS, &format!(",{},", normalize_tag(tag)))
+            }
+            MetadataFilter::Page { from, to } => Filter::range(PAGE, from.as_ref(), to.as_ref()),
+            MetadataFilter::ImportTime { from, to } => {
+                Filter::range(CREATE_TIME, from.as_ref(), to.as_ref())
+            }
+            MetadataFilter::HeadingPrefix { prefix } => {
+                let prefix = prefix.trim();
+                Filter::or([
+                    Filter::eq(HEADING_PATH, prefix),
+                    Filter::starts_with(
+                        HEADING_PATH,
+                        &format!("{}{}", prefix, HEADING_PATH_SEPARATOR),
+                    ),
+                ])
+            }
+        }
+    }
+}
+
+/// 过滤表达式中的字面量
+trait Literal {
+    fn literal(&self) -> String;
+}
+
+impl Literal for i64 {
+    fn literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for str {
+    /// 字符串字面量，单引号转义为两个单引号
+    fn literal(&self) -> String {
+        format!("'{}'", self.replace('\'', "''"))
+    }
+}
+
+/// 表中的列，类型参数为列的值类型，避免整数列按字符串比较
+struct Column<T: ?Sized> {
+    name: &'static str,
+    value_type: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized> Clone for Column<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Column<T> {}
+
+impl<T: ?Sized> Column<T> {
+    const fn new(name: &'static str) -> Self {
+        Column {
+            name,
+            value_type: PhantomData,
+        }
+    }
+}
+
+const ID: Column<i64> = Column::new("id");
+const NEXT: Column<i64> = Column::new("next");
+const BATCH_ID: Column<str> = Column::new("batch_id");
+const CREATE_TIME: Column<i64> = Column::new("create_time");
+const SOURCE: Column<str> = Column::new("source");
+const PAGE: Column<i64> = Column::new("page");
+const TAGS: Column<str> = Column::new("tags");
+const HEADING_PATH: Column<str> = Column::new("heading_path");
+
+/// like模式中的转义字符
+const LIKE_ESCAPE: char = '\\';
+
+/// 转义like模式中的通配符`%`、`_`及转义字符本身
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_') || c == LIKE_ESCAPE {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 过滤表达式构建器，值按列的类型生成字面量并转义
+#[derive(Debug, Clone)]
+struct Filter(String);
+
+impl Filter {
+    /// 等于
+    fn eq<T: ?Sized + Literal>(column: Column<T>, value: &T) -> Filter {
+        Filter(format!("{} = {}", column.name, value.literal()))
+    }
+
+    /// 大于等于
+    fn gte<T: ?Sized + Literal>(column: Column<T>, value: &T) -> Filter {
+        Filter(format!("{} >= {}", column.name, value.literal()))
+    }
+
+    /// 小于等于
+    fn lte<T: ?Sized + Literal>(column: Column<T>, value: &T) -> Filter {
+        Filter(format!("{} <= {}", column.name, value.literal()))
+    }
+
+    /// 范围，包含边界，未指定的边界不限制
+    fn range<T: ?Sized + Literal>(column: Column<T>, from: Option<&T>, to: Option<&T>) -> Filter {
+        let mut filters = vec![];
+        if let Some(from) = from {
+            filters.push(Filter::gte(column, from));
+        }
+        if let Some(to) = to {
+            filters.push(Filter::lte(column, to));
+        }
+        Filter::and(filters)
+    }
+
+    /// 为空
+    fn is_null<T: ?Sized>(column: Column<T>) -> Filter {
+        Filter(format!("{} is null", column.name))
+    }
+
+    /// 在列表中，列表为空时不匹配任何数据
+    fn is_in<T, V>(column: Column<T>, values: impl IntoIterator<Item = V>) -> Filter
+    where
+        T: ?Sized + Literal,
+        V: Borrow<T>,
+    {
+        let values = values
+            .into_iter()
+            .map(|value| value.borrow().literal())
+            .unique()
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Filter("false".to_string());
+        }
+        Filter(format!("{} in ({})", column.name, values.join(",")))
+    }
+
+    /// 包含指定的子串，子串按原样匹配
+    fn contains(column: Column<str>, value: &str) -> Filter {
+        Filter::like(column, &format!("%{}%", escape_like(value)))
+    }
+
+    /// 以指定的字符串开头，前缀按原样匹配
+    fn starts_with(column: Column<str>, value: &str) -> Filter {
+        Filter::like(column, &format!("{}%", escape_like(value)))
+    }
+
+    /// 模式匹配，`%`和`_`为通配符，需要按原样匹配的部分先使用[`escape_like`]转义
+    fn like(column: Column<str>, pattern: &str) -> Filter {
+        Filter(format!(
+            "{} like {} escape '{}'",
+            column.name,
+            pattern.literal(),
+            LIKE_ESCAPE
+        ))
+    }
+
+    /// 所有条件均满足，没有条件时匹配所有数据
+    fn and(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::join(filters, " and ", "true")
+    }
+
+    /// 任一条件满足，没有条件时不匹配任何数据
+    fn or(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::join(filters, " or ", "false")
+    }
+
+    /// 条件不满足
+    fn not(filter: Filter) -> Filter {
+        Filter(format!("not ({})", filter.0))
+    }
+
+    fn join(filters: impl IntoIterator<Item = Filter>, separator: &str, empty: &str) -> Filter {
+        let mut filters = filters.into_iter().collect::<Vec<_>>();
+        match filters.len() {
+            0 => Filter(empty.to_string()),
+            1 => filters.pop().unwrap(),
+            _ => Filter(
+                filters
+                    .iter()
+                    .map(|filter| format!("({})", filter.0))
+                    .join(separator),
+            ),
+        }
+    }
+
+    fn into_sql(self) -> String {
+        self.0
+    }
+}
+
+/// 标签中不能包含分隔符`,`
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().replace(',', "")
+}
+
+fn encode_tags(tags: &[String]) -> Option<String> {
+    let tags = tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    if tags.is_empty() {
+        None
+    } else {
+        Some(format!(",{},", tags.join(",")))
+    }
+}
+
+fn decode_tags(tags: Option<String>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .split(',')
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect()
+}
+
+fn encode_heading_path(heading_path: &[String]) -> Option<String> {
+    if heading_path.is_empty() {
+        None
+    } else {
+        Some(heading_path.join(HEADING_PATH_SEPARATOR))
+    }
+}
+
+fn decode_heading_path(heading_path: Option<String>) -> Vec<String> {
+    match heading_path {
+        Some(heading_path) if !heading_path.is_empty() => heading_path
+            .split(HEADING_PATH_SEPARATOR)
+            .map(|h| h.to_string())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// 读取字符串列的值，列不存在或值为空时返回None
+fn string_value(column: Option<&ArrayRef>, i: usize) -> Option<String> {
+    let column = column?.as_any().downcast_ref::<StringArray>()?;
+    if column.is_null(i) {
+        None
+    } else {
+        Some(column.value(i).to_string())
+    }
 }
 
 /// 内容引用
@@ -769,7 +1779,19 @@ pub struct ContentRef {
     pub images: Option<Vec<String>>,
     /// 引用的链接
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub urls: Option<String>,
+    pub urls: Option<Vec<String>>,
+}
+
+/// 检索方式
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchMode {
+    /// 向量检索
+    #[default]
+    Vector,
+    /// 全文检索（BM25）
+    FullText,
+    /// 向量检索 + 全文检索，按RRF融合
+    Hybrid,
 }
 
 #[derive(Debug, Clone, Builder, Default)]
@@ -781,8 +1803,14 @@ pub struct SearchRequest {
     pub id: Option<i64>,
     /// 批次id，对应业务ID，同一个文件的批次ID应该保持一致
     pub batch_id: Option<String>,
+    /// 元数据过滤条件
+    pub filter: Option<MetadataFilter>,
     /// 搜索关键词的特征向量
     pub vector: Option<Vec<f32>>,
+    /// 搜索关键词文本，全文检索和混合检索时使用
+    pub query: Option<String>,
+    /// 检索方式，默认为向量检索
+    pub mode: SearchMode,
     /// 上下文扩充长度，默认为0
     pub context_size: Option<usize>,
     /// 返回条数，默认为10
@@ -791,6 +1819,57 @@ pub struct SearchRequest {
     pub offset: Option<usize>,
     /// 最小匹配度，为空时不限制
     pub min_score: Option<f32>,
+    /// 向量索引检索的分区数量，越大召回率越高、速度越慢，默认为20，未建立索引时无效
+    pub nprobes: Option<usize>,
+    /// 向量索引检索的精排倍数，召回`limit * refine_factor`条后使用原始向量重新排序，
+    /// 可弥补PQ量化的精度损失，为空时不精排，未建立索引时无效
+    pub refine_factor: Option<u32>,
+}
+
+/// 向量索引类型
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorIndexType {
+    /// IVF_PQ：内存占用小，适合大数据量，召回率略低，可通过refine_factor弥补
+    #[default]
+    IvfPq,
+    /// IVF_HNSW_SQ：召回率和速度更高，内存占用更大
+    Hnsw,
+}
+
+impl VectorIndexType {
+    fn index_type(&self) -> IndexType {
+        match self {
+            VectorIndexType::IvfPq => IndexType::IvfPq,
+            VectorIndexType::Hnsw => IndexType::IvfHnswSq,
+        }
+    }
+}
+
+/// 向量索引配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorIndexOptions {
+    /// 索引类型
+    pub index_type: VectorIndexType,
+    /// 表的行数达到该值后自动建立向量索引，为0时不建立索引
+    ///
+    /// IVF_PQ索引训练需要一定的数据量，数据量过少时建立索引反而会降低召回率
+    pub min_rows: usize,
+}
+
+impl Default for VectorIndexOptions {
+    fn default() -> Self {
+        VectorIndexOptions {
+            index_type: VectorIndexType::default(),
+            min_rows: 50_000,
+        }
+    }
+}
+
+/// 设置向量索引配置，对之后的优化生效
+pub fn set_vector_index_options(options: VectorIndexOptions) {
+    *VECTOR_INDEX_OPTIONS.write().unwrap() = options;
 }
 
 #[derive(Debug, Clone)]
@@ -801,6 +1880,8 @@ pub struct SearchResult {
     pub prev: Option<i64>,
     /// 后一条数据
     pub next: Option<i64>,
+    /// 批次内的序号，从1开始
+    pub seq: i64,
     /// 自定义数据
     pub payload: Option<String>,
     /// 匹配度
@@ -815,6 +1896,87 @@ pub struct SearchResult {
     pub content_ref: Option<ContentRef>,
     /// 创建时间
     pub create_time: i64,
+    /// 元数据
+    pub metadata: RecordMetadata,
+}
+
+/// 分配count个全局唯一的记录id
+///
+/// id的高位为毫秒时间戳，低位为序号，单调递增，应用重启后也不会与已有的id重复
+fn next_record_ids(count: usize) -> Vec<i64> {
+    let count = count as i64;
+    let min = chrono::Local::now().timestamp_millis() << RECORD_ID_SEQUENCE_BITS;
+    let last = LAST_RECORD_ID
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+            Some(last.max(min - 1) + count)
+        })
+        .unwrap();
+    let first = last.max(min - 1) + 1;
+    (first..first + count).collect()
+}
+
+/// 获取Int64类型的列
+fn int64_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a Int64Array> {
+    batch
+        .column_by_name(name)
+        .and_then(|x| x.as_any().downcast_ref::<Int64Array>())
+        .ok_or(Error::Unknown)
+}
+
+/// 计算删除记录后需要重新链接的前后记录
+///
+/// - links：被删除的记录，`id -> (prev, next)`
+///
+/// 连续删除的多条记录只产生一次链接，返回`(前一条记录, 后一条记录)`，为None表示批次的开头或结尾
+fn relink(links: &HashMap<i64, (Option<i64>, Option<i64>)>) -> Vec<(Option<i64>, Option<i64>)> {
+    let mut result = vec![];
+    for (prev, next) in links.values() {
+        // 只从连续删除的第一条记录开始
+        if prev.is_some_and(|prev| links.contains_key(&prev)) {
+            continue;
+        }
+        let mut next = *next;
+        // 跳过连续删除的记录，次数以删除数量为上限，避免链接成环时死循环
+        for _ in 0..links.len() {
+            match next.and_then(|id| links.get(&id)) {
+                Some((_, n)) => next = *n,
+                None => break,
+            }
+        }
+        if prev.is_some() || next.is_some() {
+            result.push((*prev, next));
+        }
+    }
+    result
+}
+
+/// 使用RRF（Reciprocal Rank Fusion）融合多路检索结果
+///
+/// 每条记录的分数为其在各路结果中`1 / (k + rank)`之和，rank从1开始。
+/// 同一条记录以`(batch_id, id)`作为唯一标识。
+fn reciprocal_rank_fusion(lists: Vec<Vec<SearchResult>>, k: f32) -> Vec<SearchResult> {
+    let mut fused: Vec<SearchResult> = vec![];
+    let mut positions: HashMap<(String, i64), usize> = HashMap::new();
+    for list in lists {
+        for (rank, record) in list.into_iter().enumerate() {
+            let score = 1. / (k + rank as f32 + 1.);
+            let key = (record.batch_id.clone(), record.id);
+            match positions.get(&key) {
+                Some(&pos) => {
+                    let item = &mut fused[pos];
+                    item.score = Some(item.score.unwrap_or(0.) + score);
+                }
+                None => {
+                    positions.insert(key, fused.len());
+                    let mut record = record;
+                    record.score = Some(score);
+                    fused.push(record);
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    fused
 }
 
 pub struct DatabaseInfo {
@@ -829,3 +1991,98 @@ pub struct TableInfo {
     pub total_rows: usize,
     pub disk_usage: usize,
 }
+
+#[test]
+fn test_reciprocal_rank_fusion() {
+    let record = |id: i64| SearchResult {
+        id,
+        prev: None,
+        next: None,
+        seq: id,
+        payload: None,
+        score: None,
+        batch_id: "1".to_string(),
+        content: id.to_string(),
+        content_type: "text".to_string(),
+        content_ref: None,
+        create_time: 0,
+        metadata: RecordMetadata::default(),
+    };
+    let vector = vec![record(1), record(2), record(3)];
+    let text = vec![record(3), record(4)];
+    let fused = reciprocal_rank_fusion(vec![vector, text], RRF_K);
+    let ids = fused.iter().map(|r| r.id).collect::<Vec<_>>();
+    // 3在两路中都出现，排在最前
+    assert_eq!(ids, vec![3, 1, 4, 2]);
+}
+
+#[test]
+fn test_metadata_filter_to_sql() {
+    let filter = MetadataFilter::And {
+        filters: vec![
+            MetadataFilter::Tag {
+                tag: "contract".to_string(),
+            },
+            MetadataFilter::Source {
+                sources: vec!["it's.pdf".to_string()],
+            },
+            MetadataFilter::ImportTime {
+                from: Some(1),
+                to: None,
+            },
+        ],
+    };
+    assert_eq!(
+        filter.to_sql(),
+        "(tags like '%,contract,%' escape '\\') and (source in ('it''s.pdf')) and (create_time >= 1)"
+    );
+}
+
+#[test]
+fn test_heading_prefix_filter() {
+    let filter = MetadataFilter::HeadingPrefix {
+        prefix: " 第一章 ".to_string(),
+    };
+    assert_eq!(
+        filter.to_sql(),
+        "(heading_path = '第一章') or (heading_path like '第一章 > %' escape '\\')"
+    );
+}
+
+#[test]
+fn test_filter_escape() {
+    let filter = Filter::and([
+        Filter::eq(BATCH_ID, "1' or '1' = '1"),
+        Filter::is_in(ID, vec![3, 1, 3]),
+    ]);
+    assert_eq!(
+        filter.into_sql(),
+        "(batch_id = '1'' or ''1'' = ''1') and (id in (3,1))"
+    );
+    assert_eq!(Filter::is_in(ID, Vec::<i64>::new()).into_sql(), "false");
+    assert_eq!(
+        Filter::starts_with(HEADING_PATH, "a_b%c\\").into_sql(),
+        "heading_path like 'a\\_b\\%c\\\\%' escape '\\'"
+    );
+}
+
+#[test]
+fn test_next_record_ids() {
+    let first = next_record_ids(3);
+    let second = next_record_ids(2);
+    assert_eq!(first[2] - first[0], 2);
+    assert!(second[0] > first[2]);
+}
+
+#[test]
+fn test_relink() {
+    // 1 <-> 2 <-> 3 <-> 4 <-> 5，删除2、3和5
+    let links = HashMap::from([
+        (2, (Some(1), Some(3))),
+        (3, (Some(2), Some(4))),
+        (5, (Some(4), None)),
+    ]);
+    let mut result = relink(&links);
+    result.sort();
+    assert_eq!(result, vec![(Some(1), Some(4)), (Some(4), None)]);
+}
@@ -8,9 +8,15 @@ mod table_db;
 
 use crate::table_db::DbInfo;
 pub use db::AddRecordRequest;
+pub use db::MetadataFilter;
+pub use db::RecordMetadata;
+pub use db::SearchMode;
 pub use db::SearchRequest;
 pub use db::SearchRequestBuilder;
 pub use db::SearchResult;
+pub use db::VectorIndexOptions;
+pub use db::VectorIndexType;
+pub use table_db::{ColumnDefinition, ColumnSchema, ColumnType, QueryResult, TableSchema};
 
 pub type Result<T> = anyhow::Result<T>;
 pub struct Engine;
@@ -18,9 +24,9 @@ pub struct Engine;
 static DB: OnceLock<Database> = OnceLock::new();
 
 impl Engine {
-    pub async fn new_table(name: &str) -> Result<()> {
+    pub async fn new_table(name: &str, dimension: usize) -> Result<()> {
         if let Some(db) = DB.get() {
-            db.create_empty_table(name).await?;
+            db.create_empty_table(name, dimension).await?;
         } else {
             panic!("Database not initialized");
         }
@@ -38,6 +44,15 @@ impl Engine {
         Ok(())
     }
 
+    /// 表是否存在
+    pub async fn has_table(name: &str) -> Result<bool> {
+        if let Some(db) = DB.get() {
+            Ok(db.table_names().await?.iter().contains(&name.to_string()))
+        } else {
+            panic!("Database not initialized");
+        }
+    }
+
     pub async fn add_data(table: &str, data: Vec<AddRecordRequest>) -> Result<()> {
         if let Some(db) = DB.get() {
             db.add_records(table, data).await?;
@@ -56,6 +71,52 @@ impl Engine {
         Ok(())
     }
 
+    /// 删除指定id的数据，并重新链接前后数据
+    pub async fn delete_data_by_ids(table: &str, ids: Vec<i64>) -> Result<()> {
+        if let Some(db) = DB.get() {
+            db.delete_records(table, ids).await?;
+        } else {
+            panic!("Database not initialized");
+        }
+        Ok(())
+    }
+
+    /// 按顺序列出批次内的全部数据
+    pub async fn list_data(table: &str, batch_id: &str) -> Result<Vec<SearchResult>> {
+        if let Some(db) = DB.get() {
+            let result = db.list_records(table, batch_id).await?;
+            Ok(result)
+        } else {
+            panic!("Database not initialized");
+        }
+    }
+
+    /// 更新单条数据的内容及特征向量
+    pub async fn update_data(
+        table: &str,
+        id: i64,
+        content: String,
+        vector: Vec<f32>,
+    ) -> Result<()> {
+        if let Some(db) = DB.get() {
+            db.update_record(table, id, Some(content), Some(vector), None)
+                .await?;
+        } else {
+            panic!("Database not initialized");
+        }
+        Ok(())
+    }
+
+    /// 更新批次内所有数据的标签
+    pub async fn update_tags(table: &str, batch_id: &str, tags: &[String]) -> Result<()> {
+        if let Some(db) = DB.get() {
+            db.update_tags_with_batch_id(table, batch_id, tags).await?;
+        } else {
+            panic!("Database not initialized");
+        }
+        Ok(())
+    }
+
     pub async fn search_data(query: SearchRequest) -> Result<Vec<SearchResult>> {
         if let Some(db) = DB.get() {
             let result = db.search(query).await?;
@@ -90,6 +151,11 @@ impl Engine {
         }
     }
 
+    /// 设置向量索引配置，表的行数达到阈值后在优化时自动建立向量索引
+    pub fn set_vector_index_options(options: VectorIndexOptions) {
+        db::set_vector_index_options(options);
+    }
+
     pub async fn table_info(table: &str) -> Result<TableInfo> {
         if let Some(db) = DB.get() {
             let result = db.table_info(table).await?;
@@ -105,6 +171,10 @@ pub async fn init() {
     let db = Database::new(database_dir)
         .await
         .expect("Failed to create database");
+    // 升级失败的表在下次打开时重试
+    if let Err(e) = db.upgrade_tables().await {
+        log::error!("Upgrade tables error: {}", e);
+    }
     DB.get_or_init(|| db);
 }
 
@@ -114,8 +184,12 @@ impl TableEngine {
     /// 创建表
     /// - db_name: 数据库名称
     /// - name: 表名
-    /// - columns: 列名
-    pub async fn new_table(db_name: &str, name: &str, columns: Vec<String>) -> Result<()> {
+    /// - columns: 列名及列类型
+    pub async fn new_table(
+        db_name: &str,
+        name: &str,
+        columns: Vec<ColumnDefinition>,
+    ) -> Result<()> {
         table_db::Database::new_table(db_name, name, columns).await
     }
 
@@ -125,12 +199,12 @@ impl TableEngine {
     ///
     /// - db_name: 数据库名称
     /// - name: 表名
-    /// - columns: 列名
-    /// - rows: 行数据
+    /// - columns: 列名及列类型
+    /// - rows: 行数据，非文本列的值按列类型转换，空值保存为NULL
     pub async fn new_table_with_rows(
         db_name: &str,
         tb_name: &str,
-        columns: Vec<String>,
+        columns: Vec<ColumnDefinition>,
         rows: Vec<Vec<String>>,
     ) -> Result<()> {
         Self::new_table(db_name, tb_name, columns).await?;
@@ -138,12 +212,36 @@ impl TableEngine {
         Ok(())
     }
 
+    /// 创建表并添加数据，同名的表已存在时替换
+    ///
+    /// 数据先写入临时表，全部写入后再替换原表，写入失败时原表保持不变
+    pub async fn replace_table_with_rows(
+        db_name: &str,
+        tb_name: &str,
+        columns: Vec<ColumnDefinition>,
+        rows: Vec<Vec<String>>,
+    ) -> Result<()> {
+        let temp_name = format!("{}__new", tb_name);
+        table_db::Database::drop_table(db_name, &temp_name).await?;
+        if let Err(e) = Self::new_table_with_rows(db_name, &temp_name, columns, rows).await {
+            table_db::Database::drop_table(db_name, &temp_name).await?;
+            return Err(e);
+        }
+        table_db::Database::replace_table(db_name, &temp_name, tb_name).await?;
+        Ok(())
+    }
+
     /// 删除表
     pub async fn drop_table(db_name: &str, table_name: &str) -> Result<()> {
         table_db::Database::drop_table(db_name, table_name).await?;
         Ok(())
     }
 
+    /// 删除数据库及其中的所有表
+    pub async fn drop_db(db_name: &str) {
+        table_db::Database::drop_db(db_name).await
+    }
+
     /// 添加数据
     pub async fn add_rows(db_name: &str, table: &str, rows: Vec<Vec<String>>) -> Result<()> {
         table_db::Database::add_data(db_name, table, rows).await?;
@@ -157,9 +255,23 @@ impl TableEngine {
         unimplemented!()
     }
 
-    /// 查询数据
-    pub async fn query(db_name: &str, query: &String) -> Result<Vec<Vec<String>>> {
-        table_db::Database::query(db_name, query).await
+    /// 只读查询数据
+    ///
+    /// 仅允许单条SELECT语句，最多返回200行，超过10秒中断查询
+    pub async fn query(db_name: &str, query: &String) -> Result<QueryResult> {
+        table_db::Database::query(
+            db_name,
+            query,
+            table_db::DEFAULT_QUERY_MAX_ROWS,
+            table_db::DEFAULT_QUERY_TIMEOUT,
+        )
+        .await
+    }
+
+    /// 获取数据库中所有表的列及示例数据
+    /// - sample_rows：每张表返回的示例数据行数
+    pub async fn describe(db_name: &str, sample_rows: usize) -> Result<Vec<TableSchema>> {
+        table_db::Database::describe(db_name, sample_rows).await
     }
 
     /// 获取数据库信息
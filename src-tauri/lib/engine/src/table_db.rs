@@ -1,9 +1,16 @@
+use anyhow::bail;
 use common::data_dir;
+pub use common::table::ColumnType;
 use futures_util::StreamExt;
 use itertools::Itertools;
-use sqlx::{Connection, Executor, Row, SqliteConnection};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteRow};
+use sqlx::{Column, Connection, Executor, Row, SqliteConnection, TypeInfo, ValueRef};
 use std::fs::File;
+use std::str::FromStr;
 use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 static DB_PATH: LazyLock<String> = LazyLock::new(|| {
     let current_dir = std::env::current_exe().unwrap();
@@ -15,6 +22,34 @@ const SQL_CREATE_TABLE_STATISTICS: &str = "CREATE TABLE IF NOT EXISTS _statistic
             row_count INTEGER,
             char_count INTEGER
         )";
+/// 统计表名称，只读查询中不允许访问
+const STATISTICS_TABLE: &str = "_statistics_";
+/// 只读查询默认最多返回的行数
+pub const DEFAULT_QUERY_MAX_ROWS: usize = 200;
+/// 只读查询默认的超时时间
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// 只读查询中不允许出现的关键字，只读连接之外的第二道防线
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "insert",
+    "update",
+    "delete",
+    "drop",
+    "create",
+    "alter",
+    "attach",
+    "detach",
+    "pragma",
+    "vacuum",
+    "reindex",
+    "begin",
+    "commit",
+    "rollback",
+    "savepoint",
+    "release",
+];
+/// 执行多少条虚拟机指令检查一次超时
+const PROGRESS_HANDLER_OPS: i32 = 1000;
+
 pub struct Database {}
 
 impl Database {
@@ -33,7 +68,6 @@ impl Database {
     }
 
     /// 删除数据库
-    #[allow(unused)]
     pub async fn drop_db(db_name: &str) {
         if db_name.is_empty() {
             return;
@@ -48,16 +82,16 @@ impl Database {
     pub async fn new_table(
         db_name: &str,
         table_name: &str,
-        columns: Vec<String>,
+        columns: Vec<ColumnDefinition>,
     ) -> crate::Result<()> {
         let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
         let sql = &format!(
-            "CREATE TABLE IF NOT EXISTS \"{}\" ({})",
-            table_name,
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_identifier(table_name),
             columns
                 .iter()
-                .filter(|c| c.trim() != "")
-                .map(|c| { format!("\"{}\" TEXT", c) })
+                .filter(|c| c.name.trim() != "")
+                .map(|c| format!("{} {}", quote_identifier(&c.name), c.column_type.sql_type()))
                 .join(",")
         );
 
@@ -69,11 +103,11 @@ impl Database {
     /// 表是否存在
     pub async fn exists_table(db_name: &str, table_name: &str) -> crate::Result<bool> {
         let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
-        let sql = &format!(
-            "SELECT name FROM sqlite_master WHERE type='table' AND name='{}'",
-            table_name
-        );
-        let exists = sqlx::query(sql).fetch_one(&mut conn).await.is_ok();
+        let exists = sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name=?")
+            .bind(table_name)
+            .fetch_one(&mut conn)
+            .await
+            .is_ok();
         conn.close().await?;
 
         Ok(exists)
@@ -85,8 +119,11 @@ impl Database {
             return Ok(());
         }
         let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
-        conn.execute(sqlx::query(&format!("DROP TABLE \"{}\"", table_name)))
-            .await?;
+        conn.execute(sqlx::query(&format!(
+            "DROP TABLE {}",
+            quote_identifier(table_name)
+        )))
+        .await?;
         conn.close().await?;
 
         Self::delete_statistics(db_name, table_name).await?;
@@ -94,6 +131,36 @@ impl Database {
         Ok(())
     }
 
+    /// 用一张表替换另一张表，目标表不存在时直接重命名
+    ///
+    /// 删除目标表、重命名及更新统计数据在同一个事务中完成
+    pub async fn replace_table(db_name: &str, from: &str, to: &str) -> crate::Result<()> {
+        let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", quote_identifier(to)))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "ALTER TABLE {} RENAME TO {}",
+            quote_identifier(from),
+            quote_identifier(to)
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM _statistics_ WHERE table_name = ?")
+            .bind(to.to_string())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE _statistics_ SET table_name = ? WHERE table_name = ?")
+            .bind(to.to_string())
+            .bind(from.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        conn.close().await?;
+        Ok(())
+    }
+
     /// 添加数据
     pub async fn add_data(
         db_name: &str,
@@ -102,13 +169,42 @@ impl Database {
     ) -> crate::Result<()> {
         let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
 
+        // 按列的声明类型绑定值，旧版本创建的表均为TEXT
+        let column_types =
+            sqlx::query_scalar::<_, String>("SELECT type FROM pragma_table_info(?1)")
+                .bind(table_name)
+                .fetch_all(&mut conn)
+                .await?
+                .iter()
+                .map(|t| ColumnType::from_sql_type(t))
+                .collect::<Vec<_>>();
+
         for row in &data {
             let placeholders = (0..row.len()).map(|i| format!("?{}", i + 1)).join(",");
-            let sql = format!("INSERT INTO \"{}\" VALUES ({})", table_name, placeholders);
+            let sql = format!(
+                "INSERT INTO {} VALUES ({})",
+                quote_identifier(table_name),
+                placeholders
+            );
 
             let mut query = sqlx::query(sql.as_str());
-            for value in row {
-                query = query.bind(value);
+            for (i, value) in row.iter().enumerate() {
+                let column_type = column_types.get(i).copied().unwrap_or(ColumnType::Text);
+                let value = value.trim();
+                query = match column_type {
+                    ColumnType::Text => query.bind(value.to_string()),
+                    _ if value.is_empty() => query.bind(None::<String>),
+                    // 无法转换的值按原样保存
+                    ColumnType::Integer => match value.parse::<i64>() {
+                        Ok(value) => query.bind(value),
+                        Err(_) => query.bind(value.to_string()),
+                    },
+                    ColumnType::Real => match value.parse::<f64>() {
+                        Ok(value) => query.bind(value),
+                        Err(_) => query.bind(value.to_string()),
+                    },
+                    ColumnType::Date => query.bind(value.to_string()),
+                };
             }
             conn.execute(query).await?;
         }
@@ -127,27 +223,117 @@ impl Database {
         Ok(())
     }
 
-    /// 查询
-    pub async fn query(db_name: &str, query: &str) -> crate::Result<Vec<Vec<String>>> {
-        let mut conn = SqliteConnection::connect(&Self::get_db(db_name).await?).await?;
-        let mut result = Vec::new();
-        let mut rows = sqlx::query(query).fetch(&mut conn);
+    /// 以只读方式打开数据库
+    async fn connect_readonly(db_name: &str) -> crate::Result<SqliteConnection> {
+        let options =
+            SqliteConnectOptions::from_str(&Self::get_db(db_name).await?)?.read_only(true);
+        Ok(SqliteConnection::connect_with(&options).await?)
+    }
 
-        while let Some(row) = rows.next().await {
-            let row = row?;
-            let mut row_data = Vec::with_capacity(row.columns().len());
+    /// 只读查询
+    ///
+    /// 仅允许单条SELECT语句，不允许访问统计表，连接以只读方式打开。
+    /// - max_rows：最多返回的行数，超出部分丢弃并标记为截断
+    /// - timeout：超时时间，超时后中断查询
+    pub async fn query(
+        db_name: &str,
+        query: &str,
+        max_rows: usize,
+        timeout: Duration,
+    ) -> crate::Result<QueryResult> {
+        check_readonly_sql(query)?;
+
+        let mut conn = Self::connect_readonly(db_name).await?;
+        let deadline = Instant::now() + timeout;
+        conn.lock_handle()
+            .await?
+            .set_progress_handler(PROGRESS_HANDLER_OPS, move || Instant::now() < deadline);
 
-            for (i, _) in row.columns().iter().enumerate() {
-                row_data.push(row.try_get::<String, _>(i)?);
+        let result = Self::fetch(&mut conn, query, max_rows).await;
+        conn.close().await?;
+        match result {
+            Err(_) if Instant::now() >= deadline => {
+                bail!("查询超时，超过{}秒", timeout.as_secs())
             }
+            result => result,
+        }
+    }
+
+    async fn fetch(
+        conn: &mut SqliteConnection,
+        query: &str,
+        max_rows: usize,
+    ) -> crate::Result<QueryResult> {
+        let mut result = QueryResult::default();
+        let mut rows = sqlx::query(query).fetch(conn);
 
-            result.push(row_data);
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            if result.columns.is_empty() {
+                result.columns = row.columns().iter().map(|c| c.name().to_string()).collect();
+            }
+            if result.rows.len() >= max_rows {
+                result.truncated = true;
+                break;
+            }
+            result.rows.push(row_values(&row));
         }
-        //conn.close().await?;
 
         Ok(result)
     }
 
+    /// 获取数据库中所有表的结构及示例数据
+    /// - sample_rows：每张表返回的示例数据行数
+    pub async fn describe(db_name: &str, sample_rows: usize) -> crate::Result<Vec<TableSchema>> {
+        let mut conn = Self::connect_readonly(db_name).await?;
+        let table_names = sqlx::query_scalar::<_, String>(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name <> ?1 AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .bind(STATISTICS_TABLE)
+        .fetch_all(&mut conn)
+        .await?;
+
+        let mut tables = Vec::with_capacity(table_names.len());
+        for name in table_names {
+            let columns = sqlx::query("SELECT name, type FROM pragma_table_info(?1)")
+                .bind(&name)
+                .fetch_all(&mut conn)
+                .await?
+                .iter()
+                .map(|row| ColumnSchema {
+                    name: row.get::<String, _>(0),
+                    data_type: row.get::<String, _>(1),
+                })
+                .collect();
+            let row_count = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT COUNT(*) FROM {}",
+                quote_identifier(&name)
+            ))
+            .fetch_one(&mut conn)
+            .await? as usize;
+            let sample_rows = sqlx::query(&format!(
+                "SELECT * FROM {} LIMIT {}",
+                quote_identifier(&name),
+                sample_rows
+            ))
+            .fetch_all(&mut conn)
+            .await?
+            .iter()
+            .map(row_values)
+            .collect();
+
+            tables.push(TableSchema {
+                name,
+                columns,
+                row_count,
+                sample_rows,
+            });
+        }
+        conn.close().await?;
+
+        Ok(tables)
+    }
+
     /// 创建一张统计表，统计整个数据下的所有表行数，字符数
     pub async fn create_statistics_table_if_not_exists(
         db_connection_str: &str,
@@ -204,8 +390,212 @@ impl Database {
     }
 }
 
+/// 只读查询的结果
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    /// 列名，没有数据时为空
+    pub columns: Vec<String>,
+    /// 行数据，值保留数据库中的类型
+    pub rows: Vec<Vec<Value>>,
+    /// 结果是否因超出行数限制被截断
+    pub truncated: bool,
+}
+
+/// 表结构
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSchema {
+    /// 表名
+    pub name: String,
+    /// 列
+    pub columns: Vec<ColumnSchema>,
+    /// 总行数
+    pub row_count: usize,
+    /// 示例数据
+    pub sample_rows: Vec<Vec<Value>>,
+}
+
+/// 列结构
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnSchema {
+    /// 列名
+    pub name: String,
+    /// 声明的类型
+    pub data_type: String,
+}
+
+/// 列定义
+#[derive(Debug, Clone)]
+pub struct ColumnDefinition {
+    /// 列名
+    pub name: String,
+    /// 列类型
+    pub column_type: ColumnType,
+}
+
+/// 将一行数据转换为JSON值，按值在数据库中的实际存储类型转换
+fn row_values(row: &SqliteRow) -> Vec<Value> {
+    (0..row.columns().len())
+        .map(|i| {
+            let storage_type = match row.try_get_raw(i) {
+                Ok(value) if !value.is_null() => value.type_info().name().to_string(),
+                _ => return Value::Null,
+            };
+            match storage_type.as_str() {
+                "INTEGER" => row
+                    .try_get_unchecked::<i64, _>(i)
+                    .map(Value::from)
+                    .unwrap_or_default(),
+                "REAL" => row
+                    .try_get_unchecked::<f64, _>(i)
+                    .map(Value::from)
+                    .unwrap_or_default(),
+                "TEXT" => row
+                    .try_get_unchecked::<String, _>(i)
+                    .map(Value::from)
+                    .unwrap_or_default(),
+                // 二进制数据对查询没有意义
+                _ => Value::Null,
+            }
+        })
+        .collect()
+}
+
+/// 标识符，双引号转义为两个双引号
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// SQL中的词
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// 未加引号的关键字或标识符，已转为小写
+    Word(String),
+    /// 加引号的标识符，已转为小写
+    Identifier(String),
+    /// 语句分隔符
+    Semicolon,
+}
+
+/// 将SQL拆分为词，跳过注释和字符串字面量
+fn tokenize(sql: &str) -> crate::Result<Vec<Token>> {
+    let chars = sql.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    // 从start开始查找结束符，返回结束符之后的位置，连续两个结束符视为转义
+    let find_end = |start: usize, end: char| -> crate::Result<usize> {
+        let mut j = start;
+        while j < chars.len() {
+            if chars[j] == end {
+                if end != ']' && chars.get(j + 1) == Some(&end) {
+                    j += 2;
+                    continue;
+                }
+                return Ok(j + 1);
+            }
+            j += 1;
+        }
+        bail!("SQL语句不完整")
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&j| chars[j] == '*' && chars[j + 1] == '/');
+                match end {
+                    Some(end) => i = end + 2,
+                    None => bail!("SQL语句不完整"),
+                }
+            }
+            '\'' => i = find_end(i + 1, '\'')?,
+            '"' | '`' | '[' => {
+                let end = find_end(i + 1, if c == '[' { ']' } else { c })?;
+                let name = chars[i + 1..end - 1].iter().collect::<String>();
+                let name = if c == '[' {
+                    name
+                } else {
+                    name.replace(&format!("{}{}", c, c), &c.to_string())
+                };
+                tokens.push(Token::Identifier(name.to_lowercase()));
+                i = end;
+            }
+            ';' => {
+                tokens.push(Token::Semicolon);
+                i += 1;
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Word(word.to_lowercase()));
+            }
+            _ => i += 1,
+        }
+    }
+    Ok(tokens)
+}
+
+/// 检查SQL是否为只读的单条查询语句
+fn check_readonly_sql(sql: &str) -> crate::Result<()> {
+    let mut tokens = tokenize(sql)?;
+    while tokens.last() == Some(&Token::Semicolon) {
+        tokens.pop();
+    }
+    match tokens.first() {
+        Some(Token::Word(word)) if word == "select" || word == "with" => {}
+        _ => bail!("仅允许执行SELECT查询语句"),
+    }
+    for token in &tokens {
+        match token {
+            Token::Semicolon => bail!("仅允许执行单条SQL语句"),
+            Token::Word(word) if FORBIDDEN_KEYWORDS.contains(&word.as_str()) => {
+                bail!(
+                    "仅允许执行SELECT查询语句，不允许使用{}",
+                    word.to_uppercase()
+                )
+            }
+            Token::Word(name) | Token::Identifier(name) if name == STATISTICS_TABLE => {
+                bail!("不允许查询统计表")
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct DbInfo {
     pub total_rows: usize,
     pub total_chars: usize,
 }
+
+#[test]
+fn test_check_readonly_sql() {
+    assert!(check_readonly_sql("SELECT \"名称\", COUNT(*) FROM \"表1\" WHERE a = 'drop';").is_ok());
+    assert!(check_readonly_sql("with t as (select 1) select * from t").is_ok());
+    assert!(check_readonly_sql("-- 注释\nSELECT 1").is_ok());
+    assert!(check_readonly_sql("DROP TABLE \"表1\"").is_err());
+    assert!(check_readonly_sql("SELECT 1; DELETE FROM \"表1\"").is_err());
+    assert!(check_readonly_sql("WITH t AS (SELECT 1) DELETE FROM \"表1\"").is_err());
+    assert!(check_readonly_sql("SELECT * FROM \"_statistics_\"").is_err());
+    assert!(check_readonly_sql("SELECT * FROM _STATISTICS_").is_err());
+}
+
+#[test]
+fn test_quote_identifier() {
+    assert_eq!(quote_identifier("表1"), "\"表1\"");
+    assert_eq!(
+        quote_identifier("a\"; DROP TABLE b; --"),
+        "\"a\"\"; DROP TABLE b; --\""
+    );
+}
@@ -0,0 +1,439 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// 默认分段大小
+pub const DEFAULT_CHUNK_SIZE: usize = 512;
+/// 默认分段重叠大小
+pub const DEFAULT_CHUNK_OVERLAP: usize = 64;
+
+/// 递归分段使用的分隔符，按优先级排列：换行 > 句子 > 分句 > 空白
+const RECURSIVE_SEPARATORS: &[&[char]] = &[
+    &['\n'],
+    &['。', '！', '？', '!', '?', '.'],
+    &['；', ';', '，', ',', '、'],
+    &[' ', '\t'],
+];
+
+/// 分段策略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// 按固定字符数切分
+    Fixed,
+    /// 按换行、句子、分句、空白依次递归切分，尽量保持语义完整
+    #[default]
+    Recursive,
+    /// 按Markdown标题切分，每个章节单独分段，章节过长时递归切分
+    MarkdownHeading,
+    /// 按中英文句子边界切分
+    Sentence,
+    /// 按token数量切分，token数为近似值：中文按字、英文按单词、标点单独计数
+    Token,
+}
+
+/// 分段参数
+#[derive(Debug, Clone)]
+pub struct ChunkOptions {
+    /// 分段策略
+    pub strategy: ChunkStrategy,
+    /// 分段大小，Token策略下为token数，其他策略下为字符数
+    pub chunk_size: usize,
+    /// 相邻分段的重叠大小，单位同chunk_size
+    pub chunk_overlap: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        ChunkOptions {
+            strategy: ChunkStrategy::default(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chunk_overlap: DEFAULT_CHUNK_OVERLAP,
+        }
+    }
+}
+
+impl ChunkOptions {
+    /// 修正不合法的参数：分段大小至少为1，重叠必须小于分段大小
+    fn normalize(&self) -> (usize, usize) {
+        let size = self.chunk_size.max(1);
+        let overlap = self.chunk_overlap.min(size / 2);
+        (size, overlap)
+    }
+}
+
+/// 将文本分段
+pub fn split(text: &str, options: &ChunkOptions) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    split_ranges(&chars, options)
+        .into_iter()
+        .filter_map(|range| {
+            let chunk = chars[range].iter().collect::<String>();
+            let chunk = chunk.trim();
+            if chunk.is_empty() {
+                None
+            } else {
+                Some(chunk.to_string())
+            }
+        })
+        .collect()
+}
+
+/// Markdown分段
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownChunk {
+    /// 分段文本
+    pub text: String,
+    /// 分段所在的标题路径，从一级标题开始
+    pub heading_path: Vec<String>,
+}
+
+/// 将Markdown文本分段，并记录每个分段所在的标题路径
+pub fn split_markdown(text: &str, options: &ChunkOptions) -> Vec<MarkdownChunk> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let headings = markdown_headings(&chars);
+    split_ranges(&chars, options)
+        .into_iter()
+        .filter_map(|range| {
+            let chunk = chars[range.clone()].iter().collect::<String>();
+            let chunk = chunk.trim();
+            if chunk.is_empty() {
+                return None;
+            }
+            // 分段开头的空白不计入，以便以标题开头的分段包含该标题
+            let start = range.start
+                + chars[range.clone()]
+                    .iter()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+            let mut heading_path: Vec<(usize, String)> = vec![];
+            for (offset, level, title) in headings.iter() {
+                if *offset > start {
+                    break;
+                }
+                heading_path.retain(|(l, _)| l < level);
+                heading_path.push((*level, title.clone()));
+            }
+            Some(MarkdownChunk {
+                text: chunk.to_string(),
+                heading_path: heading_path.into_iter().map(|(_, title)| title).collect(),
+            })
+        })
+        .collect()
+}
+
+/// 将文本分段，返回每个分段在字符数组中的范围
+///
+/// 用于需要将分段映射回原始位置的场景，比如PDF分段需要知道分段所在的页
+pub fn split_ranges(chars: &[char], options: &ChunkOptions) -> Vec<Range<usize>> {
+    if chars.is_empty() {
+        return vec![];
+    }
+    let (size, overlap) = options.normalize();
+    let all = 0..chars.len();
+    match options.strategy {
+        ChunkStrategy::Fixed => fixed(all, size, overlap),
+        ChunkStrategy::Recursive => {
+            let units = recursive_units(chars, all, size, 0);
+            merge(units, size, overlap, |r| r.len())
+        }
+        ChunkStrategy::MarkdownHeading => markdown_sections(chars)
+            .into_iter()
+            .flat_map(|section| {
+                let units = recursive_units(chars, section, size, 0);
+                merge(units, size, overlap, |r| r.len())
+            })
+            .collect(),
+        ChunkStrategy::Sentence => {
+            let units = sentences(chars, all)
+                .into_iter()
+                .flat_map(|sentence| {
+                    if sentence.len() > size {
+                        fixed(sentence, size, 0)
+                    } else {
+                        vec![sentence]
+                    }
+                })
+                .collect();
+            merge(units, size, overlap, |r| r.len())
+        }
+        ChunkStrategy::Token => {
+            let tokens = tokens(chars);
+            merge(tokens, size, overlap, |_| 1)
+        }
+    }
+}
+
+/// 按固定长度切分
+fn fixed(range: Range<usize>, size: usize, overlap: usize) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let step = size - overlap;
+    let mut start = range.start;
+    while start < range.end {
+        let end = (start + size).min(range.end);
+        result.push(start..end);
+        if end == range.end {
+            break;
+        }
+        start += step;
+    }
+    result
+}
+
+/// 使用分隔符递归切分，直到每一段都不超过size
+fn recursive_units(
+    chars: &[char],
+    range: Range<usize>,
+    size: usize,
+    level: usize,
+) -> Vec<Range<usize>> {
+    if range.len() <= size {
+        return vec![range];
+    }
+    if level >= RECURSIVE_SEPARATORS.len() {
+        return fixed(range, size, 0);
+    }
+    let separators = RECURSIVE_SEPARATORS[level];
+    let pieces = split_after(chars, range.clone(), |c| separators.contains(&c));
+    if pieces.len() <= 1 {
+        return recursive_units(chars, range, size, level + 1);
+    }
+    pieces
+        .into_iter()
+        .flat_map(|piece| recursive_units(chars, piece, size, level + 1))
+        .collect()
+}
+
+/// 在满足条件的字符之后切开，分隔符保留在前一段末尾
+fn split_after(
+    chars: &[char],
+    range: Range<usize>,
+    is_separator: impl Fn(char) -> bool,
+) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let mut start = range.start;
+    for i in range.clone() {
+        if is_separator(chars[i]) {
+            result.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start < range.end {
+        result.push(start..range.end);
+    }
+    result
+}
+
+/// 按中英文句子边界切分
+///
+/// 中文以。！？；及换行结尾，英文以.!?;后跟空白结尾，避免切开小数和缩写
+fn sentences(chars: &[char], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let mut start = range.start;
+    for i in range.clone() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let is_end = match c {
+            '。' | '！' | '？' | '；' | '\n' => true,
+            '.' | '!' | '?' | ';' => next.map(|n| n.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+        // 连续的结束符号（如“？！”、“。”）归入同一句
+        let next_is_end = matches!(
+            next,
+            Some('。' | '！' | '？' | '”' | '’' | '"' | '）' | ')')
+        );
+        if is_end && !next_is_end {
+            result.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start < range.end {
+        result.push(start..range.end);
+    }
+    result
+}
+
+/// 按Markdown标题切分章节
+fn markdown_sections(chars: &[char]) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let mut start = 0;
+    for (offset, _, _) in markdown_headings(chars) {
+        if offset > start {
+            result.push(start..offset);
+            start = offset;
+        }
+    }
+    if start < chars.len() {
+        result.push(start..chars.len());
+    }
+    result
+}
+
+/// 查找所有Markdown标题，返回标题所在行的起始位置、级别和标题文本，代码块中的#不视为标题
+fn markdown_headings(chars: &[char]) -> Vec<(usize, usize, String)> {
+    let mut result = vec![];
+    let mut in_code_block = false;
+    let mut line_start = 0;
+    while line_start < chars.len() {
+        let line_end = chars[line_start..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map(|p| line_start + p + 1)
+            .unwrap_or(chars.len());
+        let line = chars[line_start..line_end].iter().collect::<String>();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block && is_heading(trimmed) {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let title = trimmed[level..].trim().trim_end_matches('#').trim();
+            result.push((line_start, level, title.to_string()));
+        }
+        line_start = line_end;
+    }
+    result
+}
+
+/// 是否为Markdown标题行：1到6个#后跟空白
+fn is_heading(line: &str) -> bool {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    (1..=6).contains(&level)
+        && line
+            .chars()
+            .nth(level)
+            .map(|c| c.is_whitespace())
+            .unwrap_or(false)
+}
+
+/// 近似的token切分：连续的字母数字为一个token，其他非空白字符各为一个token
+///
+/// 返回的范围首尾相接，空白归入前一个token
+fn tokens(chars: &[char]) -> Vec<Range<usize>> {
+    let mut result: Vec<Range<usize>> = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_ascii_alphanumeric() {
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+        } else {
+            i += 1;
+        }
+        // 空白不单独计数
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if c.is_whitespace() {
+            match result.last_mut() {
+                Some(last) => last.end = i,
+                None => result.push(start..i),
+            }
+        } else {
+            result.push(start..i);
+        }
+    }
+    result
+}
+
+/// 将相邻的小段合并为不超过size的分段，相邻分段之间保留不超过overlap的重叠
+///
+/// - measure: 计算每一小段的大小
+fn merge(
+    units: Vec<Range<usize>>,
+    size: usize,
+    overlap: usize,
+    measure: impl Fn(&Range<usize>) -> usize,
+) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let mut current: VecDeque<Range<usize>> = VecDeque::new();
+    let mut current_size = 0;
+    for unit in units {
+        let unit_size = measure(&unit);
+        if current_size + unit_size > size && !current.is_empty() {
+            result.push(current.front().unwrap().start..current.back().unwrap().end);
+            // 保留末尾的小段作为下一个分段的开头
+            while let Some(front) = current.front() {
+                if current_size > overlap || current_size + unit_size > size {
+                    current_size -= measure(front);
+                    current.pop_front();
+                } else {
+                    break;
+                }
+            }
+        }
+        current_size += unit_size;
+        current.push_back(unit);
+    }
+    if !current.is_empty() {
+        result.push(current.front().unwrap().start..current.back().unwrap().end);
+    }
+    result
+}
+
+#[test]
+fn test_split_fixed() {
+    let options = ChunkOptions {
+        strategy: ChunkStrategy::Fixed,
+        chunk_size: 4,
+        chunk_overlap: 1,
+    };
+    assert_eq!(split("abcdefghij", &options), vec!["abcd", "defg", "ghij"]);
+}
+
+#[test]
+fn test_split_sentence() {
+    let options = ChunkOptions {
+        strategy: ChunkStrategy::Sentence,
+        chunk_size: 16,
+        chunk_overlap: 0,
+    };
+    let chunks = split("今天天气很好。我们去公园吧！Pi is 3.14. OK?", &options);
+    assert_eq!(
+        chunks,
+        vec!["今天天气很好。我们去公园吧！", "Pi is 3.14. OK?"]
+    );
+}
+
+#[test]
+fn test_split_markdown_heading() {
+    let options = ChunkOptions {
+        strategy: ChunkStrategy::MarkdownHeading,
+        chunk_size: 100,
+        chunk_overlap: 0,
+    };
+    let text = "# A\nintro\n```\n# not heading\n```\n## B\nbody";
+    let chunks = split(text, &options);
+    assert_eq!(
+        chunks,
+        vec!["# A\nintro\n```\n# not heading\n```", "## B\nbody"]
+    );
+}
+
+#[test]
+fn test_split_token() {
+    let options = ChunkOptions {
+        strategy: ChunkStrategy::Token,
+        chunk_size: 3,
+        chunk_overlap: 1,
+    };
+    let chunks = split("hello world 你好世界", &options);
+    assert_eq!(chunks, vec!["hello world 你", "你好世", "世界"]);
+}
+
+#[test]
+fn test_split_markdown_heading_path() {
+    let options = ChunkOptions {
+        strategy: ChunkStrategy::MarkdownHeading,
+        chunk_size: 100,
+        chunk_overlap: 0,
+    };
+    let text = "# A\nintro\n## B\nbody\n# C\nend";
+    let chunks = split_markdown(text, &options)
+        .into_iter()
+        .map(|c| c.heading_path)
+        .collect::<Vec<_>>();
+    assert_eq!(chunks, vec![vec!["A"], vec!["A", "B"], vec!["C"]]);
+}
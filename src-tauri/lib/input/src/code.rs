@@ -0,0 +1,1122 @@
+//! 源代码及结构化数据
+//!
+//! 按函数、类、顶层键或子元素等语法边界分段，并记录每个分段所在的符号。
+//! 不做完整的语法分析，只根据括号、缩进及XML标签识别边界，
+//! 超出分段大小的定义先按其内部的定义切分，仍然过长时按行递归切分。
+
+use crate::chunk::{self, ChunkOptions, ChunkStrategy};
+use crate::{Input, txt};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+pub struct CodeInput;
+
+impl Input for CodeInput {
+    type Output = crate::Result<String>;
+
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let bytes = fs::read(path.as_ref())?;
+        Ok(txt::decode(bytes))
+    }
+}
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Rust,
+    Python,
+    TypeScript,
+    JavaScript,
+    Go,
+    Java,
+    Json,
+    Yaml,
+    Xml,
+}
+
+impl Language {
+    /// 根据文件扩展名确定语言，不支持的扩展名返回None
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let language = match ext.to_lowercase().as_str() {
+            "rs" => Language::Rust,
+            "py" | "pyi" => Language::Python,
+            "ts" | "tsx" | "mts" | "cts" => Language::TypeScript,
+            "js" | "jsx" | "mjs" | "cjs" => Language::JavaScript,
+            "go" => Language::Go,
+            "java" => Language::Java,
+            "json" => Language::Json,
+            "yaml" | "yml" => Language::Yaml,
+            "xml" => Language::Xml,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    /// 符号路径的分隔符
+    fn separator(self) -> &'static str {
+        match self {
+            Language::Rust => "::",
+            _ => ".",
+        }
+    }
+}
+
+/// 代码分段
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChunk {
+    /// 分段文本
+    pub text: String,
+    /// 分段包含的符号，如函数名、类名或顶层键，多个符号以逗号分隔，未识别到符号时为None
+    pub symbol: Option<String>,
+    /// 分段的起始行号，从1开始
+    pub start_line: usize,
+}
+
+/// 语法树节点，只记录范围及符号
+#[derive(Debug)]
+struct Node {
+    /// 在文本中的字节范围
+    range: Range<usize>,
+    symbol: Option<String>,
+    children: Vec<Node>,
+}
+
+/// 将源代码或结构化数据分段
+///
+/// 相邻的较小定义合并为一个分段，合并后不超过分段大小，分段之间不重叠
+pub fn split_code(text: &str, language: Language, options: &ChunkOptions) -> Vec<CodeChunk> {
+    let nodes = match language {
+        Language::Python | Language::Yaml => indent_nodes(text, language),
+        Language::Json => json_nodes(text),
+        Language::Xml => xml_nodes(text),
+        _ => brace_nodes(text, language),
+    };
+    let mut chunker = Chunker {
+        text,
+        size: options.chunk_size.max(1),
+        options,
+        separator: language.separator(),
+        pending: None,
+        chunks: vec![],
+    };
+    if nodes.is_empty() {
+        chunker.split_leaf(0..text.len(), None);
+    } else {
+        chunker.add_nodes(&nodes, None);
+    }
+    chunker.flush();
+    chunker.chunks
+}
+
+/// 按节点合并及切分分段
+struct Chunker<'a> {
+    text: &'a str,
+    /// 分段大小，按字符计算
+    size: usize,
+    options: &'a ChunkOptions,
+    separator: &'static str,
+    /// 正在合并的分段及其包含的符号
+    pending: Option<(Range<usize>, Vec<String>)>,
+    chunks: Vec<CodeChunk>,
+}
+
+impl Chunker<'_> {
+    fn add_nodes(&mut self, nodes: &[Node], parent: Option<&str>) {
+        for node in nodes {
+            let symbol = match (parent, &node.symbol) {
+                (Some(parent), Some(name)) if name.starts_with('[') => {
+                    Some(format!("{}{}", parent, name))
+                }
+                (Some(parent), Some(name)) => Some(format!("{}{}{}", parent, self.separator, name)),
+                (None, Some(name)) => Some(name.clone()),
+                (parent, None) => parent.map(|p| p.to_string()),
+            };
+            if self.len(node.range.clone()) <= self.size {
+                self.add(node.range.clone(), symbol);
+                continue;
+            }
+            match (node.children.first(), node.children.last()) {
+                (Some(first), Some(last)) => {
+                    // 定义的开头，如函数签名、类声明，可以与前面的分段合并
+                    self.add(node.range.start..first.range.start, symbol.clone());
+                    self.add_nodes(&node.children, symbol.as_deref());
+                    // 定义的结尾，如右括号，只合并到前一个分段
+                    let tail = last.range.end..node.range.end;
+                    if !self.text[tail.clone()].trim().is_empty() {
+                        self.add(tail, None);
+                    }
+                }
+                _ => {
+                    self.flush();
+                    self.split_leaf(node.range.clone(), symbol);
+                }
+            }
+        }
+    }
+
+    fn len(&self, range: Range<usize>) -> usize {
+        self.text[range].chars().count()
+    }
+
+    /// 添加一个不超过分段大小的片段，能与前一个分段合并时合并
+    fn add(&mut self, range: Range<usize>, symbol: Option<String>) {
+        if self.len(range.clone()) > self.size {
+            self.flush();
+            self.split_leaf(range, symbol);
+            return;
+        }
+        let separator = self.separator;
+        if let Some((pending, symbols)) = &mut self.pending
+            && self.text[pending.start..range.end].chars().count() <= self.size
+        {
+            pending.end = range.end;
+            if let Some(symbol) = symbol {
+                // 内部定义的符号包含了父定义的符号
+                symbols.retain(|s| !is_parent(s, &symbol, separator));
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+            return;
+        }
+        self.flush();
+        self.pending = Some((range, symbol.into_iter().collect()));
+    }
+
+    fn flush(&mut self) {
+        if let Some((range, symbols)) = self.pending.take() {
+            self.push(range, symbols);
+        }
+    }
+
+    /// 没有内部定义的过长片段按行递归切分
+    fn split_leaf(&mut self, range: Range<usize>, symbol: Option<String>) {
+        let text = &self.text[range.clone()];
+        let chars = text.chars().collect::<Vec<_>>();
+        // 字符位置 -> 字节位置
+        let offsets = text
+            .char_indices()
+            .map(|(i, _)| range.start + i)
+            .chain([range.end])
+            .collect::<Vec<_>>();
+        let options = ChunkOptions {
+            strategy: ChunkStrategy::Recursive,
+            chunk_size: self.size,
+            chunk_overlap: self.options.chunk_overlap,
+        };
+        for part in chunk::split_ranges(&chars, &options) {
+            self.push(
+                offsets[part.start]..offsets[part.end],
+                symbol.iter().cloned().collect(),
+            );
+        }
+    }
+
+    fn push(&mut self, range: Range<usize>, symbols: Vec<String>) {
+        let text = &self.text[range.clone()];
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return;
+        }
+        // 起始行号不计算分段开头的空行
+        let start = range.start + (text.len() - text.trim_start().len());
+        self.chunks.push(CodeChunk {
+            text: trimmed.to_string(),
+            symbol: if symbols.is_empty() {
+                None
+            } else {
+                Some(symbols.join(", "))
+            },
+            start_line: self.text[..start].matches('\n').count() + 1,
+        });
+    }
+}
+
+/// parent是否为symbol的父符号
+fn is_parent(parent: &str, symbol: &str, separator: &str) -> bool {
+    symbol
+        .strip_prefix(parent)
+        .is_some_and(|rest| rest.starts_with(separator) || rest.starts_with('['))
+}
+
+/// 行的类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    Blank,
+    /// 注释、属性、注解、装饰器等依附于下一行定义的行
+    Leading,
+    Code,
+}
+
+/// 源代码行
+#[derive(Debug)]
+struct Line {
+    /// 不含换行符的字节范围
+    range: Range<usize>,
+    kind: LineKind,
+    /// 行首的嵌套层数，花括号语言为括号层数，缩进语言为缩进宽度
+    level: usize,
+    /// 是否为上一行的延续，如跨行的字符串、括号或以运算符开头的行
+    continuation: bool,
+    /// 是否结束了一条语句，之后的同级行开始新的定义
+    closed: bool,
+    /// 去掉注释及字符串后的代码
+    code: String,
+}
+
+/// 按花括号语言（Rust、TypeScript、Go、Java）的定义构造节点
+fn brace_nodes(text: &str, language: Language) -> Vec<Node> {
+    let lines = brace_lines(text, language);
+    group_lines(text, &lines, 0..lines.len(), 0, language)
+}
+
+/// 逐行扫描，跳过字符串及注释，记录每行行首的括号层数
+fn brace_lines(text: &str, language: Language) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut depth = 0usize;
+    // 块注释的嵌套层数
+    let mut comment_depth = 0usize;
+    // 跨行的字符串
+    let mut string: Option<char> = None;
+    let mut offset = 0;
+    for raw in text.split_inclusive('\n') {
+        let line_range = offset..offset + raw.trim_end_matches(['\r', '\n']).len();
+        offset += raw.len();
+        let content = &text[line_range.clone()];
+        let level = depth;
+        let starts_in_comment = comment_depth > 0;
+        let starts_in_string = string.is_some();
+
+        let chars = content.chars().collect::<Vec<_>>();
+        let mut code = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if comment_depth > 0 {
+                if c == '*' && next == Some('/') {
+                    comment_depth -= 1;
+                    i += 2;
+                } else if language == Language::Rust && c == '/' && next == Some('*') {
+                    comment_depth += 1;
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+            if let Some(quote) = string {
+                if c == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if c == quote {
+                    string = None;
+                    code.push(c);
+                }
+                i += 1;
+                continue;
+            }
+            match c {
+                '/' if next == Some('/') => break,
+                '/' if next == Some('*') => {
+                    comment_depth = 1;
+                    i += 2;
+                    continue;
+                }
+                '"' | '`' => {
+                    string = Some(c);
+                    code.push(c);
+                }
+                '\'' => {
+                    // Rust的生命周期以单引号开头，只有字符字面量才跳过
+                    let is_char = language != Language::Rust
+                        || next == Some('\\')
+                        || chars.get(i + 2) == Some(&'\'');
+                    if is_char {
+                        string = Some(c);
+                    }
+                    code.push(c);
+                }
+                '{' | '(' | '[' => {
+                    depth += 1;
+                    code.push(c);
+                }
+                '}' | ')' | ']' => {
+                    depth = depth.saturating_sub(1);
+                    code.push(c);
+                }
+                _ => code.push(c),
+            }
+            i += 1;
+        }
+        // 单引号、双引号字符串不跨行
+        if matches!(string, Some('\''))
+            || (matches!(string, Some('"')) && language != Language::Rust)
+        {
+            string = None;
+        }
+
+        let code = code.trim().to_string();
+        let trimmed = content.trim();
+        let kind = if trimmed.is_empty() {
+            LineKind::Blank
+        } else if code.is_empty() || is_annotation(&code) {
+            LineKind::Leading
+        } else {
+            LineKind::Code
+        };
+        let continuation = starts_in_string
+            || (starts_in_comment && kind == LineKind::Code)
+            || is_continuation(&code);
+        let closed = kind == LineKind::Code && code.ends_with(['}', ';', ')', ',']);
+        lines.push(Line {
+            range: line_range,
+            kind,
+            level,
+            continuation,
+            closed,
+            code,
+        });
+    }
+    lines
+}
+
+/// 属性、注解或装饰器
+fn is_annotation(code: &str) -> bool {
+    (code.starts_with("#[") || code.starts_with("#![") || code.starts_with('@'))
+        && !code.starts_with("@interface")
+}
+
+/// 以运算符、右括号或where等开头的行是上一行的延续
+fn is_continuation(code: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "{",
+        "}",
+        ")",
+        "]",
+        ".",
+        "?",
+        ":",
+        "&&",
+        "||",
+        "+",
+        "-",
+        "*",
+        "/",
+        "=",
+        "|",
+        "where",
+        "extends",
+        "implements",
+        "throws",
+    ];
+    PREFIXES.iter().any(|prefix| code.starts_with(prefix))
+}
+
+/// 将指定范围内的行按同级定义分组，并递归构造内部定义
+///
+/// - lines：行，范围内的行均属于同一个父节点
+/// - level：同级定义的行首层数
+fn group_lines(
+    text: &str,
+    lines: &[Line],
+    range: Range<usize>,
+    level: usize,
+    language: Language,
+) -> Vec<Node> {
+    // 每个定义的行范围
+    let mut groups: Vec<Range<usize>> = vec![];
+    // 上一个非空行
+    let mut previous: Option<&Line> = None;
+    let mut blank_before = false;
+    for index in range {
+        let line = &lines[index];
+        if line.kind == LineKind::Blank {
+            blank_before = true;
+            continue;
+        }
+        let starts = match previous {
+            None => true,
+            Some(previous) => {
+                line.level == level
+                    && !line.continuation
+                    && previous.kind != LineKind::Leading
+                    && (blank_before || previous.closed)
+            }
+        };
+        match groups.last_mut() {
+            Some(group) if !starts => group.end = index + 1,
+            _ => groups.push(index..index + 1),
+        }
+        previous = Some(line);
+        blank_before = false;
+    }
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let symbol = lines[group.clone()]
+                .iter()
+                .filter(|line| line.kind == LineKind::Code && line.level == level)
+                .find_map(|line| symbol_name(&line.code, language, level));
+            let children = match language {
+                Language::Python | Language::Yaml => {
+                    indent_children(text, lines, group.clone(), language)
+                }
+                _ => brace_children(text, lines, group.clone(), level, language),
+            };
+            Node {
+                range: lines[group.start].range.start..lines[group.end - 1].range.end,
+                symbol,
+                children,
+            }
+        })
+        .collect()
+}
+
+/// 花括号语言的内部定义：第一个进入下一层的行到最后一个下一层的行
+fn brace_children(
+    text: &str,
+    lines: &[Line],
+    group: Range<usize>,
+    level: usize,
+    language: Language,
+) -> Vec<Node> {
+    let inner = group.clone().filter(|i| lines[*i].level > level);
+    let (Some(start), Some(end)) = (inner.clone().next(), inner.clone().next_back()) else {
+        return vec![];
+    };
+    group_lines(text, lines, start..end + 1, level + 1, language)
+}
+
+/// 按缩进语言（Python、YAML）的定义构造节点
+fn indent_nodes(text: &str, language: Language) -> Vec<Node> {
+    let lines = indent_lines(text, language);
+    let level = lines
+        .iter()
+        .filter(|line| line.kind == LineKind::Code && !line.continuation)
+        .map(|line| line.level)
+        .min()
+        .unwrap_or(0);
+    group_lines(text, &lines, 0..lines.len(), level, language)
+}
+
+/// 逐行扫描，记录缩进宽度，括号或三引号字符串中的行为延续行
+fn indent_lines(text: &str, language: Language) -> Vec<Line> {
+    let mut lines = vec![];
+    // 括号层数，YAML的流式集合也可以跨行
+    let mut depth = 0usize;
+    let mut triple_quote: Option<&str> = None;
+    let mut offset = 0;
+    let mut previous_backslash = false;
+    for raw in text.split_inclusive('\n') {
+        let line_range = offset..offset + raw.trim_end_matches(['\r', '\n']).len();
+        offset += raw.len();
+        let content = &text[line_range.clone()];
+        let continuation = depth > 0 || triple_quote.is_some() || previous_backslash;
+        let level = content.len() - content.trim_start().len();
+
+        let mut code = String::new();
+        let mut rest = content;
+        while let Some(c) = rest.chars().next() {
+            if let Some(quote) = triple_quote {
+                match rest.find(quote) {
+                    Some(end) => {
+                        rest = &rest[end + quote.len()..];
+                        triple_quote = None;
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+            if language == Language::Python
+                && (rest.starts_with("\"\"\"") || rest.starts_with("'''"))
+            {
+                triple_quote = Some(&rest[..3]);
+                rest = &rest[3..];
+                code.push_str("\"\"");
+                continue;
+            }
+            match c {
+                '#' if language == Language::Python || code.is_empty() || code.ends_with(' ') => {
+                    break;
+                }
+                '"' | '\'' => {
+                    // 单行字符串，YAML中的单引号只在值的开头表示字符串
+                    let end = rest[1..].find(c).map(|end| end + 2).unwrap_or(rest.len());
+                    if language == Language::Python || c == '"' || code.trim_end().ends_with(':') {
+                        code.push_str(&rest[..end]);
+                        rest = &rest[end..];
+                        continue;
+                    }
+                    code.push(c);
+                }
+                '(' | '[' | '{' => {
+                    depth += 1;
+                    code.push(c);
+                }
+                ')' | ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    code.push(c);
+                }
+                _ => code.push(c),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        previous_backslash = language == Language::Python && code.ends_with('\\');
+
+        let code = code.trim().to_string();
+        let kind = if content.trim().is_empty() {
+            LineKind::Blank
+        } else if content.trim_start().starts_with('#')
+            || (language == Language::Python && code.starts_with('@'))
+        {
+            LineKind::Leading
+        } else {
+            LineKind::Code
+        };
+        lines.push(Line {
+            range: line_range,
+            kind,
+            level,
+            continuation,
+            // 缩进语言以缩进区分定义，同级的行都开始新的定义
+            closed: true,
+            code,
+        });
+    }
+    lines
+}
+
+/// 缩进语言的内部定义：定义首行之后缩进最小的行
+fn indent_children(
+    text: &str,
+    lines: &[Line],
+    group: Range<usize>,
+    language: Language,
+) -> Vec<Node> {
+    // 跳过装饰器、注释等开头的行及定义首行
+    let Some(header) = group
+        .clone()
+        .find(|i| lines[*i].kind == LineKind::Code && !lines[*i].continuation)
+    else {
+        return vec![];
+    };
+    let header_level = lines[header].level;
+    let body = header + 1..group.end;
+    let level = body
+        .clone()
+        .map(|i| &lines[i])
+        .filter(|line| {
+            line.kind == LineKind::Code && !line.continuation && line.level > header_level
+        })
+        .map(|line| line.level)
+        .min();
+    match level {
+        Some(level) => {
+            let start = body
+                .clone()
+                .find(|i| lines[*i].kind != LineKind::Blank && !lines[*i].continuation)
+                .unwrap_or(body.start);
+            group_lines(text, lines, start..body.end, level, language)
+        }
+        None => vec![],
+    }
+}
+
+/// 从定义的首行中提取符号名称
+///
+/// 函数体内的变量不作为符号，只有顶层的变量、常量才有名称
+fn symbol_name(code: &str, language: Language, level: usize) -> Option<String> {
+    let words = identifiers(code);
+    let variables = ["var", "let", "const"];
+    let keywords = |keywords: &'static [&'static str]| {
+        keywords
+            .iter()
+            .copied()
+            .filter(|keyword| level == 0 || !variables.contains(keyword))
+            .collect::<Vec<_>>()
+    };
+    // 类成员方法只在类型内部
+    let method = || if level > 0 { method_name(code) } else { None };
+    match language {
+        Language::Rust => {
+            if let Some(name) = rust_impl_name(code) {
+                return Some(name);
+            }
+            definition_name(
+                &words,
+                &[
+                    "pub", "crate", "super", "in", "self", "async", "unsafe", "extern", "default",
+                ],
+                &[
+                    "fn",
+                    "struct",
+                    "enum",
+                    "trait",
+                    "union",
+                    "mod",
+                    "type",
+                    "const",
+                    "static",
+                    "macro_rules",
+                ],
+            )
+        }
+        Language::Go => {
+            // 方法：func (r *Receiver) Name(
+            if let Some(rest) = code.strip_prefix("func (") {
+                let (receiver, rest) = rest.split_once(')')?;
+                let receiver = identifiers(receiver).last()?.to_string();
+                let name = identifiers(rest).first()?.to_string();
+                return Some(format!("{}.{}", receiver, name));
+            }
+            definition_name(&words, &[], &keywords(&["func", "type", "var", "const"]))
+        }
+        Language::Java => definition_name(
+            &words,
+            &[
+                "public",
+                "private",
+                "protected",
+                "static",
+                "final",
+                "abstract",
+                "sealed",
+                "non",
+                "strictfp",
+                "default",
+            ],
+            &["class", "interface", "enum", "record"],
+        )
+        .or_else(method),
+        Language::TypeScript | Language::JavaScript => definition_name(
+            &words,
+            &[
+                "export",
+                "default",
+                "declare",
+                "async",
+                "abstract",
+                "public",
+                "private",
+                "protected",
+                "static",
+                "readonly",
+                "override",
+            ],
+            &keywords(&[
+                "function",
+                "class",
+                "interface",
+                "type",
+                "enum",
+                "namespace",
+                "module",
+                "const",
+                "let",
+                "var",
+            ]),
+        )
+        .or_else(method),
+        Language::Python => definition_name(&words, &["async"], &["def", "class"]),
+        Language::Yaml => {
+            // 列表项没有名称
+            if code.starts_with('-') {
+                return None;
+            }
+            let (key, _) = code.split_once(':')?;
+            let key = key.trim().trim_matches(['"', '\'']);
+            (!key.is_empty()).then(|| key.to_string())
+        }
+        Language::Json | Language::Xml => None,
+    }
+}
+
+/// 依次跳过修饰符，关键字之后的标识符为名称，遇到其他标识符时返回None
+fn definition_name(words: &[&str], modifiers: &[&str], keywords: &[&str]) -> Option<String> {
+    for (i, word) in words.iter().enumerate() {
+        if keywords.contains(word) {
+            match words.get(i + 1) {
+                // 关键字连用，如`const fn`、`export default class`
+                Some(next) if keywords.contains(next) => continue,
+                Some(next) => return Some(next.to_string()),
+                None => return None,
+            }
+        }
+        if !modifiers.contains(word) {
+            return None;
+        }
+    }
+    None
+}
+
+/// 类成员方法：左括号前的标识符，参数之后为方法体，排除控制语句、赋值语句及函数调用
+fn method_name(code: &str) -> Option<String> {
+    const KEYWORDS: &[&str] = &[
+        "if", "for", "while", "switch", "catch", "return", "new", "else", "do", "try", "throw",
+        "await", "yield", "super", "this",
+    ];
+    let (before, after) = code.split_once('(')?;
+    if before.contains(['=', '.', '"', '\'']) || !code.ends_with('{') {
+        return None;
+    }
+    // 参数列表之后只能是返回类型、异常声明或方法体
+    let mut depth = 1usize;
+    let end = after.find(|c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    let rest = after[end + 1..].trim_start();
+    if !(rest.starts_with('{') || rest.starts_with(':') || rest.starts_with("throws")) {
+        return None;
+    }
+    let words = identifiers(before);
+    let name = words.last()?;
+    if words.iter().any(|word| KEYWORDS.contains(word)) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// Rust的impl块以实现的类型命名
+fn rust_impl_name(code: &str) -> Option<String> {
+    let words = identifiers(code);
+    let position = words.iter().position(|word| *word == "impl")?;
+    if !words[..position]
+        .iter()
+        .all(|word| ["pub", "unsafe", "default"].contains(word))
+    {
+        return None;
+    }
+    let rest = &code[code.find("impl")? + 4..];
+    let rest = rest.split(['{', ';']).next().unwrap_or(rest);
+    let rest = rest.split(" where ").next().unwrap_or(rest);
+    // 去掉impl之后的泛型参数
+    let rest = strip_generics(rest.trim_start());
+    let target = match rest.split_once(" for ") {
+        Some((_, target)) => target,
+        None => rest.as_str(),
+    };
+    let target = strip_generics(target.trim());
+    // 路径取最后一段，如std::fmt::Display
+    let name = target.split("::").last()?.trim_start_matches(['&', ' ']);
+    let name = identifiers(name)
+        .into_iter()
+        .find(|word| *word != "mut" && *word != "dyn")?;
+    Some(name.to_string())
+}
+
+/// 去掉尖括号中的泛型参数
+fn strip_generics(text: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// 按非标识符字符拆分
+fn identifiers(code: &str) -> Vec<&str> {
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// 按JSON根对象的键或根数组的元素构造节点
+fn json_nodes(text: &str) -> Vec<Node> {
+    let bytes = text.as_bytes();
+    let start = skip_whitespace(bytes, 0);
+    json_children(text, start)
+}
+
+/// JSON值的成员，对象的每个键或数组的每个元素为一个节点
+fn json_children(text: &str, start: usize) -> Vec<Node> {
+    let bytes = text.as_bytes();
+    let is_object = match bytes.get(start) {
+        Some(b'{') => true,
+        Some(b'[') => false,
+        _ => return vec![],
+    };
+    let mut nodes = vec![];
+    let mut i = start + 1;
+    loop {
+        i = skip_whitespace(bytes, i);
+        match bytes.get(i) {
+            Some(b',') => {
+                i += 1;
+                continue;
+            }
+            Some(b'}' | b']') | None => break,
+            _ => {}
+        }
+        let member_start = i;
+        let symbol = if is_object {
+            let key_end = skip_json_value(bytes, i);
+            let key = serde_json::from_str::<String>(&text[i..key_end])
+                .unwrap_or_else(|_| text[i..key_end].trim_matches('"').to_string());
+            i = skip_whitespace(bytes, key_end);
+            if bytes.get(i) != Some(&b':') {
+                break;
+            }
+            i = skip_whitespace(bytes, i + 1);
+            key
+        } else {
+            format!("[{}]", nodes.len())
+        };
+        let value_end = skip_json_value(bytes, i);
+        if value_end <= member_start {
+            break;
+        }
+        nodes.push(Node {
+            range: member_start..value_end,
+            symbol: Some(symbol),
+            children: json_children(text, i),
+        });
+        i = value_end;
+    }
+    nodes
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// 跳过一个JSON值，返回值之后的位置
+fn skip_json_value(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            match b {
+                b'\\' => i += 1,
+                b'"' => {
+                    in_string = false;
+                    if depth == 0 {
+                        return i + 1;
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            match b {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    if depth == 0 {
+                        return i;
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        return i + 1;
+                    }
+                }
+                b',' | b':' if depth == 0 => return i,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// 按XML根元素的子元素构造节点，子元素之前的注释归入该子元素
+fn xml_nodes(text: &str) -> Vec<Node> {
+    let mut reader = Reader::from_str(text);
+    // 正在解析的元素：起始位置、符号及子元素
+    let mut stack: Vec<(usize, Option<String>, Vec<Node>)> = vec![(0, None, vec![])];
+    // 下一个元素之前的注释的起始位置
+    let mut leading: Option<usize> = None;
+    loop {
+        let position = reader.buffer_position() as usize;
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("Parse xml fail: {}", e);
+                return vec![];
+            }
+        };
+        match event {
+            Event::Start(e) => {
+                let start = leading.take().unwrap_or(position);
+                stack.push((start, Some(xml_symbol(&e)), vec![]));
+            }
+            Event::Empty(e) => {
+                let start = leading.take().unwrap_or(position);
+                let end = reader.buffer_position() as usize;
+                if let Some((_, _, children)) = stack.last_mut() {
+                    children.push(Node {
+                        range: start..end,
+                        symbol: Some(xml_symbol(&e)),
+                        children: vec![],
+                    });
+                }
+            }
+            Event::End(_) => {
+                let end = reader.buffer_position() as usize;
+                leading = None;
+                if stack.len() > 1 {
+                    let (start, symbol, children) = stack.pop().unwrap();
+                    if let Some((_, _, parent)) = stack.last_mut() {
+                        parent.push(Node {
+                            range: start..end,
+                            symbol,
+                            children,
+                        });
+                    }
+                }
+            }
+            Event::Comment(_) => {
+                leading.get_or_insert(position);
+            }
+            Event::Text(e) if !e.iter().all(|b| b.is_ascii_whitespace()) => leading = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    // 根元素的子元素作为顶层节点
+    let (_, _, mut roots) = stack.swap_remove(0);
+    match roots.len() {
+        1 => {
+            let root = roots.pop().unwrap();
+            if root.children.is_empty() {
+                vec![root]
+            } else {
+                root.children
+            }
+        }
+        _ => roots,
+    }
+}
+
+/// 元素的符号，带id或name属性时为`标签#属性值`
+fn xml_symbol(e: &quick_xml::events::BytesStart) -> String {
+    let tag = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+    let id = ["id", "name"].into_iter().find_map(|name| {
+        e.try_get_attribute(name)
+            .ok()
+            .flatten()
+            .and_then(|attr| attr.unescape_value().ok())
+            .map(|value| value.into_owned())
+    });
+    match id {
+        Some(id) => format!("{}#{}", tag, id),
+        None => tag,
+    }
+}
+
+#[test]
+fn test_split_code() {
+    let options = ChunkOptions {
+        chunk_size: 120,
+        ..Default::default()
+    };
+    let rust = "use std::fmt;\n\n/// 点\n#[derive(Debug)]\npub struct Point {\n    x: i32,\n}\n\nimpl<'a> fmt::Display for Point {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        write!(f, \"({})\", self.x)\n    }\n\n    fn area(&self) -> i32 {\n        0\n    }\n}\n";
+    let chunks = split_code(rust, Language::Rust, &options);
+    let symbols = chunks
+        .iter()
+        .map(|chunk| (chunk.symbol.as_deref(), chunk.start_line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        vec![
+            (Some("Point"), 1),
+            (Some("Point::fmt"), 10),
+            (Some("Point::area"), 14)
+        ]
+    );
+    assert!(chunks[0].text.ends_with("for Point {"));
+    assert!(chunks[2].text.ends_with("}\n}"));
+
+    let python = "import os\n\n@decorator\nclass A:\n    \"\"\"文档\n\n    说明\"\"\"\n    def f(self):\n        return 1\n\n    def g(self):\n        return [\n1]\n";
+    let chunks = split_code(
+        python,
+        Language::Python,
+        &ChunkOptions {
+            chunk_size: 40,
+            ..Default::default()
+        },
+    );
+    let symbols = chunks
+        .iter()
+        .map(|chunk| chunk.symbol.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        vec![Some("A"), Some("A"), Some("A.f"), Some("A.g")]
+    );
+    assert!(chunks[0].text.starts_with("import os"));
+
+    let json =
+        r#"{"name": "demo", "scripts": {"build": "vite build", "test": "vitest run --coverage"}}"#;
+    let chunks = split_code(
+        json,
+        Language::Json,
+        &ChunkOptions {
+            chunk_size: 40,
+            ..Default::default()
+        },
+    );
+    let symbols = chunks
+        .iter()
+        .map(|chunk| chunk.symbol.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        vec![
+            Some("name, scripts"),
+            Some("scripts.build"),
+            Some("scripts.test")
+        ]
+    );
+
+    let yaml = "# 配置\nserver:\n  port: 80\nitems:\n  - a\n  - b\n";
+    let chunks = split_code(
+        yaml,
+        Language::Yaml,
+        &ChunkOptions {
+            chunk_size: 30,
+            ..Default::default()
+        },
+    );
+    let symbols = chunks
+        .iter()
+        .map(|chunk| chunk.symbol.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, vec![Some("server"), Some("items")]);
+
+    let xml = "<beans>\n  <!-- 服务 -->\n  <bean id=\"a\" class=\"A\"/>\n  <bean id=\"b\"><property name=\"x\" value=\"1\"/></bean>\n</beans>";
+    let chunks = split_code(
+        xml,
+        Language::Xml,
+        &ChunkOptions {
+            chunk_size: 60,
+            ..Default::default()
+        },
+    );
+    let symbols = chunks
+        .iter()
+        .map(|chunk| chunk.symbol.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, vec![Some("bean#a"), Some("bean#b")]);
+    assert!(chunks[0].text.starts_with("<!-- 服务 -->"));
+}
@@ -1,13 +1,18 @@
+use crate::document::{Block, Document};
+use crate::table::{infer_column_types, row_text};
 use crate::{Input, Split};
+use common::table::ColumnType;
 use std::path::Path;
 
 pub struct CsvInput;
 
 #[derive(Debug)]
 pub struct CsvOutput {
-    /// 文本内容
+    /// 表头
     pub headers: Vec<String>,
-    /// 图片
+    /// 列类型，与表头一一对应
+    pub column_types: Vec<ColumnType>,
+    /// 行数据
     pub rows: Vec<Vec<String>>,
 }
 
@@ -18,7 +23,7 @@ impl Input for CsvInput {
         let mut reader = csv::Reader::from_path(path)?;
         let mut rows: Vec<Vec<String>> = vec![];
         let header_record = reader.headers()?;
-        let headers = header_record
+        let headers: Vec<String> = header_record
             .iter()
             .enumerate()
             .map(|(index, s)| {
@@ -30,11 +35,33 @@ impl Input for CsvInput {
             })
             .collect();
         for record in reader.records() {
-            rows.push(record?.iter().map(|s| s.to_string()).collect());
+            let mut row: Vec<String> = record?.iter().map(|s| s.to_string()).collect();
+            // 将每一行的字段数量与表头保持一致
+            row.resize(headers.len(), String::new());
+            rows.push(row);
+        }
+        let column_types = infer_column_types(&mut rows, headers.len());
+        Ok(CsvOutput {
+            headers,
+            column_types,
+            rows,
+        })
+    }
+}
+impl CsvOutput {
+    /// 转换为结构化文档，每一行作为一个段落
+    pub fn to_document(&self) -> Document {
+        Document {
+            blocks: self
+                .rows
+                .iter()
+                .map(|row| Block::Paragraph(row_text(&self.headers, row)))
+                .collect(),
+            images: vec![],
         }
-        Ok(CsvOutput { headers, rows })
     }
 }
+
 impl CsvInput {
     pub fn extra_headers(path: impl AsRef<Path>) -> crate::Result<Vec<String>> {
         let mut reader = csv::Reader::from_path(path)?;
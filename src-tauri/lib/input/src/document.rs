@@ -0,0 +1,261 @@
+//! 结构化文档
+//!
+//! DOCX、PPTX等格式解析后的统一输出，保留标题、列表、表格及图片，
+//! 转换为Markdown后可按标题分段并记录标题路径。
+
+use crate::Split;
+
+/// 支持的图片格式
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+/// 文档内容块
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    /// 标题，级别从1开始
+    Heading { level: usize, text: String },
+    /// 段落
+    Paragraph(String),
+    /// 列表项，级别从0开始
+    ListItem { level: usize, text: String },
+    /// 表格，第一行作为表头
+    Table(Vec<Vec<String>>),
+    /// 图片，值为图片在文档图片列表中的下标
+    Image(usize),
+}
+
+/// 文档中嵌入的图片
+#[derive(Debug, Clone)]
+pub struct DocumentImage {
+    /// 图片文件名，同一文档内唯一
+    pub name: String,
+    /// 图片数据
+    pub data: Vec<u8>,
+}
+
+/// 结构化文档
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    /// 内容块
+    pub blocks: Vec<Block>,
+    /// 图片
+    pub images: Vec<DocumentImage>,
+}
+
+impl Document {
+    /// 纯文本转换为文档，以空行分隔段落
+    pub fn from_text(text: &str) -> Self {
+        let mut document = Document::default();
+        let mut paragraph: Vec<&str> = vec![];
+        for line in text.lines().map(|line| line.trim_end()) {
+            if line.trim().is_empty() {
+                if !paragraph.is_empty() {
+                    document.blocks.push(Block::Paragraph(paragraph.join("\n")));
+                    paragraph.clear();
+                }
+            } else {
+                paragraph.push(line);
+            }
+        }
+        if !paragraph.is_empty() {
+            document.blocks.push(Block::Paragraph(paragraph.join("\n")));
+        }
+        document
+    }
+
+    /// 是否没有任何内容
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// 添加图片，同名图片只保留一份，返回图片下标
+    ///
+    /// emf、wmf等无法显示及识别的图片格式忽略，返回None
+    pub fn add_image(&mut self, name: String, data: Vec<u8>) -> Option<usize> {
+        let ext = name.rsplit('.').next().unwrap_or_default().to_lowercase();
+        if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+            return None;
+        }
+        if let Some(index) = self.images.iter().position(|image| image.name == name) {
+            return Some(index);
+        }
+        self.images.push(DocumentImage { name, data });
+        Some(self.images.len() - 1)
+    }
+
+    /// 将另一个文档追加到末尾
+    ///
+    /// - other：追加的文档，与已有图片重名但内容不同的图片重命名
+    /// - level_offset：追加文档的标题级别增加的值，用于将其作为当前文档的下级章节
+    pub fn append(&mut self, other: Document, level_offset: usize) {
+        let mut indexes = vec![];
+        for image in other.images {
+            let mut name = image.name;
+            let mut i = 1;
+            while self
+                .images
+                .iter()
+                .any(|existing| existing.name == name && existing.data != image.data)
+            {
+                name = match image_name_parts(&name) {
+                    (stem, Some(ext)) => format!("{}_{}.{}", stem, i, ext),
+                    (stem, None) => format!("{}_{}", stem, i),
+                };
+                i += 1;
+            }
+            indexes.push(self.add_image(name, image.data));
+        }
+        for block in other.blocks {
+            let block = match block {
+                Block::Heading { level, text } => Block::Heading {
+                    level: level + level_offset,
+                    text,
+                },
+                Block::Image(index) => match indexes.get(index).copied().flatten() {
+                    Some(index) => Block::Image(index),
+                    None => continue,
+                },
+                block => block,
+            };
+            self.blocks.push(block);
+        }
+    }
+
+    /// 转换为Markdown
+    ///
+    /// 图片以`![图片](文件名)`表示，describe返回图片的文字内容时追加在图片之后
+    pub fn to_markdown(&self, describe: impl Fn(&DocumentImage) -> Option<String>) -> String {
+        let mut blocks = vec![];
+        for block in &self.blocks {
+            let text = match block {
+                Block::Heading { level, text } => {
+                    format!("{} {}", "#".repeat((*level).clamp(1, 6)), single_line(text))
+                }
+                Block::Paragraph(text) => escape_heading(text),
+                Block::ListItem { level, text } => {
+                    format!("{}- {}", "  ".repeat(*level), single_line(text))
+                }
+                Block::Table(rows) => table_to_markdown(rows),
+                Block::Image(index) => match self.images.get(*index) {
+                    Some(image) => match describe(image) {
+                        Some(description) if !description.trim().is_empty() => {
+                            format!("![图片]({})\n{}", image.name, description.trim())
+                        }
+                        _ => format!("![图片]({})", image.name),
+                    },
+                    None => continue,
+                },
+            };
+            if !text.trim().is_empty() {
+                blocks.push(text);
+            }
+        }
+        blocks.join("\n\n")
+    }
+
+    /// 文本中引用的图片
+    pub fn images_in<'a>(&'a self, text: &str) -> Vec<&'a DocumentImage> {
+        self.images
+            .iter()
+            .filter(|image| text.contains(&format!("]({})", image.name)))
+            .collect()
+    }
+}
+
+/// 拆分文件名及扩展名
+fn image_name_parts(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
+    }
+}
+
+/// 将换行替换为空格，用于标题、列表项等只能占一行的内容
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 段落以`#`开头时转义，避免被识别为标题
+fn escape_heading(text: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with('#') {
+                format!("\\{}", line.trim_start())
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 表格转换为Markdown表格，列数不足的行以空单元格补齐
+fn table_to_markdown(rows: &[Vec<String>]) -> String {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    if width == 0 {
+        return String::new();
+    }
+    let line = |row: &Vec<String>| {
+        let cells = (0..width)
+            .map(|col| {
+                row.get(col)
+                    .map(|cell| single_line(cell).replace('|', "\\|"))
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0])];
+    lines.push(format!("|{}", " --- |".repeat(width)));
+    lines.extend(rows[1..].iter().map(line));
+    lines.join("\n")
+}
+
+impl Split for Document {}
+
+#[test]
+fn test_to_markdown() {
+    let mut document = Document::default();
+    let image = document
+        .add_image("image1.png".to_string(), vec![])
+        .unwrap();
+    assert_eq!(document.add_image("image2.emf".to_string(), vec![]), None);
+    document.blocks = vec![
+        Block::Heading {
+            level: 1,
+            text: "概述".to_string(),
+        },
+        Block::Paragraph("#1 号\n第二行".to_string()),
+        Block::ListItem {
+            level: 1,
+            text: "子项".to_string(),
+        },
+        Block::Table(vec![
+            vec!["名称".to_string(), "说明".to_string()],
+            vec!["a|b".to_string()],
+        ]),
+        Block::Image(image),
+    ];
+    let markdown = document.to_markdown(|_| Some("图片文字".to_string()));
+    assert_eq!(
+        markdown,
+        "# 概述\n\n\\#1 号\n第二行\n\n  - 子项\n\n| 名称 | 说明 |\n| --- | --- |\n| a\\|b |  |\n\n![图片](image1.png)\n图片文字"
+    );
+    assert_eq!(document.images_in("见![图片](image1.png)").len(), 1);
+
+    let mut attachment = Document::from_text("第一段\n\n第二段");
+    let index = attachment
+        .add_image("image1.png".to_string(), vec![1])
+        .unwrap();
+    attachment.blocks.push(Block::Image(index));
+    document.append(attachment, 1);
+    assert_eq!(document.images[1].name, "image1_1.png");
+    assert_eq!(
+        document.blocks[5..],
+        [
+            Block::Paragraph("第一段".to_string()),
+            Block::Paragraph("第二段".to_string()),
+            Block::Image(1),
+        ]
+    );
+}
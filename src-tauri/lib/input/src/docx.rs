@@ -1,101 +1,243 @@
-use crate::{Input, Split};
+use crate::Input;
+use crate::document::{Block, Document};
 use docx_rs::{
-    DocumentChild, ParagraphChild, RunChild, TableCellContent, TableChild, TableRowChild,
+    DocumentChild, DrawingData, Paragraph, ParagraphChild, RunChild, Table, TableCellContent,
+    TableChild, TableRowChild,
 };
-use image::DynamicImage;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
 pub struct DocxInput;
 
-#[derive(Debug)]
-pub struct DocxOutput {
-    /// 文本内容
-    pub content: String,
-    /// 图片
-    pub images: Vec<DynamicImage>,
-}
-
 impl Input for DocxInput {
-    type Output = crate::Result<DocxOutput>;
+    type Output = crate::Result<Document>;
 
+    /// 读取docx文件，保留标题、列表、表格及图片
     fn read(path: impl AsRef<Path>) -> Self::Output {
         let file = fs::read(path)?;
         let docx = docx_rs::read_docx(&file)?;
-        let mut content = String::new();
-        for document_child in docx.document.children {
+
+        let mut document = Document::default();
+        // 关系ID -> 图片下标
+        let mut images = HashMap::new();
+        for (id, path, image, _) in &docx.images {
+            let name = Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| id.clone());
+            if let Some(index) = document.add_image(name, image.0.clone()) {
+                images.insert(id.clone(), index);
+            }
+        }
+
+        for document_child in &docx.document.children {
             match document_child {
                 DocumentChild::Paragraph(paragraph) => {
-                    for child in paragraph.children {
-                        if let ParagraphChild::Run(run) = child {
-                            for run_child in run.children {
-                                match run_child {
-                                    RunChild::Text(text) => {
-                                        content.push_str(&text.text);
-                                    }
-                                    _ => {}
-                                }
-                            }
+                    let (text, image_ids) = paragraph_text(paragraph);
+                    let text = text.trim().to_string();
+                    if !text.is_empty() {
+                        document.blocks.push(paragraph_block(paragraph, text));
+                    }
+                    for id in image_ids {
+                        if let Some(index) = images.get(&id) {
+                            document.blocks.push(Block::Image(*index));
                         }
                     }
-                    content.push('\n');
                 }
                 DocumentChild::Table(table) => {
-                    table.rows.iter().for_each(|table_child| match table_child {
-                        TableChild::TableRow(table_row) => {
-                            table_row.cells.iter().for_each(
-                                |table_row_child| match table_row_child {
-                                    TableRowChild::TableCell(table_cell) => {
-                                        table_cell.children.iter().for_each(|table_cell_child| {
-                                            match table_cell_child {
-                                                TableCellContent::Paragraph(paragraph) => {
-                                                    paragraph.children.iter().for_each(
-                                                        |paragraph_child| match paragraph_child {
-                                                            ParagraphChild::Run(run) => {
-                                                                run.children.iter().for_each(
-                                                                    |run_child| match run_child {
-                                                                        RunChild::Text(text) => {
-                                                                            content.push_str(
-                                                                                &text.text,
-                                                                            );
-                                                                        }
-                                                                        _ => {}
-                                                                    },
-                                                                )
-                                                            }
-                                                            _ => {}
-                                                        },
-                                                    )
-                                                }
-                                                _ => {}
-                                            }
-                                            content.push_str(" | ");
-                                        });
-                                    }
-                                },
-                            );
-                            content.push('\n');
-                        }
-                    })
+                    let rows = table_rows(table);
+                    if !rows.is_empty() {
+                        document.blocks.push(Block::Table(rows));
+                    }
                 }
-                other => {
-                    println!("other: {:?}", other);
+                _ => {}
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+/// 根据段落样式及编号判断段落是标题、列表项还是普通段落
+fn paragraph_block(paragraph: &Paragraph, text: String) -> Block {
+    let property = &paragraph.property;
+    if let Some(level) = property
+        .style
+        .as_ref()
+        .and_then(|style| heading_level(&style.val))
+    {
+        return Block::Heading { level, text };
+    }
+    if let Some(numbering) = &property.numbering_property {
+        let level = numbering.level.as_ref().map(|level| level.val).unwrap_or(0);
+        return Block::ListItem { level, text };
+    }
+    Block::Paragraph(text)
+}
+
+/// 标题样式的级别
+///
+/// 英文版Word的标题样式ID为`Heading1`，中文版Word及WPS为`1`、`2`等数字
+fn heading_level(style_id: &str) -> Option<usize> {
+    let style_id = style_id.to_lowercase();
+    if style_id == "title" {
+        return Some(1);
+    }
+    let level = style_id.strip_prefix("heading").unwrap_or(&style_id);
+    match level.parse::<usize>() {
+        Ok(level) if (1..=9).contains(&level) => Some(level),
+        _ => None,
+    }
+}
+
+/// 段落的文本及段落中图片的关系ID
+fn paragraph_text(paragraph: &Paragraph) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut image_ids = vec![];
+    collect_paragraph_children(&paragraph.children, &mut text, &mut image_ids);
+    (text, image_ids)
+}
+
+fn collect_paragraph_children(
+    children: &[ParagraphChild],
+    text: &mut String,
+    image_ids: &mut Vec<String>,
+) {
+    for child in children {
+        match child {
+            ParagraphChild::Run(run) => {
+                for run_child in &run.children {
+                    match run_child {
+                        RunChild::Text(t) => text.push_str(&t.text),
+                        RunChild::Tab(_) => text.push('\t'),
+                        RunChild::Break(_) => text.push('\n'),
+                        RunChild::Drawing(drawing) => {
+                            if let Some(DrawingData::Pic(pic)) = &drawing.data {
+                                image_ids.push(pic.id.clone());
+                            }
+                        }
+                        _ => {}
+                    }
                 }
             }
+            ParagraphChild::Hyperlink(hyperlink) => {
+                collect_paragraph_children(&hyperlink.children, text, image_ids);
+            }
+            _ => {}
         }
-        let mut images = Vec::new();
-        docx.images.iter().for_each(|(_, _, image, _)| {
-            if let Ok(image) = image::load_from_memory(&image.0) {
-                images.push(image);
+    }
+}
+
+/// 表格的行，单元格内的多个段落以换行连接，嵌套表格展开为文本
+fn table_rows(table: &Table) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    for TableChild::TableRow(row) in &table.rows {
+        let mut cells = vec![];
+        for TableRowChild::TableCell(cell) in &row.cells {
+            let mut lines = vec![];
+            for content in &cell.children {
+                match content {
+                    TableCellContent::Paragraph(paragraph) => {
+                        lines.push(paragraph_text(paragraph).0);
+                    }
+                    TableCellContent::Table(table) => {
+                        for nested_row in table_rows(table) {
+                            lines.push(nested_row.join(" "));
+                        }
+                    }
+                    _ => {}
+                }
             }
-        });
-        Ok(DocxOutput { content, images })
+            cells.push(lines.join("\n").trim().to_string());
+        }
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            rows.push(cells);
+        }
     }
+    rows
+}
+
+#[cfg(test)]
+fn text_paragraph(text: &str) -> Paragraph {
+    Paragraph::new().add_run(docx_rs::Run::new().add_text(text))
+}
+
+#[test]
+fn test_heading_level() {
+    assert_eq!(heading_level("Heading1"), Some(1));
+    assert_eq!(heading_level("heading9"), Some(9));
+    assert_eq!(heading_level("1"), Some(1));
+    assert_eq!(heading_level("Title"), Some(1));
+    assert_eq!(heading_level("10"), None);
+    assert_eq!(heading_level("Heading0"), None);
+    assert_eq!(heading_level("Normal"), None);
+}
+
+#[test]
+fn test_paragraph_block() {
+    use docx_rs::{IndentLevel, NumberingId};
+
+    let heading = text_paragraph("概述").style("Heading2");
+    assert_eq!(
+        paragraph_block(&heading, "概述".to_string()),
+        Block::Heading {
+            level: 2,
+            text: "概述".to_string()
+        }
+    );
+    let list_item = text_paragraph("第一项").numbering(NumberingId::new(1), IndentLevel::new(1));
+    assert_eq!(
+        paragraph_block(&list_item, "第一项".to_string()),
+        Block::ListItem {
+            level: 1,
+            text: "第一项".to_string()
+        }
+    );
+    let paragraph = text_paragraph("正文").style("Normal");
+    assert_eq!(
+        paragraph_block(&paragraph, "正文".to_string()),
+        Block::Paragraph("正文".to_string())
+    );
+}
+
+#[test]
+fn test_table_rows() {
+    use docx_rs::{TableCell, TableRow};
+
+    let nested = Table::new(vec![TableRow::new(vec![
+        TableCell::new().add_paragraph(text_paragraph("x")),
+        TableCell::new().add_paragraph(text_paragraph("y")),
+    ])]);
+    let table = Table::new(vec![
+        TableRow::new(vec![
+            TableCell::new().add_paragraph(text_paragraph("名称")),
+            TableCell::new()
+                .add_paragraph(text_paragraph("说明"))
+                .add_paragraph(text_paragraph("备注")),
+        ]),
+        // 空行跳过
+        TableRow::new(vec![
+            TableCell::new().add_paragraph(text_paragraph(" ")),
+            TableCell::new(),
+        ]),
+        TableRow::new(vec![
+            TableCell::new().add_paragraph(text_paragraph("a")),
+            TableCell::new().add_table(nested),
+        ]),
+    ]);
+    assert_eq!(
+        table_rows(&table),
+        vec![
+            vec!["名称".to_string(), "说明\n备注".to_string()],
+            vec!["a".to_string(), "x y".to_string()],
+        ]
+    );
 }
 
-impl Split for DocxOutput {}
 #[test]
+#[ignore = "需要本地的docx文件"]
 fn test_docx() {
     let output = DocxInput::read("/mnt/d/download/活动发布审核流程-0819.docx").unwrap();
-    println!("{:?}", output);
+    assert!(!output.is_empty());
 }
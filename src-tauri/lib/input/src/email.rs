@@ -0,0 +1,114 @@
+//! 邮件
+//!
+//! eml及msg格式解析后的统一输出，正文为结构化文档，附件保留原始数据，由调用方按附件格式解析。
+
+use crate::document::{Block, Document};
+use crate::html;
+use crate::package::percent_decode;
+
+#[derive(Debug, Default)]
+pub struct EmailOutput {
+    /// 主题
+    pub subject: Option<String>,
+    /// 发件人
+    pub from: Option<String>,
+    /// 收件人
+    pub to: Option<String>,
+    /// 抄送
+    pub cc: Option<String>,
+    /// 发送时间
+    pub date: Option<String>,
+    /// 正文，HTML正文中引用的内嵌图片已加入文档
+    pub body: Document,
+    /// 附件
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// 邮件附件
+#[derive(Debug, Clone)]
+pub struct EmailAttachment {
+    /// 文件名
+    pub name: String,
+    /// 文件内容
+    pub data: Vec<u8>,
+}
+
+impl EmailOutput {
+    /// 转换为文档，主题作为一级标题，发件人、收件人等信息作为一个段落，正文的标题降一级
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::default();
+        if let Some(subject) = self.subject.as_ref().filter(|s| !s.trim().is_empty()) {
+            document.blocks.push(Block::Heading {
+                level: 1,
+                text: subject.trim().to_string(),
+            });
+        }
+        let header = [
+            ("发件人", &self.from),
+            ("收件人", &self.to),
+            ("抄送", &self.cc),
+            ("时间", &self.date),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = value.as_ref()?.trim();
+            (!value.is_empty()).then(|| format!("{}：{}", name, value))
+        })
+        .collect::<Vec<_>>();
+        if !header.is_empty() {
+            document.blocks.push(Block::Paragraph(header.join("\n")));
+        }
+        document.append(self.body.clone(), 1);
+        document
+    }
+}
+
+/// 内嵌图片，HTML正文中以`cid:`引用
+#[derive(Debug)]
+pub(crate) struct InlineImage {
+    /// Content-ID，不含尖括号
+    pub(crate) content_id: String,
+    pub(crate) name: String,
+    pub(crate) data: Vec<u8>,
+}
+
+/// 生成正文文档，优先使用HTML正文
+///
+/// 返回正文及未被正文引用的内嵌图片，未引用的图片按附件处理
+pub(crate) fn body_document(
+    html: Option<&str>,
+    text: Option<&str>,
+    inline_images: Vec<InlineImage>,
+) -> (Document, Vec<EmailAttachment>) {
+    let mut used = vec![false; inline_images.len()];
+    let mut document = match html {
+        Some(html) => {
+            html::convert(html, |src| {
+                let content_id = percent_decode(src.strip_prefix("cid:")?);
+                let index = inline_images
+                    .iter()
+                    .position(|image| image.content_id.eq_ignore_ascii_case(&content_id))?;
+                used[index] = true;
+                let image = &inline_images[index];
+                Some((image.name.clone(), image.data.clone()))
+            })
+            .document
+        }
+        None => Document::default(),
+    };
+    if document.is_empty()
+        && let Some(text) = text
+    {
+        document = Document::from_text(text);
+    }
+    let unused = inline_images
+        .into_iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|(image, _)| EmailAttachment {
+            name: image.name,
+            data: image.data,
+        })
+        .collect();
+    (document, unused)
+}
@@ -0,0 +1,498 @@
+use crate::Input;
+use crate::email::{self, EmailAttachment, EmailOutput, InlineImage};
+use crate::package::percent_decode_bytes;
+use crate::txt;
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use encoding_rs::Encoding;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 嵌套的MIME部分的最大层数
+const MAX_DEPTH: usize = 16;
+
+/// 忽略填充字符的base64解码
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::RequireNone),
+);
+
+pub struct EmlInput;
+
+impl Input for EmlInput {
+    type Output = crate::Result<EmailOutput>;
+
+    /// 读取eml文件（RFC 822），解析邮件头、正文及附件
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let bytes = fs::read(path)?;
+        Ok(parse_message(&bytes))
+    }
+}
+
+/// MIME部分
+struct Part<'a> {
+    /// 邮件头，名称为小写
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl Part<'_> {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 按Content-Transfer-Encoding解码后的内容
+    fn content(&self) -> Vec<u8> {
+        match self
+            .header("content-transfer-encoding")
+            .map(|encoding| encoding.trim().to_lowercase())
+            .as_deref()
+        {
+            Some("base64") => decode_base64(self.body),
+            Some("quoted-printable") => decode_quoted_printable(self.body),
+            _ => self.body.to_vec(),
+        }
+    }
+}
+
+/// 解析过程中收集的正文及附件
+#[derive(Default)]
+struct Collector {
+    text: Option<String>,
+    html: Option<String>,
+    inline_images: Vec<InlineImage>,
+    attachments: Vec<EmailAttachment>,
+}
+
+fn parse_message(bytes: &[u8]) -> EmailOutput {
+    let message = split_part(bytes);
+    let mut collector = Collector::default();
+    walk(&message, &mut collector, 0);
+
+    let (body, unused_images) = email::body_document(
+        collector.html.as_deref(),
+        collector.text.as_deref(),
+        collector.inline_images,
+    );
+    let mut attachments = collector.attachments;
+    attachments.extend(unused_images);
+
+    let header = |name: &str| message.header(name).map(decode_words);
+    EmailOutput {
+        subject: header("subject"),
+        from: header("from"),
+        to: header("to"),
+        cc: header("cc"),
+        date: header("date"),
+        body,
+        attachments,
+    }
+}
+
+/// 拆分邮件头及内容，邮件头的折行已合并
+fn split_part(bytes: &[u8]) -> Part<'_> {
+    let (header_end, body_start) = [&b"\r\n\r\n"[..], &b"\n\n"[..]]
+        .iter()
+        .filter_map(|separator| {
+            bytes
+                .windows(separator.len())
+                .position(|window| window == *separator)
+                .map(|pos| (pos, pos + separator.len()))
+        })
+        .min()
+        .unwrap_or((bytes.len(), bytes.len()));
+
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in txt::decode(bytes[..header_end].to_vec()).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push_str(line.trim_end());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    Part {
+        headers,
+        body: &bytes[body_start..],
+    }
+}
+
+fn walk(part: &Part, collector: &mut Collector, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let (content_type, params) = parse_params(part.header("content-type").unwrap_or("text/plain"));
+    if content_type.starts_with("multipart/") {
+        if let Some(boundary) = params.get("boundary") {
+            for child in split_multipart(part.body, boundary) {
+                walk(&split_part(child), collector, depth + 1);
+            }
+        }
+        return;
+    }
+
+    let (disposition, disposition_params) =
+        parse_params(part.header("content-disposition").unwrap_or_default());
+    let name = disposition_params
+        .get("filename")
+        .or_else(|| params.get("name"))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    let is_attachment = disposition == "attachment";
+    let content = part.content();
+
+    // 正文
+    if !is_attachment && name.is_none() {
+        let charset = params.get("charset").map(String::as_str);
+        match content_type.as_str() {
+            "text/plain" if collector.text.is_none() => {
+                collector.text = Some(decode_charset(&content, charset));
+                return;
+            }
+            "text/html" if collector.html.is_none() => {
+                collector.html = Some(decode_charset(&content, charset));
+                return;
+            }
+            _ => {}
+        }
+    }
+    // 数字签名等不含内容的部分
+    if content_type.ends_with("-signature") {
+        return;
+    }
+
+    let name = name.unwrap_or_else(|| {
+        format!(
+            "附件{}{}",
+            collector.attachments.len() + collector.inline_images.len() + 1,
+            extension(&content_type)
+        )
+    });
+    let content_id = part
+        .header("content-id")
+        .map(|id| {
+            id.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+        .filter(|id| !id.is_empty());
+    match content_id {
+        Some(content_id) if !is_attachment && content_type.starts_with("image/") => {
+            collector.inline_images.push(InlineImage {
+                content_id,
+                name,
+                data: content,
+            });
+        }
+        _ => collector.attachments.push(EmailAttachment {
+            name,
+            data: content,
+        }),
+    }
+}
+
+/// 没有文件名的附件按内容类型确定扩展名
+fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "text/plain" => ".txt",
+        "text/html" => ".html",
+        "message/rfc822" => ".eml",
+        "image/png" => ".png",
+        "image/jpeg" => ".jpg",
+        "image/gif" => ".gif",
+        _ => "",
+    }
+}
+
+/// 按分隔符拆分multipart的各个部分
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = vec![];
+    // 当前部分的起始位置
+    let mut start: Option<usize> = None;
+    let mut line_start = 0;
+    while line_start < body.len() {
+        let line_end = body[line_start..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map(|pos| line_start + pos)
+            .unwrap_or(body.len());
+        let line = body[line_start..line_end].trim_ascii_end();
+        if let Some(rest) = line.strip_prefix(delimiter.as_bytes()) {
+            let closing = rest.starts_with(b"--");
+            if closing || rest.trim_ascii().is_empty() {
+                if let Some(start) = start {
+                    parts.push(trim_line_break(&body[start..line_start]));
+                }
+                if closing {
+                    return parts;
+                }
+                start = Some((line_end + 1).min(body.len()));
+            }
+        }
+        line_start = line_end + 1;
+    }
+    // 缺少结束分隔符
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+/// 去掉分隔符之前的换行，换行属于分隔符
+fn trim_line_break(bytes: &[u8]) -> &[u8] {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    bytes.strip_suffix(b"\r").unwrap_or(bytes)
+}
+
+/// 解析Content-Type等带参数的邮件头，返回小写的值及参数
+///
+/// 支持RFC 2231的参数续行及编码，如`filename*0*=utf-8''%E6%96%87`，
+/// 以及不规范但常见的以RFC 2047编码字表示的参数值
+fn parse_params(value: &str) -> (String, HashMap<String, String>) {
+    let mut items = split_unquoted(value).into_iter();
+    let main = items.next().unwrap_or_default().trim().to_lowercase();
+
+    // 参数名 -> [(序号, 是否编码, 值)]
+    let mut segments: HashMap<String, Vec<(usize, bool, String)>> = HashMap::new();
+    for item in items {
+        let Some((key, value)) = item.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = unquote(value.trim());
+        let (key, encoded) = match key.strip_suffix('*') {
+            Some(key) => (key.to_string(), true),
+            None => (key, false),
+        };
+        let (name, index) = match key.split_once('*') {
+            Some((name, index)) => (name.to_string(), index.parse().unwrap_or(0)),
+            None => (key, 0),
+        };
+        segments
+            .entry(name)
+            .or_default()
+            .push((index, encoded, value));
+    }
+
+    let params = segments
+        .into_iter()
+        .map(|(name, mut parts)| {
+            parts.sort_by_key(|(index, _, _)| *index);
+            let mut charset: Option<String> = None;
+            let mut bytes = vec![];
+            for (_, encoded, value) in parts {
+                if !encoded {
+                    bytes.extend_from_slice(value.as_bytes());
+                    continue;
+                }
+                // 第一个编码的片段以`字符集'语言'`开头
+                let mut value = value.as_str();
+                if charset.is_none()
+                    && let Some((cs, rest)) = value.split_once('\'')
+                {
+                    charset = Some(cs.to_string());
+                    value = rest.split_once('\'').map(|(_, v)| v).unwrap_or(rest);
+                }
+                bytes.extend(percent_decode_bytes(value));
+            }
+            let value = decode_charset(&bytes, charset.as_deref());
+            (name, decode_words(&value))
+        })
+        .collect();
+    (main, params)
+}
+
+/// 按分号拆分，忽略引号中的分号
+fn split_unquoted(value: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                items.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    items.push(current);
+    items
+}
+
+/// 去掉引号及转义符
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) => {
+            let mut result = String::new();
+            let mut chars = value.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => result.extend(chars.next()),
+                    c => result.push(c),
+                }
+            }
+            result
+        }
+        None => value.to_string(),
+    }
+}
+
+/// 解码RFC 2047编码字，如`=?UTF-8?B?5rWL6K+V?=`，相邻编码字之间的空白忽略
+fn decode_words(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    let mut last_encoded = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, after) = rest.split_at(start);
+        match encoded_word(after) {
+            Some((decoded, len)) => {
+                if !(last_encoded && before.trim().is_empty()) {
+                    result.push_str(before);
+                }
+                result.push_str(&decoded);
+                rest = &after[len..];
+                last_encoded = true;
+            }
+            None => {
+                result.push_str(before);
+                result.push_str("=?");
+                rest = &after[2..];
+                last_encoded = false;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 解码以`=?`开头的编码字，返回解码后的文本及编码字的长度
+fn encoded_word(text: &str) -> Option<(String, usize)> {
+    let (charset, rest) = text[2..].split_once('?')?;
+    let (encoding, rest) = rest.split_once('?')?;
+    if charset.is_empty() || charset.contains(char::is_whitespace) || encoding.len() != 1 {
+        return None;
+    }
+    let end = rest.find("?=")?;
+    let data = &rest[..end];
+    let bytes = match encoding {
+        "B" | "b" => decode_base64(data.as_bytes()),
+        // Q编码中下划线表示空格
+        "Q" | "q" => decode_quoted_printable(data.replace('_', " ").as_bytes()),
+        _ => return None,
+    };
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    // 字符集之后可能带有`*语言`
+    let charset = charset.split('*').next().unwrap_or(charset);
+    Some((decode_charset(&bytes, Some(charset)), len))
+}
+
+/// 按字符集解码，未声明或无法识别的字符集依次尝试UTF-8及GBK
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.and_then(|charset| Encoding::for_label(charset.trim().as_bytes())) {
+        Some(encoding) => encoding.decode(bytes).0.into_owned(),
+        None => txt::decode(bytes.to_vec()),
+    }
+}
+
+/// base64解码，忽略换行等非编码字符
+fn decode_base64(data: &[u8]) -> Vec<u8> {
+    let mut data = data
+        .iter()
+        .copied()
+        .filter(|b| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'/')
+        .collect::<Vec<_>>();
+    // 不完整的最后一个字符无法解码
+    if data.len() % 4 == 1 {
+        data.pop();
+    }
+    BASE64.decode(&data).unwrap_or_default()
+}
+
+/// quoted-printable解码
+fn decode_quoted_printable(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'=' {
+            let rest = &data[i + 1..];
+            // 软换行
+            if rest.starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            }
+            if rest.starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (
+                rest.first().and_then(|b| hex(*b)),
+                rest.get(1).and_then(|b| hex(*b)),
+            ) {
+                result.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(data[i]);
+        i += 1;
+    }
+    result
+}
+
+#[test]
+fn test_parse_message() {
+    let eml = "From: =?UTF-8?B?5byg5LiJ?= <zhang@example.com>\r\n\
+        To: li@example.com\r\n\
+        Subject: =?UTF-8?Q?=E5=91=A8=E6=8A=A5?=\r\n =?UTF-8?B?6I2J56i/?=\r\n\
+        Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: multipart/alternative; boundary=b2\r\n\
+        \r\n\
+        --b2\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        plain\r\n\
+        --b2\r\n\
+        Content-Type: text/html; charset=utf-8\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        <p>=E6=9C=AC=E5=91=A8=\r\n\
+        =E5=AE=8C=E6=88=90</p>\r\n\
+        --b2--\r\n\
+        --b1\r\n\
+        Content-Type: text/plain\r\n\
+        Content-Disposition: attachment; filename*=utf-8''%E6%B8%85%E5%8D%95.txt\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        5riF5Y2V\r\n\
+        --b1--\r\n";
+    let output = parse_message(eml.as_bytes());
+    assert_eq!(output.subject.as_deref(), Some("周报草稿"));
+    assert_eq!(output.from.as_deref(), Some("张三 <zhang@example.com>"));
+    assert_eq!(
+        output.body.blocks,
+        vec![crate::document::Block::Paragraph("本周完成".to_string())]
+    );
+    assert_eq!(output.attachments.len(), 1);
+    assert_eq!(output.attachments[0].name, "清单.txt");
+    assert_eq!(output.attachments[0].data, "清单".as_bytes());
+}
@@ -0,0 +1,166 @@
+use crate::Input;
+use crate::document::Document;
+use crate::html;
+use crate::package::{
+    attribute, file_name, parent_dir, percent_decode, read_bytes, read_entry, resolve_path,
+};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+pub struct EpubInput;
+
+#[derive(Debug)]
+pub struct EpubOutput {
+    /// 书名
+    pub title: Option<String>,
+    /// 章节，按阅读顺序排列
+    pub chapters: Vec<Document>,
+}
+
+/// OPF文件中的书籍信息
+#[derive(Debug, Default, PartialEq)]
+struct Package {
+    title: Option<String>,
+    /// 阅读顺序中的章节路径，相对于OPF文件
+    spine: Vec<String>,
+}
+
+impl Input for EpubInput {
+    type Output = crate::Result<EpubOutput>;
+
+    /// 读取epub文件，按阅读顺序将每个章节转换为文档
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let opf_path = rootfile(&mut archive)?;
+        let package = parse_package(&read_entry(&mut archive, &opf_path)?)?;
+
+        let mut chapters = vec![];
+        for href in package.spine {
+            let chapter_path = resolve_path(parent_dir(&opf_path), &percent_decode(&href));
+            let Ok(content) = read_bytes(&mut archive, &chapter_path) else {
+                log::warn!("epub chapter not found: {}", chapter_path);
+                continue;
+            };
+            let chapter_dir = parent_dir(&chapter_path).to_string();
+            let output = html::convert(&html::decode(&content, None), |src| {
+                if src.contains(':') {
+                    return None;
+                }
+                let src = percent_decode(src.split(['?', '#']).next().unwrap_or_default());
+                let image_path = resolve_path(&chapter_dir, &src);
+                let data = read_bytes(&mut archive, &image_path).ok()?;
+                Some((file_name(&image_path).to_string(), data))
+            });
+            if !output.document.is_empty() {
+                chapters.push(output.document);
+            }
+        }
+
+        Ok(EpubOutput {
+            title: package.title,
+            chapters,
+        })
+    }
+}
+
+/// 从`META-INF/container.xml`中读取OPF文件的路径
+fn rootfile<R: Read + Seek>(archive: &mut ZipArchive<R>) -> crate::Result<String> {
+    let xml = read_entry(archive, "META-INF/container.xml")?;
+    let mut reader = Reader::from_str(&xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"rootfile" => {
+                if let Some(path) = attribute(&e, "full-path") {
+                    return Ok(path);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Err("rootfile not found in META-INF/container.xml".into())
+}
+
+/// 解析OPF文件，读取书名及阅读顺序
+fn parse_package(xml: &str) -> crate::Result<Package> {
+    let mut reader = Reader::from_str(xml);
+    let mut title: Option<String> = None;
+    let mut in_title = false;
+    // 资源ID -> (路径, 媒体类型)
+    let mut manifest: HashMap<String, (String, String)> = HashMap::new();
+    let mut itemrefs = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"title" && title.is_none() => {
+                in_title = true;
+            }
+            Event::Text(e) if in_title => {
+                let text = e.unescape()?.trim().to_string();
+                if !text.is_empty() {
+                    title = Some(text);
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"title" => in_title = false,
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attribute(&e, "id"), attribute(&e, "href")) {
+                        let media_type = attribute(&e, "media-type").unwrap_or_default();
+                        manifest.insert(id, (href, media_type));
+                    }
+                }
+                b"itemref" => {
+                    if let Some(idref) = attribute(&e, "idref") {
+                        itemrefs.push(idref);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let spine = itemrefs
+        .iter()
+        .filter_map(|idref| manifest.get(idref))
+        .filter(|(_, media_type)| {
+            matches!(
+                media_type.as_str(),
+                "application/xhtml+xml" | "text/html" | ""
+            )
+        })
+        .map(|(href, _)| href.clone())
+        .collect();
+    Ok(Package { title, spine })
+}
+
+#[test]
+fn test_parse_package() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+            <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:title>三体</dc:title>
+            </metadata>
+            <manifest>
+                <item id="c2" href="text/chapter%202.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c1" href="text/chapter1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="cover" href="images/cover.jpg" media-type="image/jpeg"/>
+            </manifest>
+            <spine><itemref idref="c1"/><itemref idref="cover"/><itemref idref="c2"/></spine>
+        </package>"#;
+    assert_eq!(
+        parse_package(xml).unwrap(),
+        Package {
+            title: Some("三体".to_string()),
+            spine: vec![
+                "text/chapter1.xhtml".to_string(),
+                "text/chapter%202.xhtml".to_string()
+            ],
+        }
+    );
+}
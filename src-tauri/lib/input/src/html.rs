@@ -0,0 +1,569 @@
+//! 网页正文提取
+//!
+//! 去掉脚本、导航、侧边栏、评论等非正文内容后，选取段落文字最多的区域作为正文，
+//! 转换为保留标题、列表及表格的结构化文档，并收集正文中的链接。
+
+use crate::Input;
+use crate::document::{Block, Document};
+use crate::package::percent_decode;
+use kuchikiki::NodeRef;
+use kuchikiki::traits::TendrilSink;
+use std::fs;
+use std::path::Path;
+use url::Url;
+
+/// 直接移除的元素
+///
+/// 不包括form，部分网站将整个页面放在form中
+const REMOVE_TAGS: &str = "script, style, noscript, template, iframe, svg, canvas, button, \
+    input, select, textarea, nav, aside, footer";
+
+/// class或id中包含这些单词的元素视为非正文
+const NOISE_WORDS: &[&str] = &[
+    "ad",
+    "ads",
+    "advert",
+    "breadcrumb",
+    "comment",
+    "comments",
+    "copyright",
+    "footer",
+    "menu",
+    "nav",
+    "navbar",
+    "recommend",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+];
+
+/// 块级元素，进入及离开时结束当前段落
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "blockquote",
+    "dl",
+    "dt",
+    "dd",
+    "figure",
+    "figcaption",
+    "address",
+    "details",
+    "summary",
+    "hr",
+];
+
+/// 正文的最少字数，article、main等元素的文字少于该值时按段落评分选取正文
+const MIN_MAIN_CHARS: usize = 200;
+
+/// 网页提取结果
+#[derive(Debug)]
+pub struct HtmlOutput {
+    /// 网页标题
+    pub title: Option<String>,
+    /// 正文
+    pub document: Document,
+    /// 正文中的链接，已转换为绝对地址并去重
+    pub links: Vec<HtmlLink>,
+}
+
+/// 链接
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlLink {
+    /// 链接文字
+    pub text: String,
+    /// 链接地址
+    pub url: String,
+}
+
+impl HtmlOutput {
+    /// 正文的文字数量
+    pub fn text_len(&self) -> usize {
+        self.document
+            .to_markdown(|_| None)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .count()
+    }
+}
+
+pub struct HtmlInput;
+
+impl Input for HtmlInput {
+    type Output = crate::Result<HtmlOutput>;
+
+    /// 读取本地网页文件，提取正文，加载网页引用的本地图片
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let path = path.as_ref();
+        let html = decode(&fs::read(path)?, None);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Ok(extract_with_images(&html, None, |src| {
+            // 只加载本地图片，忽略网络图片及内嵌的data地址
+            if src.contains(':') {
+                return None;
+            }
+            let src = percent_decode(src.split(['?', '#']).next().unwrap_or_default());
+            let path = dir.join(&src);
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            Some((name, fs::read(&path).ok()?))
+        }))
+    }
+}
+
+/// 提取网页的标题、正文及链接，忽略图片
+///
+/// - html：网页内容
+/// - base_url：网页地址，用于将相对链接转换为绝对地址
+pub fn extract(html: &str, base_url: Option<&str>) -> HtmlOutput {
+    extract_with_images(html, base_url, |_| None)
+}
+
+/// 提取网页的标题、正文及链接
+///
+/// - html：网页内容
+/// - base_url：网页地址，用于将相对链接转换为绝对地址
+/// - load_image：根据img的src加载图片，返回图片文件名及数据，返回None时忽略该图片
+pub fn extract_with_images(
+    html: &str,
+    base_url: Option<&str>,
+    load_image: impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) -> HtmlOutput {
+    let root = kuchikiki::parse_html().one(html).document_node;
+    let title = page_title(&root);
+
+    remove_noise(&root);
+    let main = main_content(&root);
+    build(title, &main, base_url, load_image)
+}
+
+/// 转换完整的HTML文档，不筛选正文，用于电子书章节、邮件正文等不含导航等内容的文档
+///
+/// - html：HTML内容
+/// - load_image：根据img的src加载图片，返回图片文件名及数据，返回None时忽略该图片
+pub fn convert(
+    html: &str,
+    load_image: impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) -> HtmlOutput {
+    let root = kuchikiki::parse_html().one(html).document_node;
+    let title = page_title(&root);
+
+    let nodes = root
+        .select("script, style, noscript, template")
+        .map(|elements| elements.map(|e| e.as_node().clone()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for node in nodes {
+        node.detach();
+    }
+    let body = root
+        .select_first("body")
+        .map(|body| body.as_node().clone())
+        .unwrap_or_else(|_| root.clone());
+    build(title, &body, None, load_image)
+}
+
+/// 将正文元素转换为文档，并收集链接
+fn build(
+    title: Option<String>,
+    main: &NodeRef,
+    base_url: Option<&str>,
+    mut load_image: impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) -> HtmlOutput {
+    let mut converter = Converter::default();
+    converter.walk(main);
+    converter.flush();
+
+    // 加载图片，加载失败或格式不支持的图片从内容块中移除
+    let mut document = Document::default();
+    let indexes = converter
+        .images
+        .iter()
+        .map(|src| load_image(src).and_then(|(name, data)| document.add_image(name, data)))
+        .collect::<Vec<_>>();
+    document.blocks = converter
+        .blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Image(index) => indexes[index].map(Block::Image),
+            block => Some(block),
+        })
+        .collect();
+
+    let base_url = base_url.and_then(|url| Url::parse(url).ok());
+    HtmlOutput {
+        title,
+        document,
+        links: links(main, base_url.as_ref()),
+    }
+}
+
+/// 按响应头或网页中声明的字符集解码，未声明时按UTF-8解码
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(2048)]).to_lowercase();
+    let label = content_type
+        .map(|content_type| content_type.to_lowercase())
+        .and_then(|content_type| charset(&content_type))
+        .or_else(|| charset(&head));
+    let encoding = label
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+/// 从`charset=gbk`形式的文本中取出字符集
+fn charset(text: &str) -> Option<String> {
+    let start = text.find("charset=")? + "charset=".len();
+    let label = text[start..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>();
+    if label.is_empty() { None } else { Some(label) }
+}
+
+/// 网页标题，依次取og:title、title及第一个h1
+fn page_title(root: &NodeRef) -> Option<String> {
+    let og_title = root
+        .select_first("meta[property='og:title']")
+        .ok()
+        .and_then(|meta| {
+            let attributes = meta.attributes.borrow();
+            attributes.get("content").map(normalize)
+        });
+    let title = || {
+        root.select_first("title")
+            .ok()
+            .map(|title| normalize(&title.text_contents()))
+    };
+    let h1 = || {
+        root.select_first("h1")
+            .ok()
+            .map(|h1| normalize(&h1.text_contents()))
+    };
+    [og_title, title(), h1()]
+        .into_iter()
+        .flatten()
+        .find(|title| !title.is_empty())
+}
+
+/// 移除脚本、导航等非正文元素
+fn remove_noise(root: &NodeRef) {
+    let mut nodes = vec![];
+    if let Ok(elements) = root.select(REMOVE_TAGS) {
+        nodes.extend(elements.map(|element| element.as_node().clone()));
+    }
+    for node in root.descendants() {
+        let Some(element) = node.as_element() else {
+            continue;
+        };
+        if matches!(&*element.name.local, "html" | "body" | "main" | "article") {
+            continue;
+        }
+        let attributes = element.attributes.borrow();
+        let names = [attributes.get("class"), attributes.get("id")]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        if names
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| NOISE_WORDS.contains(&word))
+        {
+            nodes.push(node.clone());
+        }
+    }
+    for node in nodes {
+        node.detach();
+    }
+}
+
+/// 选取正文所在的元素
+///
+/// 优先使用文字足够多的article、main元素，否则按段落评分：
+/// 每个段落的字数计入父元素，一半计入祖父元素，得分最高的元素为正文
+fn main_content(root: &NodeRef) -> NodeRef {
+    if let Ok(elements) = root.select("article, main, [role='main']") {
+        for element in elements {
+            if text_len(element.as_node()) >= MIN_MAIN_CHARS {
+                return element.as_node().clone();
+            }
+        }
+    }
+
+    let mut scores: Vec<(NodeRef, usize)> = vec![];
+    let mut add_score =
+        |node: NodeRef, score: usize| match scores.iter_mut().find(|(n, _)| *n == node) {
+            Some((_, total)) => *total += score,
+            None => scores.push((node, score)),
+        };
+    if let Ok(paragraphs) = root.select("p, pre") {
+        for paragraph in paragraphs {
+            let len = text_len(paragraph.as_node());
+            if let Some(parent) = paragraph.as_node().parent() {
+                if let Some(grandparent) = parent.parent() {
+                    add_score(grandparent, len / 2);
+                }
+                add_score(parent, len);
+            }
+        }
+    }
+    scores
+        .into_iter()
+        .filter(|(_, score)| *score > 0)
+        .max_by_key(|(_, score)| *score)
+        .map(|(node, _)| node)
+        .or_else(|| {
+            root.select_first("body")
+                .ok()
+                .map(|body| body.as_node().clone())
+        })
+        .unwrap_or_else(|| root.clone())
+}
+
+/// 正文中的链接，忽略页内锚点、脚本及邮件链接
+fn links(main: &NodeRef, base_url: Option<&Url>) -> Vec<HtmlLink> {
+    let mut links: Vec<HtmlLink> = vec![];
+    let Ok(anchors) = main.select("a[href]") else {
+        return links;
+    };
+    for anchor in anchors {
+        let Some(href) = anchor.attributes.borrow().get("href").map(str::to_string) else {
+            continue;
+        };
+        let url = match base_url {
+            Some(base_url) => base_url.join(&href),
+            None => Url::parse(&href),
+        };
+        let Ok(mut url) = url else {
+            continue;
+        };
+        if url.scheme() != "http" && url.scheme() != "https" {
+            continue;
+        }
+        url.set_fragment(None);
+        let url = url.to_string();
+        if base_url.is_some_and(|base_url| base_url.as_str() == url) {
+            continue;
+        }
+        if links.iter().any(|link| link.url == url) {
+            continue;
+        }
+        links.push(HtmlLink {
+            text: normalize(&anchor.text_contents()),
+            url,
+        });
+    }
+    links
+}
+
+fn text_len(node: &NodeRef) -> usize {
+    node.text_contents()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count()
+}
+
+/// 合并连续的空白字符
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 合并连续的空白字符，保留首尾的空白，以便与相邻的行内元素之间保持分隔
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::new();
+    let mut last_whitespace = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_whitespace {
+                result.push(' ');
+            }
+            last_whitespace = true;
+        } else {
+            result.push(c);
+            last_whitespace = false;
+        }
+    }
+    result
+}
+
+/// 将正文元素转换为内容块
+#[derive(Default)]
+struct Converter {
+    blocks: Vec<Block>,
+    /// 当前段落的文字
+    buffer: String,
+    /// 列表嵌套层数
+    list_depth: usize,
+    /// 当前所在列表项的级别
+    list_item: Option<usize>,
+    /// 图片地址，内容块中的图片下标对应该列表
+    images: Vec<String>,
+}
+
+impl Converter {
+    fn walk(&mut self, node: &NodeRef) {
+        for child in node.children() {
+            if let Some(text) = child.as_text() {
+                self.buffer.push_str(&collapse_whitespace(&text.borrow()));
+                continue;
+            }
+            let Some(element) = child.as_element() else {
+                continue;
+            };
+            let name = element.name.local.to_string();
+            match name.as_str() {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    self.flush();
+                    let text = normalize(&child.text_contents());
+                    if !text.is_empty() {
+                        self.blocks.push(Block::Heading {
+                            level: name[1..].parse().unwrap_or(1),
+                            text,
+                        });
+                    }
+                }
+                "ul" | "ol" => {
+                    self.flush();
+                    self.list_depth += 1;
+                    self.walk(&child);
+                    self.flush();
+                    self.list_depth -= 1;
+                }
+                "li" => {
+                    self.flush();
+                    let outer = self.list_item.replace(self.list_depth.saturating_sub(1));
+                    self.walk(&child);
+                    self.flush();
+                    self.list_item = outer;
+                }
+                "table" => {
+                    self.flush();
+                    let rows = table_rows(&child);
+                    // 单行或单列的表格多用于排版，按普通内容处理
+                    if rows.len() > 1 && rows.iter().any(|row| row.len() > 1) {
+                        self.blocks.push(Block::Table(rows));
+                    } else {
+                        self.walk(&child);
+                        self.flush();
+                    }
+                }
+                "pre" => {
+                    self.flush();
+                    let text = child.text_contents().trim().to_string();
+                    if !text.is_empty() {
+                        self.blocks.push(Block::Paragraph(text));
+                    }
+                }
+                "br" => self.buffer.push('\n'),
+                "img" => {
+                    let src = element.attributes.borrow().get("src").map(str::to_string);
+                    if let Some(src) = src.filter(|src| !src.trim().is_empty()) {
+                        self.flush();
+                        self.blocks.push(Block::Image(self.images.len()));
+                        self.images.push(src.trim().to_string());
+                    }
+                }
+                name if BLOCK_TAGS.contains(&name) => {
+                    self.flush();
+                    self.walk(&child);
+                    self.flush();
+                }
+                _ => self.walk(&child),
+            }
+        }
+    }
+
+    /// 结束当前段落，列表项内的段落作为列表项
+    fn flush(&mut self) {
+        let text = self
+            .buffer
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.buffer.clear();
+        if text.is_empty() {
+            return;
+        }
+        match self.list_item {
+            Some(level) => self.blocks.push(Block::ListItem { level, text }),
+            None => self.blocks.push(Block::Paragraph(text)),
+        }
+    }
+}
+
+/// 表格的行，嵌套表格的内容计入所在的单元格
+fn table_rows(table: &NodeRef) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let Ok(trs) = table.select("tr") else {
+        return rows;
+    };
+    for tr in trs {
+        let cells = tr
+            .as_node()
+            .children()
+            .filter(|cell| {
+                cell.as_element()
+                    .is_some_and(|element| matches!(&*element.name.local, "td" | "th"))
+            })
+            .map(|cell| normalize(&cell.text_contents()))
+            .collect::<Vec<_>>();
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            rows.push(cells);
+        }
+    }
+    rows
+}
+
+#[test]
+fn test_extract() {
+    let html = r#"<html><head><title>文章标题 - 网站</title></head><body>
+        <nav><a href="/">首页</a></nav>
+        <div class="main-content">
+            <h1>文章标题</h1>
+            <p>第一段，<a href="/docs/a.html#top">参考文档</a>。</p>
+            <ul><li>第一项<ul><li>子项</li></ul></li></ul>
+            <table><tr><th>名称</th><th>值</th></tr><tr><td>a</td><td>1</td></tr></table>
+        </div>
+        <div class="comments"><p>评论内容</p></div>
+    </body></html>"#;
+    let output = extract(html, Some("https://example.com/post/1.html"));
+    assert_eq!(output.title.as_deref(), Some("文章标题 - 网站"));
+    assert_eq!(
+        output.document.blocks,
+        vec![
+            Block::Heading {
+                level: 1,
+                text: "文章标题".to_string()
+            },
+            Block::Paragraph("第一段，参考文档。".to_string()),
+            Block::ListItem {
+                level: 0,
+                text: "第一项".to_string()
+            },
+            Block::ListItem {
+                level: 1,
+                text: "子项".to_string()
+            },
+            Block::Table(vec![
+                vec!["名称".to_string(), "值".to_string()],
+                vec!["a".to_string(), "1".to_string()],
+            ]),
+        ]
+    );
+    assert_eq!(
+        output.links,
+        vec![HtmlLink {
+            text: "参考文档".to_string(),
+            url: "https://example.com/docs/a.html".to_string()
+        }]
+    );
+    let (bytes, _, _) = encoding_rs::GBK.encode("<meta charset=\"gbk\">中文");
+    assert_eq!(decode(&bytes, None), "<meta charset=\"gbk\">中文");
+}
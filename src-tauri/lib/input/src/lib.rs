@@ -1,12 +1,25 @@
+pub mod chunk;
+pub mod code;
 pub mod csv;
+pub mod document;
 pub mod docx;
+pub mod email;
+pub mod eml;
+pub mod epub;
+pub mod html;
 pub mod md;
+pub mod msg;
+pub mod odf;
+mod package;
 pub mod pdf;
+pub mod pptx;
+pub mod rtf;
+pub mod table;
 pub mod txt;
 pub mod url;
 pub mod xlsx;
 
-use std::cmp::min;
+use crate::chunk::ChunkOptions;
 use std::path::Path;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -17,20 +30,13 @@ pub trait Input {
 }
 
 pub trait Split {
+    /// 使用默认的分段参数分段
     fn split(s: String) -> Vec<String> {
-        // 每512个字符切分
-        let mut result = vec![];
-        let chars: Vec<char> = s.chars().collect();
-        let chunk_size = 512;
-        //let overlap = 128;
+        Self::split_with(s, &ChunkOptions::default())
+    }
 
-        let mut start = 0;
-        while start < chars.len() {
-            let end = start + chunk_size;
-            let chunk: String = chars[start..min(end, chars.len())].iter().collect();
-            result.push(chunk);
-            start = end;
-        }
-        result
+    /// 使用指定的分段策略、分段大小及重叠大小分段
+    fn split_with(s: String, options: &ChunkOptions) -> Vec<String> {
+        chunk::split(&s, options)
     }
 }
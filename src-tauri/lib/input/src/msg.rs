@@ -0,0 +1,198 @@
+use crate::Input;
+use crate::email::{self, EmailAttachment, EmailOutput, InlineImage};
+use crate::txt;
+use cfb::CompoundFile;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// 附件所在存储的名称前缀
+const ATTACHMENT_PREFIX: &str = "__attach_version1.0_#";
+
+/// MAPI属性
+const SUBJECT: u16 = 0x0037;
+const SENDER_NAME: u16 = 0x0C1A;
+const SENDER_EMAIL: u16 = 0x0C1F;
+const SENDER_SMTP_ADDRESS: u16 = 0x5D01;
+const DISPLAY_CC: u16 = 0x0E03;
+const DISPLAY_TO: u16 = 0x0E04;
+const BODY: u16 = 0x1000;
+const BODY_HTML: u16 = 0x1013;
+const ATTACH_DATA: u16 = 0x3701;
+const ATTACH_LONG_FILENAME: u16 = 0x3707;
+const ATTACH_FILENAME: u16 = 0x3704;
+const DISPLAY_NAME: u16 = 0x3001;
+const ATTACH_CONTENT_ID: u16 = 0x3712;
+
+/// 发送时间、接收时间，类型为PT_SYSTIME
+const SUBMIT_TIME_TAG: u32 = 0x0039_0040;
+const DELIVERY_TIME_TAG: u32 = 0x0E06_0040;
+
+pub struct MsgInput;
+
+impl Input for MsgInput {
+    type Output = crate::Result<EmailOutput>;
+
+    /// 读取Outlook的msg文件，解析邮件头、正文及附件
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let mut file = CompoundFile::open(File::open(path)?)?;
+
+        let sender_name = string_property(&mut file, "", SENDER_NAME);
+        let sender_email = string_property(&mut file, "", SENDER_SMTP_ADDRESS)
+            .or_else(|| string_property(&mut file, "", SENDER_EMAIL))
+            .filter(|email| email.contains('@'));
+        let from = match (sender_name, sender_email) {
+            (Some(name), Some(email)) if name != email => Some(format!("{} <{}>", name, email)),
+            (name, email) => name.or(email),
+        };
+
+        let attachment_storages = file
+            .read_root_storage()
+            .filter(|entry| entry.is_storage() && entry.name().starts_with(ATTACHMENT_PREFIX))
+            .map(|entry| entry.name().to_string())
+            .collect::<Vec<_>>();
+        let mut attachments = vec![];
+        let mut inline_images = vec![];
+        for (index, storage) in attachment_storages.iter().enumerate() {
+            let Some(data) = binary_property(&mut file, storage, ATTACH_DATA) else {
+                // 嵌入的邮件、OLE对象等没有附件数据
+                continue;
+            };
+            let name = [ATTACH_LONG_FILENAME, ATTACH_FILENAME, DISPLAY_NAME]
+                .into_iter()
+                .find_map(|id| string_property(&mut file, storage, id))
+                .unwrap_or_else(|| format!("附件{}", index + 1));
+            match string_property(&mut file, storage, ATTACH_CONTENT_ID) {
+                Some(content_id) => inline_images.push(InlineImage {
+                    content_id: content_id.trim_matches(['<', '>']).to_string(),
+                    name,
+                    data,
+                }),
+                None => attachments.push(EmailAttachment { name, data }),
+            }
+        }
+
+        // HTML正文可能以二进制属性保存，编码由正文中的meta声明，按UTF-8及GBK尝试解码
+        let html = string_property(&mut file, "", BODY_HTML)
+            .or_else(|| binary_property(&mut file, "", BODY_HTML).map(txt::decode));
+        let text = string_property(&mut file, "", BODY);
+        let (body, unused_images) =
+            email::body_document(html.as_deref(), text.as_deref(), inline_images);
+        attachments.extend(unused_images);
+
+        Ok(EmailOutput {
+            subject: string_property(&mut file, "", SUBJECT),
+            from,
+            to: string_property(&mut file, "", DISPLAY_TO),
+            cc: string_property(&mut file, "", DISPLAY_CC),
+            date: date_property(&mut file),
+            body,
+            attachments,
+        })
+    }
+}
+
+/// 读取流的全部内容，流不存在时返回None
+fn read_stream<F: Read + Seek>(file: &mut CompoundFile<F>, path: &str) -> Option<Vec<u8>> {
+    let mut stream = file.open_stream(path).ok()?;
+    let mut data = vec![];
+    stream.read_to_end(&mut data).ok()?;
+    Some(data)
+}
+
+/// 读取字符串属性，依次尝试Unicode（PT_UNICODE）及8位（PT_STRING8）字符串
+fn string_property<F: Read + Seek>(
+    file: &mut CompoundFile<F>,
+    storage: &str,
+    id: u16,
+) -> Option<String> {
+    let text = match read_stream(file, &format!("{}/__substg1.0_{:04X}001F", storage, id)) {
+        Some(data) => {
+            let units = data
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        None => txt::decode(read_stream(
+            file,
+            &format!("{}/__substg1.0_{:04X}001E", storage, id),
+        )?),
+    };
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// 读取二进制属性（PT_BINARY）
+fn binary_property<F: Read + Seek>(
+    file: &mut CompoundFile<F>,
+    storage: &str,
+    id: u16,
+) -> Option<Vec<u8>> {
+    read_stream(file, &format!("{}/__substg1.0_{:04X}0102", storage, id))
+}
+
+/// 从属性流中读取发送时间，没有发送时间时使用接收时间
+///
+/// 邮件的属性流以32字节的头部开始，之后每个属性16字节：标签、标志各4字节，值8字节
+fn date_property<F: Read + Seek>(file: &mut CompoundFile<F>) -> Option<String> {
+    let data = read_stream(file, "/__properties_version1.0")?;
+    let entries = data.get(32..)?.chunks_exact(16);
+    let find = |tag: u32| {
+        entries.clone().find_map(|entry| {
+            let entry_tag = u32::from_le_bytes(entry[0..4].try_into().ok()?);
+            let value = u64::from_le_bytes(entry[8..16].try_into().ok()?);
+            (entry_tag == tag).then_some(value)
+        })
+    };
+    let filetime = find(SUBMIT_TIME_TAG).or_else(|| find(DELIVERY_TIME_TAG))?;
+    format_filetime(filetime)
+}
+
+/// 将FILETIME（自1601-01-01起的100纳秒数）格式化为UTC时间
+fn format_filetime(filetime: u64) -> Option<String> {
+    // 1601-01-01至1970-01-01的秒数
+    const EPOCH_DIFFERENCE: i64 = 11_644_473_600;
+    let seconds = (filetime / 10_000_000) as i64 - EPOCH_DIFFERENCE;
+    if seconds < 0 {
+        return None;
+    }
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days);
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+/// 自1970-01-01起的天数转换为年月日
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[test]
+fn test_format_filetime() {
+    assert_eq!(
+        format_filetime(133_486_382_450_000_000).as_deref(),
+        Some("2024-01-02 03:04:05 UTC")
+    );
+    assert_eq!(
+        format_filetime(116_444_736_000_000_000).as_deref(),
+        Some("1970-01-01 00:00:00 UTC")
+    );
+    assert_eq!(format_filetime(0), None);
+}
@@ -0,0 +1,319 @@
+use crate::Input;
+use crate::document::{Block, Document};
+use crate::package::{attribute, file_name, read_bytes, read_entry};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::fs::File;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// 重复的行或单元格最多展开的数量，电子表格常用重复属性表示直到表格末尾的空行、空列
+const MAX_REPEAT: usize = 1000;
+
+pub struct OdfInput;
+
+#[derive(Debug)]
+pub struct OdfOutput {
+    /// 演示文稿（odp）每页幻灯片一个文档，文本文档（odt）及电子表格（ods）只有一个文档
+    pub pages: Vec<Document>,
+}
+
+impl Input for OdfInput {
+    type Output = crate::Result<OdfOutput>;
+
+    /// 读取OpenDocument文件，保留标题、列表、表格及图片，电子表格的每个工作表作为一级标题
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let xml = read_entry(&mut archive, "content.xml")?;
+        let pages = parse_content(&xml, |href| {
+            let data = read_bytes(&mut archive, href).ok()?;
+            Some((file_name(href).to_string(), data))
+        })?;
+        Ok(OdfOutput { pages })
+    }
+}
+
+/// 正在解析的段落
+struct OdfParagraph {
+    text: String,
+    /// 标题级别，普通段落为None
+    heading: Option<usize>,
+}
+
+/// 正在解析的表格
+#[derive(Default)]
+struct OdfTable {
+    rows: Vec<Vec<String>>,
+    row: Option<(Vec<String>, usize)>,
+    cell: Option<(Vec<String>, usize)>,
+}
+
+/// 解析content.xml
+///
+/// - xml：文档内容
+/// - load_image：根据图片在压缩包中的路径加载图片，返回图片文件名及数据
+fn parse_content(
+    xml: &str,
+    mut load_image: impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) -> crate::Result<Vec<Document>> {
+    let mut reader = Reader::from_str(xml);
+    let mut pages = vec![];
+    let mut document = Document::default();
+    let mut paragraphs: Vec<OdfParagraph> = vec![];
+    let mut tables: Vec<OdfTable> = vec![];
+    let mut list_depth = 0usize;
+    // 当前是否在幻灯片的标题框中
+    let mut title_frame = false;
+    // 跳过的元素嵌套层数，如修订记录中已删除的内容
+    let mut skip_depth = 0usize;
+    let spreadsheet = is_spreadsheet(xml);
+
+    loop {
+        let event = reader.read_event()?;
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(e) => match e.name().as_ref() {
+                b"text:tracked-changes" | b"office:annotation" | b"text:note-citation" => {
+                    skip_depth = 1
+                }
+                b"draw:page" => {
+                    document = Document::default();
+                }
+                b"draw:frame" => {
+                    title_frame = attribute(&e, "presentation:class").as_deref() == Some("title")
+                }
+                b"text:h" => paragraphs.push(OdfParagraph {
+                    text: String::new(),
+                    heading: Some(
+                        attribute(&e, "text:outline-level")
+                            .and_then(|level| level.parse::<usize>().ok())
+                            .unwrap_or(1),
+                    ),
+                }),
+                b"text:p" => paragraphs.push(OdfParagraph {
+                    text: String::new(),
+                    heading: None,
+                }),
+                b"text:list" => list_depth += 1,
+                b"table:table" => {
+                    // 电子表格的工作表名称作为标题
+                    if let (true, Some(name), None) =
+                        (spreadsheet, attribute(&e, "table:name"), tables.last())
+                    {
+                        document.blocks.push(Block::Heading {
+                            level: 1,
+                            text: name,
+                        });
+                    }
+                    tables.push(OdfTable::default());
+                }
+                b"table:table-row" => {
+                    if let Some(table) = tables.last_mut() {
+                        table.row = Some((vec![], repeat(&e, "table:number-rows-repeated")));
+                    }
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    if let Some(table) = tables.last_mut() {
+                        table.cell = Some((vec![], repeat(&e, "table:number-columns-repeated")));
+                    }
+                }
+                b"draw:image" => add_image(&mut document, &e, &tables, &mut load_image),
+                _ => {}
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"text:s" => push_text(
+                    &mut paragraphs,
+                    &" ".repeat(
+                        attribute(&e, "text:c")
+                            .and_then(|c| c.parse::<usize>().ok())
+                            .unwrap_or(1)
+                            .min(MAX_REPEAT),
+                    ),
+                ),
+                b"text:tab" => push_text(&mut paragraphs, "\t"),
+                b"text:line-break" => push_text(&mut paragraphs, "\n"),
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    if let Some((row, _)) = tables.last_mut().and_then(|t| t.row.as_mut()) {
+                        let count = repeat(&e, "table:number-columns-repeated");
+                        row.extend(std::iter::repeat_n(String::new(), count));
+                    }
+                }
+                b"draw:image" => add_image(&mut document, &e, &tables, &mut load_image),
+                _ => {}
+            },
+            Event::Text(e) => push_text(&mut paragraphs, &e.unescape()?),
+            Event::End(e) => match e.name().as_ref() {
+                b"text:h" | b"text:p" => {
+                    let Some(paragraph) = paragraphs.pop() else {
+                        continue;
+                    };
+                    let text = paragraph.text.trim().to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if let Some((lines, _)) = tables.last_mut().and_then(|t| t.cell.as_mut()) {
+                        lines.push(text);
+                    } else if let Some(level) = paragraph.heading {
+                        document.blocks.push(Block::Heading { level, text });
+                    } else if title_frame {
+                        document.blocks.push(Block::Heading { level: 1, text });
+                    } else if list_depth > 0 {
+                        document.blocks.push(Block::ListItem {
+                            level: list_depth - 1,
+                            text,
+                        });
+                    } else {
+                        document.blocks.push(Block::Paragraph(text));
+                    }
+                }
+                b"text:list" => list_depth = list_depth.saturating_sub(1),
+                b"draw:frame" => title_frame = false,
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    let Some(table) = tables.last_mut() else {
+                        continue;
+                    };
+                    if let (Some((lines, count)), Some((row, _))) =
+                        (table.cell.take(), table.row.as_mut())
+                    {
+                        row.extend(std::iter::repeat_n(lines.join("\n"), count));
+                    }
+                }
+                b"table:table-row" => {
+                    let Some(table) = tables.last_mut() else {
+                        continue;
+                    };
+                    if let Some((mut cells, count)) = table.row.take() {
+                        // 去掉行尾的空单元格
+                        while cells.last().is_some_and(|cell| cell.is_empty()) {
+                            cells.pop();
+                        }
+                        if !cells.is_empty() {
+                            table.rows.extend(std::iter::repeat_n(cells, count));
+                        }
+                    }
+                }
+                b"table:table" => {
+                    let Some(table) = tables.pop() else {
+                        continue;
+                    };
+                    if table.rows.is_empty() {
+                        continue;
+                    }
+                    match tables.last_mut().and_then(|t| t.cell.as_mut()) {
+                        // 嵌套表格展开为单元格内的文本
+                        Some((lines, _)) => {
+                            lines.extend(table.rows.iter().map(|row| row.join(" ")));
+                        }
+                        None => document.blocks.push(Block::Table(table.rows)),
+                    }
+                }
+                b"draw:page" => {
+                    pages.push(std::mem::take(&mut document));
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    // 文本文档及电子表格没有分页
+    if pages.is_empty() {
+        pages.push(document);
+    }
+    Ok(pages)
+}
+
+/// 是否为电子表格，content.xml的根元素中声明了文档类型
+fn is_spreadsheet(xml: &str) -> bool {
+    xml.contains("<office:spreadsheet>") || xml.contains("<office:spreadsheet ")
+}
+
+/// 行或单元格的重复次数
+fn repeat(e: &BytesStart, name: &str) -> usize {
+    attribute(e, name)
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_REPEAT)
+}
+
+fn push_text(paragraphs: &mut [OdfParagraph], text: &str) {
+    if let Some(paragraph) = paragraphs.last_mut() {
+        paragraph.text.push_str(text);
+    }
+}
+
+/// 添加图片，表格中的图片忽略
+fn add_image(
+    document: &mut Document,
+    e: &BytesStart,
+    tables: &[OdfTable],
+    load_image: &mut impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) {
+    if !tables.is_empty() {
+        return;
+    }
+    if let Some(index) = attribute(e, "xlink:href")
+        .filter(|href| !href.contains(':'))
+        .and_then(|href| load_image(href.trim_start_matches("./")))
+        .and_then(|(name, data)| document.add_image(name, data))
+    {
+        document.blocks.push(Block::Image(index));
+    }
+}
+
+#[test]
+fn test_parse_content() {
+    let xml = r#"<office:document-content><office:body><office:text>
+        <text:h text:outline-level="2">概述</text:h>
+        <text:p>第一段<text:s text:c="2"/>空格<text:note-citation>1</text:note-citation></text:p>
+        <text:list><text:list-item><text:p>一级</text:p>
+            <text:list><text:list-item><text:p>二级</text:p></text:list-item></text:list>
+        </text:list-item></text:list>
+        <table:table table:name="表1">
+            <table:table-row><table:table-cell><text:p>名称</text:p></table:table-cell>
+                <table:table-cell table:number-columns-repeated="2"><text:p>值</text:p></table:table-cell>
+                <table:table-cell table:number-columns-repeated="1024"/></table:table-row>
+            <table:table-row table:number-rows-repeated="1048576"><table:table-cell/></table:table-row>
+        </table:table>
+        <text:p><draw:frame><draw:image xlink:href="Pictures/1.png"/></draw:frame></text:p>
+    </office:text></office:body></office:document-content>"#;
+    let pages = parse_content(xml, |href| {
+        assert_eq!(href, "Pictures/1.png");
+        Some(("1.png".to_string(), vec![]))
+    })
+    .unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(
+        pages[0].blocks,
+        vec![
+            Block::Heading {
+                level: 2,
+                text: "概述".to_string()
+            },
+            Block::Paragraph("第一段  空格".to_string()),
+            Block::ListItem {
+                level: 0,
+                text: "一级".to_string()
+            },
+            Block::ListItem {
+                level: 1,
+                text: "二级".to_string()
+            },
+            Block::Table(vec![vec![
+                "名称".to_string(),
+                "值".to_string(),
+                "值".to_string()
+            ]]),
+            Block::Image(0),
+        ]
+    );
+}
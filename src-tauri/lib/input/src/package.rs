@@ -0,0 +1,92 @@
+//! 压缩包格式文档的公共方法
+//!
+//! PPTX、ODF、EPUB等格式均为zip压缩包，内容以XML文件保存，资源之间以相对路径引用。
+
+use quick_xml::events::BytesStart;
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+/// 读取压缩包中的文本文件
+pub(crate) fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> crate::Result<String> {
+    let mut content = String::new();
+    archive.by_name(name)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// 读取压缩包中的文件
+pub(crate) fn read_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> crate::Result<Vec<u8>> {
+    let mut data = vec![];
+    archive.by_name(name)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// 将相对于base_dir的路径转换为压缩包内的路径
+pub(crate) fn resolve_path(base_dir: &str, target: &str) -> String {
+    let mut segments = match target.strip_prefix('/') {
+        Some(_) => vec![],
+        None => base_dir
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>(),
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// 文件所在的目录
+pub(crate) fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+/// 文件名
+pub(crate) fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// 读取XML元素的属性，name为带前缀的属性名，如`r:id`
+pub(crate) fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// 解码URL中的百分号编码，如`chapter%201.xhtml`
+pub(crate) fn percent_decode(text: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(text)).into_owned()
+}
+
+/// 解码百分号编码，返回原始字节
+pub(crate) fn percent_decode_bytes(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                result.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    result
+}
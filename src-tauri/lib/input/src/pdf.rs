@@ -1,4 +1,5 @@
-use crate::{Input, Split};
+use crate::chunk::ChunkOptions;
+use crate::{Input, Split, chunk};
 use common::{resources_dir, temp_dir};
 use log::log;
 use pdfium_render::prelude::{PdfPageObjectsCommon, Pdfium};
@@ -33,59 +34,66 @@ impl Input for PdfInput {
     type Output = crate::Result<PdfOutput>;
 
     fn read(path: impl AsRef<Path>) -> Self::Output {
+        Self::read_with_progress(path, |_, _| {})
+    }
+}
+
+impl PdfInput {
+    /// 读取pdf并渲染每一页的快照，每渲染完一页回调一次on_page(已渲染页数, 总页数)
+    pub fn read_with_progress(
+        path: impl AsRef<Path>,
+        mut on_page: impl FnMut(usize, usize),
+    ) -> crate::Result<PdfOutput> {
         let mut data = vec![];
         #[cfg(target_os = "windows")]
         let lib_path = resources_dir!("dylib", "windows", "pdfium.dll");
         let binds = Pdfium::bind_to_library(lib_path)?;
-        Pdfium::new(binds)
-            .load_pdf_from_file(&path, None)?
-            .pages()
-            .iter()
-            .enumerate()
-            .for_each(|(index, page)| {
-                // 提取文本
-                let text = match page.text() {
-                    Ok(text) => &text.all(),
-                    Err(err) => {
-                        log::error!(
-                            "⚠️  read pdf text fail, file: {}, current page: {}, error: {}",
-                            path.as_ref().to_string_lossy(),
-                            index,
-                            err
-                        );
-                        ""
-                    }
-                };
-
-                // 毫米转像素，转换为 300 DPI 下的像素值
-                let mm_to_pixel = |mm: f32, dpi: f32| (mm * dpi / 25.4) as u32;
-                let width_pixel = mm_to_pixel(page.width().to_mm(), 150.0) as i32;
-                let height_pixel = mm_to_pixel(page.height().to_mm(), 150.0) as i32;
-                let image = page.render(width_pixel, height_pixel, None).unwrap();
-                // 保存图片
-                //let image_save_path = format!("data/temp/{}.png", uuid::Uuid::new_v4().to_string());
-                let image_save_path =
-                    temp_dir!(format!("{}.png", uuid::Uuid::new_v4().to_string()))
-                        .to_string_lossy()
-                        .into_owned();
-
-                let image = image.as_image();
-                image.save(&image_save_path).expect("save pdf image fail");
-
-                log::info!("saved pdf snapshot image to: {}", image_save_path);
-
-                data.push(PageContent {
-                    page_index: index,
-                    text: text.to_string(),
-                    snapshot: image_save_path,
-                })
+        let pdfium = Pdfium::new(binds);
+        let document = pdfium.load_pdf_from_file(&path, None)?;
+        let pages = document.pages();
+        let total = pages.len() as usize;
+        pages.iter().enumerate().for_each(|(index, page)| {
+            // 提取文本
+            let text = match page.text() {
+                Ok(text) => &text.all(),
+                Err(err) => {
+                    log::error!(
+                        "⚠️  read pdf text fail, file: {}, current page: {}, error: {}",
+                        path.as_ref().to_string_lossy(),
+                        index,
+                        err
+                    );
+                    ""
+                }
+            };
+
+            // 毫米转像素，转换为 300 DPI 下的像素值
+            let mm_to_pixel = |mm: f32, dpi: f32| (mm * dpi / 25.4) as u32;
+            let width_pixel = mm_to_pixel(page.width().to_mm(), 150.0) as i32;
+            let height_pixel = mm_to_pixel(page.height().to_mm(), 150.0) as i32;
+            let image = page.render(width_pixel, height_pixel, None).unwrap();
+            // 保存图片
+            //let image_save_path = format!("data/temp/{}.png", uuid::Uuid::new_v4().to_string());
+            let image_save_path = temp_dir!(format!("{}.png", uuid::Uuid::new_v4().to_string()))
+                .to_string_lossy()
+                .into_owned();
+
+            let image = image.as_image();
+            image.save(&image_save_path).expect("save pdf image fail");
+
+            log::info!("saved pdf snapshot image to: {}", image_save_path);
+
+            data.push(PageContent {
+                page_index: index,
+                text: text.to_string(),
+                snapshot: image_save_path,
             });
+            on_page(index + 1, total);
+        });
 
         Ok(PdfOutput { pages: data })
     }
-}
 
-impl PdfInput {
     pub fn read_and_not_snapshot(path: impl AsRef<Path>) -> crate::Result<PdfOutput> {
         let mut data = vec![];
         #[cfg(target_os = "windows")]
@@ -122,49 +130,57 @@ impl PdfInput {
 }
 #[derive(Debug)]
 pub struct PdfSplitResult {
-    /// 文本，512个字符
+    /// 文本
     pub text: String,
     /// 引用的快照图片
     pub snapshot: Vec<String>,
+    /// 分段所在的页码，从0开始
+    pub pages: Vec<usize>,
 }
 impl PdfOutput {
+    /// 使用默认的分段参数分段
     pub fn split(self) -> Vec<PdfSplitResult> {
-        let mut result = Vec::new();
-        let mut temp_text = String::new();
-        let mut temp_ref_snapshots = Vec::new();
-        for page_content in self.pages {
-            // 当前页文本
-            let mut text = page_content.text;
-            // 当前页快照图片
-            let snapshot = page_content.snapshot;
-            // 临时存储
-            temp_ref_snapshots.push(snapshot.clone());
-            // 不足512的
-            let mut count = temp_text.chars().count() + text.chars().count();
-            while count > 512 {
-                let pos = 512 - temp_text.chars().count();
-                let first = text.chars().take(pos).collect::<String>();
-                let last = text.chars().skip(pos).collect::<String>();
-
-                result.push(PdfSplitResult {
-                    text: format!("{}{}", temp_text, first),
-                    snapshot: temp_ref_snapshots.clone(),
-                });
-                text = last.to_string();
-                temp_text.clear();
-                temp_ref_snapshots.clear();
-                temp_ref_snapshots.push(snapshot.clone());
-                count = text.chars().count();
+        self.split_with(&ChunkOptions::default())
+    }
+
+    /// 跨页分段，每个分段引用其所跨越的页面的快照图片
+    pub fn split_with(self, options: &ChunkOptions) -> Vec<PdfSplitResult> {
+        // 拼接所有页面的文本，记录每一页在拼接后文本中的范围
+        let mut chars = Vec::new();
+        let mut page_ranges = Vec::new();
+        for page_content in &self.pages {
+            let start = chars.len();
+            chars.extend(page_content.text.chars());
+            if !page_content.text.ends_with('\n') {
+                chars.push('\n');
             }
-            temp_text.push_str(&text);
+            page_ranges.push(start..chars.len());
         }
-        if !temp_text.is_empty() {
-            result.push(PdfSplitResult {
-                text: temp_text.to_string(),
-                snapshot: temp_ref_snapshots.clone(),
-            });
-        }
-        result
+
+        chunk::split_ranges(&chars, options)
+            .into_iter()
+            .filter_map(|range| {
+                let text = chars[range.clone()].iter().collect::<String>();
+                let text = text.trim();
+                if text.is_empty() {
+                    return None;
+                }
+                let pages = self
+                    .pages
+                    .iter()
+                    .zip(&page_ranges)
+                    .filter(|(_, page_range)| {
+                        page_range.start < range.end && range.start < page_range.end
+                    })
+                    .map(|(page_content, _)| page_content)
+                    .collect::<Vec<_>>();
+                Some(PdfSplitResult {
+                    text: text.to_string(),
+                    snapshot: pages.iter().map(|p| p.snapshot.clone()).collect(),
+                    pages: pages.iter().map(|p| p.page_index).collect(),
+                })
+            })
+            .collect()
     }
 }
 
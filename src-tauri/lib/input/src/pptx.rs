@@ -0,0 +1,319 @@
+use crate::Input;
+use crate::document::{Block, Document};
+use crate::package::{attribute, file_name, parent_dir, read_bytes, read_entry, resolve_path};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+pub struct PptxInput;
+
+#[derive(Debug)]
+pub struct PptxOutput {
+    /// 幻灯片，按放映顺序排列
+    pub slides: Vec<Document>,
+}
+
+impl Input for PptxInput {
+    type Output = crate::Result<PptxOutput>;
+
+    /// 读取pptx文件，每张幻灯片的标题作为一级标题，保留列表、表格及图片
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut slides = vec![];
+        for slide_path in slide_paths(&mut archive)? {
+            let xml = read_entry(&mut archive, &slide_path)?;
+            // 幻灯片引用的图片等资源
+            let relationships = match read_entry(&mut archive, &rels_path(&slide_path)) {
+                Ok(rels) => relationships(&rels, &slide_path)?,
+                Err(_) => HashMap::new(),
+            };
+            let slide = parse_slide(&xml, |id| {
+                let target = relationships.get(id)?;
+                let data = read_bytes(&mut archive, target).ok()?;
+                Some((file_name(target).to_string(), data))
+            })?;
+            slides.push(slide);
+        }
+        Ok(PptxOutput { slides })
+    }
+}
+
+/// 按放映顺序排列的幻灯片路径
+///
+/// 放映顺序记录在presentation.xml中，读取失败时按幻灯片文件的序号排列
+fn slide_paths<R: Read + Seek>(archive: &mut ZipArchive<R>) -> crate::Result<Vec<String>> {
+    let presentation_path = "ppt/presentation.xml";
+    let ordered = read_entry(archive, presentation_path).and_then(|xml| {
+        let rels = read_entry(archive, &rels_path(presentation_path))?;
+        let relationships = relationships(&rels, presentation_path)?;
+        let mut paths = vec![];
+        let mut reader = Reader::from_str(&xml);
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sldId" => {
+                    if let Some(path) = attribute(&e, "r:id").and_then(|id| relationships.get(&id))
+                    {
+                        paths.push(path.clone());
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(paths)
+    });
+    if let Ok(paths) = ordered {
+        if !paths.is_empty() {
+            return Ok(paths);
+        }
+    }
+
+    let mut paths = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name
+                .strip_prefix("ppt/slides/slide")?
+                .strip_suffix(".xml")?
+                .parse::<usize>()
+                .ok()?;
+            Some((number, name.to_string()))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths.into_iter().map(|(_, name)| name).collect())
+}
+
+/// 部件对应的关系文件路径，如`ppt/slides/slide1.xml`对应`ppt/slides/_rels/slide1.xml.rels`
+fn rels_path(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/_rels/{}.rels", dir, name),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+/// 解析关系文件，返回`关系ID -> 资源在压缩包中的路径`，忽略外部链接
+fn relationships(xml: &str, part: &str) -> crate::Result<HashMap<String, String>> {
+    let base_dir = parent_dir(part);
+    let mut result = HashMap::new();
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if attribute(&e, "TargetMode").as_deref() == Some("External") {
+                    continue;
+                }
+                if let (Some(id), Some(target)) = (attribute(&e, "Id"), attribute(&e, "Target")) {
+                    result.insert(id, resolve_path(base_dir, &target));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+/// 正在解析的段落
+struct SlideParagraph {
+    text: String,
+    /// 缩进级别
+    level: usize,
+    /// 是否带项目符号或编号
+    bullet: bool,
+}
+
+/// 解析幻灯片
+///
+/// - xml：幻灯片内容
+/// - load_image：根据关系ID加载图片，返回图片文件名及数据
+fn parse_slide(
+    xml: &str,
+    mut load_image: impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) -> crate::Result<Document> {
+    let mut document = Document::default();
+    let mut reader = Reader::from_str(xml);
+    // 当前形状是否为标题占位符
+    let mut title_shape = false;
+    let mut paragraph: Option<SlideParagraph> = None;
+    let mut in_text = false;
+    let mut table: Option<Vec<Vec<String>>> = None;
+    let mut row: Option<Vec<String>> = None;
+    let mut cell: Option<Vec<String>> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"sp" => title_shape = false,
+                b"ph" => title_shape |= is_title_placeholder(&e),
+                b"p" => {
+                    paragraph = Some(SlideParagraph {
+                        text: String::new(),
+                        level: 0,
+                        bullet: false,
+                    })
+                }
+                b"pPr" => set_paragraph_level(&mut paragraph, &e),
+                b"buChar" | b"buAutoNum" => set_bullet(&mut paragraph, true),
+                b"buNone" => set_bullet(&mut paragraph, false),
+                b"t" => in_text = true,
+                b"tbl" => table = Some(vec![]),
+                b"tr" => row = Some(vec![]),
+                b"tc" => cell = Some(vec![]),
+                b"blip" => add_image(&mut document, &e, &mut load_image),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"ph" => title_shape |= is_title_placeholder(&e),
+                b"pPr" => set_paragraph_level(&mut paragraph, &e),
+                b"buChar" | b"buAutoNum" => set_bullet(&mut paragraph, true),
+                b"buNone" => set_bullet(&mut paragraph, false),
+                b"br" => {
+                    if let Some(paragraph) = paragraph.as_mut() {
+                        paragraph.text.push('\n');
+                    }
+                }
+                b"tc" => {
+                    if let Some(row) = row.as_mut() {
+                        row.push(String::new());
+                    }
+                }
+                b"blip" => add_image(&mut document, &e, &mut load_image),
+                _ => {}
+            },
+            Event::Text(e) if in_text => {
+                if let Some(paragraph) = paragraph.as_mut() {
+                    paragraph.text.push_str(&e.unescape()?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"sp" => title_shape = false,
+                b"t" => in_text = false,
+                b"p" => {
+                    let Some(finished) = paragraph.take() else {
+                        continue;
+                    };
+                    let text = finished.text.trim().to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if let Some(cell) = cell.as_mut() {
+                        cell.push(text);
+                    } else if title_shape {
+                        document.blocks.push(Block::Heading { level: 1, text });
+                    } else if finished.bullet || finished.level > 0 {
+                        document.blocks.push(Block::ListItem {
+                            level: finished.level,
+                            text,
+                        });
+                    } else {
+                        document.blocks.push(Block::Paragraph(text));
+                    }
+                }
+                b"tc" => {
+                    if let (Some(lines), Some(row)) = (cell.take(), row.as_mut()) {
+                        row.push(lines.join("\n"));
+                    }
+                }
+                b"tr" => {
+                    if let (Some(cells), Some(table)) = (row.take(), table.as_mut()) {
+                        if cells.iter().any(|cell| !cell.is_empty()) {
+                            table.push(cells);
+                        }
+                    }
+                }
+                b"tbl" => {
+                    if let Some(rows) = table.take() {
+                        if !rows.is_empty() {
+                            document.blocks.push(Block::Table(rows));
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(document)
+}
+
+fn is_title_placeholder(e: &BytesStart) -> bool {
+    matches!(
+        attribute(e, "type").as_deref(),
+        Some("title") | Some("ctrTitle")
+    )
+}
+
+fn set_paragraph_level(paragraph: &mut Option<SlideParagraph>, e: &BytesStart) {
+    if let (Some(paragraph), Some(level)) = (
+        paragraph.as_mut(),
+        attribute(e, "lvl").and_then(|lvl| lvl.parse::<usize>().ok()),
+    ) {
+        paragraph.level = level;
+    }
+}
+
+fn set_bullet(paragraph: &mut Option<SlideParagraph>, bullet: bool) {
+    if let Some(paragraph) = paragraph.as_mut() {
+        paragraph.bullet = bullet;
+    }
+}
+
+fn add_image(
+    document: &mut Document,
+    e: &BytesStart,
+    load_image: &mut impl FnMut(&str) -> Option<(String, Vec<u8>)>,
+) {
+    if let Some(index) = attribute(e, "r:embed")
+        .and_then(|id| load_image(&id))
+        .and_then(|(name, data)| document.add_image(name, data))
+    {
+        document.blocks.push(Block::Image(index));
+    }
+}
+
+#[test]
+fn test_parse_slide() {
+    let xml = r#"<p:sld xmlns:a="a" xmlns:p="p" xmlns:r="r"><p:cSld><p:spTree>
+        <p:sp><p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr>
+            <p:txBody><a:p><a:r><a:t>季度总结</a:t></a:r></a:p></p:txBody></p:sp>
+        <p:sp><p:txBody>
+            <a:p><a:pPr lvl="1"/><a:r><a:t>收入 &amp; 利润</a:t></a:r></a:p>
+            <a:p><a:r><a:t>说明</a:t></a:r></a:p>
+        </p:txBody></p:sp>
+        <p:graphicFrame><a:graphic><a:graphicData><a:tbl>
+            <a:tr><a:tc><a:txBody><a:p><a:r><a:t>项目</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+            <a:tr><a:tc><a:txBody><a:p><a:r><a:t>A</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+        </a:tbl></a:graphicData></a:graphic></p:graphicFrame>
+        <p:pic><p:blipFill><a:blip r:embed="rId2"/></p:blipFill></p:pic>
+    </p:spTree></p:cSld></p:sld>"#;
+    let document = parse_slide(xml, |id| {
+        assert_eq!(id, "rId2");
+        Some(("image1.png".to_string(), vec![]))
+    })
+    .unwrap();
+    assert_eq!(
+        document.blocks,
+        vec![
+            Block::Heading {
+                level: 1,
+                text: "季度总结".to_string()
+            },
+            Block::ListItem {
+                level: 1,
+                text: "收入 & 利润".to_string()
+            },
+            Block::Paragraph("说明".to_string()),
+            Block::Table(vec![vec!["项目".to_string()], vec!["A".to_string()]]),
+            Block::Image(0),
+        ]
+    );
+    assert_eq!(
+        resolve_path("ppt/slides", "../media/image1.png"),
+        "ppt/media/image1.png"
+    );
+}
@@ -0,0 +1,442 @@
+use crate::Input;
+use crate::document::{Block, Document};
+use encoding_rs::Encoding;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 不包含正文的目标组，整组跳过
+const SKIP_DESTINATIONS: &[&str] = &[
+    "author",
+    "buptim",
+    "colortbl",
+    "comment",
+    "creatim",
+    "doccomm",
+    "fldinst",
+    "footer",
+    "footerf",
+    "footerl",
+    "footerr",
+    "footnote",
+    "header",
+    "headerf",
+    "headerl",
+    "headerr",
+    "info",
+    "keywords",
+    "listtable",
+    "listoverridetable",
+    "nonshppict",
+    "object",
+    "operator",
+    "pict",
+    "printim",
+    "revtim",
+    "rsidtbl",
+    "stylesheet",
+    "subject",
+    "title",
+    "xmlnstbl",
+];
+
+pub struct RtfInput;
+
+impl Input for RtfInput {
+    type Output = crate::Result<Document>;
+
+    /// 读取rtf文件，保留大纲级别标题及表格
+    fn read(path: impl AsRef<Path>) -> Self::Output {
+        let bytes = fs::read(path)?;
+        if !bytes.starts_with(b"{\\rtf") {
+            return Err("not a rtf file".into());
+        }
+        Ok(parse(&bytes))
+    }
+}
+
+/// 组的状态，离开组时恢复
+#[derive(Clone, Copy)]
+struct GroupState {
+    destination: Destination,
+    /// `\u`之后需要跳过的替代字符数量
+    unicode_skip: usize,
+    /// 当前字体
+    font: Option<i32>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Destination {
+    /// 正文
+    Text,
+    /// 字体表，记录字体的字符集
+    FontTable,
+    /// 不输出内容
+    Skip,
+}
+
+/// 解析rtf文档
+struct Parser {
+    state: GroupState,
+    stack: Vec<GroupState>,
+    /// 文档的默认代码页
+    codepage: u32,
+    /// 字体 -> 代码页
+    font_codepages: HashMap<i32, u32>,
+    /// 字体表中正在定义的字体
+    font_definition: Option<i32>,
+    /// 待解码的字节及其代码页
+    pending: Vec<u8>,
+    pending_codepage: u32,
+    /// `\u`之后还需跳过的替代字符数量
+    skip_chars: usize,
+    /// 超出基本平面的字符以两个`\u`表示，记录前一个代理项
+    high_surrogate: Option<u32>,
+    document: Document,
+    paragraph: String,
+    /// 当前段落的标题级别
+    heading: Option<usize>,
+    /// 当前段落是否在表格中
+    in_table: bool,
+    row: Vec<String>,
+    table: Vec<Vec<String>>,
+}
+
+fn parse(bytes: &[u8]) -> Document {
+    let mut parser = Parser {
+        state: GroupState {
+            destination: Destination::Text,
+            unicode_skip: 1,
+            font: None,
+        },
+        stack: vec![],
+        codepage: 1252,
+        font_codepages: HashMap::new(),
+        font_definition: None,
+        pending: vec![],
+        pending_codepage: 1252,
+        skip_chars: 0,
+        high_surrogate: None,
+        document: Document::default(),
+        paragraph: String::new(),
+        heading: None,
+        in_table: false,
+        row: vec![],
+        table: vec![],
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                parser.stack.push(parser.state);
+                i += 1;
+            }
+            b'}' => {
+                if let Some(state) = parser.stack.pop() {
+                    parser.state = state;
+                }
+                i += 1;
+            }
+            b'\\' => i = parser.control(bytes, i + 1),
+            b'\r' | b'\n' => i += 1,
+            byte => {
+                parser.push_byte(byte);
+                i += 1;
+            }
+        }
+    }
+    parser.finish_paragraph();
+    parser.finish_table();
+    parser.document
+}
+
+impl Parser {
+    /// 解析控制字或控制符号，返回之后的位置
+    fn control(&mut self, bytes: &[u8], start: usize) -> usize {
+        let Some(&first) = bytes.get(start) else {
+            return start;
+        };
+        if !first.is_ascii_alphabetic() {
+            return match first {
+                b'\'' => {
+                    let hex = bytes.get(start + 1..start + 3).and_then(|hex| {
+                        u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+                    });
+                    if let Some(byte) = hex {
+                        self.push_byte(byte);
+                    }
+                    start + 3
+                }
+                b'*' => {
+                    // 无法识别时可以忽略的目标组
+                    self.state.destination = Destination::Skip;
+                    start + 1
+                }
+                b'~' => {
+                    self.push_char(' ');
+                    start + 1
+                }
+                b'_' => {
+                    self.push_char('-');
+                    start + 1
+                }
+                b'\r' | b'\n' => {
+                    self.paragraph_break();
+                    start + 1
+                }
+                b'\\' | b'{' | b'}' => {
+                    self.push_char(first as char);
+                    start + 1
+                }
+                _ => start + 1,
+            };
+        }
+
+        let mut end = start;
+        while end < bytes.len() && bytes[end].is_ascii_alphabetic() {
+            end += 1;
+        }
+        let word = std::str::from_utf8(&bytes[start..end]).unwrap_or_default();
+        let param_start = end;
+        if end < bytes.len() && bytes[end] == b'-' {
+            end += 1;
+        }
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        let param = std::str::from_utf8(&bytes[param_start..end])
+            .ok()
+            .and_then(|param| param.parse::<i32>().ok());
+        // 控制字之后的一个空格是分隔符
+        if end < bytes.len() && bytes[end] == b' ' {
+            end += 1;
+        }
+
+        if word == "bin" {
+            // 二进制数据，直接跳过
+            return end + param.unwrap_or(0).max(0) as usize;
+        }
+        self.control_word(word, param);
+        end
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>) {
+        if SKIP_DESTINATIONS.contains(&word) {
+            self.state.destination = Destination::Skip;
+            return;
+        }
+        match word {
+            "fonttbl" => self.state.destination = Destination::FontTable,
+            "ansicpg" => self.codepage = param.unwrap_or(1252).max(0) as u32,
+            "uc" => self.state.unicode_skip = param.unwrap_or(1).max(0) as usize,
+            "f" => match self.state.destination {
+                Destination::FontTable => self.font_definition = param,
+                _ => self.state.font = param,
+            },
+            "fcharset" => {
+                if let (Some(font), Some(codepage)) =
+                    (self.font_definition, param.and_then(charset_codepage))
+                {
+                    self.font_codepages.insert(font, codepage);
+                }
+            }
+            "u" => {
+                if let Some(code) = param {
+                    // 大于32767的字符以负数表示
+                    let code = if code < 0 { code + 65536 } else { code } as u32;
+                    match (self.high_surrogate.take(), code) {
+                        (_, 0xD800..=0xDBFF) => self.high_surrogate = Some(code),
+                        (Some(high), 0xDC00..=0xDFFF) => {
+                            let code = 0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00);
+                            if let Some(c) = char::from_u32(code) {
+                                self.push_char(c);
+                            }
+                        }
+                        _ => {
+                            if let Some(c) = char::from_u32(code) {
+                                self.push_char(c);
+                            }
+                        }
+                    }
+                    self.skip_chars = self.state.unicode_skip;
+                }
+            }
+            "par" | "sect" | "page" => self.paragraph_break(),
+            "line" => self.push_char('\n'),
+            "tab" => self.push_char('\t'),
+            "emdash" => self.push_char('—'),
+            "endash" => self.push_char('–'),
+            "bullet" => self.push_char('•'),
+            "lquote" => self.push_char('‘'),
+            "rquote" => self.push_char('’'),
+            "ldblquote" => self.push_char('“'),
+            "rdblquote" => self.push_char('”'),
+            "pard" => {
+                self.heading = None;
+                self.in_table = false;
+            }
+            "intbl" => self.in_table = true,
+            "outlinelevel" => {
+                self.heading = param
+                    .filter(|level| (0..9).contains(level))
+                    .map(|level| level as usize + 1);
+            }
+            "cell" if self.is_text() => {
+                self.flush_pending();
+                let text = std::mem::take(&mut self.paragraph);
+                self.row.push(text.trim().to_string());
+            }
+            "row" if self.is_text() => {
+                let row = std::mem::take(&mut self.row);
+                if row.iter().any(|cell| !cell.is_empty()) {
+                    self.table.push(row);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        self.state.destination == Destination::Text
+    }
+
+    /// 当前字体对应的代码页
+    fn current_codepage(&self) -> u32 {
+        self.state
+            .font
+            .and_then(|font| self.font_codepages.get(&font).copied())
+            .unwrap_or(self.codepage)
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.skip_chars > 0 {
+            self.skip_chars -= 1;
+            return;
+        }
+        if !self.is_text() {
+            return;
+        }
+        let codepage = self.current_codepage();
+        if codepage != self.pending_codepage {
+            self.flush_pending();
+            self.pending_codepage = codepage;
+        }
+        self.pending.push(byte);
+    }
+
+    fn push_char(&mut self, c: char) {
+        if !self.is_text() {
+            return;
+        }
+        self.flush_pending();
+        self.paragraph.push(c);
+    }
+
+    /// 按代码页解码待解码的字节
+    fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let text = codepage_encoding(self.pending_codepage)
+            .decode(&self.pending)
+            .0
+            .into_owned();
+        self.paragraph.push_str(&text);
+        self.pending.clear();
+    }
+
+    /// 段落结束，表格中的段落为单元格内的换行
+    fn paragraph_break(&mut self) {
+        if !self.is_text() {
+            return;
+        }
+        if self.in_table {
+            self.push_char('\n');
+        } else {
+            self.finish_paragraph();
+        }
+    }
+
+    fn finish_paragraph(&mut self) {
+        self.flush_pending();
+        let text = std::mem::take(&mut self.paragraph);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        self.finish_table();
+        let block = match self.heading {
+            Some(level) => Block::Heading {
+                level,
+                text: text.to_string(),
+            },
+            None => Block::Paragraph(text.to_string()),
+        };
+        self.document.blocks.push(block);
+    }
+
+    fn finish_table(&mut self) {
+        if !self.table.is_empty() {
+            let rows = std::mem::take(&mut self.table);
+            self.document.blocks.push(Block::Table(rows));
+        }
+    }
+}
+
+/// 字体字符集对应的代码页，默认字符集返回None，使用文档的代码页
+fn charset_codepage(charset: i32) -> Option<u32> {
+    let codepage = match charset {
+        128 => 932,
+        129 => 949,
+        134 => 936,
+        136 => 950,
+        161 => 1253,
+        162 => 1254,
+        163 => 1258,
+        177 => 1255,
+        178 => 1256,
+        186 => 1257,
+        204 => 1251,
+        222 => 874,
+        238 => 1250,
+        _ => return None,
+    };
+    Some(codepage)
+}
+
+fn codepage_encoding(codepage: u32) -> &'static Encoding {
+    let label = match codepage {
+        932 => "shift_jis".to_string(),
+        936 => "gbk".to_string(),
+        949 => "euc-kr".to_string(),
+        950 => "big5".to_string(),
+        65001 => "utf-8".to_string(),
+        codepage => format!("windows-{}", codepage),
+    };
+    Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::WINDOWS_1252)
+}
+
+#[test]
+fn test_parse() {
+    let rtf = br"{\rtf1\ansi\ansicpg936\deff0{\fonttbl{\f0\fnil\fcharset134 \'cb\'ce\'cc\'e5;}{\f1\fnil\fcharset0 Arial;}}
+{\*\generator Riched20 10.0.19041}{\info{\title x}}\viewkind4\uc1
+\pard\outlinelevel0\f0\'b8\'c5\'ca\'f6\par
+\pard\f1 caf\u233?\f0\'d6\'d0\'ce\'c4\line next\par
+\trowd\cellx1000\cellx2000\pard\intbl A\cell B\cell\row
+\pard after\par
+}";
+    let document = parse(rtf);
+    assert_eq!(
+        document.blocks,
+        vec![
+            Block::Heading {
+                level: 1,
+                text: "概述".to_string()
+            },
+            Block::Paragraph("café中文\nnext".to_string()),
+            Block::Table(vec![vec!["A".to_string(), "B".to_string()]]),
+            Block::Paragraph("after".to_string()),
+        ]
+    );
+}
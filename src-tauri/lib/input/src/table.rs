@@ -0,0 +1,195 @@
+//! 表格数据的列类型推断及文本转换
+//!
+//! 根据列中所有非空的值推断列类型，并将值规范化为对应类型的格式：
+//! 数字去掉千分位分隔符，日期转换为`YYYY-MM-DD`或`YYYY-MM-DD HH:MM:SS`。
+
+use common::table::ColumnType;
+
+/// 推断每一列的类型，并将值规范化为对应类型的格式
+///
+/// 空值不参与推断，全部为空的列视为文本。
+/// 整数与小数混合的列视为小数，其余混合的列视为文本。
+/// - rows：行数据，每一行的字段数量需与列数一致
+/// - columns：列数
+pub fn infer_column_types(rows: &mut [Vec<String>], columns: usize) -> Vec<ColumnType> {
+    let mut types = Vec::with_capacity(columns);
+    for column in 0..columns {
+        let values = || {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let column_type = if values().next().is_none() {
+            ColumnType::Text
+        } else if values().all(|value| normalize_integer(value).is_some()) {
+            ColumnType::Integer
+        } else if values().all(|value| normalize_real(value).is_some()) {
+            ColumnType::Real
+        } else if values().all(|value| normalize_date(value).is_some()) {
+            ColumnType::Date
+        } else {
+            ColumnType::Text
+        };
+
+        let normalize: fn(&str) -> Option<String> = match column_type {
+            ColumnType::Integer => normalize_integer,
+            ColumnType::Real => normalize_real,
+            ColumnType::Date => normalize_date,
+            ColumnType::Text => |_| None,
+        };
+        for row in rows.iter_mut() {
+            if let Some(value) = row.get_mut(column) {
+                if let Some(normalized) = normalize(value.trim()) {
+                    *value = normalized;
+                }
+            }
+        }
+        types.push(column_type);
+    }
+    types
+}
+
+/// 将一行数据转换为`列名: 值`形式的文本，忽略空值，便于分段后每一行仍能对应到列
+pub fn row_text(headers: &[String], row: &[String]) -> String {
+    headers
+        .iter()
+        .zip(row)
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(header, value)| format!("{}: {}", header, value.trim()))
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// 去掉千分位分隔符，分隔符位置不正确时返回None
+fn remove_thousands_separator(value: &str) -> Option<String> {
+    if !value.contains(',') {
+        return Some(value.to_string());
+    }
+    let unsigned = value.trim_start_matches(['+', '-']);
+    let integer_part = unsigned.split('.').next().unwrap_or_default();
+    let groups = integer_part.split(',').collect::<Vec<_>>();
+    let valid = !groups[0].is_empty()
+        && groups[0].len() <= 3
+        && groups[1..].iter().all(|group| group.len() == 3);
+    if valid {
+        Some(value.replace(',', ""))
+    } else {
+        None
+    }
+}
+
+/// 整数，不接受前导0（如编号`007`），避免丢失信息
+fn normalize_integer(value: &str) -> Option<String> {
+    let value = remove_thousands_separator(value)?;
+    let digits = value.trim_start_matches(['+', '-']);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None;
+    }
+    value.parse::<i64>().ok().map(|value| value.to_string())
+}
+
+/// 小数，只接受普通的十进制写法
+fn normalize_real(value: &str) -> Option<String> {
+    let value = remove_thousands_separator(value)?;
+    let digits = value.trim_start_matches(['+', '-']);
+    let mut parts = digits.splitn(2, '.');
+    let integer_part = parts.next().unwrap_or_default();
+    let fraction_part = parts.next().unwrap_or_default();
+    if integer_part.is_empty() && fraction_part.is_empty() {
+        return None;
+    }
+    if !integer_part.chars().all(|c| c.is_ascii_digit())
+        || !fraction_part.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    if integer_part.len() > 1 && integer_part.starts_with('0') {
+        return None;
+    }
+    value.parse::<f64>().ok().map(|value| value.to_string())
+}
+
+/// 日期，支持`2024-01-02`、`2024/1/2`、`2024.1.2`、`2024年1月2日`，可带`HH:MM`或`HH:MM:SS`时间
+fn normalize_date(value: &str) -> Option<String> {
+    let (date, time) = match value.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time.trim())),
+        None => (value, None),
+    };
+
+    let date = date.trim_end_matches('日');
+    let parts = date.split(['-', '/', '.', '年', '月']).collect::<Vec<_>>();
+    if parts.len() != 3 || parts[0].len() != 4 {
+        return None;
+    }
+    let year = parse_part(parts[0], 1000, 9999)?;
+    let month = parse_part(parts[1], 1, 12)?;
+    let day = parse_part(parts[2], 1, days_in_month(year, month))?;
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+
+    let time = match time {
+        None => return Some(date),
+        Some(time) => time,
+    };
+    let parts = time.split(':').collect::<Vec<_>>();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+    let hour = parse_part(parts[0], 0, 23)?;
+    let minute = parse_part(parts[1], 0, 59)?;
+    let second = match parts.get(2) {
+        Some(second) => parse_part(second, 0, 59)?,
+        None => 0,
+    };
+    Some(format!("{} {:02}:{:02}:{:02}", date, hour, minute, second))
+}
+
+fn parse_part(part: &str, min: u32, max: u32) -> Option<u32> {
+    if part.is_empty() || part.len() > 4 || !part.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value = part.parse::<u32>().ok()?;
+    if value < min || value > max {
+        return None;
+    }
+    Some(value)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[test]
+fn test_infer_column_types() {
+    let mut rows = vec![
+        vec!["1", "1,200", "2024/1/2", "007", ""],
+        vec!["2", "3.5", "2024年02月29日", "a", ""],
+        vec!["3", "", "2024-03-01 08:30", "1", ""],
+    ]
+    .into_iter()
+    .map(|row| row.into_iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    .collect::<Vec<_>>();
+    let types = infer_column_types(&mut rows, 5);
+    assert_eq!(
+        types,
+        vec![
+            ColumnType::Integer,
+            ColumnType::Real,
+            ColumnType::Date,
+            ColumnType::Text,
+            ColumnType::Text,
+        ]
+    );
+    assert_eq!(rows[0][1], "1200");
+    assert_eq!(rows[1][2], "2024-02-29");
+    assert_eq!(rows[2][2], "2024-03-01 08:30:00");
+    assert_eq!(rows[0][3], "007");
+}
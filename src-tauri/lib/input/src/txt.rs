@@ -10,16 +10,17 @@ impl Input for TxtInput {
 
     fn read(path: impl AsRef<Path>) -> Self::Output {
         let bytes = fs::read(path.as_ref())?;
+        Ok(decode(bytes))
+    }
+}
 
-        // 尝试 UTF-8
-        if let Ok(s) = String::from_utf8(bytes.clone()) {
-            return Ok(s);
-        }
-
+/// 解码未声明编码的文本，依次尝试UTF-8及GBK
+pub fn decode(bytes: Vec<u8>) -> String {
+    // 尝试 UTF-8
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
         // 尝试 GBK
-        let (cow, _, _) = GB18030.decode(&bytes);
-
-        Ok(cow.into_owned())
+        Err(e) => GB18030.decode(e.as_bytes()).0.into_owned(),
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::html::{self, HtmlOutput};
 use crate::{Input, Split};
 use common::resources_dir;
 use headless_chrome::{Browser, LaunchOptions};
@@ -5,34 +6,39 @@ use std::path::Path;
 
 pub struct UrlInput;
 impl Input for UrlInput {
-    type Output = crate::Result<String>;
+    type Output = crate::Result<HtmlOutput>;
 
+    /// 使用浏览器打开网页并提取正文，适用于需要执行脚本才能渲染内容的网页
     fn read(url: impl AsRef<Path>) -> Self::Output {
-        let browser = Browser::new(LaunchOptions {
-            headless: true,
-            // 禁用沙盒，该选项启用后会导致docker容器里的chrome连接超时,
-            // 最终导致错误：ChromeLaunchError::NoAvailablePorts
-            sandbox: false,
-            //path: Some("resources/driver/chrome/chrome".into()),
-            path: Some(resources_dir!("driver", "chrome", "chrome")),
-            ..Default::default()
-        })?;
-
-        let tab = browser.new_tab()?;
-        tab.navigate_to(url.as_ref().to_str().unwrap())?;
-        tab.wait_until_navigated()?;
-        tab.find_element("body")?.get_inner_text()?;
-        let content = tab.find_element("body")?.get_inner_text()?;
-        Ok(content)
+        let url = url.as_ref().to_str().unwrap();
+        let content = Self::render(url)?;
+        Ok(html::extract(&content, Some(url)))
     }
 }
 
 impl Split for UrlInput {}
 
 impl UrlInput {
+    /// 使用浏览器打开网页，返回渲染后的HTML
+    pub fn render(url: &str) -> crate::Result<String> {
+        let browser = Self::launch()?;
+        let tab = browser.new_tab()?;
+        tab.navigate_to(url)?;
+        tab.wait_until_navigated()?;
+        Ok(tab.get_content()?)
+    }
+
     /// 获取网页标题
     pub fn get_url_title(url: &str) -> crate::Result<String> {
-        let browser = Browser::new(LaunchOptions {
+        let browser = Self::launch()?;
+        let tab = browser.new_tab()?;
+        tab.navigate_to(url)?;
+        tab.wait_until_navigated()?;
+        Ok(tab.get_title()?)
+    }
+
+    fn launch() -> crate::Result<Browser> {
+        Ok(Browser::new(LaunchOptions {
             headless: true,
             // 禁用沙盒，该选项启用后会导致docker容器里的chrome连接超时,
             // 最终导致错误：ChromeLaunchError::NoAvailablePorts
@@ -40,11 +46,6 @@ impl UrlInput {
             //path: Some("resources/driver/chrome/chrome".into()),
             path: Some(resources_dir!("driver", "chrome", "chrome")),
             ..Default::default()
-        })?;
-
-        let tab = browser.new_tab()?;
-        tab.navigate_to(url)?;
-        tab.wait_until_navigated()?;
-        Ok(tab.get_title()?)
+        })?)
     }
 }
@@ -1,14 +1,32 @@
+use crate::document::{Block, Document};
+use crate::table::{infer_column_types, row_text};
 use crate::{Input, Split};
-use calamine::{RangeDeserializerBuilder, Reader, Xls, Xlsx, open_workbook};
+use calamine::{Data, Dimensions, Reader, Sheets, open_workbook_auto};
+use common::table::ColumnType;
+use std::collections::HashSet;
+use std::io::{Read, Seek};
 use std::path::Path;
 
+/// 在前多少行中查找表头
+const HEADER_SCAN_ROWS: usize = 10;
+
 pub struct XlsxInput;
 
 #[derive(Debug)]
 pub struct XlsxOutput {
-    /// 文本内容
+    /// 工作表，没有数据的工作表已忽略
+    pub sheets: Vec<XlsxSheet>,
+}
+
+#[derive(Debug)]
+pub struct XlsxSheet {
+    /// 工作表名称
+    pub name: String,
+    /// 表头
     pub headers: Vec<String>,
-    /// 图片
+    /// 列类型，与表头一一对应
+    pub column_types: Vec<ColumnType>,
+    /// 行数据
     pub rows: Vec<Vec<String>>,
 }
 
@@ -16,98 +34,226 @@ impl Input for XlsxInput {
     type Output = crate::Result<XlsxOutput>;
 
     fn read(path: impl AsRef<Path>) -> Self::Output {
-        let extension = path.as_ref().extension().unwrap().to_str().unwrap();
-        match extension {
-            "xlsx" => {
-                let mut workbook: Xlsx<_> = open_workbook(path)?;
-                let range = workbook.worksheets();
-                // 暂只支持获取第一个sheet
-                let sheet = range[0].to_owned().1;
-                let mut iter = RangeDeserializerBuilder::new()
-                    .has_headers(false)
-                    .from_range(&sheet)?;
-                // 首行作为表头，只支持单个表头
-                let headers: Vec<String> = iter.next().transpose()?.unwrap_or_default();
-                let headers = trim_headers(headers);
-
-                let mut rows: Vec<Vec<String>> = iter.map(|r| r.unwrap_or_default()).collect();
-                trim_rows(&headers, &mut rows);
-
-                Ok(XlsxOutput { headers, rows })
-            }
-            "xls" => {
-                let mut workbook: Xls<_> = open_workbook(path)?;
-                let range = workbook.worksheets();
-                let sheet = range[0].to_owned().1;
-                let mut iter = RangeDeserializerBuilder::new()
-                    .has_headers(false)
-                    .from_range(&sheet)?;
-
-                // 首行作为表头，只支持单个表头
-                let headers: Vec<String> = iter.next().transpose()?.unwrap_or_default();
-                let headers = trim_headers(headers);
-
-                let mut rows: Vec<Vec<String>> = iter.map(|r| r.unwrap_or_default()).collect();
-                trim_rows(&headers, &mut rows);
-
-                Ok(XlsxOutput { headers, rows })
-            }
-            _ => unimplemented!(),
+        let mut workbook = open_workbook_auto(path)?;
+        let mut sheets = vec![];
+        for name in workbook.sheet_names() {
+            let Some((headers, mut rows)) = read_sheet(&mut workbook, &name)? else {
+                continue;
+            };
+            let headers = trim_headers(headers);
+            trim_rows(&headers, &mut rows);
+            let column_types = infer_column_types(&mut rows, headers.len());
+            sheets.push(XlsxSheet {
+                name,
+                headers,
+                column_types,
+                rows,
+            });
         }
+
+        Ok(XlsxOutput { sheets })
     }
 }
 
 impl XlsxInput {
-    /// 提取表头
-    pub fn extra_headers(path: impl AsRef<Path>) -> crate::Result<Vec<String>> {
-        let extension = path.as_ref().extension().unwrap().to_str().unwrap();
-        match extension {
-            "xlsx" => {
-                let mut workbook: Xlsx<_> = open_workbook(path)?;
-                let range = workbook.worksheets();
-                let sheet = range[0].to_owned().1;
-                let mut iter = RangeDeserializerBuilder::new()
-                    .has_headers(false)
-                    .from_range(&sheet)?;
-
-                // 首行作为表头，只支持单个表头
-                let headers: Vec<String> = iter.next().transpose()?.unwrap_or_default();
-                let headers = trim_headers(headers);
-
-                Ok(headers)
+    /// 提取每个工作表的表头，返回`(工作表名称, 表头)`
+    pub fn extra_headers(path: impl AsRef<Path>) -> crate::Result<Vec<(String, Vec<String>)>> {
+        let mut workbook = open_workbook_auto(path)?;
+        let mut result = vec![];
+        for name in workbook.sheet_names() {
+            if let Some((headers, _)) = read_sheet(&mut workbook, &name)? {
+                result.push((name, trim_headers(headers)));
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// 读取工作表，填充合并单元格后识别表头，返回表头及数据行，没有数据时返回None
+fn read_sheet<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    name: &str,
+) -> crate::Result<Option<(Vec<String>, Vec<Vec<String>>)>> {
+    let range = workbook.worksheet_range(name)?;
+    let (start_row, start_col) = match range.start() {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let mut grid: Vec<Vec<String>> = range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect())
+        .collect();
+
+    let merged_regions = merged_regions(workbook, name);
+    let merged_rows = fill_merged_cells(&mut grid, &merged_regions, start_row, start_col);
+
+    Ok(detect_table(grid, &merged_rows))
+}
+
+/// 工作表中的合并单元格区域，不支持的格式返回空
+fn merged_regions<RS: Read + Seek>(workbook: &mut Sheets<RS>, name: &str) -> Vec<Dimensions> {
+    match workbook {
+        Sheets::Xlsx(xlsx) => xlsx
+            .worksheet_merge_cells(name)
+            .and_then(|regions| regions.ok())
+            .unwrap_or_default(),
+        Sheets::Xls(xls) => xls.worksheet_merge_cells(name).unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// 将合并单元格左上角的值填充到整个合并区域
+///
+/// 返回包含横向合并单元格的行，这些行可能是多级表头的上级表头
+fn fill_merged_cells(
+    grid: &mut [Vec<String>],
+    regions: &[Dimensions],
+    start_row: u32,
+    start_col: u32,
+) -> HashSet<usize> {
+    let mut merged_rows = HashSet::new();
+    for region in regions {
+        if region.start.0 < start_row || region.start.1 < start_col {
+            continue;
+        }
+        let (top, left) = (
+            (region.start.0 - start_row) as usize,
+            (region.start.1 - start_col) as usize,
+        );
+        let (bottom, right) = (
+            (region.end.0 - start_row) as usize,
+            (region.end.1 - start_col) as usize,
+        );
+        let value = match grid.get(top).and_then(|row| row.get(left)) {
+            Some(value) if !value.is_empty() => value.clone(),
+            _ => continue,
+        };
+        for row in top..=bottom.min(grid.len().saturating_sub(1)) {
+            if right > left {
+                merged_rows.insert(row);
             }
-            "xls" => {
-                let mut workbook: Xls<_> = open_workbook(path)?;
-                let range = workbook.worksheets();
-                let sheet = range[0].to_owned().1;
-                let mut iter = RangeDeserializerBuilder::new()
-                    .has_headers(false)
-                    .from_range(&sheet)?;
-
-                // 首行作为表头，只支持单个表头
-                let headers: Vec<String> = iter.next().transpose()?.unwrap_or_default();
-                let headers = trim_headers(headers);
-
-                Ok(headers)
+            for col in left..=right.min(grid[row].len().saturating_sub(1)) {
+                if grid[row][col].is_empty() {
+                    grid[row][col] = value.clone();
+                }
             }
-            _ => unimplemented!(),
         }
     }
+    merged_rows
 }
 
+/// 识别表头及数据行
+///
+/// 跳过表头之前的标题行、空行，表头为前几行中第一个由多个不同的文本组成的行。
+/// 表头包含横向合并的单元格且下一行也是表头时，合并为多级表头，列名为`上级_下级`。
+fn detect_table(
+    grid: Vec<Vec<String>>,
+    merged_rows: &HashSet<usize>,
+) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+    // 有数据的列数
+    let used_columns = (0..width)
+        .filter(|&col| {
+            grid.iter()
+                .any(|row| row.get(col).is_some_and(|v| !v.is_empty()))
+        })
+        .count();
+    if used_columns == 0 {
+        return None;
+    }
+
+    let is_header = |row: &Vec<String>| {
+        let values = row.iter().filter(|v| !v.is_empty()).collect::<Vec<_>>();
+        let distinct = values.iter().collect::<HashSet<_>>().len();
+        distinct >= 2
+            && values.len() * 2 >= used_columns
+            && values.iter().all(|v| v.parse::<f64>().is_err())
+    };
+    let header_index = grid
+        .iter()
+        .take(HEADER_SCAN_ROWS)
+        .position(is_header)
+        .or_else(|| {
+            grid.iter()
+                .position(|row| row.iter().any(|v| !v.is_empty()))
+        })?;
+
+    let mut headers = grid[header_index].clone();
+    let mut data_start = header_index + 1;
+    if merged_rows.contains(&header_index) {
+        if let Some(sub_headers) = grid.get(header_index + 1).filter(|row| is_header(row)) {
+            headers = headers
+                .iter()
+                .zip(sub_headers)
+                .map(|(parent, child)| {
+                    if parent.is_empty() || parent == child {
+                        child.clone()
+                    } else if child.is_empty() {
+                        parent.clone()
+                    } else {
+                        format!("{}_{}", parent, child)
+                    }
+                })
+                .collect();
+            data_start += 1;
+        }
+    }
+
+    let rows = grid
+        .into_iter()
+        .skip(data_start)
+        .filter(|row| row.iter().any(|v| !v.is_empty()))
+        .collect::<Vec<_>>();
+    Some((headers, rows))
+}
+
+/// 单元格转换为字符串，整数形式的小数去掉小数部分，日期转换为`YYYY-MM-DD`或`YYYY-MM-DD HH:MM:SS`
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Int(value) => value.to_string(),
+        Data::Float(value) if value.fract() == 0. && value.abs() < 1e15 => {
+            (*value as i64).to_string()
+        }
+        Data::Float(value) => value.to_string(),
+        Data::String(value) => value.trim().to_string(),
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(value) => match value.as_datetime() {
+            Some(datetime) => {
+                let datetime = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+                match datetime.strip_suffix(" 00:00:00") {
+                    Some(date) => date.to_string(),
+                    None => datetime,
+                }
+            }
+            None => value.as_f64().to_string(),
+        },
+        Data::DateTimeIso(value) | Data::DurationIso(value) => value.clone(),
+        Data::Error(_) | Data::Empty => String::new(),
+    }
+}
+
+/// 在表头前追加行号，空表头命名为`unknown_序号`，重复的表头追加序号
 fn trim_headers(headers: Vec<String>) -> Vec<String> {
     let mut headers = headers;
     headers.insert(0, "行号".to_string());
+    let mut names = HashSet::new();
     headers
         .iter()
         .enumerate()
-        .filter_map(|(index, h)| {
+        .map(|(index, h)| {
             let h = h.trim();
-            if h == "" {
-                Some(format!("unknown_{}", index))
+            let name = if h == "" {
+                format!("unknown_{}", index)
             } else {
-                Some(h.to_string())
+                h.to_string()
+            };
+            let mut unique_name = name.clone();
+            let mut suffix = 2;
+            while !names.insert(unique_name.to_lowercase()) {
+                unique_name = format!("{}_{}", name, suffix);
+                suffix += 1;
             }
+            unique_name
         })
         .collect::<Vec<_>>()
 }
@@ -131,19 +277,39 @@ fn trim_rows(headers: &Vec<String>, rows: &mut Vec<Vec<String>>) {
 }
 
 impl XlsxOutput {
+    /// 转换为结构化文档，工作表名称作为标题，每一行作为一个段落
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::default();
+        for sheet in &self.sheets {
+            document.blocks.push(Block::Heading {
+                level: 1,
+                text: sheet.name.clone(),
+            });
+            for row in &sheet.rows {
+                document
+                    .blocks
+                    .push(Block::Paragraph(row_text(&sheet.headers, row)));
+            }
+        }
+        document
+    }
+
     pub fn to_text(&self) -> String {
         let mut text = String::new();
-        let headers = &self.headers;
-        for (row_index, row) in self.rows.iter().enumerate() {
-            for (cell_index, cell) in row.iter().enumerate() {
-                text += &format!(
-                    "{}: ",
-                    headers.get(cell_index).unwrap_or(&"unknown".to_string())
-                );
-                text += cell;
-                text += "\t";
+        for sheet in &self.sheets {
+            text += &format!("工作表：{}\n", sheet.name);
+            let headers = &sheet.headers;
+            for row in sheet.rows.iter() {
+                for (cell_index, cell) in row.iter().enumerate() {
+                    text += &format!(
+                        "{}: ",
+                        headers.get(cell_index).unwrap_or(&"unknown".to_string())
+                    );
+                    text += cell;
+                    text += "\t";
+                }
+                text += "\n";
             }
-            text += "\n";
         }
         text
     }
@@ -169,3 +335,28 @@ fn test_xlsx() -> crate::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_detect_table() {
+    let grid = vec![
+        vec![
+            "2024年成绩表",
+            "2024年成绩表",
+            "2024年成绩表",
+            "2024年成绩表",
+        ],
+        vec!["姓名", "班级", "成绩", "成绩"],
+        vec!["姓名", "班级", "语文", "数学"],
+        vec!["张三", "一班", "90", "95"],
+        vec!["", "", "", ""],
+        vec!["李四", "二班", "85", "80"],
+    ]
+    .into_iter()
+    .map(|row| row.into_iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    .collect::<Vec<_>>();
+    let merged_rows = HashSet::from([0, 1]);
+    let (headers, rows) = detect_table(grid, &merged_rows).unwrap();
+    assert_eq!(headers, vec!["姓名", "班级", "成绩_语文", "成绩_数学"]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1][0], "李四");
+}
@@ -14,10 +14,12 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 mod v0_1_0;
+mod v0_2_0;
 
 #[derive(EnumIter, Debug, PartialEq)]
 pub enum AppVersion {
     V0_1_0,
+    V0_2_0,
 }
 
 impl FromStr for AppVersion {
@@ -25,6 +27,7 @@ impl FromStr for AppVersion {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "0.1.0" => Ok(AppVersion::V0_1_0),
+            "0.2.0" => Ok(AppVersion::V0_2_0),
             _ => bail!("Unknown version: {}", s),
         }
     }
@@ -34,6 +37,7 @@ impl Display for AppVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
             AppVersion::V0_1_0 => "0.1.0".to_string(),
+            AppVersion::V0_2_0 => "0.2.0".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -46,12 +50,14 @@ pub trait Migrator {
 
 pub enum MigratorHandler {
     V0_1_0(v0_1_0::Handler),
+    V0_2_0(v0_2_0::Handler),
 }
 
 impl MigratorHandler {
     pub async fn up(&self, conn: &mut RBatis) -> anyhow::Result<()> {
         match self {
             MigratorHandler::V0_1_0(handler) => handler.up(conn).await,
+            MigratorHandler::V0_2_0(handler) => handler.up(conn).await,
         }
     }
 }
@@ -60,6 +66,7 @@ impl AppVersion {
     fn get_migrator(&self) -> MigratorHandler {
         match self {
             AppVersion::V0_1_0 => MigratorHandler::V0_1_0(v0_1_0::Handler),
+            AppVersion::V0_2_0 => MigratorHandler::V0_2_0(v0_2_0::Handler),
         }
     }
 }
@@ -0,0 +1,21 @@
+use crate::db::migrations::{AppVersion, Migrator};
+use rbatis::RBatis;
+
+pub(crate) struct Handler;
+
+impl Migrator for Handler {
+    fn version(&self) -> AppVersion {
+        AppVersion::V0_2_0
+    }
+
+    async fn up(&self, conn: &mut RBatis) -> anyhow::Result<()> {
+        for sql in include_str!("up.sql")
+            .split(';')
+            .map(|sql| sql.trim())
+            .filter(|sql| !sql.is_empty())
+        {
+            conn.exec(sql, vec![]).await?;
+        }
+        Ok(())
+    }
+}
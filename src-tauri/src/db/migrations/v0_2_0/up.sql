@@ -0,0 +1,43 @@
+-- 知识库使用的嵌入模型及向量维度
+alter table knowledge_base add column embedding_config text null;
+
+-- 导入记录的标签
+alter table knowledge_base_import_record add column tags text null;
+
+-- 导入记录对应的数据表
+alter table knowledge_base_import_record add column table_names text null;
+
+-- 导入记录的分段所在的批次，重新解析时在两个批次间切换
+alter table knowledge_base_import_record add column batch_id text null;
+
+-- 导入文件的内容哈希、修改时间及所属的监听文件夹
+alter table knowledge_base_import_record add column file_hash text null;
+alter table knowledge_base_import_record add column file_mtime bigint null;
+alter table knowledge_base_import_record add column watch_folder_id bigint null;
+
+-- 导入记录的重试次数，导入记录同时作为导入队列的任务
+alter table knowledge_base_import_record add column retry_count int default 0 null;
+
+-- 导入记录最近一次的解析进度，解析失败时可查看失败所在的阶段
+alter table knowledge_base_import_record add column progress text null;
+
+-- 知识库监听的文件夹
+create table if not exists knowledge_base_watch_folder
+(
+    id                bigint               not null primary key, -- 主键
+    knowledge_base_id bigint               not null,             -- 知识库ID
+    dir               text                 not null,             -- 文件夹路径
+    include           text                 null,                 -- 包含的文件，json数组，glob格式
+    exclude           text                 null,                 -- 排除的文件及文件夹，json数组，glob格式
+    max_file_size     bigint               null,                 -- 单个文件的最大字节数
+    tags              text                 null,                 -- 导入记录的标签，json数组
+    last_sync_time    datetime             null,                 -- 上次同步时间
+    last_sync_msg     text                 null,                 -- 上次同步的结果信息
+    create_user_id    bigint               null,                 -- 创建人ID
+    update_user_id    bigint               null,                 -- 修改人ID
+    create_time       datetime             null,                 -- 创建时间
+    update_time       datetime             null,                 -- 更新时间
+    remark            varchar(500)         null,                 -- 备注
+    user_id           bigint               null,                 -- 用户ID
+    is_delete         tinyint(1) default 0 null                  -- 是否删除
+);
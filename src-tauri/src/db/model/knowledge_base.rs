@@ -1,8 +1,10 @@
 use crate::db::model::knowledge_base_import_record::{
     KnowledgeBaseImportFileContentExtractModelConfig, KnowledgeBaseImportFileContentExtractType,
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use derive_builder::Builder;
+use input::chunk;
+use input::chunk::{ChunkOptions, ChunkStrategy};
 use rbatis::executor::Executor;
 use rbatis::rbdc::DateTime;
 use rbatis::{crud, htmlsql};
@@ -35,6 +37,8 @@ pub struct KnowledgeBase {
     pub model_id: Option<i64>,
     /// 文件内容提取方式
     pub file_content_extract_type: Option<KnowledgeBaseImportFileContentExtractType>,
+    /// 嵌入模型配置，为空时使用默认模型
+    pub embedding_config: Option<KnowledgeBaseEmbeddingConfig>,
     /// 创建人ID
     pub create_user_id: Option<i64>,
     /// 修改人ID
@@ -79,6 +83,84 @@ pub struct KnowledgeBaseConfig {
     pub is_rerank: bool,
     /// rerank结果数量限制
     pub rerank_limit: usize,
+    /// 重排模型
+    #[serde(default)]
+    pub rerank_model: KnowledgeBaseRerankModel,
+    /// 重排的最低分数（0~1），低于该分数的结果丢弃，为空时不过滤
+    #[serde(default)]
+    pub rerank_min_score: Option<f32>,
+    /// 混合检索开关，开启后同时使用关键词（BM25）和向量检索，并融合结果
+    #[serde(default)]
+    pub is_hybrid_search: bool,
+    /// 分段策略
+    #[serde(default = "default_chunk_strategy")]
+    pub chunk_strategy: ChunkStrategy,
+    /// 分段大小，Token策略下为token数，其他策略下为字符数
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    /// 相邻分段的重叠大小
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
+    /// 向量索引检索的分区数量，为空时使用默认值，未建立向量索引时无效
+    #[serde(default)]
+    pub search_nprobes: Option<usize>,
+    /// 向量索引检索的精排倍数，为空时不精排，未建立向量索引时无效
+    #[serde(default)]
+    pub search_refine_factor: Option<u32>,
+    /// 分段去重的相似度阈值（0~1），导入时与知识库中已有分段或同批分段的相似度不低于该值的分段不写入，
+    /// 为空时不去重。与相似度阈值同一尺度，即`(1 + 余弦相似度) / 2`。
+    /// 开启后每个分段写入前都要检索一次向量表，会增加导入耗时
+    #[serde(default)]
+    pub chunk_dedup_score: Option<f32>,
+}
+
+fn default_chunk_size() -> usize {
+    chunk::DEFAULT_CHUNK_SIZE
+}
+
+// 分段配置加入前创建的知识库按固定512字符、无重叠分段，配置中缺少分段字段时沿用原方式，
+// 避免同一知识库中混用两种分段方式；新建的知识库使用KnowledgeBaseConfig::default()
+fn default_chunk_strategy() -> ChunkStrategy {
+    ChunkStrategy::Fixed
+}
+
+fn default_chunk_overlap() -> usize {
+    0
+}
+
+impl KnowledgeBaseConfig {
+    /// 分段配置加入前创建的知识库的配置
+    fn legacy() -> Self {
+        KnowledgeBaseConfig {
+            chunk_strategy: default_chunk_strategy(),
+            chunk_overlap: default_chunk_overlap(),
+            ..Default::default()
+        }
+    }
+
+    /// 校验配置的取值范围
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let in_range = |score: Option<f32>| score.is_none_or(|score| (0.0..=1.0).contains(&score));
+        if !in_range(Some(self.search_min_score)) {
+            bail!("相似度阈值需在0~1之间");
+        }
+        if !in_range(self.rerank_min_score) {
+            bail!("重排最低分数需在0~1之间");
+        }
+        if !in_range(self.chunk_dedup_score) {
+            bail!("分段去重的相似度阈值需在0~1之间");
+        }
+        Ok(())
+    }
+
+    /// 导入文件时使用的分段参数
+    pub fn chunk_options(&self) -> ChunkOptions {
+        ChunkOptions {
+            strategy: self.chunk_strategy.clone(),
+            chunk_size: self.chunk_size,
+            chunk_overlap: self.chunk_overlap,
+        }
+    }
 }
 impl Default for KnowledgeBaseConfig {
     fn default() -> Self {
@@ -88,6 +170,84 @@ impl Default for KnowledgeBaseConfig {
             search_extend_size: 1,
             is_rerank: false,
             rerank_limit: 3,
+            rerank_model: KnowledgeBaseRerankModel::default(),
+            rerank_min_score: None,
+            is_hybrid_search: false,
+            chunk_strategy: ChunkStrategy::default(),
+            chunk_size: chunk::DEFAULT_CHUNK_SIZE,
+            chunk_overlap: chunk::DEFAULT_CHUNK_OVERLAP,
+            search_nprobes: None,
+            search_refine_factor: None,
+            chunk_dedup_score: None,
+        }
+    }
+}
+
+/// 知识库的嵌入模型配置
+///
+/// 在创建知识库时确定，向量表的维度与其保持一致，仅在重建索引时可以变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeBaseEmbeddingConfig {
+    /// 嵌入模型
+    pub model: KnowledgeBaseEmbeddingModel,
+    /// 向量维度
+    pub dimension: usize,
+}
+
+impl Default for KnowledgeBaseEmbeddingConfig {
+    fn default() -> Self {
+        KnowledgeBaseEmbeddingConfig {
+            model: KnowledgeBaseEmbeddingModel::default(),
+            dimension: embedding::DEFAULT_TEXT_DIMENSION,
+        }
+    }
+}
+
+/// 嵌入模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    rename_all_fields = "snake_case",
+    tag = "type"
+)]
+pub enum KnowledgeBaseEmbeddingModel {
+    /// 内置的ONNX模型
+    Bundled { name: String },
+    /// 用户提供的ONNX模型
+    Onnx { dir: String },
+    /// 模型管理中配置的OpenAI兼容的嵌入模型
+    Remote { model_id: i64 },
+}
+
+impl Default for KnowledgeBaseEmbeddingModel {
+    fn default() -> Self {
+        KnowledgeBaseEmbeddingModel::Bundled {
+            name: embedding::DEFAULT_TEXT_MODEL.to_string(),
+        }
+    }
+}
+
+/// 重排模型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    rename_all_fields = "snake_case",
+    tag = "type"
+)]
+pub enum KnowledgeBaseRerankModel {
+    /// 内置的ONNX交叉编码器
+    Bundled { name: String },
+    /// 用户提供的ONNX交叉编码器
+    Onnx { dir: String },
+    /// 模型管理中配置的OpenAI风格的`/rerank`接口
+    Remote { model_id: i64 },
+}
+
+impl Default for KnowledgeBaseRerankModel {
+    fn default() -> Self {
+        KnowledgeBaseRerankModel::Bundled {
+            name: embedding::DEFAULT_RERANK_MODEL.to_string(),
         }
     }
 }
@@ -100,6 +260,15 @@ impl KnowledgeBase {
             KnowledgeBaseConfig::default()
         }
     }
+
+    pub fn get_embedding_config(&self) -> KnowledgeBaseEmbeddingConfig {
+        self.embedding_config.clone().unwrap_or_default()
+    }
+}
+
+/// 向量表对应的图片表名，图片以CLIP向量单独存放，与文本分段的向量不在同一空间
+pub fn image_table_name(table_name: &str) -> String {
+    format!("{}_image", table_name)
 }
 
 fn deserialize_config<'de, D>(deserializer: D) -> Result<Option<KnowledgeBaseConfig>, D::Error>
@@ -108,7 +277,8 @@ where
 {
     let value = serde_json::Value::deserialize(deserializer)?;
     match value {
-        serde_json::Value::Null => Ok(Some(KnowledgeBaseConfig::default())),
+        // 新建知识库时会写入默认配置，没有配置的是分段配置加入前创建的知识库
+        serde_json::Value::Null => Ok(Some(KnowledgeBaseConfig::legacy())),
         _ => Ok(Some(
             serde_json::from_str(&value.to_string()).unwrap_or_else(|e| {
                 log::error!("Deserialize knowledge config error: {}", e);
@@ -119,4 +289,41 @@ where
 }
 
 crud!(KnowledgeBase {});
+
+#[test]
+fn test_legacy_chunk_config() {
+    let config: KnowledgeBaseConfig = serde_json::from_str(
+        r#"{"searchMinScore":0.7,"searchLimit":10,"searchExtendSize":1,"isRerank":false,"rerankLimit":3}"#,
+    )
+    .unwrap();
+    assert_eq!(config.chunk_strategy, ChunkStrategy::Fixed);
+    assert_eq!(config.chunk_size, chunk::DEFAULT_CHUNK_SIZE);
+    assert_eq!(config.chunk_overlap, 0);
+
+    let config = serde_json::to_string(&KnowledgeBaseConfig::default()).unwrap();
+    let config: KnowledgeBaseConfig = serde_json::from_str(&config).unwrap();
+    assert_eq!(config.chunk_strategy, ChunkStrategy::Recursive);
+    assert_eq!(config.chunk_overlap, chunk::DEFAULT_CHUNK_OVERLAP);
+}
+
+#[test]
+fn test_validate_config() {
+    assert!(KnowledgeBaseConfig::default().validate().is_ok());
+    let config = KnowledgeBaseConfig {
+        chunk_dedup_score: Some(1.0),
+        rerank_min_score: Some(0.0),
+        ..Default::default()
+    };
+    assert!(config.validate().is_ok());
+    let config = KnowledgeBaseConfig {
+        chunk_dedup_score: Some(1.2),
+        ..Default::default()
+    };
+    assert!(config.validate().is_err());
+    let config = KnowledgeBaseConfig {
+        rerank_min_score: Some(-0.1),
+        ..Default::default()
+    };
+    assert!(config.validate().is_err());
+}
 htmlsql!(remove_mcp_server_id(rb: &dyn Executor, mcp_server_id: i64) -> Option<u32> => "src/db/mapper/knowledge_base.html");
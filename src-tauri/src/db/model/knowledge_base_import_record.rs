@@ -27,19 +27,35 @@ pub struct KnowledgeBaseImportRecord {
     pub file_size: Option<u64>,
     /// 文件路径
     pub file_path: Option<String>,
+    /// 文件内容的SHA-256，十六进制
+    pub file_hash: Option<String>,
+    /// 原始文件的修改时间，毫秒时间戳
+    pub file_mtime: Option<i64>,
+    /// 所属的监听文件夹ID，由监听文件夹同步导入的记录才有值
+    pub watch_folder_id: Option<i64>,
     /// 文件内容类型：1文档 2数据表
     pub file_content_type: Option<i8>,
     /// 文件内容提取方式型
     pub file_content_extract_type: Option<KnowledgeBaseImportFileContentExtractType>,
     /// 网页地址地址
     pub url: Option<String>,
+    /// 标签，写入每个分段的元数据，用于检索过滤
+    pub tags: Option<Vec<String>>,
+    /// 数据表名称，表格文件的每个工作表对应一张数据表
+    pub table_names: Option<Vec<String>>,
+    /// 分段所在的批次，为空时为导入记录ID，见[`KnowledgeBaseImportRecord::current_batch_id`]
+    pub batch_id: Option<String>,
     /// 导入记录的自然语言描述
     pub nld: Option<String>,
-    /// 状态：0待解析 1成功 2导入中 3失败
+    /// 状态：0待解析 1成功 2导入中 3失败 4已取消
     pub status: Option<i8>,
     /// 状态信息
     #[serde(deserialize_with = "crate::common::deserialize_to_string")]
     pub status_msg: Option<String>,
+    /// 解析失败后已重试的次数
+    pub retry_count: Option<i32>,
+    /// 最近一次的解析进度，解析失败时为失败所在的阶段
+    pub progress: Option<ImportProgress>,
     /// 开始时间
     pub start_time: Option<DateTime>,
     /// 结束时间
@@ -90,6 +106,40 @@ pub enum KnowledgeBaseImportStatus {
     Importing = 2,
     /// 失败
     Failed = 3,
+    /// 已取消
+    Cancelled = 4,
+}
+
+/// 解析阶段
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStage {
+    /// 读取文件或转换格式
+    Converting,
+    /// 渲染pdf页面快照
+    Rendering,
+    /// 识别图片中的文字（OCR或视觉模型）
+    Ocr,
+    /// 生成分段的向量
+    Embedding,
+    /// 写入向量库
+    Writing,
+}
+
+/// 解析进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    /// 导入记录ID
+    pub record_id: i64,
+    /// 当前阶段
+    pub stage: ImportStage,
+    /// 当前阶段已完成的数量，如已渲染的页数、已嵌入的分段数
+    pub current: Option<usize>,
+    /// 当前阶段的总数量
+    pub total: Option<usize>,
+    /// 毫秒时间戳
+    pub time: i64,
 }
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
@@ -178,5 +228,51 @@ pub(crate) struct KnowledgeBaseImportFileContentExtractModelConfig {
     pub(crate) model_id: i64,
 }
 
+impl KnowledgeBaseImportRecord {
+    /// 分段所在的批次
+    ///
+    /// 导入记录交替使用两个批次：`导入记录ID`和`导入记录ID-1`。重新解析时写入另一个批次，
+    /// 解析成功后切换到该批次并删除原批次的分段，解析失败时原批次的分段保持不变
+    pub fn current_batch_id(&self) -> String {
+        self.batch_id
+            .clone()
+            .unwrap_or_else(|| self.id.unwrap().to_string())
+    }
+
+    /// 另一个批次，重新解析时写入该批次
+    pub fn other_batch_id(&self) -> String {
+        let id = self.id.unwrap().to_string();
+        if self.current_batch_id() == id {
+            format!("{}-1", id)
+        } else {
+            id
+        }
+    }
+
+    /// 导入记录的两个批次
+    pub fn batch_ids(&self) -> Vec<String> {
+        vec![self.current_batch_id(), self.other_batch_id()]
+    }
+}
+
+/// 批次对应的导入记录ID
+pub fn batch_record_id(batch_id: &str) -> Option<i64> {
+    batch_id.split('-').next()?.parse().ok()
+}
+
 crud!(KnowledgeBaseImportRecord {});
 htmlsql_select_page!(list_page(param: &KnowledgeBaseImportRecordListReq) -> KnowledgeBaseImportRecord => "src/db/mapper/knowledge_base_import_record.html");
+
+#[test]
+fn test_batch_ids() {
+    let mut record = KnowledgeBaseImportRecordBuilder::default()
+        .id(Some(42))
+        .build()
+        .unwrap();
+    assert_eq!(record.batch_ids(), vec!["42", "42-1"]);
+    record.batch_id = Some(record.other_batch_id());
+    assert_eq!(record.batch_ids(), vec!["42-1", "42"]);
+    assert_eq!(batch_record_id("42"), Some(42));
+    assert_eq!(batch_record_id("42-1"), Some(42));
+    assert_eq!(batch_record_id("abc"), None);
+}
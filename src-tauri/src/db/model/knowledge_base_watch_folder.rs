@@ -0,0 +1,43 @@
+use derive_builder::Builder;
+use rbatis::crud;
+use rbatis::rbdc::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// 知识库监听的文件夹，文件夹中新增、修改、删除的文件定时同步到知识库
+#[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
+#[builder(default)]
+pub struct KnowledgeBaseWatchFolder {
+    pub id: Option<i64>,
+    /// 知识库ID
+    pub knowledge_base_id: Option<i64>,
+    /// 文件夹路径
+    pub dir: Option<String>,
+    /// 包含的文件，glob格式，相对于文件夹
+    pub include: Option<Vec<String>>,
+    /// 排除的文件及文件夹，glob格式，相对于文件夹
+    pub exclude: Option<Vec<String>>,
+    /// 单个文件的最大字节数
+    pub max_file_size: Option<u64>,
+    /// 导入记录的标签
+    pub tags: Option<Vec<String>>,
+    /// 上次同步时间
+    pub last_sync_time: Option<DateTime>,
+    /// 上次同步的结果信息
+    pub last_sync_msg: Option<String>,
+    /// 创建人ID
+    pub create_user_id: Option<i64>,
+    /// 修改人ID
+    pub update_user_id: Option<i64>,
+    /// 创建时间
+    pub create_time: Option<DateTime>,
+    /// 更新时间
+    pub update_time: Option<DateTime>,
+    /// 备注
+    pub remark: Option<String>,
+    /// 用户ID
+    pub user_id: Option<i64>,
+    /// 是否删除
+    pub is_delete: Option<i8>,
+}
+
+crud!(KnowledgeBaseWatchFolder {});
@@ -1,6 +1,7 @@
 pub(crate) mod chat_message;
 pub(crate) mod knowledge_base;
 pub(crate) mod knowledge_base_import_record;
+pub(crate) mod knowledge_base_watch_folder;
 pub(crate) mod mcp_server;
 pub(crate) mod mcp_server_define;
 pub(crate) mod model;
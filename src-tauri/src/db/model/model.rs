@@ -28,7 +28,7 @@ pub struct Model {
     pub api_key: Option<String>,
     /// 最大token
     pub max_token: Option<i32>,
-    /// 适用的任务类型：1文本生成 2视觉问答
+    /// 适用的任务类型：1文本生成 2视觉问答 3文本嵌入
     pub task_type: Option<i8>,
     /// 创建人ID
     pub create_user_id: Option<i64>,
@@ -74,6 +74,8 @@ pub enum ModelTaskType {
     TextGen = 1,
     /// 视觉问答
     VisionQA = 2,
+    /// 文本嵌入
+    Embedding = 3,
 }
 
 crud!(Model {});
@@ -18,6 +18,7 @@ create table if not exists knowledge_base
     mcp_server_ids            text                 null,                 -- MCP服务ID列表,数组格式
     model_id                  bigint               null,                 -- 语言模型ID
     file_content_extract_type tinyint(1)           null,                 -- 文件内容提取配置。该配置针对整个知识库生效，在knowledge_base_import_record中会冗余一份，记录其使用的是哪种抽取方式
+    embedding_config          text                 null,                 -- 嵌入模型配置,json格式，包含模型和向量维度
     create_user_id            bigint               null,                 -- 创建人id
     update_user_id            bigint               null,                 -- 修改人ID
     create_time               datetime             null,                 -- 创建时间
@@ -59,12 +60,19 @@ create table if not exists knowledge_base_import_record
     file_name                 text                 null,                 -- 文件名称
     file_size                 int                  null,                 -- 文件大小
     file_path                 text                 null,                 -- 文件路径
+    file_hash                 text                 null,                 -- 文件内容的SHA-256
+    file_mtime                bigint               null,                 -- 原始文件的修改时间，毫秒时间戳
+    watch_folder_id           bigint               null,                 -- 所属的监听文件夹ID
     file_content_type         tinyint(1)           null,                 -- 文件内容类型：1文档 2数据表
     file_content_extract_type text                 null,                 -- 文件内容提取配置
     url                       text                 null,                 -- 网页地址
+    tags                      text                 null,                 -- 标签，json数组，写入每个分段的元数据，用于检索过滤
+    table_names               text                 null,                 -- 数据表名称，json数组，表格文件的每个工作表对应一张数据表
     nld                       text                 null,                 -- 导入记录的自然语言描述（Natural language description）
-    status                    tinyint(1)           null,                 -- 状态：0待解析 1导入成功 2导入中 3导入失败
+    status                    tinyint(1)           null,                 -- 状态：0待解析 1导入成功 2导入中 3导入失败 4已取消
     status_msg                text                 null,                 -- 状态信息
+    retry_count               int        default 0 null,                 -- 解析失败后已重试的次数
+    progress                  text                 null,                 -- 最近一次的解析进度，json
     start_time                datetime             null,                 -- 开始时间
     end_time                  datetime             null,                 -- 结束时间
     create_user_id            bigint               null,                 -- 创建人ID
@@ -76,6 +84,27 @@ create table if not exists knowledge_base_import_record
     is_delete                 tinyint(1) default 0 null                  -- 是否删除
 );
 
+-- 知识库监听的文件夹
+create table if not exists knowledge_base_watch_folder
+(
+    id                bigint               not null primary key, -- 主键
+    knowledge_base_id bigint               not null,             -- 知识库ID
+    dir               text                 not null,             -- 文件夹路径
+    include           text                 null,                 -- 包含的文件，json数组，glob格式
+    exclude           text                 null,                 -- 排除的文件及文件夹，json数组，glob格式
+    max_file_size     bigint               null,                 -- 单个文件的最大字节数
+    tags              text                 null,                 -- 导入记录的标签，json数组
+    last_sync_time    datetime             null,                 -- 上次同步时间
+    last_sync_msg     text                 null,                 -- 上次同步的结果信息
+    create_user_id    bigint               null,                 -- 创建人ID
+    update_user_id    bigint               null,                 -- 修改人ID
+    create_time       datetime             null,                 -- 创建时间
+    update_time       datetime             null,                 -- 更新时间
+    remark            varchar(500)         null,                 -- 备注
+    user_id           bigint               null,                 -- 用户ID
+    is_delete         tinyint(1) default 0 null                  -- 是否删除
+);
+
 -- 模型定义
 create table if not exists model
 (
@@ -89,7 +118,7 @@ create table if not exists model
     base_url       text                 not null,             -- 请求地址
     api_key        text                 null,                 -- api key
     max_token      bigint               null,                 -- 最大token
-    task_type      tinyint(1)           null,                 -- 适用的任务类型：1文本生成 2视觉问答
+    task_type      tinyint(1)           null,                 -- 适用的任务类型：1文本生成 2视觉问答 3文本嵌入
     create_user_id bigint               null,                 -- 创建人id
     update_user_id bigint               null,                 -- 修改人ID
     create_time    datetime             null,                 -- 创建时间
@@ -49,8 +49,36 @@ pub fn run() {
             server::kb::commands::update_kb,
             server::kb::commands::kb_detail,
             server::kb::commands::add_kb_file,
+            server::kb::commands::add_kb_folder,
+            server::kb::commands::add_kb_url,
+            server::kb::commands::add_kb_text,
+            server::kb::commands::refresh_kb_import_record,
+            server::kb::commands::rerun_kb_import_record,
+            server::kb::commands::cancel_kb_import_record,
             server::kb::commands::kb_import_record_list,
             server::kb::commands::delete_kb_import_record,
+            server::kb::commands::update_kb_import_record_tags,
+            server::kb::commands::list_kb_chunks,
+            server::kb::commands::add_kb_chunk,
+            server::kb::commands::update_kb_chunk,
+            server::kb::commands::delete_kb_chunk,
+            server::kb::commands::add_kb_watch_folder,
+            server::kb::commands::list_kb_watch_folders,
+            server::kb::commands::delete_kb_watch_folder,
+            server::kb::commands::sync_kb_watch_folder,
+            server::kb::commands::reindex_kb,
+            server::kb::commands::kb_reindex_progress,
+            server::kb::commands::get_vector_index_options,
+            server::kb::commands::update_vector_index_options,
+            server::kb::commands::get_import_queue_options,
+            server::kb::commands::update_import_queue_options,
+            server::kb::commands::get_embedding_pool_options,
+            server::kb::commands::update_embedding_pool_options,
+            server::kb::commands::get_embedding_cache_options,
+            server::kb::commands::update_embedding_cache_options,
+            server::kb::commands::clear_embedding_cache,
+            server::kb::commands::subscribe_kb_import_events,
+            server::kb::commands::unsubscribe_kb_import_events,
             server::chat::commands::chat,
             server::chat::commands::resume,
             server::chat::commands::list_all_history_messages,
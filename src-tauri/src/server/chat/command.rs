@@ -56,7 +56,7 @@ async fn get_text(file_path: &str) -> anyhow::Result<String> {
             .collect::<String>()),
         "doc" | "docx" => Ok(DocxInput::read(file_path)
             .map_err(|e| anyhow::anyhow!(e))?
-            .content),
+            .to_markdown(|_| None)),
         "png" | "jpg" | "jpeg" | "bmp" => Ok(ocr::run(file_path).map_err(|e| anyhow::anyhow!(e))?),
         _ => Err(anyhow::anyhow!("暂不支持从该文件中提取文本")),
     }
@@ -1,11 +1,20 @@
 use crate::common::res::{PageRes, Res};
+use crate::db::model::knowledge_base_watch_folder::KnowledgeBaseWatchFolder;
+use crate::server::kb::progress::ImportEvent;
+use crate::server::kb::queue::ImportQueueOptions;
+use crate::server::kb::reindex::KnowledgeBaseReindexProgress;
 use crate::server::kb::request::{
-    KbAddReq, KnowledgeBaseImportRecordListReq, KnowledgeBaseUpdateReq,
+    KbAddFolderReq, KbAddReq, KbReindexReq, KnowledgeBaseChunkListReq,
+    KnowledgeBaseImportRecordListReq, KnowledgeBaseUpdateReq,
 };
 use crate::server::kb::response::{
-    KnowledgeBaseDetailRes, KnowledgeBaseImportRecordListRes, KnowledgeBaseListRes,
+    KbAddFileRes, KnowledgeBaseChunkRes, KnowledgeBaseDetailRes, KnowledgeBaseImportRecordListRes,
+    KnowledgeBaseListRes,
 };
 use crate::server::kb::service;
+use embedding::{EmbeddingCacheOptions, EmbeddingPoolOptions};
+use engine::VectorIndexOptions;
+use tauri::ipc::Channel;
 
 #[tauri::command]
 pub(crate) fn hello(name: &str) -> String {
@@ -53,8 +62,69 @@ pub(crate) async fn kb_detail(id: i64) -> Res<KnowledgeBaseDetailRes> {
 }
 
 #[tauri::command]
-pub(crate) async fn add_kb_file(kb_id: i64, files: Vec<String>) -> Res<()> {
-    match service::add_kb_file(kb_id, files).await {
+pub(crate) async fn add_kb_file(
+    kb_id: i64,
+    files: Vec<String>,
+    tags: Option<Vec<String>>,
+) -> Res<KbAddFileRes> {
+    match service::add_kb_file(kb_id, files, tags).await {
+        Ok(r) => Res::success(r),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_kb_folder(req: KbAddFolderReq) -> Res<KbAddFileRes> {
+    match service::add_kb_folder(req).await {
+        Ok(r) => Res::success(r),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_kb_url(
+    kb_id: i64,
+    urls: Vec<String>,
+    tags: Option<Vec<String>>,
+) -> Res<()> {
+    match service::add_kb_url(kb_id, urls, tags).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_kb_text(
+    kb_id: i64,
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+) -> Res<()> {
+    match service::add_kb_text(kb_id, title, content, tags).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn refresh_kb_import_record(id: i64) -> Res<()> {
+    match service::refresh_kb_import_record(id).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn rerun_kb_import_record(id: i64) -> Res<()> {
+    match service::rerun_kb_import_record(id).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn cancel_kb_import_record(id: i64) -> Res<()> {
+    match service::cancel_kb_import_record(id).await {
         Ok(_) => Res::success(()),
         Err(e) => Res::error(e.to_string().as_str()),
     }
@@ -77,3 +147,184 @@ pub(crate) async fn delete_kb_import_record(id: i64) -> Res<()> {
         Err(e) => Res::error(e.to_string().as_str()),
     }
 }
+
+#[tauri::command]
+pub(crate) async fn update_kb_import_record_tags(id: i64, tags: Vec<String>) -> Res<()> {
+    match service::update_kb_import_record_tags(id, tags).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn list_kb_chunks(
+    req: KnowledgeBaseChunkListReq,
+) -> Res<PageRes<KnowledgeBaseChunkRes>> {
+    match service::list_kb_chunks(req).await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_kb_chunk(record_id: i64, content: String) -> Res<()> {
+    match service::add_kb_chunk(record_id, content).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_kb_chunk(record_id: i64, chunk_id: i64, content: String) -> Res<()> {
+    match service::update_kb_chunk(record_id, chunk_id, content).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn delete_kb_chunk(record_id: i64, chunk_id: i64) -> Res<()> {
+    match service::delete_kb_chunk(record_id, chunk_id).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn add_kb_watch_folder(req: KbAddFolderReq) -> Res<()> {
+    match service::add_kb_watch_folder(req).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn list_kb_watch_folders(kb_id: i64) -> Res<Vec<KnowledgeBaseWatchFolder>> {
+    match service::list_kb_watch_folders(kb_id).await {
+        Ok(r) => Res::success(r),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn delete_kb_watch_folder(id: i64) -> Res<()> {
+    match service::delete_kb_watch_folder(id).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn sync_kb_watch_folder(id: i64) -> Res<KbWatchFolderSyncRes> {
+    match service::sync_kb_watch_folder(id).await {
+        Ok(r) => Res::success(r),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn reindex_kb(req: KbReindexReq) -> Res<()> {
+    match service::reindex_kb(req).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn kb_reindex_progress(kb_id: i64) -> Res<Option<KnowledgeBaseReindexProgress>> {
+    match service::kb_reindex_progress(kb_id).await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_vector_index_options() -> Res<VectorIndexOptions> {
+    match service::get_vector_index_options().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_vector_index_options(options: VectorIndexOptions) -> Res<()> {
+    match service::update_vector_index_options(options).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_import_queue_options() -> Res<ImportQueueOptions> {
+    match service::get_import_queue_options().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_import_queue_options(options: ImportQueueOptions) -> Res<()> {
+    match service::update_import_queue_options(options).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_embedding_pool_options() -> Res<EmbeddingPoolOptions> {
+    match service::get_embedding_pool_options().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_embedding_pool_options(options: EmbeddingPoolOptions) -> Res<()> {
+    match service::update_embedding_pool_options(options).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_embedding_cache_options() -> Res<EmbeddingCacheOptions> {
+    match service::get_embedding_cache_options().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn update_embedding_cache_options(options: EmbeddingCacheOptions) -> Res<()> {
+    match service::update_embedding_cache_options(options).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn clear_embedding_cache() -> Res<()> {
+    match service::clear_embedding_cache().await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn subscribe_kb_import_events(
+    kb_id: i64,
+    channel: Channel<ImportEvent>,
+) -> Res<()> {
+    match service::subscribe_kb_import_events(kb_id, channel).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn unsubscribe_kb_import_events(kb_id: i64) -> Res<()> {
+    match service::unsubscribe_kb_import_events(kb_id).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(e.to_string().as_str()),
+    }
+}
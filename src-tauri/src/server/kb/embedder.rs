@@ -0,0 +1,89 @@
+use crate::constant;
+use crate::db::model::knowledge_base::{KnowledgeBase, KnowledgeBaseEmbeddingModel};
+use crate::db::model::model::Model;
+use crate::db::Pool;
+use anyhow::{anyhow, bail};
+use embedding::{Embeddings, TextEmbeddingModel, BUNDLED_TEXT_MODELS};
+use rbs::value;
+
+/// 将知识库的嵌入模型配置转换为嵌入模型
+pub(crate) async fn resolve_model(
+    model: &KnowledgeBaseEmbeddingModel,
+) -> anyhow::Result<TextEmbeddingModel> {
+    let model = match model {
+        KnowledgeBaseEmbeddingModel::Bundled { name } => {
+            if !BUNDLED_TEXT_MODELS.contains(&name.as_str()) {
+                bail!("内置嵌入模型不存在：{}", name);
+            }
+            TextEmbeddingModel::Bundled { name: name.clone() }
+        }
+        KnowledgeBaseEmbeddingModel::Onnx { dir } => TextEmbeddingModel::Onnx { dir: dir.clone() },
+        KnowledgeBaseEmbeddingModel::Remote { model_id } => {
+            let model = Model::select_by_map(Pool::get()?, value! {"id": model_id}).await?;
+            if model.is_empty() {
+                bail!("嵌入模型不存在");
+            }
+            let model = model[0].clone();
+            TextEmbeddingModel::OpenAi {
+                base_url: model.base_url.unwrap_or_default(),
+                api_key: model.api_key,
+                model: model.name.unwrap_or_default(),
+            }
+        }
+    };
+    Ok(model)
+}
+
+/// 使用嵌入模型将文本转换为向量
+pub(crate) async fn embed(
+    model: &TextEmbeddingModel,
+    texts: Vec<String>,
+) -> anyhow::Result<Vec<Vec<f32>>> {
+    Embeddings::embed_texts(model, texts)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))
+}
+
+/// 使用知识库的嵌入模型将文本转换为向量，用于导入
+pub(crate) async fn embed_texts(
+    kb: &KnowledgeBase,
+    texts: Vec<String>,
+) -> anyhow::Result<Vec<Vec<f32>>> {
+    let model = resolve_model(&kb.get_embedding_config().model).await?;
+    embed(&model, texts).await
+}
+
+/// 使用知识库的嵌入模型将检索文本转换为向量
+pub(crate) async fn embed_query(kb: &KnowledgeBase, query: &str) -> anyhow::Result<Vec<f32>> {
+    let model = resolve_model(&kb.get_embedding_config().model).await?;
+    embed_query_with_model(&model, query).await
+}
+
+/// 将检索文本转换为向量
+///
+/// 默认模型（bge）需要在检索文本前添加指令，其他模型直接使用原文
+pub(crate) async fn embed_query_with_model(
+    model: &TextEmbeddingModel,
+    query: &str,
+) -> anyhow::Result<Vec<f32>> {
+    let text = if model.is_default() {
+        format!("{}{}", constant::TEXT_SEARCH_INSTRUCTION, query)
+    } else {
+        query.to_string()
+    };
+    let mut vectors = embed(model, vec![text]).await?;
+    vectors.pop().ok_or(anyhow!("嵌入模型未返回向量"))
+}
+
+/// 获取嵌入模型的输出维度
+///
+/// 通过嵌入一段测试文本得到，同时用于校验模型是否可用，因此不使用缓存
+pub(crate) async fn probe_dimension(model: &TextEmbeddingModel) -> anyhow::Result<usize> {
+    let vectors = Embeddings::embed_texts_uncached(model, vec!["dimension".to_string()])
+        .await
+        .map_err(|e| anyhow!(e.to_string()))?;
+    match vectors.first() {
+        Some(vector) if !vector.is_empty() => Ok(vector.len()),
+        _ => bail!("嵌入模型未返回向量"),
+    }
+}
@@ -1,13 +1,39 @@
 use crate::constant;
 use crate::db::model::knowledge_base::{KnowledgeBase, KnowledgeBaseConfig};
-use embedding::{Embedding, EmbeddingInput, Embeddings, Reranker};
+use embedding::{Embedding, EmbeddingInput, Embeddings};
 use engine::Engine;
 
 pub(crate) mod commands;
+pub(crate) mod embedder;
 mod parse;
+mod progress;
+mod queue;
+mod reindex;
 pub(crate) mod request;
+pub(crate) mod reranker;
 mod response;
 mod search;
 mod service;
+mod watch;
 
 pub(crate) use search::search;
+pub(crate) use watch::sync_all_watch_folders;
+
+pub(crate) async fn init() {
+    if let Err(e) = service::load_vector_index_options().await {
+        log::error!("[kb] Failed to load vector index options, reason: {}", e);
+    }
+    if let Err(e) = service::load_embedding_pool_options().await {
+        log::error!("[kb] Failed to load embedding pool options, reason: {}", e);
+    }
+    if let Err(e) = service::load_embedding_cache_options().await {
+        log::error!("[kb] Failed to load embedding cache options, reason: {}", e);
+    }
+}
+
+/// 启动导入队列，需在嵌入模型等解析依赖初始化之后调用
+pub(crate) async fn start_import_queue() {
+    if let Err(e) = service::start_import_queue().await {
+        log::error!("[kb] Failed to start import queue, reason: {}", e);
+    }
+}
@@ -1,50 +1,114 @@
-use crate::db::model::knowledge_base::KnowledgeBase;
+use crate::common::pool::HTTP_CLIENT;
+use crate::db::model::knowledge_base::{image_table_name, KnowledgeBase};
 use crate::db::model::knowledge_base_import_record::{
-    KnowledgeBaseImportFileContentExtractType, KnowledgeBaseImportFileContentType,
-    KnowledgeBaseImportRecord, KnowledgeBaseImportSource,
+    ImportStage, KnowledgeBaseImportFileContentExtractType, KnowledgeBaseImportFileContentType,
+    KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder, KnowledgeBaseImportSource,
 };
 use crate::db::model::model::Model;
 use crate::db::Pool;
-use crate::utils::file_util::make_kb_ref_file;
-use anyhow::{anyhow, bail};
+use crate::server::kb::{embedder, progress};
+use crate::utils::file_util::{copy_kb_ref_file, make_kb_ref_file};
+use anyhow::{anyhow, bail, Context};
 use common::temp_dir;
-use embedding::{Embedding, EmbeddingInput, Embeddings};
+use embedding::{Embeddings, IMAGE_DIMENSION};
 use engine::db::ContentRef;
-use engine::{AddRecordRequest, Engine, TableEngine};
+use engine::{
+    AddRecordRequest, ColumnDefinition, ColumnType, Engine, MetadataFilter, RecordMetadata,
+    SearchRequestBuilder, TableEngine,
+};
+use input::chunk;
+use input::code::{self, CodeInput, Language};
 use input::csv::CsvInput;
+use input::document::{Block, Document};
+use input::docx::DocxInput;
+use input::email::EmailOutput;
+use input::eml::EmlInput;
+use input::epub::EpubInput;
+use input::html::{self, HtmlInput, HtmlOutput};
 use input::md::MdInput;
+use input::msg::MsgInput;
+use input::odf::OdfInput;
 use input::pdf::PdfInput;
+use input::pptx::PptxInput;
+use input::rtf::RtfInput;
 use input::txt::TxtInput;
+use input::url::UrlInput;
 use input::xlsx::XlsxInput;
 use input::{Input, Split};
 use rbs::value;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
+
+/// 直接请求网页的超时时间
+const URL_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// 直接请求网页时使用的User-Agent，部分网站会拒绝没有User-Agent的请求
+const URL_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36";
+/// 每次提交嵌入的分段数量，完成后上报一次进度
+///
+/// 提交的分段在嵌入线程池中按批大小再次拆分，由多个线程同时处理，该值需大于批大小才能并行
+const EMBED_PROGRESS_BATCH: usize = 256;
+/// 直接请求网页提取的正文少于该字数时，视为需要执行脚本才能渲染内容的网页，改用浏览器打开
+const URL_MIN_CONTENT_CHARS: usize = 100;
+
+/// 可以导入的文件扩展名，源代码及结构化数据的扩展名见[`Language::from_extension`]
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "txt", "pdf", "md", "doc", "docx", "ppt", "pptx", "xls", "xlsx", "csv", "epub", "html", "htm",
+    "rtf", "odt", "ods", "odp", "eml", "msg", "png", "jpg", "jpeg", "bmp",
+];
+
+/// 是否为可以导入的文件
+pub(crate) fn is_supported_file(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    let ext = ext.to_lowercase();
+    SUPPORTED_EXTENSIONS.contains(&ext.as_str()) || Language::from_extension(&ext).is_some()
+}
 
 impl KnowledgeBaseImportRecord {
     pub(crate) async fn parse(&mut self) -> anyhow::Result<()> {
+        let kb = get_kb(self.knowledge_base_id.unwrap()).await?;
+        self.parse_with(&kb).await
+    }
+
+    /// 解析导入记录并写入知识库的向量表
+    ///
+    /// 向量表及嵌入模型均取自kb，重建索引时可传入指向新表的知识库
+    pub(crate) async fn parse_with(&mut self, kb: &KnowledgeBase) -> anyhow::Result<()> {
         let source = KnowledgeBaseImportSource::try_from(self.source.unwrap())?;
         match source {
             KnowledgeBaseImportSource::LocalFile => {
                 let file_path = &self.file_path.clone().unwrap();
-                let ext = Path::new(file_path).extension().unwrap().to_str().unwrap();
-                match ext {
-                    "txt" => parse_txt(self).await?,
-                    "pdf" => parse_pdf(self).await?,
-                    "md" => parse_md(self).await?,
-                    "doc" | "docx" => parse_docx(self).await?,
-                    "xls" | "xlsx" => parse_xlsx(self).await?,
-                    "csv" => parse_csv(self).await?,
-                    "png" | "jpg" | "jpeg" | "bmp" => parse_image(self).await?,
+                let ext = Path::new(file_path)
+                    .extension()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_lowercase();
+                match ext.as_str() {
+                    "txt" => parse_txt(self, kb).await?,
+                    "pdf" => parse_pdf(self, kb).await?,
+                    "md" => parse_md(self, kb).await?,
+                    "doc" | "docx" => parse_docx(self, kb).await?,
+                    "ppt" | "pptx" => parse_pptx(self, kb).await?,
+                    "xls" | "xlsx" => parse_xlsx(self, kb).await?,
+                    "csv" => parse_csv(self, kb).await?,
+                    "epub" => parse_epub(self, kb).await?,
+                    "html" | "htm" => parse_html(self, kb).await?,
+                    "rtf" => parse_rtf(self, kb).await?,
+                    "odt" | "ods" | "odp" => parse_odf(self, kb).await?,
+                    "eml" | "msg" => parse_email(self, kb).await?,
+                    ext if Language::from_extension(ext).is_some() => parse_code(self, kb).await?,
+                    "png" | "jpg" | "jpeg" | "bmp" => parse_image(self, kb).await?,
                     _ => bail!(format!("不支持的文件类型：{}", ext)),
                 };
             }
-            KnowledgeBaseImportSource::Url => {
-                bail!("暂不支持导入网页");
-            }
-            KnowledgeBaseImportSource::CustomText => {
-                bail!("暂不支持导入自定义文本");
-            }
+            KnowledgeBaseImportSource::Url => parse_url(self, kb).await?,
+            // 自定义文本保存为Markdown文件，按标题分段
+            KnowledgeBaseImportSource::CustomText => parse_md(self, kb).await?,
         }
 
         Ok(())
@@ -52,6 +116,11 @@ impl KnowledgeBaseImportRecord {
 }
 
 pub(crate) async fn get_table_name(knowledge_base_id: i64) -> anyhow::Result<String> {
+    let kb = get_kb(knowledge_base_id).await?;
+    Ok(kb.table_name.clone().unwrap())
+}
+
+pub(crate) async fn get_kb(knowledge_base_id: i64) -> anyhow::Result<KnowledgeBase> {
     // 获取知识库
     let kb = KnowledgeBase::select_by_map(
         Pool::get()?,
@@ -63,27 +132,24 @@ pub(crate) async fn get_table_name(knowledge_base_id: i64) -> anyhow::Result<Str
     if kb.is_empty() {
         bail!("知识库不存在");
     }
-    let kb = kb.first().unwrap();
-    Ok(kb.table_name.clone().unwrap())
+    Ok(kb.first().unwrap().clone())
 }
-async fn parse_txt(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
-    // 导入记录ID
-    let id = record.id.unwrap();
-    // 知识库ID
-    let kb_id = record.knowledge_base_id.unwrap();
+
+async fn parse_txt(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
     // 知识库对应的向量数据库表名
-    let table_name = &get_table_name(kb_id).await?;
+    let table_name = &kb.table_name.clone().unwrap();
 
     // 读取文件
     let output = TxtInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
 
     // 分段处理
-    let mut data = Vec::new();
-    for segment in TxtInput::split(output) {
-        data.push(convert_to_vector_record(id, segment, None)?);
-    }
+    let segments = TxtInput::split_with(output, &kb.get_config().chunk_options())
+        .into_iter()
+        .map(Segment::text)
+        .collect();
+    let data = convert_to_vector_records(kb, record, segments).await?;
 
     // 添加数据
     Engine::add_data(table_name, data).await?;
@@ -91,25 +157,38 @@ async fn parse_txt(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn parse_pdf(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
-    // 导入记录ID
-    let id = record.id.unwrap();
-    // 知识库ID
-    let kb_id = record.knowledge_base_id.unwrap();
+pub async fn parse_pdf(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
     // 知识库对应的向量数据库表名
-    let table_name = &get_table_name(kb_id).await?;
+    let table_name = &kb.table_name.clone().unwrap();
 
-    // 读取文件
-    let mut output = PdfInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+    // 读取文件，渲染每一页的快照
+    progress::stage(record, ImportStage::Rendering).await;
+    let mut output = PdfInput::read_with_progress(file_path, |current, total| {
+        progress::step(record, ImportStage::Rendering, current, total)
+    })
+    .map_err(|e| anyhow!(e.to_string()))?;
+
+    let extract_type = record.file_content_extract_type.clone().unwrap();
+    let ocr = !matches!(
+        extract_type,
+        KnowledgeBaseImportFileContentExtractType::Text
+    );
+    if ocr {
+        progress::stage(record, ImportStage::Ocr).await;
+    }
+    let total = output.pages.len();
+    let mut images = vec![];
 
     // 遍历每一页
-    for item in &mut output.pages {
+    for (index, item) in output.pages.iter_mut().enumerate() {
         log::debug!("Processing pdf snapshot image: {}", item.snapshot);
         let mut text = Some(String::new());
-        let extract_type = record.file_content_extract_type.clone().unwrap();
-        match extract_type {
+        match extract_type.clone() {
             KnowledgeBaseImportFileContentExtractType::Text => {
                 text = Some(item.text.clone());
             }
@@ -127,12 +206,13 @@ pub async fn parse_pdf(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()>
                 .await?;
             }
         }
-
-        if text.is_none() || text.as_ref().unwrap().is_empty() {
-            continue;
+        if ocr {
+            progress::step(record, ImportStage::Ocr, index + 1, total);
         }
 
-        item.text = text.unwrap_or_default();
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            item.text = text;
+        }
         let file_name = Path::new(&item.snapshot)
             .file_name()
             .unwrap()
@@ -148,50 +228,70 @@ pub async fn parse_pdf(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()>
         // 删除临时图片
         fs::remove_file(&item.snapshot)?;
 
-        item.snapshot = download_file;
+        item.snapshot = download_file.clone();
+        images.push(ImageFile {
+            path: save_file,
+            download_file,
+            page: Some(index as i64 + 1),
+        });
     }
 
-    // 最终需要添加到向量库的数据
-    let mut data = Vec::new();
-
-    let split_res = output.split();
+    let split_res = output.split_with(&kb.get_config().chunk_options());
     log::debug!("Text splitting result: {:?}", split_res);
 
-    // 文本拆分
-    for item in split_res {
-        data.push(convert_to_vector_record(
-            id,
-            item.text,
-            Some(item.snapshot),
-        )?);
-    }
+    // 文本拆分，最终需要添加到向量库的数据
+    let segments = split_res
+        .into_iter()
+        .map(|item| Segment {
+            text: item.text,
+            images: Some(item.snapshot),
+            urls: None,
+            payload: None,
+            metadata: RecordMetadata {
+                // 跨页的分段取起始页
+                page: item.pages.first().map(|page| *page as i64 + 1),
+                ..Default::default()
+            },
+        })
+        .collect();
+    let data = convert_to_vector_records(kb, record, segments).await?;
 
     // 添加数据
     Engine::add_data(table_name, data)
         .await
         .map_err(|e| anyhow!(e.to_string()))?;
+    add_images(kb, record, images).await;
 
     Ok(())
 }
 
-pub async fn parse_md(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
-    // 导入记录ID
-    let id = record.id.unwrap();
-    // 知识库ID
-    let kb_id = record.knowledge_base_id.unwrap();
+pub async fn parse_md(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
     // 知识库对应的向量数据库表名
-    let table_name = &get_table_name(kb_id).await?;
+    let table_name = &kb.table_name.clone().unwrap();
 
     // 读取文件
     let output = MdInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
 
     // 分段处理
-    let mut data = Vec::new();
-    for segment in MdInput::split(output) {
-        data.push(convert_to_vector_record(id, segment, None)?);
-    }
+    let segments = chunk::split_markdown(&output, &kb.get_config().chunk_options())
+        .into_iter()
+        .map(|chunk| Segment {
+            text: chunk.text,
+            images: None,
+            urls: None,
+            payload: None,
+            metadata: RecordMetadata {
+                heading_path: chunk.heading_path,
+                ..Default::default()
+            },
+        })
+        .collect();
+    let data = convert_to_vector_records(kb, record, segments).await?;
 
     // 添加数据
     Engine::add_data(table_name, data).await?;
@@ -199,162 +299,779 @@ pub async fn parse_md(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()>
     Ok(())
 }
 
-/// 解析 doc 和 docx 文件
-pub async fn parse_docx(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+/// 解析源代码及JSON、YAML、XML等结构化数据
+///
+/// 按函数、类或顶层键等语法边界分段，分段的payload记录文件路径及符号名称
+async fn parse_code(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
-    // 创建临时 pdf 文件
-    //let temp_pdf = format!("data/temp/{}.pdf", uuid::Uuid::new_v4().to_string());
-    let temp_pdf = temp_dir!(format!("{}.pdf", uuid::Uuid::new_v4().to_string()))
-        .to_string_lossy()
-        .into_owned();
+    // 知识库对应的向量数据库表名
+    let table_name = &kb.table_name.clone().unwrap();
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let language = Language::from_extension(ext).context("不支持的代码文件类型")?;
+    // 记录用户导入的原始路径，而不是知识库中保存的副本
+    let source_path = record
+        .original_file_path
+        .clone()
+        .unwrap_or_else(|| file_path.clone());
 
-    // 将 docx 转换为 pdf
-    doc_to_pdf::convert(&file_path, &temp_pdf)?;
-    // 解析 pdf
-    let mut record = record.clone();
-    record.file_path = Some(temp_pdf.clone());
-    parse_pdf(&record).await?;
+    // 读取文件
+    let output = CodeInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
 
-    // 删除临时 pdf 文件
-    fs::remove_file(temp_pdf)?;
+    // 分段处理
+    let segments = code::split_code(&output, language, &kb.get_config().chunk_options())
+        .into_iter()
+        .map(|chunk| Segment {
+            payload: Some(
+                serde_json::json!({
+                    "file_path": source_path,
+                    "symbol": chunk.symbol,
+                    "start_line": chunk.start_line,
+                })
+                .to_string(),
+            ),
+            text: chunk.text,
+            images: None,
+            urls: None,
+            metadata: RecordMetadata::default(),
+        })
+        .collect();
+    let data = convert_to_vector_records(kb, record, segments).await?;
+
+    // 添加数据
+    Engine::add_data(table_name, data).await?;
 
     Ok(())
 }
 
+/// 解析 doc 和 docx 文件
+///
+/// docx文件直接解析文档结构，doc文件或解析失败时转换为pdf后解析
+pub async fn parse_docx(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    if has_extension(file_path, "docx") {
+        match DocxInput::read(file_path) {
+            Ok(document) if !document.is_empty() => {
+                return parse_documents(record, kb, vec![document], false).await;
+            }
+            Ok(_) => log::warn!("Docx has no content, fall back to pdf: {}", file_path),
+            Err(e) => log::warn!(
+                "Parse docx fail, fall back to pdf: {}, error: {}",
+                file_path,
+                e
+            ),
+        }
+    }
+
+    parse_as_pdf(record, kb).await
+}
+
 /// 解析 xlsx 文件
-pub(crate) async fn parse_xlsx(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+///
+/// 每个工作表的每一行作为文本写入向量表，读取失败时转换为pdf后解析。
+/// 文件内容类型为数据表时，每个工作表创建一张数据表，数据表名称保存到导入记录
+pub(crate) async fn parse_xlsx(
+    record: &mut KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
-    // 创建临时 pdf 文件
-    //let temp_pdf = format!("data/temp/{}.pdf", uuid::Uuid::new_v4().to_string());
-    let temp_pdf = temp_dir!(format!("{}.pdf", uuid::Uuid::new_v4().to_string()))
-        .to_string_lossy()
-        .into_owned();
+    let file_content_type =
+        KnowledgeBaseImportFileContentType::try_from(record.file_content_type.unwrap())?;
 
-    // 将 xlsx 转换为 pdf
-    doc_to_pdf::convert(&file_path, &temp_pdf)?;
+    // 读取文件
+    let output = match XlsxInput::read(file_path) {
+        Ok(output) => output,
+        Err(e) => match file_content_type {
+            KnowledgeBaseImportFileContentType::Document => {
+                log::warn!(
+                    "Read xlsx fail, fall back to pdf: {}, error: {}",
+                    file_path,
+                    e
+                );
+                return parse_as_pdf(record, kb).await;
+            }
+            KnowledgeBaseImportFileContentType::Table => bail!(e.to_string()),
+        },
+    };
+    parse_documents(record, kb, vec![output.to_document()], false).await?;
 
-    // 解析 pdf
-    let mut record = record.clone();
-    record.file_path = Some(temp_pdf.clone());
-    parse_pdf(&record).await?;
+    match file_content_type {
+        KnowledgeBaseImportFileContentType::Document => Ok(()),
+        KnowledgeBaseImportFileContentType::Table => {
+            // 知识库对应的向量数据库表名
+            let table_name = &kb.table_name.clone().unwrap();
+            let title = record.title.clone().unwrap();
 
-    // 删除临时 pdf 文件
-    fs::remove_file(temp_pdf)?;
+            // 只有一个工作表时数据表与标题同名，否则以`标题-工作表名称`命名
+            let single_sheet = output.sheets.len() == 1;
+            let data_table_names = output
+                .sheets
+                .iter()
+                .map(|sheet| {
+                    if single_sheet {
+                        title.clone()
+                    } else {
+                        format!("{}-{}", title, sheet.name)
+                    }
+                })
+                .collect::<Vec<_>>();
+            save_table_names(record, &data_table_names).await?;
+            for (sheet, data_table_name) in output.sheets.into_iter().zip(&data_table_names) {
+                let columns = column_definitions(sheet.headers, sheet.column_types);
+
+                // 创建数据表，重新解析时替换原有的数据表
+                TableEngine::replace_table_with_rows(
+                    table_name,
+                    data_table_name,
+                    columns,
+                    sheet.rows,
+                )
+                .await?;
+            }
 
+            Ok(())
+        }
+    }
+}
+
+/// 解析 CSV 文件
+///
+/// 每一行作为文本写入向量表，读取失败时转换为pdf后解析。
+/// 文件内容类型为数据表时，创建与标题同名的数据表，数据表名称保存到导入记录
+pub(crate) async fn parse_csv(
+    record: &mut KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
     let file_content_type =
         KnowledgeBaseImportFileContentType::try_from(record.file_content_type.unwrap())?;
+
+    // 读取文件
+    let output = match CsvInput::read(file_path) {
+        Ok(output) => output,
+        Err(e) => match file_content_type {
+            KnowledgeBaseImportFileContentType::Document => {
+                log::warn!(
+                    "Read csv fail, fall back to pdf: {}, error: {}",
+                    file_path,
+                    e
+                );
+                return parse_as_pdf(record, kb).await;
+            }
+            KnowledgeBaseImportFileContentType::Table => bail!(e.to_string()),
+        },
+    };
+    parse_documents(record, kb, vec![output.to_document()], false).await?;
+
     match file_content_type {
-        KnowledgeBaseImportFileContentType::Document => {
-            // nothing
-        }
+        KnowledgeBaseImportFileContentType::Document => Ok(()),
         KnowledgeBaseImportFileContentType::Table => {
-            // 知识库ID
-            let kb_id = record.knowledge_base_id.unwrap();
             // 知识库对应的向量数据库表名
-            let table_name = &get_table_name(kb_id).await?;
-
-            // 读取文件
-            let output = XlsxInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+            let table_name = &kb.table_name.clone().unwrap();
+            let title = record.title.clone().unwrap();
 
-            let headers = output.headers;
+            let columns = column_definitions(output.headers, output.column_types);
             let rows = output.rows;
 
-            // 创建数据表
-            TableEngine::new_table_with_rows(table_name, &record.title.unwrap(), headers, rows)
-                .await?;
+            // 创建数据表，重新解析时替换原有的数据表
+            save_table_names(record, std::slice::from_ref(&title)).await?;
+            TableEngine::replace_table_with_rows(table_name, &title, columns, rows).await?;
+
+            Ok(())
         }
     }
+}
 
+/// 创建数据表前保存数据表名称
+///
+/// 数据库中的名称与原有的名称合并保存，解析失败、取消或中断时，已创建的数据表仍可按导入记录中的名称删除；
+/// 导入记录中只保存本次解析的名称，解析成功后不在其中的原有数据表被删除
+async fn save_table_names(
+    record: &mut KnowledgeBaseImportRecord,
+    names: &[String],
+) -> anyhow::Result<()> {
+    let saved = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": record.id})
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|saved| saved.table_names);
+    let mut saved = saved.unwrap_or_default();
+    let mut table_names = record.table_names.clone().unwrap_or_default();
+    for name in names {
+        if !saved.contains(name) {
+            saved.push(name.clone());
+        }
+        if !table_names.contains(name) {
+            table_names.push(name.clone());
+        }
+    }
+    KnowledgeBaseImportRecord::update_by_map(
+        Pool::get()?,
+        &KnowledgeBaseImportRecordBuilder::default()
+            .table_names(Some(saved))
+            .build()?,
+        value! {"id": record.id},
+    )
+    .await?;
+    record.table_names = Some(table_names);
     Ok(())
 }
 
-/// 解析 CSV 文件
-pub(crate) async fn parse_csv(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+/// 解析PPT文件
+///
+/// pptx文件直接解析每张幻灯片，ppt文件或解析失败时转换为pdf后解析
+pub(crate) async fn parse_pptx(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
-    // 创建临时 pdf 文件
-    //let temp_pdf = format!("data/temp/{}.pdf", uuid::Uuid::new_v4().to_string());
-    let temp_pdf = temp_dir!(format!("{}.pdf", uuid::Uuid::new_v4().to_string()))
-        .to_string_lossy()
-        .into_owned();
+    if has_extension(file_path, "pptx") {
+        match PptxInput::read(file_path) {
+            Ok(output) if output.slides.iter().any(|slide| !slide.is_empty()) => {
+                return parse_documents(record, kb, output.slides, true).await;
+            }
+            Ok(_) => log::warn!("Pptx has no content, fall back to pdf: {}", file_path),
+            Err(e) => log::warn!(
+                "Parse pptx fail, fall back to pdf: {}, error: {}",
+                file_path,
+                e
+            ),
+        }
+    }
 
-    // 将 csv 转换为 pdf
-    doc_to_pdf::convert(&file_path, &temp_pdf)?;
+    parse_as_pdf(record, kb).await
+}
 
-    // 解析 pdf
-    let mut record = record.clone();
-    record.file_path = Some(temp_pdf.clone());
-    parse_pdf(&record).await?;
+/// 解析EPUB电子书，每个章节作为一个文档，标题取自电子书的元数据
+async fn parse_epub(
+    record: &mut KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    let output = EpubInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+    if output.chapters.iter().all(|chapter| chapter.is_empty()) {
+        bail!("未能从电子书中提取到内容");
+    }
+    if let Some(title) = output.title.filter(|title| !title.trim().is_empty()) {
+        record.title = Some(title);
+    }
+    parse_documents(record, kb, output.chapters, false).await
+}
 
-    // 删除临时 pdf 文件
-    fs::remove_file(temp_pdf)?;
+/// 解析本地网页文件
+async fn parse_html(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    let output = HtmlInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+    if output.document.is_empty() {
+        bail!("未能从网页中提取到内容");
+    }
+    parse_documents(record, kb, vec![output.document], false).await
+}
 
-    let file_content_type =
-        KnowledgeBaseImportFileContentType::try_from(record.file_content_type.unwrap())?;
-    match file_content_type {
-        KnowledgeBaseImportFileContentType::Document => {
-            // nothing
-        }
-        KnowledgeBaseImportFileContentType::Table => {
-            // 知识库ID
-            let kb_id = record.knowledge_base_id.unwrap();
-            // 知识库对应的向量数据库表名
-            let table_name = &get_table_name(kb_id).await?;
+/// 解析RTF文件
+async fn parse_rtf(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    let document = RtfInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+    parse_documents(record, kb, vec![document], false).await
+}
 
-            // 读取文件
-            let output = CsvInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+/// 解析OpenDocument文件，演示文稿按幻灯片分页
+async fn parse_odf(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    let output = OdfInput::read(file_path).map_err(|e| anyhow!(e.to_string()))?;
+    let paged = has_extension(file_path, "odp");
+    parse_documents(record, kb, output.pages, paged).await
+}
 
-            let headers = output.headers;
-            let rows = output.rows;
+/// 解析邮件
+///
+/// 主题、发件人等信息及正文作为一个文档，可以解析的附件追加在正文之后
+async fn parse_email(record: &KnowledgeBaseImportRecord, kb: &KnowledgeBase) -> anyhow::Result<()> {
+    // 文件路径
+    let file_path = &record.file_path.clone().unwrap();
+    let output = read_email(file_path).map_err(|e| anyhow!(e.to_string()))?;
 
-            // 创建数据表
-            TableEngine::new_table_with_rows(table_name, &record.title.unwrap(), headers, rows)
-                .await?;
+    let mut document = output.to_document();
+    for attachment in &output.attachments {
+        let Some(content) = read_attachment(&attachment.name, &attachment.data) else {
+            continue;
+        };
+        document.blocks.push(Block::Heading {
+            level: 2,
+            text: format!("附件：{}", attachment.name),
+        });
+        document.append(content, 2);
+    }
+    parse_documents(record, kb, vec![document], false).await
+}
+
+/// 按扩展名读取eml或msg邮件
+fn read_email(file_path: &str) -> input::Result<EmailOutput> {
+    if has_extension(file_path, "msg") {
+        MsgInput::read(file_path)
+    } else {
+        EmlInput::read(file_path)
+    }
+}
+
+/// 读取邮件附件，不支持的格式或读取失败时返回None
+///
+/// 附件写入临时文件后按扩展名解析
+fn read_attachment(name: &str, data: &[u8]) -> Option<Document> {
+    let ext = Path::new(name).extension()?.to_str()?.to_lowercase();
+    let temp_file = temp_dir!(format!("{}.{}", uuid::Uuid::new_v4().to_string(), ext))
+        .to_string_lossy()
+        .into_owned();
+    if let Err(e) = fs::write(&temp_file, data) {
+        log::warn!("Save email attachment fail: {}, error: {}", name, e);
+        return None;
+    }
+    let result = read_attachment_file(&temp_file, &ext, name, data);
+
+    // 删除临时文件
+    let _ = fs::remove_file(&temp_file);
+
+    match result {
+        Ok(document) => document.filter(|document| !document.is_empty()),
+        Err(e) => {
+            log::warn!("Read email attachment fail: {}, error: {}", name, e);
+            None
         }
     }
+}
 
-    Ok(())
+/// 按扩展名解析附件，附件中的邮件只读取正文，不再解析其附件
+fn read_attachment_file(
+    file_path: &str,
+    ext: &str,
+    name: &str,
+    data: &[u8],
+) -> input::Result<Option<Document>> {
+    // 分页的文档合并为一个文档
+    let merge = |pages: Vec<Document>| {
+        let mut document = Document::default();
+        for page in pages {
+            document.append(page, 0);
+        }
+        document
+    };
+    let document = match ext {
+        "txt" => Document::from_text(&TxtInput::read(file_path)?),
+        "md" => Document::from_text(&MdInput::read(file_path)?),
+        "docx" => DocxInput::read(file_path)?,
+        "pptx" => merge(PptxInput::read(file_path)?.slides),
+        "xls" | "xlsx" => XlsxInput::read(file_path)?.to_document(),
+        "csv" => CsvInput::read(file_path)?.to_document(),
+        "html" | "htm" => HtmlInput::read(file_path)?.document,
+        "rtf" => RtfInput::read(file_path)?,
+        "odt" | "ods" | "odp" => merge(OdfInput::read(file_path)?.pages),
+        "epub" => merge(EpubInput::read(file_path)?.chapters),
+        "eml" | "msg" => read_email(file_path)?.to_document(),
+        "pdf" => {
+            let output = PdfInput::read(file_path)?;
+            let texts = output
+                .pages
+                .iter()
+                .map(|page| {
+                    // 附件只抽取文本，删除页面快照
+                    let _ = fs::remove_file(&page.snapshot);
+                    page.text.trim()
+                })
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>();
+            Document::from_text(&texts.join("\n\n"))
+        }
+        "png" | "jpg" | "jpeg" | "bmp" | "gif" | "webp" => {
+            let mut document = Document::default();
+            if let Some(index) = document.add_image(name.to_string(), data.to_vec()) {
+                document.blocks.push(Block::Image(index));
+            }
+            document
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(document))
 }
 
-/// 解析PPT文件
-pub(crate) async fn parse_pptx(record: KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+/// 将文件转换为pdf后按页解析，用于无法直接解析的文件
+async fn parse_as_pdf(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
     // 创建临时 pdf 文件
-    //let temp_pdf = format!("data/temp/{}.pdf", uuid::Uuid::new_v4().to_string());
     let temp_pdf = temp_dir!(format!("{}.pdf", uuid::Uuid::new_v4().to_string()))
         .to_string_lossy()
         .into_owned();
 
-    // 将 docx 转换为 pdf
+    // 转换为 pdf
     doc_to_pdf::convert(&file_path, &temp_pdf)?;
     // 解析 pdf
-    // 解析 pdf
     let mut record = record.clone();
     record.file_path = Some(temp_pdf.clone());
-    parse_pdf(&record).await?;
+    let result = parse_pdf(&record, kb).await;
 
     // 删除临时 pdf 文件
     fs::remove_file(temp_pdf)?;
 
+    result
+}
+
+/// 解析结构化文档，转换为Markdown后按标题分段
+///
+/// 文档中的图片保存到refs目录，并按导入记录的内容提取方式识别图片中的文字
+/// - documents: 文档，分页的文件（如幻灯片）每页一个文档
+/// - paged: 是否分页，分页时记录分段所在的页码
+async fn parse_documents(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+    documents: Vec<Document>,
+    paged: bool,
+) -> anyhow::Result<()> {
+    // 知识库对应的向量数据库表名
+    let table_name = &kb.table_name.clone().unwrap();
+    let (segments, images) = document_segments(record, kb, documents, paged).await?;
+    let data = convert_to_vector_records(kb, record, segments).await?;
+
+    // 添加数据
+    Engine::add_data(table_name, data).await?;
+    add_images(kb, record, images).await;
+
     Ok(())
 }
 
+/// 将结构化文档转换为Markdown后按标题分段，参数同[`parse_documents`]
+///
+/// 返回分段及保存到refs目录的图片
+async fn document_segments(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+    documents: Vec<Document>,
+    paged: bool,
+) -> anyhow::Result<(Vec<Segment>, Vec<ImageFile>)> {
+    // 知识库对应的向量数据库表名
+    let table_name = &kb.table_name.clone().unwrap();
+    let extract_type = record.file_content_extract_type.clone().unwrap();
+    let chunk_options = kb.get_config().chunk_options();
+
+    // 图片文件名 -> (下载地址, 图片中的文字)，多页共用的图片只保存一次
+    let mut saved_images: HashMap<String, (String, Option<String>)> = HashMap::new();
+    let ocr = !matches!(
+        extract_type,
+        KnowledgeBaseImportFileContentExtractType::Text
+    );
+    let total_images = documents
+        .iter()
+        .flat_map(|document| &document.images)
+        .map(|image| &image.name)
+        .collect::<HashSet<_>>()
+        .len();
+    if ocr && total_images > 0 {
+        progress::stage(record, ImportStage::Ocr).await;
+    }
+    let mut segments = vec![];
+    let mut images = vec![];
+    for (index, document) in documents.iter().enumerate() {
+        for image in &document.images {
+            if saved_images.contains_key(&image.name) {
+                continue;
+            }
+            let (_, save_file, download_file) =
+                make_kb_ref_file(table_name, record.id.unwrap(), &image.name)?;
+            fs::write(&save_file, &image.data)?;
+            let text = image_text(&extract_type, &save_file)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Extract image text fail: {}, error: {}", save_file, e);
+                    None
+                });
+            images.push(ImageFile {
+                path: save_file,
+                download_file: download_file.clone(),
+                page: if paged { Some(index as i64 + 1) } else { None },
+            });
+            saved_images.insert(image.name.clone(), (download_file, text));
+            if ocr {
+                progress::step(record, ImportStage::Ocr, saved_images.len(), total_images);
+            }
+        }
+
+        let markdown = document.to_markdown(|image| {
+            saved_images
+                .get(&image.name)
+                .and_then(|(_, text)| text.clone())
+        });
+        for chunk in chunk::split_markdown(&markdown, &chunk_options) {
+            let images = document
+                .images_in(&chunk.text)
+                .into_iter()
+                .filter_map(|image| saved_images.get(&image.name))
+                .map(|(download_file, _)| download_file.clone())
+                .collect::<Vec<_>>();
+            segments.push(Segment {
+                text: chunk.text,
+                images: if images.is_empty() {
+                    None
+                } else {
+                    Some(images)
+                },
+                urls: None,
+                payload: None,
+                metadata: RecordMetadata {
+                    page: if paged { Some(index as i64 + 1) } else { None },
+                    heading_path: chunk.heading_path,
+                    ..Default::default()
+                },
+            });
+        }
+    }
+    Ok((segments, images))
+}
+
+/// 解析网页
+///
+/// 先直接请求网页并提取正文，请求失败或正文过少时使用浏览器渲染后提取。
+/// 网页标题作为导入记录的标题，分段引用网页地址及分段中出现的链接
+async fn parse_url(
+    record: &mut KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
+    let url = record.url.clone().context("网页地址不能为空")?;
+    // 知识库对应的向量数据库表名
+    let table_name = &kb.table_name.clone().unwrap();
+
+    let output = match fetch_url(&url).await {
+        Ok(output) if output.text_len() >= URL_MIN_CONTENT_CHARS => output,
+        fetched => {
+            if let Err(e) = &fetched {
+                log::warn!(
+                    "Fetch url fail, fall back to browser: {}, error: {}",
+                    url,
+                    e
+                );
+            }
+            let render_url = url.clone();
+            let rendered = tokio::task::spawn_blocking(move || {
+                UrlInput::read(&render_url).map_err(|e| anyhow!(e.to_string()))
+            })
+            .await?;
+            match (rendered, fetched) {
+                (Ok(rendered), Ok(fetched)) if fetched.text_len() > rendered.text_len() => fetched,
+                (Ok(rendered), _) => rendered,
+                (Err(e), Ok(fetched)) => {
+                    log::warn!("Render url fail: {}, error: {}", url, e);
+                    fetched
+                }
+                (Err(e), Err(_)) => return Err(e),
+            }
+        }
+    };
+    if output.document.is_empty() {
+        bail!("未能从网页中提取到内容");
+    }
+
+    if let Some(title) = &output.title {
+        record.title = Some(title.clone());
+    }
+    record.nld = Some(format!(
+        "网页：{}，地址：{}",
+        record.title.clone().unwrap_or_default(),
+        url
+    ));
+
+    let (mut segments, images) =
+        document_segments(record, kb, vec![output.document], false).await?;
+    for segment in &mut segments {
+        let mut urls = vec![url.clone()];
+        urls.extend(
+            output
+                .links
+                .iter()
+                // 过短的链接文字容易误匹配
+                .filter(|link| link.text.chars().count() >= 2 && segment.text.contains(&link.text))
+                .map(|link| link.url.clone()),
+        );
+        segment.urls = Some(urls);
+    }
+    let data = convert_to_vector_records(kb, record, segments).await?;
+
+    // 添加数据
+    Engine::add_data(table_name, data).await?;
+    add_images(kb, record, images).await;
+
+    Ok(())
+}
+
+/// 使用另一张向量表中保存的分段重建导入记录，只重新生成特征向量，分段内容及元数据保持不变
+///
+/// 重建索引时网页记录不重新抓取，重新抓取的内容可能与导入时不同，离线时也无法抓取。
+/// 分段及图片表引用的图片复制到kb的向量表的引用目录
+pub(crate) async fn reembed_from_table(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+    source_table_name: &str,
+) -> anyhow::Result<()> {
+    let table_name = kb.table_name.clone().unwrap();
+    let batch_id = record.current_batch_id();
+    let rows = Engine::list_data(source_table_name, &batch_id).await?;
+    let source_image_table_name = image_table_name(source_table_name);
+    let image_rows = if Engine::has_table(&source_image_table_name).await? {
+        Engine::list_data(&source_image_table_name, &batch_id).await?
+    } else {
+        vec![]
+    };
+
+    // 复制引用的图片，key为原下载地址，value为新的文件路径及下载地址
+    let mut copied: HashMap<String, (String, String)> = HashMap::new();
+    let download_files = rows
+        .iter()
+        .filter_map(|row| row.content_ref.as_ref()?.images.as_ref())
+        .flatten()
+        .chain(image_rows.iter().map(|row| &row.content));
+    for download_file in download_files {
+        if copied.contains_key(download_file) {
+            continue;
+        }
+        match copy_kb_ref_file(
+            source_table_name,
+            &table_name,
+            record.id.unwrap(),
+            download_file,
+        ) {
+            Ok(copy) => {
+                copied.insert(download_file.clone(), copy);
+            }
+            Err(e) => log::warn!(
+                "[kb] Failed to copy referenced file {}, reason: {}",
+                download_file,
+                e
+            ),
+        }
+    }
+
+    let segments = rows
+        .into_iter()
+        .map(|row| {
+            let (images, urls) = match row.content_ref {
+                Some(content_ref) => (content_ref.images, content_ref.urls),
+                None => (None, None),
+            };
+            Segment {
+                text: row.content,
+                images: images.map(|images| {
+                    images
+                        .iter()
+                        .filter_map(|image| Some(copied.get(image)?.1.clone()))
+                        .collect()
+                }),
+                urls,
+                payload: row.payload,
+                metadata: row.metadata,
+            }
+        })
+        .collect::<Vec<_>>();
+    if !segments.is_empty() {
+        let data = embed_segments(kb, record, segments).await?;
+        progress::stage(record, ImportStage::Writing).await;
+        Engine::add_data(&table_name, data).await?;
+    }
+
+    let images = image_rows
+        .into_iter()
+        .filter_map(|row| {
+            let (path, download_file) = copied.get(&row.content)?.clone();
+            Some(ImageFile {
+                path,
+                download_file,
+                page: row.metadata.page,
+            })
+        })
+        .collect();
+    add_images(kb, record, images).await;
+    Ok(())
+}
+
+/// 直接请求网页并提取正文
+async fn fetch_url(url: &str) -> anyhow::Result<HtmlOutput> {
+    let response = HTTP_CLIENT
+        .get(url)
+        .header(reqwest::header::USER_AGENT, URL_USER_AGENT)
+        .timeout(URL_FETCH_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    if let Some(content_type) = &content_type {
+        if !content_type.contains("html") && !content_type.contains("xml") {
+            bail!("不支持的网页类型：{}", content_type);
+        }
+    }
+    // 重定向后的地址，用于转换相对链接
+    let final_url = response.url().to_string();
+    let bytes = response.bytes().await?;
+    let content = html::decode(&bytes, content_type.as_deref());
+    Ok(html::extract(&content, Some(&final_url)))
+}
+
+/// 按内容提取方式识别图片中的文字，仅抽取文本时返回None
+async fn image_text(
+    extract_type: &KnowledgeBaseImportFileContentExtractType,
+    file_path: &str,
+) -> anyhow::Result<Option<String>> {
+    match extract_type {
+        KnowledgeBaseImportFileContentExtractType::Text => Ok(None),
+        KnowledgeBaseImportFileContentExtractType::Ocr => Ok(Some(ocr::run(file_path)?)),
+        KnowledgeBaseImportFileContentExtractType::VisionModel { model_id } => {
+            let model = get_model(*model_id).await?;
+            image_to_text::extra(
+                file_path,
+                &model.base_url.unwrap(),
+                &model.name.unwrap(),
+                &model.api_key.unwrap_or_default(),
+            )
+            .await
+        }
+    }
+}
+
+fn has_extension(file_path: &str, ext: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
 /// 解析图片
-pub(crate) async fn parse_image(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
-    // 导入记录ID
-    let id = record.id.unwrap();
-    // 知识库ID
-    let kb_id = record.knowledge_base_id.unwrap();
+pub(crate) async fn parse_image(
+    record: &KnowledgeBaseImportRecord,
+    kb: &KnowledgeBase,
+) -> anyhow::Result<()> {
     // 文件路径
     let file_path = &record.file_path.clone().unwrap();
     // 知识库对应的向量数据库表名
-    let table_name = &get_table_name(kb_id).await?;
+    let table_name = &kb.table_name.clone().unwrap();
 
     let extract_type = record.file_content_extract_type.clone().unwrap();
     let mut text = Some(String::new());
+    progress::stage(record, ImportStage::Ocr).await;
     match extract_type {
         KnowledgeBaseImportFileContentExtractType::Text => {
             bail!("仅抽取文本模式下无法解析图片");
@@ -376,49 +1093,297 @@ pub(crate) async fn parse_image(record: &KnowledgeBaseImportRecord) -> anyhow::R
     if text.is_none() || text.as_ref().unwrap().is_empty() {
         bail!("文本提取失败");
     }
-    let mut data = Vec::new();
-    for segment in TxtInput::split(text.unwrap()) {
-        data.push(convert_to_vector_record(id, segment, None)?);
-    }
+    let segments = TxtInput::split_with(text.unwrap(), &kb.get_config().chunk_options())
+        .into_iter()
+        .map(Segment::text)
+        .collect();
+    let data = convert_to_vector_records(kb, record, segments).await?;
 
-    // let file_name = Path::new(&file_path).file_name().unwrap().to_str().unwrap();
-    //
-    // let (_, save_file, download_file) =
-    //     make_kb_ref_file(table_name, record.id.unwrap(), file_name)?;
-    //
-    // // 移动图片到refs目录下
-    // fs::copy(&file_path, &save_file)?;
+    let file_name = Path::new(&file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .context("文件路径错误")?;
+
+    let (_, save_file, download_file) =
+        make_kb_ref_file(table_name, record.id.unwrap(), &file_name)?;
+
+    // 复制图片到refs目录下
+    fs::copy(file_path, &save_file)?;
 
     // 添加数据
     Engine::add_data(table_name, data).await?;
+    add_images(
+        kb,
+        record,
+        vec![ImageFile {
+            path: save_file,
+            download_file,
+            page: None,
+        }],
+    )
+    .await;
 
     Ok(())
 }
 
-/// 转换为向量库需要的数据格式
+/// 保存到refs目录的图片
+struct ImageFile {
+    /// 图片文件路径
+    path: String,
+    /// 图片下载地址
+    download_file: String,
+    /// 图片所在的页码
+    page: Option<i64>,
+}
+
+/// 将图片的CLIP向量写入知识库的图片表，用于以文搜图
 ///
-/// - knowledge_import_record_id: 知识库导入记录 id
-/// - segment: 分段内容
-/// - segment_ref_image: 分段引用的快照图片
-
-fn convert_to_vector_record(
-    knowledge_import_record_id: i64,
-    segment: String,
-    segment_ref_image: Option<Vec<String>>,
-) -> anyhow::Result<AddRecordRequest> {
-    let vector = Embeddings::embedding(EmbeddingInput::Text(segment.clone()))
+/// 图片表不存在时创建；写入失败仅记录日志，不影响文本分段的导入
+async fn add_images(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    images: Vec<ImageFile>,
+) {
+    if images.is_empty() {
+        return;
+    }
+    if let Err(e) = try_add_images(kb, record, images).await {
+        log::warn!(
+            "[kb] Failed to add images of import record {}, reason: {}",
+            record.id.unwrap(),
+            e
+        );
+    }
+}
+
+/// 删除导入记录的批次写入图片表的数据
+pub(crate) async fn delete_images(table_name: &str, batch_ids: Vec<String>) -> anyhow::Result<()> {
+    let image_table_name = image_table_name(table_name);
+    if Engine::has_table(&image_table_name).await? {
+        Engine::delete_data(&image_table_name, batch_ids).await?;
+    }
+    Ok(())
+}
+
+/// 更新导入记录的批次写入图片表的数据的标签
+pub(crate) async fn update_image_tags(
+    table_name: &str,
+    batch_id: &str,
+    tags: &[String],
+) -> anyhow::Result<()> {
+    let image_table_name = image_table_name(table_name);
+    if Engine::has_table(&image_table_name).await? {
+        Engine::update_tags(&image_table_name, batch_id, tags).await?;
+    }
+    Ok(())
+}
+
+async fn try_add_images(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    images: Vec<ImageFile>,
+) -> anyhow::Result<()> {
+    let table_name = image_table_name(&kb.table_name.clone().unwrap());
+    let paths = images.iter().map(|image| image.path.clone()).collect();
+    let vectors = Embeddings::embed_images(paths)
+        .await
         .map_err(|e| anyhow!(e.to_string()))?;
-    Ok(AddRecordRequest {
-        batch_id: knowledge_import_record_id.to_string(),
-        vector,
-        content: segment,
-        content_type: "text".to_string(),
-        content_ref: Some(ContentRef {
-            images: segment_ref_image,
+    // 无法读取的图片已跳过
+    let data = images
+        .into_iter()
+        .zip(vectors)
+        .filter_map(|(image, vector)| Some((image, vector?)))
+        .map(|(image, vector)| AddRecordRequest {
+            batch_id: record.current_batch_id(),
+            vector,
+            content: image.download_file.clone(),
+            content_type: "image".to_string(),
+            content_ref: Some(ContentRef {
+                images: Some(vec![image.download_file]),
+                urls: None,
+            }),
+            payload: None,
+            metadata: RecordMetadata {
+                source: record.title.clone(),
+                tags: record.tags.clone().unwrap_or_default(),
+                page: image.page,
+                ..Default::default()
+            },
+        })
+        .collect::<Vec<_>>();
+    if data.is_empty() {
+        return Ok(());
+    }
+    Engine::new_table(&table_name, IMAGE_DIMENSION).await?;
+    Engine::add_data(&table_name, data).await?;
+    Ok(())
+}
+
+/// 分段
+struct Segment {
+    /// 分段内容
+    text: String,
+    /// 分段引用的快照图片
+    images: Option<Vec<String>>,
+    /// 分段引用的链接
+    urls: Option<Vec<String>>,
+    /// 分段的自定义数据，JSON格式
+    payload: Option<String>,
+    /// 分段的元数据，来源和标签取自导入记录，无需设置
+    metadata: RecordMetadata,
+}
+
+impl Segment {
+    fn text(text: String) -> Self {
+        Segment {
+            text,
+            images: None,
             urls: None,
-        }),
-        payload: None,
-    })
+            payload: None,
+            metadata: RecordMetadata::default(),
+        }
+    }
+}
+
+/// 转换为向量库需要的数据格式，知识库开启分段去重时去除近似重复的分段
+///
+/// - kb: 知识库，使用其配置的嵌入模型生成向量
+/// - record: 知识库导入记录
+/// - segments: 分段
+async fn convert_to_vector_records(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    segments: Vec<Segment>,
+) -> anyhow::Result<Vec<AddRecordRequest>> {
+    let data = embed_segments(kb, record, segments).await?;
+    let data = match kb.get_config().chunk_dedup_score {
+        Some(min_score) => dedup_records(kb, record, data, min_score).await?,
+        None => data,
+    };
+    // 调用方随后写入向量库
+    progress::stage(record, ImportStage::Writing).await;
+    Ok(data)
+}
+
+/// 生成分段的向量，分批嵌入并上报进度
+async fn embed_segments(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    segments: Vec<Segment>,
+) -> anyhow::Result<Vec<AddRecordRequest>> {
+    progress::stage(record, ImportStage::Embedding).await;
+    let model = embedder::resolve_model(&kb.get_embedding_config().model).await?;
+    let total = segments.len();
+    let mut vectors = Vec::with_capacity(total);
+    for batch in segments.chunks(EMBED_PROGRESS_BATCH) {
+        let texts = batch
+            .iter()
+            .map(|segment| segment.text.clone())
+            .collect::<Vec<_>>();
+        vectors.extend(embedder::embed(&model, texts).await?);
+        progress::step(record, ImportStage::Embedding, vectors.len(), total);
+    }
+    Ok(segments
+        .into_iter()
+        .zip(vectors)
+        .map(|(segment, vector)| AddRecordRequest {
+            batch_id: record.current_batch_id(),
+            vector,
+            content: segment.text,
+            content_type: "text".to_string(),
+            content_ref: Some(ContentRef {
+                images: segment.images,
+                urls: segment.urls,
+            }),
+            payload: segment.payload,
+            metadata: RecordMetadata {
+                source: record.title.clone(),
+                tags: record.tags.clone().unwrap_or_default(),
+                ..segment.metadata
+            },
+        })
+        .collect())
+}
+
+/// 去除近似重复的分段
+///
+/// 与知识库中已有分段或本批中前面的分段相似度不低于min_score的分段不写入。
+/// 重新解析时导入记录原批次的分段在解析成功后才删除，不参与比较。
+///
+/// 相似度与向量检索的分数同一尺度，见[`similarity_score`]。
+/// 每个分段都要在向量表中做一次近邻检索，分段数量较多时会明显增加导入耗时
+async fn dedup_records(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    data: Vec<AddRecordRequest>,
+    min_score: f32,
+) -> anyhow::Result<Vec<AddRecordRequest>> {
+    let table_name = kb.table_name.clone().unwrap();
+    let total = data.len();
+    let mut result: Vec<AddRecordRequest> = vec![];
+    for item in data {
+        if result
+            .iter()
+            .any(|kept| similarity_score(&kept.vector, &item.vector) >= min_score)
+        {
+            continue;
+        }
+        let similar = Engine::search_data(
+            SearchRequestBuilder::default()
+                .table_name(table_name.clone())
+                .vector(Some(item.vector.clone()))
+                .filter(Some(MetadataFilter::Not {
+                    filter: Box::new(MetadataFilter::Batch {
+                        batch_ids: vec![record.other_batch_id()],
+                    }),
+                }))
+                .min_score(Some(min_score))
+                .limit(Some(1))
+                .build()?,
+        )
+        .await?;
+        if !similar.is_empty() {
+            continue;
+        }
+        result.push(item);
+    }
+    if result.len() < total {
+        log::info!(
+            "[kb] Skipped {} duplicate chunks in {}",
+            total - result.len(),
+            table_name
+        );
+    }
+    Ok(result)
+}
+
+/// 相似度分数，与向量检索的分数同一尺度：`(1 + 余弦相似度) / 2`，取值0~1
+fn similarity_score(a: &[f32], b: &[f32]) -> f32 {
+    (1. + cosine_similarity(a, b)) / 2.
+}
+
+/// 余弦相似度
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm = norm(a) * norm(b);
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+/// 在导入记录的最后追加一个文本分段
+pub(crate) async fn add_text(
+    kb: &KnowledgeBase,
+    record: &KnowledgeBaseImportRecord,
+    text: String,
+) -> anyhow::Result<()> {
+    // 手动添加的分段不去重
+    let data = embed_segments(kb, record, vec![Segment::text(text)]).await?;
+    Engine::add_data(&kb.table_name.clone().unwrap(), data).await?;
+    Ok(())
 }
 
 async fn get_model(model_id: i64) -> anyhow::Result<Model> {
@@ -428,3 +1393,23 @@ async fn get_model(model_id: i64) -> anyhow::Result<Model> {
     }
     Ok(model[0].clone())
 }
+
+/// 根据表头及推断的列类型生成列定义
+fn column_definitions(
+    headers: Vec<String>,
+    column_types: Vec<ColumnType>,
+) -> Vec<ColumnDefinition> {
+    headers
+        .into_iter()
+        .zip(column_types)
+        .map(|(name, column_type)| ColumnDefinition { name, column_type })
+        .collect()
+}
+
+#[test]
+fn test_similarity_score() {
+    assert!((similarity_score(&[1., 0.], &[2., 0.]) - 1.).abs() < 1e-6);
+    assert!((similarity_score(&[1., 0.], &[0., 1.]) - 0.5).abs() < 1e-6);
+    assert!(similarity_score(&[1., 0.], &[-1., 0.]).abs() < 1e-6);
+    assert_eq!(similarity_score(&[0., 0.], &[1., 0.]), 0.5);
+}
@@ -0,0 +1,132 @@
+//! 导入进度
+//!
+//! 解析过程中按阶段上报进度，通过channel实时推送给订阅了知识库导入事件的前端。
+//! 每个阶段开始时将进度保存到导入记录，解析失败时可查看失败所在的阶段。
+//! 只有导入队列中正在解析的记录上报进度，重建索引等重新解析已导入记录的场景不上报。
+
+use crate::db::model::knowledge_base_import_record::{
+    ImportProgress, ImportStage, KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder,
+    KnowledgeBaseImportStatus,
+};
+use crate::db::Pool;
+use dashmap::DashMap;
+use rbs::value;
+use serde::Serialize;
+use std::sync::LazyLock;
+use tauri::ipc::Channel;
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub(crate) enum ImportEvent {
+    // 解析进度
+    Progress(ImportProgress),
+    // 解析结束，status为导入记录的最终状态，重试时为待解析
+    Finish {
+        record_id: i64,
+        status: i8,
+        status_msg: String,
+        progress: Option<ImportProgress>,
+    },
+}
+
+/// 和前端通信的channel，key为知识库ID
+static CHANNELS: LazyLock<DashMap<i64, Channel<ImportEvent>>> = LazyLock::new(DashMap::new);
+
+/// 正在解析的记录的最新进度，key为导入记录ID，value为(知识库ID, 进度)
+static LATEST: LazyLock<DashMap<i64, (i64, ImportProgress)>> = LazyLock::new(DashMap::new);
+
+/// 订阅知识库的导入事件，同一知识库只保留最后一个订阅
+pub(crate) fn subscribe(kb_id: i64, channel: Channel<ImportEvent>) {
+    CHANNELS.insert(kb_id, channel);
+}
+
+pub(crate) fn unsubscribe(kb_id: i64) {
+    CHANNELS.remove(&kb_id);
+}
+
+/// 进入新的解析阶段，保存到导入记录并推送给前端
+pub(crate) async fn stage(record: &KnowledgeBaseImportRecord, stage: ImportStage) {
+    let Some(progress) = update(record, stage, None, None) else {
+        return;
+    };
+    if let Err(e) = save(record.id.unwrap(), progress).await {
+        log::error!("[kb] Failed to save import progress, reason: {}", e);
+    }
+}
+
+async fn save(id: i64, progress: ImportProgress) -> anyhow::Result<()> {
+    KnowledgeBaseImportRecord::update_by_map(
+        Pool::get()?,
+        &KnowledgeBaseImportRecordBuilder::default()
+            .progress(Some(progress))
+            .build()?,
+        value! {"id": id},
+    )
+    .await?;
+    Ok(())
+}
+
+/// 当前阶段完成了current/total，仅推送给前端，不保存
+pub(crate) fn step(
+    record: &KnowledgeBaseImportRecord,
+    stage: ImportStage,
+    current: usize,
+    total: usize,
+) {
+    update(record, stage, Some(current), Some(total));
+}
+
+/// 解析结束，推送结束事件并返回最新进度，由调用方随状态一起保存
+pub(crate) fn finish(id: i64, status: i8, status_msg: &str) -> Option<ImportProgress> {
+    let (kb_id, progress) = LATEST.remove(&id).map(|(_, latest)| latest)?;
+    send(
+        kb_id,
+        ImportEvent::Finish {
+            record_id: id,
+            status,
+            status_msg: status_msg.to_string(),
+            progress: Some(progress.clone()),
+        },
+    );
+    Some(progress)
+}
+
+fn update(
+    record: &KnowledgeBaseImportRecord,
+    stage: ImportStage,
+    current: Option<usize>,
+    total: Option<usize>,
+) -> Option<ImportProgress> {
+    if record.status != Some(KnowledgeBaseImportStatus::Importing as i8) {
+        return None;
+    }
+    let id = record.id?;
+    let kb_id = record.knowledge_base_id?;
+    let progress = ImportProgress {
+        record_id: id,
+        stage,
+        current,
+        total,
+        time: chrono::Local::now().timestamp_millis(),
+    };
+    LATEST.insert(id, (kb_id, progress.clone()));
+    send(kb_id, ImportEvent::Progress(progress.clone()));
+    Some(progress)
+}
+
+fn send(kb_id: i64, event: ImportEvent) {
+    let Some(channel) = CHANNELS.get(&kb_id) else {
+        return;
+    };
+    if let Err(e) = channel.send(event) {
+        // 前端页面已关闭
+        log::debug!("[kb] Failed to send import event, reason: {}", e);
+        drop(channel);
+        CHANNELS.remove(&kb_id);
+    }
+}
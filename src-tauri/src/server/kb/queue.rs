@@ -0,0 +1,364 @@
+//! 导入队列
+//!
+//! 导入记录即队列中的任务：状态为待解析的记录按ID（创建顺序）由后台的工作协程依次解析，
+//! 同时解析的数量不超过配置的工作协程数量。解析失败的记录延时后重试，超过重试次数后标记为失败。
+//! 队列保存在数据库中，应用重启后待解析及中断的记录继续解析。
+
+use crate::db::model::knowledge_base_import_record::{
+    ImportStage, KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder,
+    KnowledgeBaseImportStatus,
+};
+use crate::db::{tools, Pool};
+use crate::server::kb::service::{clear_pending_data, clear_replaced_data};
+use crate::server::kb::{progress, reindex};
+use dashmap::DashMap;
+use rbs::{value, Value};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+
+/// 重试前的等待时间，按重试次数递增
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+/// 没有新任务通知时，工作协程重新检查队列的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// 每次从队列中取出的候选记录数量
+const CLAIM_CANDIDATES: usize = 50;
+
+/// 导入队列配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportQueueOptions {
+    /// 同时解析的导入记录数量
+    pub(crate) worker_count: usize,
+    /// 解析失败后的最大重试次数
+    pub(crate) max_retries: i32,
+}
+
+impl Default for ImportQueueOptions {
+    fn default() -> Self {
+        ImportQueueOptions {
+            worker_count: 2,
+            max_retries: 2,
+        }
+    }
+}
+
+static OPTIONS: LazyLock<RwLock<ImportQueueOptions>> =
+    LazyLock::new(|| RwLock::new(ImportQueueOptions::default()));
+
+/// 新任务通知
+static NOTIFY: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// 已启动的工作协程数量
+static WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+/// 正在解析的导入记录，key为导入记录ID，用于取消解析
+static RUNNING: LazyLock<DashMap<i64, AbortHandle>> = LazyLock::new(DashMap::new);
+
+/// 等待重试的导入记录，key为导入记录ID，value为可以重试的时间
+///
+/// 仅保存在内存中，应用重启后立即重试
+static RETRY_AFTER: LazyLock<DashMap<i64, Instant>> = LazyLock::new(DashMap::new);
+
+/// 启动导入队列，应用启动时调用
+///
+/// 上次退出时正在解析的记录重新加入队列
+pub(crate) async fn start(options: ImportQueueOptions) {
+    if let Err(e) = resume_interrupted().await {
+        log::error!("[kb] Failed to resume interrupted imports, reason: {}", e);
+    }
+    set_options(options);
+}
+
+/// 更新队列配置，工作协程数量增加时立即启动新的工作协程，减少时多余的工作协程在完成当前任务后退出
+pub(crate) fn set_options(options: ImportQueueOptions) {
+    let worker_count = options.worker_count.max(1);
+    *OPTIONS.write().unwrap() = options;
+    while WORKERS.load(Ordering::SeqCst) < worker_count {
+        let index = WORKERS.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(worker(index));
+    }
+    NOTIFY.notify_waiters();
+}
+
+/// 通知工作协程有新的待解析记录
+pub(crate) fn notify() {
+    NOTIFY.notify_waiters();
+}
+
+/// 取消正在解析的记录，返回是否取消成功
+pub(crate) fn cancel(id: i64) -> bool {
+    match RUNNING.get(&id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/// 将中断的记录重新加入队列，并删除中断的解析已写入的部分分段
+async fn resume_interrupted() -> anyhow::Result<()> {
+    let records = KnowledgeBaseImportRecord::select_by_map(
+        Pool::get()?,
+        value! {"status": KnowledgeBaseImportStatus::Importing as i8},
+    )
+    .await?;
+    for record in records {
+        log::info!(
+            "[kb] Resume interrupted import: {}",
+            record.title.as_deref().unwrap_or_default()
+        );
+        if let Err(e) = clear_pending_data(&record).await {
+            log::error!("[kb] Failed to clear import data, reason: {}", e);
+            continue;
+        }
+        update_status(
+            record.id.unwrap(),
+            KnowledgeBaseImportStatus::Waiting,
+            String::new(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn worker(index: usize) {
+    loop {
+        // 工作协程数量减少后，编号靠后的工作协程退出
+        let worker_count = OPTIONS.read().unwrap().worker_count.max(1);
+        if index >= worker_count {
+            WORKERS.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+        // 先注册通知，避免检查队列与等待通知之间的通知丢失
+        let notified = NOTIFY.notified();
+        match claim().await {
+            Ok(Some(record)) => run(record).await,
+            Ok(None) => {
+                let _ = tokio::time::timeout(POLL_INTERVAL, notified).await;
+            }
+            Err(e) => {
+                log::error!("[kb] Failed to claim import record, reason: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// 从队列中取出一条待解析的记录，并将其状态更新为导入中
+///
+/// 正在重建索引的知识库及未到重试时间的记录跳过
+async fn claim() -> anyhow::Result<Option<KnowledgeBaseImportRecord>> {
+    let candidates: Vec<KnowledgeBaseImportRecord> = Pool::get()?
+        .query_decode(
+            "select * from knowledge_base_import_record where status = ? order by id limit ?",
+            vec![
+                Value::I32(KnowledgeBaseImportStatus::Waiting as i32),
+                Value::U64(CLAIM_CANDIDATES as u64),
+            ],
+        )
+        .await?;
+    let now = Instant::now();
+    for mut record in candidates {
+        let id = record.id.unwrap();
+        if reindex::is_reindexing(record.knowledge_base_id.unwrap()) {
+            continue;
+        }
+        if is_retry_pending(id, now) {
+            continue;
+        }
+        // 以状态为条件更新，多个工作协程同时取到同一条记录时只有一个成功
+        let start_time = tools::now();
+        let result = KnowledgeBaseImportRecord::update_by_map(
+            Pool::get()?,
+            &KnowledgeBaseImportRecordBuilder::default()
+                .status(Some(KnowledgeBaseImportStatus::Importing as i8))
+                .status_msg(Some(String::new()))
+                .start_time(Some(start_time.clone()))
+                .build()?,
+            value! {"id": id, "status": KnowledgeBaseImportStatus::Waiting as i8},
+        )
+        .await?;
+        if result.rows_affected == 0 {
+            continue;
+        }
+        RETRY_AFTER.remove(&id);
+        record.status = Some(KnowledgeBaseImportStatus::Importing as i8);
+        record.start_time = Some(start_time);
+        return Ok(Some(record));
+    }
+    Ok(None)
+}
+
+/// 解析导入记录，解析完成后更新导入记录的状态
+///
+/// 解析结果写入导入记录的另一个批次，解析成功后切换到该批次并删除原有的分段，
+/// 解析失败时原有的分段保持不变，重新导入时不会因解析失败丢失内容
+async fn run(record: KnowledgeBaseImportRecord) {
+    let id = record.id.unwrap();
+    let title = record.title.clone().unwrap_or_default();
+    let batch_id = record.batch_id.clone();
+    let table_names = record.table_names.clone().unwrap_or_default();
+    let handle = tokio::spawn(async move {
+        let mut record = record;
+        record.batch_id = Some(record.other_batch_id());
+        // 只保留本次解析创建的数据表名称，解析成功后不在其中的原有数据表被删除
+        record.table_names = Some(vec![]);
+        progress::stage(&record, ImportStage::Converting).await;
+        let result = record.parse().await;
+        (record, result)
+    });
+    RUNNING.insert(id, handle.abort_handle());
+    let result = handle.await;
+    RUNNING.remove(&id);
+
+    let result = match result {
+        Ok((record, Ok(_))) => {
+            log::info!("parse success: {}", title);
+            let result = finish(
+                record.clone(),
+                KnowledgeBaseImportStatus::Success,
+                String::new(),
+            )
+            .await;
+            if result.is_ok() {
+                if let Err(e) = clear_replaced_data(&record, table_names).await {
+                    log::error!("[kb] Failed to clear replaced import data, reason: {}", e);
+                }
+            }
+            result
+        }
+        Ok((mut record, Err(e))) => {
+            log::error!("parse error: {}, {}", title, e);
+            let retry_count = record.retry_count.unwrap_or_default();
+            let max_retries = OPTIONS.read().unwrap().max_retries;
+            let retry_delay = retry_delay(retry_count, max_retries);
+            // 恢复到原批次，删除本次解析已写入的分段
+            record.batch_id = batch_id;
+            // 数据表名称保持数据库中保存的值，其中包含原有的及本次解析创建的数据表
+            record.table_names = None;
+            if let Err(e) = clear_pending_data(&record).await {
+                log::error!("[kb] Failed to clear import data, reason: {}", e);
+            }
+            if let Some(retry_delay) = retry_delay {
+                // 延时后重试
+                record.retry_count = Some(retry_count + 1);
+                RETRY_AFTER.insert(id, Instant::now() + retry_delay);
+                let msg = format!("第{}次重试，上次失败原因：{}", retry_count + 1, e);
+                finish(record, KnowledgeBaseImportStatus::Waiting, msg).await
+            } else {
+                finish(record, KnowledgeBaseImportStatus::Failed, e.to_string()).await
+            }
+        }
+        Err(e) if e.is_cancelled() => {
+            log::info!("parse cancelled: {}", title);
+            cancelled(id).await
+        }
+        Err(e) => {
+            log::error!("parse panicked: {}, {}", title, e);
+            panicked(id, e.to_string()).await
+        }
+    };
+    if let Err(e) = result {
+        log::error!("[kb] Failed to update import record, reason: {}", e);
+    }
+}
+
+/// 记录是否未到重试时间
+fn is_retry_pending(id: i64, now: Instant) -> bool {
+    RETRY_AFTER.get(&id).is_some_and(|after| *after > now)
+}
+
+/// 已重试retry_count次后再次失败时，返回下次重试前的等待时间，超过最大重试次数时返回None
+fn retry_delay(retry_count: i32, max_retries: i32) -> Option<Duration> {
+    (retry_count < max_retries).then(|| RETRY_DELAY * (retry_count.max(0) as u32 + 1))
+}
+
+/// 解析结束，保存导入记录，解析过程中会更新数据表名称等字段
+async fn finish(
+    mut record: KnowledgeBaseImportRecord,
+    status: KnowledgeBaseImportStatus,
+    status_msg: String,
+) -> anyhow::Result<()> {
+    let status = status as i8;
+    record.progress = progress::finish(record.id.unwrap(), status, &status_msg).or(record.progress);
+    record.status = Some(status);
+    record.status_msg = Some(status_msg);
+    record.end_time = Some(tools::now());
+    KnowledgeBaseImportRecord::update_by_map(Pool::get()?, &record, value! {"id": record.id})
+        .await?;
+    Ok(())
+}
+
+/// 解析被取消，删除本次解析已写入的部分分段
+async fn cancelled(id: i64) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    // 记录已被删除
+    let Some(record) = record.first() else {
+        progress::finish(id, KnowledgeBaseImportStatus::Cancelled as i8, "已取消");
+        return Ok(());
+    };
+    clear_pending_data(record).await?;
+    update_status(
+        id,
+        KnowledgeBaseImportStatus::Cancelled,
+        "已取消".to_string(),
+    )
+    .await
+}
+
+/// 解析时发生panic，删除本次解析已写入的部分分段
+///
+/// 从数据库读取的记录仍是原批次，与解析失败时恢复到原批次一致
+async fn panicked(id: i64, status_msg: String) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if let Some(record) = record.first() {
+        if let Err(e) = clear_pending_data(record).await {
+            log::error!("[kb] Failed to clear import data, reason: {}", e);
+        }
+    }
+    update_status(id, KnowledgeBaseImportStatus::Failed, status_msg).await
+}
+
+async fn update_status(
+    id: i64,
+    status: KnowledgeBaseImportStatus,
+    status_msg: String,
+) -> anyhow::Result<()> {
+    let status = status as i8;
+    let progress = progress::finish(id, status, &status_msg);
+    KnowledgeBaseImportRecord::update_by_map(
+        Pool::get()?,
+        &KnowledgeBaseImportRecordBuilder::default()
+            .status(Some(status))
+            .status_msg(Some(status_msg))
+            .progress(progress)
+            .end_time(Some(tools::now()))
+            .build()?,
+        value! {"id": id},
+    )
+    .await?;
+    Ok(())
+}
+
+#[test]
+fn test_retry_delay() {
+    assert_eq!(retry_delay(0, 2), Some(RETRY_DELAY));
+    assert_eq!(retry_delay(1, 2), Some(RETRY_DELAY * 2));
+    assert_eq!(retry_delay(2, 2), None);
+    assert_eq!(retry_delay(0, 0), None);
+}
+
+#[test]
+fn test_is_retry_pending() {
+    let now = Instant::now();
+    assert!(!is_retry_pending(-1, now));
+    RETRY_AFTER.insert(-1, now + RETRY_DELAY);
+    assert!(is_retry_pending(-1, now));
+    assert!(!is_retry_pending(-1, now + RETRY_DELAY));
+    RETRY_AFTER.remove(&-1);
+}
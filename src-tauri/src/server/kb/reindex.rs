@@ -0,0 +1,278 @@
+use crate::db::model::knowledge_base::{
+    image_table_name, KnowledgeBase, KnowledgeBaseBuilder, KnowledgeBaseEmbeddingConfig,
+    KnowledgeBaseEmbeddingModel,
+};
+use crate::db::model::knowledge_base_import_record::{
+    KnowledgeBaseImportRecord, KnowledgeBaseImportSource, KnowledgeBaseImportStatus,
+};
+use crate::db::{tools, Pool};
+use crate::server::kb::parse::get_kb;
+use crate::server::kb::request::KbReindexReq;
+use crate::server::kb::service::rebuild_nld;
+use crate::server::kb::{embedder, parse};
+use crate::{db, db_error, message_error};
+use anyhow::bail;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use engine::{Engine, TableEngine};
+use rbs::value;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// 重建索引进度，key为知识库ID
+///
+/// 仅保存在内存中，应用重启后进度丢失，重建需要重新发起
+static REINDEX_PROGRESS: LazyLock<DashMap<i64, KnowledgeBaseReindexProgress>> =
+    LazyLock::new(|| DashMap::new());
+
+/// 重建索引状态
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum KnowledgeBaseReindexStatus {
+    /// 重建中
+    Running,
+    /// 成功，已切换到新索引
+    Success,
+    /// 失败，继续使用原索引
+    Failed,
+}
+
+/// 重建索引进度
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KnowledgeBaseReindexProgress {
+    pub(crate) kb_id: i64,
+    pub(crate) status: KnowledgeBaseReindexStatus,
+    /// 需要重建的导入记录数量
+    pub(crate) total: usize,
+    /// 已完成的导入记录数量
+    pub(crate) finished: usize,
+    /// 当前处理的导入记录标题
+    pub(crate) current: Option<String>,
+    /// 失败原因
+    pub(crate) message: Option<String>,
+}
+
+/// 知识库是否正在重建索引
+pub(crate) fn is_reindexing(kb_id: i64) -> bool {
+    REINDEX_PROGRESS
+        .get(&kb_id)
+        .map(|progress| progress.status == KnowledgeBaseReindexStatus::Running)
+        .unwrap_or(false)
+}
+
+/// 获取知识库重建索引进度
+pub(crate) fn reindex_progress(kb_id: i64) -> Option<KnowledgeBaseReindexProgress> {
+    REINDEX_PROGRESS
+        .get(&kb_id)
+        .map(|progress| progress.clone())
+}
+
+/// 在后台重建知识库索引
+///
+/// 按导入记录重新解析文件，写入影子表，全部完成后将知识库切换到影子表。
+/// 重建过程中知识库仍使用原表检索，切换后删除原表。
+/// 有待解析或正在导入的记录时不能重建，这些记录会写入原表，切换后随原表删除；
+/// 重建期间导入队列不解析该知识库的记录，重建结束后写入新表。
+pub(crate) async fn reindex_kb(req: KbReindexReq) -> anyhow::Result<()> {
+    let kb = get_kb(req.kb_id).await?;
+
+    // 先占位，避免同一知识库同时重建
+    match REINDEX_PROGRESS.entry(req.kb_id) {
+        Entry::Occupied(entry) if entry.get().status == KnowledgeBaseReindexStatus::Running => {
+            bail!("知识库正在重建索引");
+        }
+        entry => {
+            entry.insert(new_progress(req.kb_id));
+        }
+    }
+
+    let (shadow_kb, records) = match prepare(&kb, req.embedding_model).await {
+        Ok(res) => res,
+        Err(e) => {
+            REINDEX_PROGRESS.remove(&req.kb_id);
+            return Err(e);
+        }
+    };
+    if let Some(mut progress) = REINDEX_PROGRESS.get_mut(&req.kb_id) {
+        progress.total = records.len();
+    }
+
+    tokio::spawn(async move {
+        let kb_id = kb.id.unwrap();
+        match run(&kb, &shadow_kb, records).await {
+            Ok(_) => {
+                log::info!("[kb] Reindex success, kb id: {}", kb_id);
+                if let Some(mut progress) = REINDEX_PROGRESS.get_mut(&kb_id) {
+                    progress.status = KnowledgeBaseReindexStatus::Success;
+                    progress.current = None;
+                }
+            }
+            Err(e) => {
+                log::error!("[kb] Reindex failed, kb id: {}, reason: {}", kb_id, e);
+                if let Err(e) = drop_tables(shadow_kb.table_name.as_ref().unwrap()).await {
+                    log::error!("[kb] Failed to drop shadow table, reason: {}", e);
+                }
+                if let Some(mut progress) = REINDEX_PROGRESS.get_mut(&kb_id) {
+                    progress.status = KnowledgeBaseReindexStatus::Failed;
+                    progress.message = Some(e.to_string());
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn new_progress(kb_id: i64) -> KnowledgeBaseReindexProgress {
+    KnowledgeBaseReindexProgress {
+        kb_id,
+        status: KnowledgeBaseReindexStatus::Running,
+        total: 0,
+        finished: 0,
+        current: None,
+        message: None,
+    }
+}
+
+/// 确定新的嵌入配置，创建影子表，返回指向影子表的知识库及需要重建的导入记录
+async fn prepare(
+    kb: &KnowledgeBase,
+    embedding_model: Option<KnowledgeBaseEmbeddingModel>,
+) -> anyhow::Result<(KnowledgeBase, Vec<KnowledgeBaseImportRecord>)> {
+    // 更换嵌入模型时重新确定向量维度
+    let embedding_config = match embedding_model {
+        Some(embedding_model) => {
+            let model = embedder::resolve_model(&embedding_model).await?;
+            let dimension = embedder::probe_dimension(&model).await.map_err(|e| {
+                log::error!("[嵌入模型不可用]{}，原因：{}", model.id(), e);
+                message_error!("嵌入模型不可用")
+            })?;
+            KnowledgeBaseEmbeddingConfig {
+                model: embedding_model,
+                dimension,
+            }
+        }
+        None => kb.get_embedding_config(),
+    };
+
+    // 先占位再检查，占位后导入队列不再取出该知识库的记录
+    let pending = KnowledgeBaseImportRecord::select_by_map(
+        Pool::get()?,
+        value! {
+            "knowledge_base_id": kb.id,
+            "status": &vec![
+                KnowledgeBaseImportStatus::Waiting as i8,
+                KnowledgeBaseImportStatus::Importing as i8,
+            ],
+        },
+    )
+    .await?;
+    if !pending.is_empty() {
+        bail!("知识库有未完成的导入任务，请等待导入完成后再重建索引");
+    }
+
+    // 只重建已成功导入的记录，其余记录在原表中也没有数据
+    let records = KnowledgeBaseImportRecord::select_by_map(
+        Pool::get()?,
+        value! {
+            "knowledge_base_id": kb.id,
+            "status": KnowledgeBaseImportStatus::Success as i8,
+        },
+    )
+    .await?;
+
+    let shadow_table_name = uuid::Uuid::new_v4().to_string();
+    Engine::new_table(&shadow_table_name, embedding_config.dimension).await?;
+
+    let mut shadow_kb = kb.clone();
+    shadow_kb.table_name = Some(shadow_table_name);
+    shadow_kb.embedding_config = Some(embedding_config);
+
+    Ok((shadow_kb, records))
+}
+
+async fn run(
+    kb: &KnowledgeBase,
+    shadow_kb: &KnowledgeBase,
+    records: Vec<KnowledgeBaseImportRecord>,
+) -> anyhow::Result<()> {
+    let kb_id = kb.id.unwrap();
+    let old_table_name = kb.table_name.clone().unwrap();
+    let shadow_table_name = shadow_kb.table_name.clone().unwrap();
+
+    for mut record in records {
+        if let Some(mut progress) = REINDEX_PROGRESS.get_mut(&kb_id) {
+            progress.current = record.title.clone();
+        }
+        // 网页记录使用原表中的分段，重建索引只改变特征向量，不改变内容
+        let result = if record.source == Some(KnowledgeBaseImportSource::Url as i8) {
+            parse::reembed_from_table(&record, shadow_kb, &old_table_name).await
+        } else {
+            record.parse_with(shadow_kb).await
+        };
+        if let Err(e) = result {
+            bail!("{}：{}", record.title.unwrap_or_default(), e);
+        }
+        if let Some(mut progress) = REINDEX_PROGRESS.get_mut(&kb_id) {
+            progress.finished += 1;
+        }
+    }
+
+    // 切换表名，以原表名作为条件，避免覆盖期间发生的其他变更
+    let swapped = db::tx(|tx| {
+        let old_table_name = old_table_name.clone();
+        let shadow_kb = shadow_kb.clone();
+        async move {
+            let result = KnowledgeBase::update_by_map(
+                &tx,
+                &KnowledgeBaseBuilder::default()
+                    .table_name(shadow_kb.table_name.clone())
+                    .embedding_config(shadow_kb.embedding_config.clone())
+                    .update_time(Some(tools::now()))
+                    .build()
+                    .unwrap(),
+                value! {"id": kb_id, "table_name": old_table_name},
+            )
+            .await
+            .map_err(|e| db_error!(e))?;
+            if result.rows_affected == 0 {
+                return Ok(false);
+            }
+
+            // 重建nld
+            rebuild_nld(&tx, kb_id)
+                .await
+                .map_err(|e| message_error!(e))?;
+            Ok(true)
+        }
+    })
+    .await?;
+    if !swapped {
+        bail!("知识库已被删除或修改");
+    }
+
+    // 删除原表，此后的检索均使用新表
+    if let Err(e) = drop_tables(&old_table_name).await {
+        log::error!(
+            "[kb] Failed to drop old table {}, reason: {}",
+            old_table_name,
+            e
+        );
+    }
+    log::info!(
+        "[kb] Reindex swapped table {} -> {}",
+        old_table_name,
+        shadow_table_name
+    );
+
+    Ok(())
+}
+
+/// 删除向量表及对应的图片表、数据表
+async fn drop_tables(table_name: &str) -> anyhow::Result<()> {
+    Engine::drop_table(table_name).await?;
+    Engine::drop_table(&image_table_name(table_name)).await?;
+    TableEngine::drop_db(table_name).await;
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use crate::common::req::PageReq;
+use crate::db::model::knowledge_base::{KnowledgeBaseConfig, KnowledgeBaseEmbeddingModel};
 use crate::db::model::knowledge_base_import_record::{
     KnowledgeBaseImportFileContentExtractModelConfig, KnowledgeBaseImportFileContentExtractType,
 };
@@ -11,6 +12,8 @@ pub(crate) struct KbAddReq {
     pub name: String,
     pub description: Option<String>,
     pub icon: Option<String>,
+    /// 嵌入模型，为空时使用默认模型，创建后不可修改
+    pub embedding_model: Option<KnowledgeBaseEmbeddingModel>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -21,6 +24,15 @@ pub struct KnowledgeBaseImportRecordListReq {
 }
 impl_pagination!(KnowledgeBaseImportRecordListReq);
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeBaseChunkListReq {
+    /// 导入记录ID
+    pub record_id: i64,
+    pub page: PageReq,
+}
+impl_pagination!(KnowledgeBaseChunkListReq);
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct KnowledgeBaseUpdateReq {
@@ -33,4 +45,29 @@ pub struct KnowledgeBaseUpdateReq {
     /// 取值：[`crate::db::model::knowledge_base_import_record::KnowledgeBaseImportFileContentExtractType`]
     pub file_content_extract_type: Option<KnowledgeBaseImportFileContentExtractType>,
     pub file_content_extract_model_config: Option<KnowledgeBaseImportFileContentExtractModelConfig>,
+    /// 检索配置
+    pub config: Option<KnowledgeBaseConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KbReindexReq {
+    pub kb_id: i64,
+    /// 新的嵌入模型，为空时沿用知识库当前的嵌入模型
+    pub embedding_model: Option<KnowledgeBaseEmbeddingModel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KbAddFolderReq {
+    pub kb_id: i64,
+    /// 文件夹路径
+    pub dir: String,
+    /// 包含的文件，glob格式，相对于文件夹，如`**/*.md`，为空时包含全部文件
+    pub include: Option<Vec<String>>,
+    /// 排除的文件及文件夹，glob格式，相对于文件夹，如`node_modules/**`
+    pub exclude: Option<Vec<String>>,
+    /// 单个文件的最大字节数，超过的文件不导入
+    pub max_file_size: Option<u64>,
+    pub tags: Option<Vec<String>>,
 }
@@ -0,0 +1,76 @@
+use crate::db::model::knowledge_base::{KnowledgeBaseConfig, KnowledgeBaseRerankModel};
+use crate::db::model::model::Model;
+use crate::db::Pool;
+use anyhow::{anyhow, bail};
+use embedding::{RerankModel, RerankScore, Reranker, BUNDLED_RERANK_MODELS};
+use rbs::value;
+
+/// 将知识库的重排模型配置转换为重排模型
+pub(crate) async fn resolve_model(model: &KnowledgeBaseRerankModel) -> anyhow::Result<RerankModel> {
+    let model = match model {
+        KnowledgeBaseRerankModel::Bundled { name } => {
+            if !BUNDLED_RERANK_MODELS.contains(&name.as_str()) {
+                bail!("内置重排模型不存在：{}", name);
+            }
+            RerankModel::Bundled { name: name.clone() }
+        }
+        KnowledgeBaseRerankModel::Onnx { dir } => RerankModel::Onnx { dir: dir.clone() },
+        KnowledgeBaseRerankModel::Remote { model_id } => {
+            let model = Model::select_by_map(Pool::get()?, value! {"id": model_id}).await?;
+            if model.is_empty() {
+                bail!("重排模型不存在");
+            }
+            let model = model[0].clone();
+            RerankModel::Api {
+                base_url: model.base_url.unwrap_or_default(),
+                api_key: model.api_key,
+                model: model.name.unwrap_or_default(),
+            }
+        }
+    };
+    Ok(model)
+}
+
+/// 使用知识库的重排模型对检索结果重排
+///
+/// 返回按分数从高到低排序的结果，低于知识库配置的最低分数的结果已丢弃
+pub(crate) async fn rerank(
+    config: &KnowledgeBaseConfig,
+    query: &str,
+    documents: Vec<String>,
+) -> anyhow::Result<Vec<RerankScore>> {
+    let model = resolve_model(&config.rerank_model).await?;
+    let scores = Reranker::rerank(&model, query, documents)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))?;
+    Ok(filter_min_score(scores, config.rerank_min_score))
+}
+
+/// 丢弃低于最低分数的重排结果，未配置最低分数时全部保留
+fn filter_min_score(scores: Vec<RerankScore>, min_score: Option<f32>) -> Vec<RerankScore> {
+    scores
+        .into_iter()
+        .filter(|score| min_score.is_none_or(|min_score| score.score >= min_score))
+        .collect()
+}
+
+#[test]
+fn test_filter_min_score() {
+    let scores = vec![
+        RerankScore {
+            index: 1,
+            score: 0.9,
+        },
+        RerankScore {
+            index: 0,
+            score: 0.5,
+        },
+        RerankScore {
+            index: 2,
+            score: 0.1,
+        },
+    ];
+    assert_eq!(filter_min_score(scores.clone(), None), scores);
+    assert_eq!(filter_min_score(scores.clone(), Some(0.5)), scores[..2]);
+    assert!(filter_min_score(scores, Some(0.95)).is_empty());
+}
@@ -1,5 +1,7 @@
 use crate::db::model::knowledge_base::KnowledgeBase;
-use crate::db::model::knowledge_base_import_record::KnowledgeBaseImportRecord;
+use crate::db::model::knowledge_base_import_record::{ImportProgress, KnowledgeBaseImportRecord};
+use engine::db::ContentRef;
+use engine::RecordMetadata;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,12 +38,71 @@ pub(crate) struct KnowledgeBaseImportRecordListRes {
     pub(crate) file_size: Option<u64>,
     /// 网页地址
     pub(crate) url: Option<String>,
+    /// 标签
+    pub(crate) tags: Option<Vec<String>>,
+    /// 数据表名称
+    pub(crate) table_names: Option<Vec<String>>,
     /// 来源：1文件 2网页 3自定义文本
     pub(crate) source: Option<i8>,
-    /// 状态：0待解析 1导入成功 2导入中 3导入失败
+    /// 状态：0待解析 1导入成功 2导入中 3导入失败 4已取消
     pub(crate) status: Option<i8>,
     /// 状态信息
     pub(crate) status_msg: Option<String>,
+    /// 最近一次的解析进度
+    pub(crate) progress: Option<ImportProgress>,
     /// 耗时
     pub(crate) use_time: Option<usize>,
 }
+
+/// 导入记录的分段
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KnowledgeBaseChunkRes {
+    pub(crate) id: i64,
+    /// 分段在导入记录中的序号
+    pub(crate) seq: i64,
+    /// 分段内容
+    pub(crate) content: String,
+    /// 内容类型：text | image
+    pub(crate) content_type: String,
+    /// 引用的图片及链接
+    pub(crate) content_ref: Option<ContentRef>,
+    /// 元数据
+    pub(crate) metadata: RecordMetadata,
+    /// 创建时间
+    pub(crate) create_time: i64,
+}
+
+/// 导入文件的结果
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KbAddFileRes {
+    /// 导入的文件数
+    pub(crate) imported: usize,
+    /// 跳过的文件
+    pub(crate) skipped: Vec<KbSkippedFile>,
+}
+
+/// 导入时跳过的文件
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KbSkippedFile {
+    /// 文件路径
+    pub(crate) path: String,
+    /// 跳过原因
+    pub(crate) reason: String,
+}
+
+/// 监听文件夹的同步结果
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KbWatchFolderSyncRes {
+    /// 新增的文件数
+    pub(crate) added: usize,
+    /// 修改后重新导入的文件数
+    pub(crate) modified: usize,
+    /// 删除的文件数
+    pub(crate) deleted: usize,
+    /// 跳过的文件
+    pub(crate) skipped: Vec<KbSkippedFile>,
+}
@@ -1,29 +1,45 @@
-use crate::constant;
 use crate::db::model::knowledge_base::KnowledgeBase;
-use embedding::{Embedding, EmbeddingInput, Embeddings, Reranker};
-use engine::Engine;
+use crate::server::kb::{embedder, reranker};
+use engine::{Engine, MetadataFilter, SearchMode, SearchRequestBuilder};
 
-pub(crate) async fn search(kb: &KnowledgeBase, content: &String) -> Option<String> {
-    let vector = Embeddings::embedding(EmbeddingInput::Text(format!(
-        "{}{}",
-        constant::TEXT_SEARCH_INSTRUCTION,
-        content
-    )))
-    .unwrap();
+/// 检索知识库，返回拼接后的检索结果
+///
+/// - filter: 元数据过滤条件，为空时不过滤
+pub(crate) async fn search(
+    kb: &KnowledgeBase,
+    content: &String,
+    filter: Option<MetadataFilter>,
+) -> Option<String> {
+    let vector = match embedder::embed_query(kb, content).await {
+        Ok(vector) => vector,
+        Err(e) => {
+            log::error!("知识库检索失败：{}", e);
+            return None;
+        }
+    };
     // 知识库配置
     let kb_config = kb.get_config();
 
     log::debug!("Knowledge base config: {:?}", kb_config);
 
-    match Engine::simple_search(
-        kb.table_name.clone().unwrap().as_str(),
-        vector,
-        kb_config.search_extend_size,
-        kb_config.search_min_score,
-        kb_config.search_limit,
-    )
-    .await
-    {
+    let mut builder = SearchRequestBuilder::default();
+    builder
+        .table_name(kb.table_name.clone().unwrap())
+        .vector(Some(vector))
+        .context_size(Some(kb_config.search_extend_size))
+        .min_score(Some(kb_config.search_min_score))
+        .limit(Some(kb_config.search_limit))
+        .nprobes(kb_config.search_nprobes)
+        .refine_factor(kb_config.search_refine_factor)
+        .filter(filter);
+    // 混合检索：关键词使用原始的检索文本
+    if kb_config.is_hybrid_search {
+        builder
+            .query(Some(content.to_string()))
+            .mode(SearchMode::Hybrid);
+    }
+
+    match Engine::search_data(builder.build().unwrap()).await {
         Ok(list) => {
             if list.is_empty() {
                 return None;
@@ -39,22 +55,23 @@ pub(crate) async fn search(kb: &KnowledgeBase, content: &String) -> Option<Strin
                 .collect::<Vec<_>>();
 
             let list = if kb_config.is_rerank {
-                // 重排结果
-                let rerank_results = match Reranker::rerank(content.to_string(), contents.clone()) {
-                    Ok(results) => results
-                        .into_iter()
-                        .take(kb_config.rerank_limit)
-                        .map(|(index, _)| contents[index].clone())
-                        .collect::<Vec<_>>(),
-                    Err(e) => {
-                        log::error!("Rerank error: {}", e);
-                        // 重排失败，使用原始顺序
-                        contents
+                // 重排结果，低于最低分数的结果已丢弃
+                let rerank_results =
+                    match reranker::rerank(&kb_config, content, contents.clone()).await {
+                        Ok(scores) => scores
                             .into_iter()
                             .take(kb_config.rerank_limit)
-                            .collect::<Vec<_>>()
-                    }
-                };
+                            .map(|score| contents[score.index].clone())
+                            .collect::<Vec<_>>(),
+                        Err(e) => {
+                            log::error!("Rerank error: {}", e);
+                            // 重排失败，使用原始顺序
+                            contents
+                                .into_iter()
+                                .take(kb_config.rerank_limit)
+                                .collect::<Vec<_>>()
+                        }
+                    };
                 log::info!("Rerank {} similar entries", rerank_results.len());
                 rerank_results
             } else {
@@ -1,33 +1,64 @@
 use crate::common::id;
 use crate::common::req::Pagination;
 use crate::common::res::{IntoPageRes, PageRes};
-use crate::db::model::knowledge_base::{KnowledgeBase, KnowledgeBaseBuilder, KnowledgeBaseSource};
+use crate::db::model::knowledge_base::{
+    KnowledgeBase, KnowledgeBaseBuilder, KnowledgeBaseConfig, KnowledgeBaseEmbeddingConfig,
+    KnowledgeBaseSource,
+};
 use crate::db::model::knowledge_base_import_record;
 use crate::db::model::knowledge_base_import_record::{
     KnowledgeBaseImportFileContentExtractType, KnowledgeBaseImportFileContentType,
     KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder, KnowledgeBaseImportSource,
     KnowledgeBaseImportStatus,
 };
+use crate::db::model::knowledge_base_watch_folder::KnowledgeBaseWatchFolder;
+use crate::db::model::system_config::{SystemConfig, SystemConfigBuilder};
 use crate::db::{tools, Pool};
-use crate::server::kb::parse;
+use crate::server::kb::progress::ImportEvent;
+use crate::server::kb::queue::ImportQueueOptions;
+use crate::server::kb::reindex::KnowledgeBaseReindexProgress;
 use crate::server::kb::request::{
-    KbAddReq, KnowledgeBaseImportRecordListReq, KnowledgeBaseUpdateReq,
+    KbAddFolderReq, KbAddReq, KbReindexReq, KnowledgeBaseChunkListReq,
+    KnowledgeBaseImportRecordListReq, KnowledgeBaseUpdateReq,
 };
 use crate::server::kb::response::{
+    KbAddFileRes, KbSkippedFile, KbWatchFolderSyncRes, KnowledgeBaseChunkRes,
     KnowledgeBaseDetailRes, KnowledgeBaseImportRecordListRes, KnowledgeBaseListRes,
 };
+use crate::server::kb::{embedder, parse, progress, queue, reindex, watch};
+use crate::utils::file_util;
 use crate::utils::file_util::make_save_file;
 use crate::{business_error, db, db_error, message_error};
-use anyhow::{bail, Context};
-use engine::{Engine, TableEngine};
+use anyhow::{anyhow, bail, Context};
+use embedding::{EmbeddingCacheOptions, EmbeddingPoolOptions, Embeddings};
+use engine::{Engine, SearchRequestBuilder, TableEngine, VectorIndexOptions};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use rbatis::executor::RBatisTxExecutor;
 use rbs::value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Channel;
+use walkdir::WalkDir;
 
 pub(crate) async fn add_kb(req: KbAddReq) -> anyhow::Result<()> {
+    // 确定嵌入模型及向量维度
+    let embedding_model = req.embedding_model.clone().unwrap_or_default();
+    let model = embedder::resolve_model(&embedding_model).await?;
+    let dimension = embedder::probe_dimension(&model).await.map_err(|e| {
+        log::error!("[嵌入模型不可用]{}，原因：{}", model.id(), e);
+        message_error!("嵌入模型不可用")
+    })?;
+    let embedding_config = KnowledgeBaseEmbeddingConfig {
+        model: embedding_model,
+        dimension,
+    };
+
     db::tx(|tx| {
         let req = req.clone();
+        let embedding_config = embedding_config.clone();
         async move {
             let table_name = uuid::Uuid::new_v4().to_string();
             let kb = KnowledgeBaseBuilder::default()
@@ -38,6 +69,9 @@ pub(crate) async fn add_kb(req: KbAddReq) -> anyhow::Result<()> {
                 .source(Some(KnowledgeBaseSource::Custom as i8))
                 .table_name(Some(table_name.clone()))
                 .file_content_extract_type(Some(KnowledgeBaseImportFileContentExtractType::Ocr))
+                .embedding_config(Some(embedding_config.clone()))
+                // 写入默认配置，与分段配置加入前创建、没有配置的知识库区分
+                .config(Some(KnowledgeBaseConfig::default()))
                 .build()
                 .unwrap();
             KnowledgeBase::insert(Pool::get()?, &kb)
@@ -46,10 +80,12 @@ pub(crate) async fn add_kb(req: KbAddReq) -> anyhow::Result<()> {
 
             // 创建知识库文档表
             // 一个知识库只有1个文档表，用于相似性搜索，但可以有多个数据表，数据表使用sql精确查询
-            Engine::new_table(&table_name).await.map_err(|e| {
-                log::error!("[创建知识库表]失败，原因：{}", e);
-                message_error!("创建知识库表失败")
-            })?;
+            Engine::new_table(&table_name, embedding_config.dimension)
+                .await
+                .map_err(|e| {
+                    log::error!("[创建知识库表]失败，原因：{}", e);
+                    message_error!("创建知识库表失败")
+                })?;
 
             // 重建nld
             rebuild_nld(&tx, kb.id.unwrap())
@@ -65,7 +101,7 @@ pub(crate) async fn add_kb(req: KbAddReq) -> anyhow::Result<()> {
 
 // 构建知识库的自然语言描述
 // 这些描述信息会传给LLM，由LLM进行相关决策
-async fn rebuild_nld(tx: &RBatisTxExecutor, id: i64) -> anyhow::Result<()> {
+pub(crate) async fn rebuild_nld(tx: &RBatisTxExecutor, id: i64) -> anyhow::Result<()> {
     //获取知识库
     let knowledge_base = KnowledgeBase::select_by_map(tx, value! {"id": id}).await?;
     let knowledge_base = knowledge_base.first().cloned().unwrap();
@@ -126,10 +162,14 @@ pub(crate) async fn list_all_kb() -> anyhow::Result<Vec<KnowledgeBaseListRes>> {
 
 pub(crate) async fn delete_kb(id: i64) -> anyhow::Result<()> {
     KnowledgeBase::delete_by_map(Pool::get()?, value! {"id": id}).await?;
+    KnowledgeBaseWatchFolder::delete_by_map(Pool::get()?, value! {"knowledge_base_id": id}).await?;
     Ok(())
 }
 
 pub(crate) async fn update_kb(req: KnowledgeBaseUpdateReq) -> anyhow::Result<()> {
+    if let Some(config) = &req.config {
+        config.validate()?;
+    }
     db::tx(|tx| {
         let req = req.clone();
         async move {
@@ -141,6 +181,7 @@ pub(crate) async fn update_kb(req: KnowledgeBaseUpdateReq) -> anyhow::Result<()>
                 .mcp_server_ids(req.mcp_server_ids)
                 .model_id(req.model_id)
                 .file_content_extract_type(req.file_content_extract_type)
+                .config(req.config)
                 .update_time(Some(tools::now()))
                 .build()
                 .unwrap();
@@ -169,74 +210,518 @@ pub(crate) async fn kb_detail(id: i64) -> anyhow::Result<KnowledgeBaseDetailRes>
     Ok(KnowledgeBaseDetailRes { inner: kb.clone() })
 }
 
-pub(crate) async fn add_kb_file(kb_id: i64, files: Vec<String>) -> anyhow::Result<()> {
+/// 导入文件，与知识库中已导入的文件内容相同的文件跳过
+pub(crate) async fn add_kb_file(
+    kb_id: i64,
+    files: Vec<String>,
+    tags: Option<Vec<String>>,
+) -> anyhow::Result<KbAddFileRes> {
     log::info!("add_kb_file: {:?}", files);
-    let mut records = vec![];
+    if reindex::is_reindexing(kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
     let kb = KnowledgeBase::select_by_map(Pool::get()?, value! {"id": kb_id}).await?;
     if kb.is_empty() {
         bail!("知识库不存在");
     }
     let kb = kb.first().unwrap();
+
+    // 计算文件哈希及复制文件较为耗时，不阻塞异步运行时
+    let kb_clone = kb.clone();
+    let mut imported = imported_file_hashes(kb_id).await?;
+    let (records, skipped) = tokio::task::spawn_blocking(move || {
+        let mut skipped = vec![];
+        let files = files.into_iter().map(PathBuf::from).collect();
+        let files = dedup_files(files, &mut imported, &mut skipped)?;
+        let records = new_file_records(&kb_clone, files, &tags)?;
+        Ok::<_, anyhow::Error>((records, skipped))
+    })
+    .await??;
+
+    insert_file_records(&records).await?;
+    let res = KbAddFileRes {
+        imported: records.len(),
+        skipped,
+    };
+    queue::notify();
+    Ok(res)
+}
+
+/// 知识库中已导入文件的内容哈希及标题，导入失败的记录不计入
+pub(crate) async fn imported_file_hashes(kb_id: i64) -> anyhow::Result<HashMap<String, String>> {
+    let records =
+        KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"knowledge_base_id": kb_id})
+            .await?;
+    Ok(records
+        .into_iter()
+        .filter(|record| record.status != Some(KnowledgeBaseImportStatus::Failed as i8))
+        .filter_map(|record| Some((record.file_hash?, record.title.unwrap_or_default())))
+        .collect())
+}
+
+/// 按内容哈希去除重复的文件，返回需要导入的文件及其哈希
+///
+/// 与已导入的文件或本次导入的其他文件内容相同的文件跳过，imported中会加入本次导入的文件
+pub(crate) fn dedup_files(
+    files: Vec<PathBuf>,
+    imported: &mut HashMap<String, String>,
+    skipped: &mut Vec<KbSkippedFile>,
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut result = vec![];
     for file in files {
-        let file = Path::new(&file);
-        let original_file_name = file.file_name().unwrap().to_str().unwrap();
-        let original_file_path = file.to_str().unwrap();
-        let file_size = file.metadata()?.len();
+        let file_hash = file_util::file_hash(&file)?;
+        if let Some(title) = imported.get(&file_hash) {
+            skipped.push(KbSkippedFile {
+                path: file.to_string_lossy().to_string(),
+                reason: format!("与已导入的文件内容相同：{}", title),
+            });
+            continue;
+        }
+        let title = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        imported.insert(file_hash.clone(), title);
+        result.push((file, file_hash));
+    }
+    Ok(result)
+}
+
+/// 复制文件到数据目录，生成待解析的导入记录
+pub(crate) fn new_file_record(
+    kb: &KnowledgeBase,
+    file: &Path,
+    file_hash: String,
+    tags: &Option<Vec<String>>,
+) -> anyhow::Result<KnowledgeBaseImportRecord> {
+    let (Some(original_file_name), Some(original_file_path)) = (
+        file.file_name().and_then(|name| name.to_str()),
+        file.to_str(),
+    ) else {
+        bail!("文件路径包含无法识别的字符：{}", file.to_string_lossy());
+    };
+    let file_size = file.metadata()?.len();
+    let file_mtime = file_util::file_mtime(file)?;
+
+    let (file_name, file_path) = make_save_file(original_file_name)?;
+
+    // 复制文件
+    fs::copy(original_file_path, &file_path)?;
+
+    // 导入记录的nld
+    let nld = format!("文件名：{}，路径：{}", file_name, file_path);
+    // 生成导入记录，状态为待解析
+    let record = KnowledgeBaseImportRecordBuilder::default()
+        .id(Some(id::next()))
+        .knowledge_base_id(kb.id)
+        .title(Some(original_file_name.to_string()))
+        .original_file_name(Some(original_file_name.to_string()))
+        .original_file_path(Some(original_file_path.to_string()))
+        .file_name(Some(file_name.to_string()))
+        .file_path(Some(file_path.to_string()))
+        .file_size(Some(file_size))
+        .file_hash(Some(file_hash))
+        .file_mtime(Some(file_mtime))
+        .file_content_type(Some(KnowledgeBaseImportFileContentType::Document as i8))
+        .file_content_extract_type(kb.file_content_extract_type.clone())
+        .source(Some(KnowledgeBaseImportSource::LocalFile as i8))
+        .tags(tags.clone())
+        .status(Some(KnowledgeBaseImportStatus::Waiting as i8))
+        .nld(Some(nld))
+        .build()?;
+    Ok(record)
+}
+
+/// 为每个文件生成待解析的导入记录，失败时删除已复制的文件
+pub(crate) fn new_file_records(
+    kb: &KnowledgeBase,
+    files: Vec<(PathBuf, String)>,
+    tags: &Option<Vec<String>>,
+) -> anyhow::Result<Vec<KnowledgeBaseImportRecord>> {
+    let mut records = vec![];
+    for (file, file_hash) in files {
+        match new_file_record(kb, &file, file_hash, tags) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                remove_copied_files(&records);
+                return Err(e);
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// 保存文件的导入记录，保存失败时删除未保存的记录复制的文件
+pub(crate) async fn insert_file_records(
+    records: &[KnowledgeBaseImportRecord],
+) -> anyhow::Result<()> {
+    const BATCH_SIZE: usize = 100;
+    for (i, chunk) in records.chunks(BATCH_SIZE).enumerate() {
+        let result = async {
+            KnowledgeBaseImportRecord::insert_batch(Pool::get()?, chunk, 10).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = result {
+            remove_copied_files(&records[i * BATCH_SIZE..]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// 删除导入记录复制到数据目录的文件，导入记录未能保存时调用
+pub(crate) fn remove_copied_files(records: &[KnowledgeBaseImportRecord]) {
+    for file_path in records
+        .iter()
+        .filter_map(|record| record.file_path.as_ref())
+    {
+        if let Err(e) = fs::remove_file(file_path) {
+            log::error!("[kb] File deletion failed, reason: {}", e);
+        }
+    }
+}
+
+/// 导入文件夹，递归扫描文件夹下的文件，每个文件生成一条导入记录
+///
+/// 隐藏文件及文件夹、不支持的文件类型、超过大小限制的文件、与已导入的文件内容相同的文件不导入，
+/// 除隐藏文件外，跳过的文件在结果中列出
+pub(crate) async fn add_kb_folder(req: KbAddFolderReq) -> anyhow::Result<KbAddFileRes> {
+    log::info!("add_kb_folder: {:?}", req);
+    if reindex::is_reindexing(req.kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let kb = KnowledgeBase::select_by_map(Pool::get()?, value! {"id": req.kb_id}).await?;
+    if kb.is_empty() {
+        bail!("知识库不存在");
+    }
+    let kb = kb.first().unwrap();
+    if !Path::new(&req.dir).is_dir() {
+        bail!("文件夹不存在：{}", req.dir);
+    }
+
+    // 扫描及复制文件较为耗时，不阻塞异步运行时
+    let kb_clone = kb.clone();
+    let mut imported = imported_file_hashes(req.kb_id).await?;
+    let (records, skipped) = tokio::task::spawn_blocking(move || {
+        let (files, mut skipped) = scan_files(
+            &req.dir,
+            req.include.as_deref().unwrap_or_default(),
+            req.exclude.as_deref().unwrap_or_default(),
+            req.max_file_size,
+        )?;
+        let files = dedup_files(files, &mut imported, &mut skipped)?;
+        let records = new_file_records(&kb_clone, files, &req.tags)?;
+        Ok::<_, anyhow::Error>((records, skipped))
+    })
+    .await??;
+
+    insert_file_records(&records).await?;
+    let res = KbAddFileRes {
+        imported: records.len(),
+        skipped,
+    };
+    queue::notify();
+    Ok(res)
+}
 
-        let (file_name, file_path) = make_save_file(original_file_name)?;
+/// 递归扫描文件夹，返回可以导入的文件及跳过的文件
+///
+/// include、exclude为相对于文件夹的glob，隐藏文件及文件夹不扫描
+pub(crate) fn scan_files(
+    dir: &str,
+    include: &[String],
+    exclude: &[String],
+    max_file_size: Option<u64>,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<KbSkippedFile>)> {
+    let include = build_globset(include)?;
+    let exclude = build_globset(exclude)?;
+    let root = Path::new(dir);
+    // glob匹配文件夹下的相对路径，统一使用`/`分隔
+    let relative_path = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
 
-        // 复制文件
-        fs::copy(original_file_path, &file_path)?;
+    let mut files = vec![];
+    let mut skipped = vec![];
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        if entry.depth() == 0 {
+            return true;
+        }
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        !hidden && !exclude.is_match(relative_path(entry.path()))
+    });
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("[扫描文件夹失败]{}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if !include.is_empty() && !include.is_match(relative_path(path)) {
+            continue;
+        }
+        let path_str = path.to_string_lossy().to_string();
+        if path.to_str().is_none() {
+            skipped.push(KbSkippedFile {
+                path: path_str,
+                reason: "文件路径包含无法识别的字符".to_string(),
+            });
+            continue;
+        }
+        if !parse::is_supported_file(path) {
+            skipped.push(KbSkippedFile {
+                path: path_str,
+                reason: "不支持的文件类型".to_string(),
+            });
+            continue;
+        }
+        let file_size = entry.metadata()?.len();
+        if max_file_size.is_some_and(|max_file_size| file_size > max_file_size) {
+            skipped.push(KbSkippedFile {
+                path: path_str,
+                reason: "文件大小超过限制".to_string(),
+            });
+            continue;
+        }
+        files.push(path.to_path_buf());
+    }
+    Ok((files, skipped))
+}
 
-        // 导入记录的nld
-        let nld = format!("文件名：{}，路径：{}", file_name, file_path);
-        // 生成导入记录，状态为待解析
+/// 构建glob匹配
+pub(crate) fn build_globset(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).with_context(|| format!("匹配规则格式错误：{}", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// 导入网页，已导入过的网页重新抓取内容
+pub(crate) async fn add_kb_url(
+    kb_id: i64,
+    urls: Vec<String>,
+    tags: Option<Vec<String>>,
+) -> anyhow::Result<()> {
+    log::info!("add_kb_url: {:?}", urls);
+    if reindex::is_reindexing(kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let kb = KnowledgeBase::select_by_map(Pool::get()?, value! {"id": kb_id}).await?;
+    if kb.is_empty() {
+        bail!("知识库不存在");
+    }
+    let kb = kb.first().unwrap();
+    let mut records = vec![];
+    for url in urls {
+        let url = url.trim().to_string();
+        if url.is_empty() {
+            continue;
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("网页地址格式错误：{}", url);
+        }
+
+        let imported = KnowledgeBaseImportRecord::select_by_map(
+            Pool::get()?,
+            value! {
+                "knowledge_base_id": kb_id,
+                "source": KnowledgeBaseImportSource::Url as i8,
+                "url": &url,
+            },
+        )
+        .await?;
+        if let Some(record) = imported.first() {
+            refresh_kb_import_record(record.id.unwrap()).await?;
+            continue;
+        }
+
+        // 生成导入记录，标题在解析后替换为网页标题
         let record = KnowledgeBaseImportRecordBuilder::default()
             .id(Some(id::next()))
             .knowledge_base_id(Some(kb_id))
-            .title(Some(original_file_name.to_string()))
-            .original_file_name(Some(original_file_name.to_string()))
-            .original_file_path(Some(original_file_path.to_string()))
-            .file_name(Some(file_name.to_string()))
-            .file_path(Some(file_path.to_string()))
-            .file_size(Some(file_size))
+            .title(Some(url.clone()))
+            .url(Some(url.clone()))
             .file_content_type(Some(KnowledgeBaseImportFileContentType::Document as i8))
             .file_content_extract_type(kb.file_content_extract_type.clone())
-            .source(Some(KnowledgeBaseImportSource::LocalFile as i8))
-            .status(Some(KnowledgeBaseImportStatus::Importing as i8))
-            .nld(Some(nld))
+            .source(Some(KnowledgeBaseImportSource::Url as i8))
+            .tags(tags.clone())
+            .status(Some(KnowledgeBaseImportStatus::Waiting as i8))
+            .nld(Some(format!("网页地址：{}", url)))
             .build()?;
         records.push(record);
     }
 
-    KnowledgeBaseImportRecord::insert_batch(Pool::get()?, &records, 10).await?;
-
-    records.into_iter().for_each(|mut record| {
-        tokio::spawn(async move {
-            // 解析文件
-            match record.parse().await {
-                Ok(_) => {
-                    log::info!("parse file success: {}", record.file_name.as_ref().unwrap());
-                    record.status = Some(KnowledgeBaseImportStatus::Success as i8);
-                }
-                Err(e) => {
-                    log::error!("parse file error: {}", e);
-                    record.status = Some(KnowledgeBaseImportStatus::Failed as i8);
-                    record.status_msg = Some(e.to_string());
-                }
-            }
-            // 更新导入记录，状态为完成
-            KnowledgeBaseImportRecord::update_by_map(
-                Pool::get()?,
-                &record,
-                value! {"id": record.id},
-            )
-            .await
-            .context("更新导入记录失败")?;
+    if !records.is_empty() {
+        KnowledgeBaseImportRecord::insert_batch(Pool::get()?, &records, 10).await?;
+    }
 
-            Ok::<(), anyhow::Error>(())
-        });
-    });
+    queue::notify();
+    Ok(())
+}
+
+/// 导入自定义文本
+///
+/// 文本保存为Markdown文件，与本地文件一样在后台解析，重建索引时从该文件重新解析
+pub(crate) async fn add_kb_text(
+    kb_id: i64,
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+) -> anyhow::Result<()> {
+    let title = title.trim();
+    if title.is_empty() {
+        bail!("标题不能为空");
+    }
+    if content.trim().is_empty() {
+        bail!("文本内容不能为空");
+    }
+    if reindex::is_reindexing(kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let kb = KnowledgeBase::select_by_map(Pool::get()?, value! {"id": kb_id}).await?;
+    if kb.is_empty() {
+        bail!("知识库不存在");
+    }
+    let kb = kb.first().unwrap();
+
+    let id = id::next();
+    let (file_name, file_path) = make_save_file(&format!("{}.md", id))?;
+    fs::write(&file_path, &content)?;
+
+    let record = KnowledgeBaseImportRecordBuilder::default()
+        .id(Some(id))
+        .knowledge_base_id(Some(kb_id))
+        .title(Some(title.to_string()))
+        .file_name(Some(file_name))
+        .file_path(Some(file_path))
+        .file_size(Some(content.len() as u64))
+        .file_content_type(Some(KnowledgeBaseImportFileContentType::Document as i8))
+        .file_content_extract_type(kb.file_content_extract_type.clone())
+        .source(Some(KnowledgeBaseImportSource::CustomText as i8))
+        .tags(tags)
+        .status(Some(KnowledgeBaseImportStatus::Waiting as i8))
+        .nld(Some(format!("自定义文本：{}", title)))
+        .build()?;
+    KnowledgeBaseImportRecord::insert(Pool::get()?, &record).await?;
+
+    queue::notify();
+    Ok(())
+}
+
+/// 重新导入网页，重新抓取并解析，解析成功后替换已导入的分段
+pub(crate) async fn refresh_kb_import_record(id: i64) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    let record = record.first().unwrap().clone();
+    if record.source != Some(KnowledgeBaseImportSource::Url as i8) {
+        bail!("仅支持重新导入网页");
+    }
+    requeue(record).await
+}
+
+/// 重新导入，重新加入导入队列，解析成功后替换已导入的分段
+pub(crate) async fn rerun_kb_import_record(id: i64) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    requeue(record.first().unwrap().clone()).await
+}
+
+async fn requeue(mut record: KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+    if is_pending(&record) {
+        bail!("正在导入中，请稍后再试");
+    }
+    if reindex::is_reindexing(record.knowledge_base_id.unwrap()) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+
+    record.status = Some(KnowledgeBaseImportStatus::Waiting as i8);
+    record.status_msg = Some(String::new());
+    record.retry_count = Some(0);
+    record.update_time = Some(tools::now());
+    KnowledgeBaseImportRecord::update_by_map(Pool::get()?, &record, value! {"id": record.id})
+        .await?;
+
+    queue::notify();
+    Ok(())
+}
+
+/// 取消导入，待解析的记录直接标记为已取消，正在解析的记录中止解析
+pub(crate) async fn cancel_kb_import_record(id: i64) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    let record = record.first().unwrap();
+    if record.status == Some(KnowledgeBaseImportStatus::Waiting as i8) {
+        // 以状态为条件更新，避免与工作协程取出记录冲突
+        let result = KnowledgeBaseImportRecord::update_by_map(
+            Pool::get()?,
+            &KnowledgeBaseImportRecordBuilder::default()
+                .status(Some(KnowledgeBaseImportStatus::Cancelled as i8))
+                .status_msg(Some("已取消".to_string()))
+                .build()?,
+            value! {"id": id, "status": KnowledgeBaseImportStatus::Waiting as i8},
+        )
+        .await?;
+        if result.rows_affected > 0 {
+            return Ok(());
+        }
+    }
+    if !queue::cancel(id) {
+        bail!("记录未在导入中");
+    }
+    Ok(())
+}
+
+/// 是否在导入队列中，即待解析或导入中
+pub(crate) fn is_pending(record: &KnowledgeBaseImportRecord) -> bool {
+    record.status == Some(KnowledgeBaseImportStatus::Waiting as i8)
+        || record.status == Some(KnowledgeBaseImportStatus::Importing as i8)
+}
+
+/// 删除导入记录正在解析的批次已写入的分段
+///
+/// 解析失败、取消或中断时调用，上次解析成功的分段保持不变
+pub(crate) async fn clear_pending_data(record: &KnowledgeBaseImportRecord) -> anyhow::Result<()> {
+    let table_name = parse::get_table_name(record.knowledge_base_id.unwrap()).await?;
+    Engine::delete_data(&table_name, vec![record.other_batch_id()]).await?;
+    parse::delete_images(&table_name, vec![record.other_batch_id()]).await?;
+    Ok(())
+}
+
+/// 重新解析成功后删除原有的数据
+///
+/// - record：已切换到新批次的导入记录
+/// - table_names：解析前的数据表名称，新的解析结果中不再使用的数据表被删除
+pub(crate) async fn clear_replaced_data(
+    record: &KnowledgeBaseImportRecord,
+    table_names: Vec<String>,
+) -> anyhow::Result<()> {
+    clear_pending_data(record).await?;
+    let table_name = parse::get_table_name(record.knowledge_base_id.unwrap()).await?;
+    let current = record.table_names.clone().unwrap_or_default();
+    for data_table_name in table_names {
+        if !current.contains(&data_table_name) {
+            TableEngine::drop_table(&table_name, &data_table_name).await?;
+        }
+    }
     Ok(())
 }
 
@@ -257,9 +742,12 @@ pub(crate) async fn kb_import_record_list(
                 file_path: item.file_path,
                 file_size: item.file_size,
                 url: item.url,
+                tags: item.tags,
+                table_names: item.table_names,
                 source: item.source,
                 status: item.status,
                 status_msg: item.status_msg,
+                progress: item.progress,
                 use_time: if let Some(start_time) = item.start_time {
                     // 如果是导入中，则使用当前时间减去开始时间
                     let end_time =
@@ -292,6 +780,11 @@ pub(crate) async fn delete_kb_import_record(id: i64) -> anyhow::Result<()> {
             return Err(message_error!("记录不存在"));
         }
         let record = record.first().unwrap();
+        if reindex::is_reindexing(record.knowledge_base_id.unwrap()) {
+            return Err(message_error!("知识库正在重建索引，请稍后再试"));
+        }
+        // 中止正在进行的解析
+        queue::cancel(id);
         if let Some(file_path) = &record.file_path {
             if let Err(e) = fs::remove_file(file_path) {
                 log::error!("[kb] File deletion failed, reason: {}", e);
@@ -303,7 +796,7 @@ pub(crate) async fn delete_kb_import_record(id: i64) -> anyhow::Result<()> {
             .map_err(|e| business_error!(e))?;
 
         // 按batch_id从向量数据库中删除
-        Engine::delete_data(&table_name, vec![record.id.unwrap().to_string()])
+        Engine::delete_data(&table_name, record.batch_ids())
             .await
             .map_err(|e| {
                 log::error!(
@@ -312,16 +805,32 @@ pub(crate) async fn delete_kb_import_record(id: i64) -> anyhow::Result<()> {
                 );
                 message_error!("删除记录失败")
             })?;
-
-        // TODO 清理ref文件
-
-        TableEngine::drop_table(&table_name, &record.title.clone().unwrap())
+        parse::delete_images(&table_name, record.batch_ids())
             .await
             .map_err(|e| {
-                log::error!("[kb] Failed to delete data table, reason: {}", e);
+                log::error!(
+                    "[kb] Failed to delete images from vector database, reason: {}",
+                    e
+                );
                 message_error!("删除记录失败")
             })?;
 
+        // TODO 清理ref文件
+
+        // 旧版本的导入记录未保存数据表名称，数据表与标题同名
+        let data_table_names = record
+            .table_names
+            .clone()
+            .unwrap_or_else(|| vec![record.title.clone().unwrap()]);
+        for data_table_name in data_table_names {
+            TableEngine::drop_table(&table_name, &data_table_name)
+                .await
+                .map_err(|e| {
+                    log::error!("[kb] Failed to delete data table, reason: {}", e);
+                    message_error!("删除记录失败")
+                })?;
+        }
+
         KnowledgeBaseImportRecord::delete_by_map(&tx, value! {"id": id})
             .await
             .map_err(|e| db_error!(e))?;
@@ -336,3 +845,338 @@ pub(crate) async fn delete_kb_import_record(id: i64) -> anyhow::Result<()> {
     .await?;
     Ok(())
 }
+
+/// 更新导入记录的标签，同时更新已导入分段的标签
+pub(crate) async fn update_kb_import_record_tags(id: i64, tags: Vec<String>) -> anyhow::Result<()> {
+    let record = KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    let record = record.first().unwrap();
+    if reindex::is_reindexing(record.knowledge_base_id.unwrap()) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let tags = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+
+    let table_name = parse::get_table_name(record.knowledge_base_id.unwrap()).await?;
+    Engine::update_tags(&table_name, &record.current_batch_id(), &tags).await?;
+    parse::update_image_tags(&table_name, &record.current_batch_id(), &tags).await?;
+
+    KnowledgeBaseImportRecord::update_by_map(
+        Pool::get()?,
+        &KnowledgeBaseImportRecordBuilder::default()
+            .tags(Some(tags))
+            .update_time(Some(tools::now()))
+            .build()?,
+        value! {"id": id},
+    )
+    .await?;
+    Ok(())
+}
+
+/// 分页查询导入记录的分段，按分段在原文中的顺序排列
+pub(crate) async fn list_kb_chunks(
+    req: KnowledgeBaseChunkListReq,
+) -> anyhow::Result<PageRes<KnowledgeBaseChunkRes>> {
+    let record =
+        KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": req.record_id})
+            .await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    let table_name = parse::get_table_name(record[0].knowledge_base_id.unwrap()).await?;
+    let chunks = Engine::list_data(&table_name, &record[0].current_batch_id()).await?;
+
+    let page_size = req.page_size().max(1);
+    let skip = (req.page_num().max(1) - 1) * page_size;
+    Ok(PageRes {
+        page_num: req.page_num(),
+        page_size,
+        total: chunks.len() as u64,
+        list: chunks
+            .into_iter()
+            .skip(skip as usize)
+            .take(page_size as usize)
+            .map(|chunk| KnowledgeBaseChunkRes {
+                id: chunk.id,
+                seq: chunk.seq,
+                content: chunk.content,
+                content_type: chunk.content_type,
+                content_ref: chunk.content_ref,
+                metadata: chunk.metadata,
+                create_time: chunk.create_time,
+            })
+            .collect(),
+    })
+}
+
+/// 在导入记录的最后添加一个分段
+pub(crate) async fn add_kb_chunk(record_id: i64, content: String) -> anyhow::Result<()> {
+    let content = content.trim();
+    if content.is_empty() {
+        bail!("分段内容不能为空");
+    }
+    let (record, kb) = editable_record(record_id).await?;
+    parse::add_text(&kb, &record, content.to_string()).await
+}
+
+/// 修改分段内容，使用知识库的嵌入模型重新生成向量
+///
+/// 重新导入网页或重建索引时按原文重新解析，手动修改、添加的分段不会保留
+pub(crate) async fn update_kb_chunk(
+    record_id: i64,
+    chunk_id: i64,
+    content: String,
+) -> anyhow::Result<()> {
+    let content = content.trim();
+    if content.is_empty() {
+        bail!("分段内容不能为空");
+    }
+    let (record, kb) = editable_record(record_id).await?;
+    let table_name = kb.table_name.clone().unwrap();
+    check_chunk(&table_name, &record, chunk_id).await?;
+
+    let vector = embedder::embed_texts(&kb, vec![content.to_string()])
+        .await?
+        .pop()
+        .context("生成向量失败")?;
+    Engine::update_data(&table_name, chunk_id, content.to_string(), vector).await?;
+    Ok(())
+}
+
+/// 删除分段
+pub(crate) async fn delete_kb_chunk(record_id: i64, chunk_id: i64) -> anyhow::Result<()> {
+    let (record, kb) = editable_record(record_id).await?;
+    let table_name = kb.table_name.clone().unwrap();
+    check_chunk(&table_name, &record, chunk_id).await?;
+    Engine::delete_data_by_ids(&table_name, vec![chunk_id]).await?;
+    Ok(())
+}
+
+/// 获取可以修改分段的导入记录及其知识库
+///
+/// 导入中及重建索引期间分段会被整体替换，不允许修改
+async fn editable_record(
+    record_id: i64,
+) -> anyhow::Result<(KnowledgeBaseImportRecord, KnowledgeBase)> {
+    let record =
+        KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"id": record_id}).await?;
+    if record.is_empty() {
+        bail!("记录不存在");
+    }
+    let record = record.first().unwrap().clone();
+    if is_pending(&record) {
+        bail!("正在导入中，请稍后再试");
+    }
+    let kb_id = record.knowledge_base_id.unwrap();
+    if reindex::is_reindexing(kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let kb = parse::get_kb(kb_id).await?;
+    Ok((record, kb))
+}
+
+/// 检查分段是否属于导入记录
+async fn check_chunk(
+    table_name: &str,
+    record: &KnowledgeBaseImportRecord,
+    chunk_id: i64,
+) -> anyhow::Result<()> {
+    let chunks = Engine::search_data(
+        SearchRequestBuilder::default()
+            .table_name(table_name.to_string())
+            .id(Some(chunk_id))
+            .batch_id(Some(record.current_batch_id()))
+            .limit(Some(1))
+            .build()?,
+    )
+    .await?;
+    if chunks.is_empty() {
+        bail!("分段不存在");
+    }
+    Ok(())
+}
+
+pub(crate) async fn add_kb_watch_folder(req: KbAddFolderReq) -> anyhow::Result<()> {
+    watch::add_watch_folder(req).await
+}
+
+pub(crate) async fn list_kb_watch_folders(
+    kb_id: i64,
+) -> anyhow::Result<Vec<KnowledgeBaseWatchFolder>> {
+    watch::list_watch_folders(kb_id).await
+}
+
+pub(crate) async fn delete_kb_watch_folder(id: i64) -> anyhow::Result<()> {
+    watch::delete_watch_folder(id).await
+}
+
+pub(crate) async fn sync_kb_watch_folder(id: i64) -> anyhow::Result<KbWatchFolderSyncRes> {
+    watch::sync_watch_folder(id).await
+}
+
+pub(crate) async fn reindex_kb(req: KbReindexReq) -> anyhow::Result<()> {
+    reindex::reindex_kb(req).await
+}
+
+pub(crate) async fn kb_reindex_progress(
+    kb_id: i64,
+) -> anyhow::Result<Option<KnowledgeBaseReindexProgress>> {
+    Ok(reindex::reindex_progress(kb_id))
+}
+
+/// 读取system_config中以JSON保存的配置，未配置时返回默认配置
+async fn get_config<T: DeserializeOwned + Default>(key: &str) -> anyhow::Result<T> {
+    let config = SystemConfig::select_by_map(Pool::get()?, value! {"config_key": key}).await?;
+    let config = match config.first().and_then(|c| c.config_value.clone()) {
+        Some(value) => serde_json::from_str(&value)?,
+        None => T::default(),
+    };
+    Ok(config)
+}
+
+/// 以JSON保存配置到system_config，替换原有的配置
+async fn save_config<T: Serialize>(key: &str, value: &T) -> anyhow::Result<()> {
+    let config = SystemConfigBuilder::default()
+        .config_key(Some(key.to_string()))
+        .config_value(Some(serde_json::to_string(value)?))
+        .build()?;
+    db::tx(|tx| {
+        let config = config.clone();
+        async move {
+            SystemConfig::delete_by_map(&tx, value! {"config_key": &config.config_key})
+                .await
+                .map_err(|e| db_error!(e))?;
+            SystemConfig::insert(&tx, &config)
+                .await
+                .map_err(|e| db_error!(e))?;
+            Ok(())
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+/// 向量索引配置在system_config中的key
+const VECTOR_INDEX_OPTIONS_KEY: &str = "vector_index_options";
+
+/// 获取向量索引配置，未配置时返回默认配置
+pub(crate) async fn get_vector_index_options() -> anyhow::Result<VectorIndexOptions> {
+    get_config(VECTOR_INDEX_OPTIONS_KEY).await
+}
+
+/// 更新向量索引配置，在之后的表优化中生效
+pub(crate) async fn update_vector_index_options(options: VectorIndexOptions) -> anyhow::Result<()> {
+    save_config(VECTOR_INDEX_OPTIONS_KEY, &options).await?;
+    Engine::set_vector_index_options(options);
+    Ok(())
+}
+
+/// 启动时加载向量索引配置
+pub(crate) async fn load_vector_index_options() -> anyhow::Result<()> {
+    Engine::set_vector_index_options(get_vector_index_options().await?);
+    Ok(())
+}
+
+/// 导入队列配置在system_config中的key
+const IMPORT_QUEUE_OPTIONS_KEY: &str = "import_queue_options";
+
+/// 获取导入队列配置，未配置时返回默认配置
+pub(crate) async fn get_import_queue_options() -> anyhow::Result<ImportQueueOptions> {
+    get_config(IMPORT_QUEUE_OPTIONS_KEY).await
+}
+
+/// 更新导入队列配置，立即生效
+pub(crate) async fn update_import_queue_options(options: ImportQueueOptions) -> anyhow::Result<()> {
+    if options.worker_count == 0 {
+        bail!("同时导入的数量不能小于1");
+    }
+    save_config(IMPORT_QUEUE_OPTIONS_KEY, &options).await?;
+    queue::set_options(options);
+    Ok(())
+}
+
+/// 启动导入队列，继续解析待解析及中断的导入记录
+pub(crate) async fn start_import_queue() -> anyhow::Result<()> {
+    queue::start(get_import_queue_options().await?).await;
+    Ok(())
+}
+
+/// 嵌入线程池配置在system_config中的key
+const EMBEDDING_POOL_OPTIONS_KEY: &str = "embedding_pool_options";
+
+/// 获取嵌入线程池配置，未配置时返回默认配置
+pub(crate) async fn get_embedding_pool_options() -> anyhow::Result<EmbeddingPoolOptions> {
+    get_config(EMBEDDING_POOL_OPTIONS_KEY).await
+}
+
+/// 更新嵌入线程池配置，立即生效
+pub(crate) async fn update_embedding_pool_options(
+    options: EmbeddingPoolOptions,
+) -> anyhow::Result<()> {
+    if options.batch_size == 0 {
+        bail!("批大小不能小于1");
+    }
+    if options.threads == 0 {
+        bail!("线程数量不能小于1");
+    }
+    save_config(EMBEDDING_POOL_OPTIONS_KEY, &options).await?;
+    Embeddings::set_pool_options(options);
+    Ok(())
+}
+
+/// 启动时加载嵌入线程池配置
+pub(crate) async fn load_embedding_pool_options() -> anyhow::Result<()> {
+    Embeddings::set_pool_options(get_embedding_pool_options().await?);
+    Ok(())
+}
+
+/// 嵌入缓存配置在system_config中的key
+const EMBEDDING_CACHE_OPTIONS_KEY: &str = "embedding_cache_options";
+
+/// 获取嵌入缓存配置，未配置时返回默认配置
+pub(crate) async fn get_embedding_cache_options() -> anyhow::Result<EmbeddingCacheOptions> {
+    get_config(EMBEDDING_CACHE_OPTIONS_KEY).await
+}
+
+/// 更新嵌入缓存配置，立即生效
+pub(crate) async fn update_embedding_cache_options(
+    options: EmbeddingCacheOptions,
+) -> anyhow::Result<()> {
+    save_config(EMBEDDING_CACHE_OPTIONS_KEY, &options).await?;
+    Embeddings::set_cache_options(options);
+    Ok(())
+}
+
+/// 启动时加载嵌入缓存配置
+pub(crate) async fn load_embedding_cache_options() -> anyhow::Result<()> {
+    Embeddings::set_cache_options(get_embedding_cache_options().await?);
+    Ok(())
+}
+
+/// 清空嵌入缓存
+pub(crate) async fn clear_embedding_cache() -> anyhow::Result<()> {
+    tokio::task::spawn_blocking(|| Embeddings::clear_cache().map_err(|e| e.to_string()))
+        .await?
+        .map_err(|e| anyhow!(e))
+}
+
+/// 订阅知识库的导入事件，实时接收导入记录的解析进度
+pub(crate) async fn subscribe_kb_import_events(
+    kb_id: i64,
+    channel: Channel<ImportEvent>,
+) -> anyhow::Result<()> {
+    parse::get_kb(kb_id).await?;
+    progress::subscribe(kb_id, channel);
+    Ok(())
+}
+
+/// 取消订阅知识库的导入事件
+pub(crate) async fn unsubscribe_kb_import_events(kb_id: i64) -> anyhow::Result<()> {
+    progress::unsubscribe(kb_id);
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! 监听文件夹
+//!
+//! 将本地文件夹绑定到知识库，定时扫描文件夹，按修改时间及内容哈希检测文件变化：
+//! 新增的文件导入；修改的文件删除原导入记录后重新导入；删除的文件删除对应的导入记录。
+//! 监听文件夹的导入记录与文件一一对应，不按内容哈希跳过与其他导入记录重复的文件。
+
+use crate::common::id;
+use crate::db::model::knowledge_base::KnowledgeBase;
+use crate::db::model::knowledge_base_import_record::KnowledgeBaseImportRecord;
+use crate::db::model::knowledge_base_watch_folder::{
+    KnowledgeBaseWatchFolder, KnowledgeBaseWatchFolderBuilder,
+};
+use crate::db::{tools, Pool};
+use crate::server::kb::parse::get_kb;
+use crate::server::kb::queue;
+use crate::server::kb::reindex;
+use crate::server::kb::request::KbAddFolderReq;
+use crate::server::kb::response::KbWatchFolderSyncRes;
+use crate::server::kb::service::{
+    build_globset, delete_kb_import_record, insert_file_records, is_pending, new_file_record,
+    remove_copied_files, scan_files,
+};
+use crate::utils::file_util;
+use anyhow::bail;
+use dashmap::DashSet;
+use rbs::{value, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// 正在同步的监听文件夹ID，避免定时同步与手动同步同时执行
+static SYNCING: LazyLock<DashSet<i64>> = LazyLock::new(DashSet::new);
+
+/// 添加监听文件夹，添加后立即在后台同步一次
+pub(crate) async fn add_watch_folder(req: KbAddFolderReq) -> anyhow::Result<()> {
+    log::info!("add_watch_folder: {:?}", req);
+    get_kb(req.kb_id).await?;
+    if !Path::new(&req.dir).is_dir() {
+        bail!("文件夹不存在：{}", req.dir);
+    }
+    // 提前校验匹配规则
+    build_globset(req.include.as_deref().unwrap_or_default())?;
+    build_globset(req.exclude.as_deref().unwrap_or_default())?;
+
+    let exists = KnowledgeBaseWatchFolder::select_by_map(
+        Pool::get()?,
+        value! {"knowledge_base_id": req.kb_id, "dir": &req.dir},
+    )
+    .await?;
+    if !exists.is_empty() {
+        bail!("文件夹已在监听中");
+    }
+
+    let folder = KnowledgeBaseWatchFolderBuilder::default()
+        .id(Some(id::next()))
+        .knowledge_base_id(Some(req.kb_id))
+        .dir(Some(req.dir))
+        .include(req.include)
+        .exclude(req.exclude)
+        .max_file_size(req.max_file_size)
+        .tags(req.tags)
+        .create_time(Some(tools::now()))
+        .build()?;
+    KnowledgeBaseWatchFolder::insert(Pool::get()?, &folder).await?;
+
+    tokio::spawn(async move {
+        if let Err(e) = sync_watch_folder(folder.id.unwrap()).await {
+            log::error!("[kb] Failed to sync watch folder, reason: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// 知识库的监听文件夹
+pub(crate) async fn list_watch_folders(
+    kb_id: i64,
+) -> anyhow::Result<Vec<KnowledgeBaseWatchFolder>> {
+    let list =
+        KnowledgeBaseWatchFolder::select_by_map(Pool::get()?, value! {"knowledge_base_id": kb_id})
+            .await?;
+    Ok(list)
+}
+
+/// 取消监听文件夹，已导入的记录保留，转为普通的文件导入记录
+pub(crate) async fn delete_watch_folder(id: i64) -> anyhow::Result<()> {
+    if SYNCING.contains(&id) {
+        bail!("文件夹正在同步，请稍后再试");
+    }
+    KnowledgeBaseWatchFolder::delete_by_map(Pool::get()?, value! {"id": id}).await?;
+    Pool::get()?
+        .exec(
+            "update knowledge_base_import_record set watch_folder_id = null where watch_folder_id = ?",
+            vec![Value::I64(id)],
+        )
+        .await?;
+    Ok(())
+}
+
+/// 同步全部监听文件夹，由定时任务调用
+pub(crate) async fn sync_all_watch_folders() {
+    if let Err(e) = sync_all().await {
+        log::error!("[kb] Failed to sync watch folders, reason: {}", e);
+    }
+}
+
+async fn sync_all() -> anyhow::Result<()> {
+    for folder in KnowledgeBaseWatchFolder::select_all(Pool::get()?).await? {
+        let id = folder.id.unwrap();
+        if let Err(e) = sync_watch_folder(id).await {
+            log::error!("[kb] Failed to sync watch folder {}, reason: {}", id, e);
+        }
+    }
+    Ok(())
+}
+
+/// 同步监听文件夹
+pub(crate) async fn sync_watch_folder(id: i64) -> anyhow::Result<KbWatchFolderSyncRes> {
+    if !SYNCING.insert(id) {
+        bail!("文件夹正在同步，请稍后再试");
+    }
+    let result = sync(id).await;
+    SYNCING.remove(&id);
+
+    // 记录同步结果
+    let folder = KnowledgeBaseWatchFolder::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    if let Some(mut folder) = folder.into_iter().next() {
+        folder.last_sync_time = Some(tools::now());
+        folder.last_sync_msg = Some(match &result {
+            Ok(res) => format!(
+                "新增{}个，修改{}个，删除{}个，跳过{}个",
+                res.added,
+                res.modified,
+                res.deleted,
+                res.skipped.len()
+            ),
+            Err(e) => e.to_string(),
+        });
+        KnowledgeBaseWatchFolder::update_by_map(Pool::get()?, &folder, value! {"id": id}).await?;
+    }
+    result
+}
+
+/// 文件夹中的文件与导入记录的对比结果
+#[derive(Default)]
+struct Changes {
+    /// 新增及修改的文件生成的导入记录
+    records: Vec<KnowledgeBaseImportRecord>,
+    /// 需要删除的导入记录，文件已修改或已删除
+    deleted: Vec<i64>,
+    /// 内容未变化、仅修改时间变化的导入记录
+    touched: Vec<KnowledgeBaseImportRecord>,
+    added: usize,
+    modified: usize,
+}
+
+async fn sync(id: i64) -> anyhow::Result<KbWatchFolderSyncRes> {
+    let folder = KnowledgeBaseWatchFolder::select_by_map(Pool::get()?, value! {"id": id}).await?;
+    let Some(folder) = folder.into_iter().next() else {
+        bail!("监听文件夹不存在");
+    };
+    let kb_id = folder.knowledge_base_id.unwrap();
+    if reindex::is_reindexing(kb_id) {
+        bail!("知识库正在重建索引，请稍后再试");
+    }
+    let kb = get_kb(kb_id).await?;
+    let dir = folder.dir.clone().unwrap_or_default();
+    // 文件夹不可访问时（如移动硬盘未连接）不做处理，避免误删导入记录
+    if !Path::new(&dir).is_dir() {
+        bail!("文件夹不存在：{}", dir);
+    }
+
+    let records =
+        KnowledgeBaseImportRecord::select_by_map(Pool::get()?, value! {"watch_folder_id": id})
+            .await?;
+    let (changes, skipped) = tokio::task::spawn_blocking(move || {
+        let (files, skipped) = scan_files(
+            &dir,
+            folder.include.as_deref().unwrap_or_default(),
+            folder.exclude.as_deref().unwrap_or_default(),
+            folder.max_file_size,
+        )?;
+        let changes = diff(&kb, &folder, files, records)?;
+        Ok::<_, anyhow::Error>((changes, skipped))
+    })
+    .await??;
+
+    if let Err(e) = apply_changes(&changes).await {
+        remove_copied_files(&changes.records);
+        return Err(e);
+    }
+    insert_file_records(&changes.records).await?;
+    let res = KbWatchFolderSyncRes {
+        added: changes.added,
+        modified: changes.modified,
+        deleted: changes.deleted.len() - changes.modified,
+        skipped,
+    };
+    queue::notify();
+    Ok(res)
+}
+
+/// 删除已删除或已修改的文件的导入记录，更新未修改的文件的修改时间
+async fn apply_changes(changes: &Changes) -> anyhow::Result<()> {
+    for record_id in &changes.deleted {
+        delete_kb_import_record(*record_id).await?;
+    }
+    for record in &changes.touched {
+        KnowledgeBaseImportRecord::update_by_map(Pool::get()?, record, value! {"id": record.id})
+            .await?;
+    }
+    Ok(())
+}
+
+/// 对比扫描到的文件与已有的导入记录
+///
+/// 修改时间及文件大小均未变化的文件视为未修改，否则比较内容哈希。
+/// 待解析及正在导入的记录本次不处理，留到下次同步。
+/// 对比失败时删除已复制到数据目录的文件
+fn diff(
+    kb: &KnowledgeBase,
+    folder: &KnowledgeBaseWatchFolder,
+    files: Vec<PathBuf>,
+    records: Vec<KnowledgeBaseImportRecord>,
+) -> anyhow::Result<Changes> {
+    let mut changes = Changes::default();
+    if let Err(e) = diff_files(kb, folder, files, records, &mut changes) {
+        remove_copied_files(&changes.records);
+        return Err(e);
+    }
+    Ok(changes)
+}
+
+fn diff_files(
+    kb: &KnowledgeBase,
+    folder: &KnowledgeBaseWatchFolder,
+    files: Vec<PathBuf>,
+    records: Vec<KnowledgeBaseImportRecord>,
+    changes: &mut Changes,
+) -> anyhow::Result<()> {
+    let mut records = records
+        .into_iter()
+        .filter_map(|record| Some((record.original_file_path.clone()?, record)))
+        .collect::<HashMap<_, _>>();
+    for file in files {
+        let path = file.to_string_lossy().to_string();
+        let Some(mut record) = records.remove(&path) else {
+            let file_hash = file_util::file_hash(&file)?;
+            let mut record = new_file_record(kb, &file, file_hash, &folder.tags)?;
+            record.watch_folder_id = folder.id;
+            changes.records.push(record);
+            changes.added += 1;
+            continue;
+        };
+        if is_pending(&record) {
+            continue;
+        }
+        let mtime = file_util::file_mtime(&file)?;
+        let size = file.metadata()?.len();
+        if record.file_mtime == Some(mtime) && record.file_size == Some(size) {
+            continue;
+        }
+        let file_hash = file_util::file_hash(&file)?;
+        if record.file_hash.as_deref() == Some(file_hash.as_str()) {
+            record.file_mtime = Some(mtime);
+            changes.touched.push(record);
+            continue;
+        }
+        let mut new_record = new_file_record(kb, &file, file_hash, &folder.tags)?;
+        new_record.watch_folder_id = folder.id;
+        changes.records.push(new_record);
+        changes.deleted.push(record.id.unwrap());
+        changes.modified += 1;
+    }
+    // 剩余的记录对应的文件已删除或不再匹配
+    changes.deleted.extend(
+        records
+            .into_values()
+            .filter(|record| !is_pending(record))
+            .filter_map(|record| record.id),
+    );
+    Ok(())
+}
@@ -7,7 +7,7 @@ use crate::server::kb;
 use crate::server::mcp::default;
 use crate::{constant, server};
 use anyhow::bail;
-use engine::TableEngine;
+use engine::{MetadataFilter, TableEngine};
 use openai_dive::v1::resources::chat::{
     ChatCompletionFunction, ChatCompletionTool, ChatCompletionToolType,
 };
@@ -15,10 +15,67 @@ use rbs::value;
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
+/// 查询表结构时每张表返回的示例数据行数
+const DESCRIBE_SAMPLE_ROWS: usize = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct KbDocSearchReq {
     knowledge_base_db_names: String,
     search_text: String,
+    /// 标签，多个用英文逗号分隔，需全部匹配
+    tags: Option<String>,
+    /// 导入时间起始日期，格式yyyy-MM-dd
+    imported_after: Option<String>,
+    /// 导入时间截止日期，格式yyyy-MM-dd
+    imported_before: Option<String>,
+}
+
+impl KbDocSearchReq {
+    /// 根据工具参数构建元数据过滤条件
+    fn filter(&self) -> anyhow::Result<Option<MetadataFilter>> {
+        let mut filters = vec![];
+        if let Some(tags) = &self.tags {
+            for tag in tags.split(",").map(|tag| tag.trim()) {
+                if !tag.is_empty() {
+                    filters.push(MetadataFilter::Tag {
+                        tag: tag.to_string(),
+                    });
+                }
+            }
+        }
+        let from = parse_date(self.imported_after.as_deref(), false)?;
+        let to = parse_date(self.imported_before.as_deref(), true)?;
+        if from.is_some() || to.is_some() {
+            filters.push(MetadataFilter::ImportTime { from, to });
+        }
+
+        Ok(match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(MetadataFilter::And { filters }),
+        })
+    }
+}
+
+/// 解析yyyy-MM-dd格式的日期为本地时间的毫秒时间戳，end_of_day为true时取当天最后一毫秒
+fn parse_date(date: Option<&str>, end_of_day: bool) -> anyhow::Result<Option<i64>> {
+    let date = match date.map(|date| date.trim()) {
+        Some(date) if !date.is_empty() => date,
+        _ => return Ok(None),
+    };
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("日期格式错误：{}", date))?;
+    let time = if end_of_day {
+        date.and_hms_milli_opt(23, 59, 59, 999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+    .unwrap();
+    let time = time
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.timestamp_millis());
+    Ok(time)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,7 +93,8 @@ pub async fn kb_doc_search(parameters: &str) -> anyhow::Result<String> {
     let parameters = serde_json::from_str::<KbDocSearchReq>(parameters)?;
     let knowledge_base_db_names = parameters.knowledge_base_db_names;
     let table_names = knowledge_base_db_names.split(",").collect::<Vec<&str>>();
-    let search_text = parameters.search_text;
+    let search_text = parameters.search_text.clone();
+    let filter = parameters.filter()?;
 
     log::info!(
         "Knowledge base search: database name: {}, search text: {}",
@@ -49,7 +107,7 @@ pub async fn kb_doc_search(parameters: &str) -> anyhow::Result<String> {
 
     let mut results = vec![];
     for kb in kbs {
-        if let Some(knowledge_base_content) = kb::search(&kb, &search_text).await {
+        if let Some(knowledge_base_content) = kb::search(&kb, &search_text, filter.clone()).await {
             results.push(format!(
                 "数据库编号为【{}】的查询结果：{}",
                 kb.table_name.unwrap(),
@@ -75,13 +133,23 @@ pub async fn kb_data_search(parameters: &str) -> anyhow::Result<String> {
             query_sql
         );
 
-        let rows = TableEngine::query(db_name, &query_sql).await?;
+        let result = match TableEngine::query(db_name, &query_sql).await {
+            Ok(result) => result,
+            Err(e) => {
+                results.push(format!("数据库编号为【{}】的查询失败：{}", db_name, e));
+                continue;
+            }
+        };
 
-        let result = rows
-            .iter()
-            .map(|row| row.join("\t"))
-            .collect::<Vec<_>>()
-            .join("\n");
+        // 以JSON返回，保留数字类型，便于模型区分数字与文本
+        let mut result_json = serde_json::to_string(&result)?;
+        if result.truncated {
+            result_json.push_str(&format!(
+                "\n（结果过多，仅返回前{}行，请使用更精确的查询条件或聚合查询）",
+                result.rows.len()
+            ));
+        }
+        let result = result_json;
 
         results.push(format!("数据库编号为【{}】的查询结果：{}", db_name, result));
     }
@@ -90,6 +158,35 @@ pub async fn kb_data_search(parameters: &str) -> anyhow::Result<String> {
     Ok(results.join("\n\n"))
 }
 
+/// 查询表格型知识库中所有表的列及示例数据
+pub async fn kb_data_describe(parameters: &str) -> anyhow::Result<String> {
+    let parameters = serde_json::from_str::<KbListItemReq>(parameters)?;
+    let db_name = parameters.knowledge_base_db_name;
+    let tables = TableEngine::describe(&db_name, DESCRIBE_SAMPLE_ROWS).await?;
+
+    let mut results = vec![];
+    for table in tables {
+        let mut lines = vec![];
+        lines.push(format!("表名：\"{}\"，共{}行", table.name, table.row_count));
+        lines.push(format!(
+            "列：{}",
+            table
+                .columns
+                .iter()
+                .map(|c| format!("\"{}\" {}", c.name, c.data_type))
+                .collect::<Vec<_>>()
+                .join("，")
+        ));
+        lines.push("示例数据：".to_string());
+        for row in &table.sample_rows {
+            lines.push(serde_json::to_string(row)?);
+        }
+        results.push(lines.join("\n"));
+    }
+
+    Ok(results.join("\n\n"))
+}
+
 pub(crate) async fn list_kb_items(parameters: &str) -> anyhow::Result<String> {
     let parameters = serde_json::from_str::<KbListItemReq>(parameters)?;
     let table_name = parameters.knowledge_base_db_name;
@@ -105,7 +202,19 @@ pub(crate) async fn list_kb_items(parameters: &str) -> anyhow::Result<String> {
     .await?;
     let mut results = vec![];
     for item in list {
-        results.push(item.nld.unwrap_or_default());
+        match item.table_names {
+            // 表格文件的每个工作表对应一张数据表，逐一列出便于跨表查询
+            Some(table_names) if !table_names.is_empty() => {
+                for table_name in table_names {
+                    results.push(format!(
+                        "数据表：\"{}\"，来源文件：{}",
+                        table_name,
+                        item.title.clone().unwrap_or_default()
+                    ));
+                }
+            }
+            _ => results.push(item.nld.unwrap_or_default()),
+        }
     }
     Ok(results.join("\n"))
 }
@@ -117,6 +226,7 @@ impl KbMcp {
             // 知识库的文档检索
             default::KB_DOC_SEARCH_TOOL => kb_doc_search(parameters).await,
             default::KB_TABLE_SEARCH_TOOL => kb_data_search(parameters).await,
+            default::KB_TABLE_DESCRIBE_TOOL => kb_data_describe(parameters).await,
             default::KB_LIST_ITEM_TOOL => list_kb_items(parameters).await,
             _ => {
                 bail!("未知的工具名称：{}", tool_name);
@@ -146,6 +256,18 @@ pub fn default_tools() -> Vec<ChatCompletionTool> {
                         "search_text": {
                             "type": "string",
                             "description": "要检索的关键字文本"
+                        },
+                        "tags": {
+                            "type": "string",
+                            "description": "仅检索包含这些标签的内容，多个用英文逗号分隔，可选"
+                        },
+                        "imported_after": {
+                            "type": "string",
+                            "description": "仅检索在该日期及之后导入的内容，格式yyyy-MM-dd，可选"
+                        },
+                        "imported_before": {
+                            "type": "string",
+                            "description": "仅检索在该日期及之前导入的内容，格式yyyy-MM-dd，可选"
                         }
                     },
                     "required":["knowledge_base_db_names","search_text"]
@@ -169,13 +291,33 @@ pub fn default_tools() -> Vec<ChatCompletionTool> {
                         },
                         "query_sql": {
                             "type": "string",
-                            "description": "符合sqlite规范的完整的且正确的数据查询sql，所有表名、字段名需要用双引号包裹，所有结果列需要转为字符串类型"
+                            "description": "符合sqlite规范的完整的且正确的单条SELECT查询sql，所有表名、字段名需要用双引号包裹，INTEGER和REAL类型的列可直接计算和排序，DATE类型的列格式为YYYY-MM-DD，不确定表结构时先查询表结构"
                         }
                     },
                     "required":["knowledge_base_db_names","query_sql"]
                 }),
             },
         },
+        ChatCompletionTool {
+            r#type: ChatCompletionToolType::Function,
+            function: ChatCompletionFunction {
+                name: default::KB_TABLE_DESCRIBE_TOOL.to_string(),
+                description: Some(
+                    "查询【表格】类型知识库中所有表的表名、列名、列类型及示例数据，编写查询sql前使用该工具"
+                        .to_string(),
+                ),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "knowledge_base_db_name":{
+                            "type": "string",
+                            "description": "知识库的数据库编码"
+                        },
+                    },
+                    "required":["knowledge_base_db_name"]
+                }),
+            },
+        },
         ChatCompletionTool {
             r#type: ChatCompletionToolType::Function,
             function: ChatCompletionFunction {
@@ -5,6 +5,8 @@ use openai_dive::v1::resources::chat::ChatCompletionTool;
 pub const KB_DOC_SEARCH_TOOL: &str = concat!("inner", "A-_-A", "kb_doc_search");
 /// 内置知识库表格搜索工具名称
 pub const KB_TABLE_SEARCH_TOOL: &str = concat!("inner", "A-_-A", "kb_table_search");
+/// 内置知识库表格结构查询工具名称
+pub const KB_TABLE_DESCRIBE_TOOL: &str = concat!("inner", "A-_-A", "kb_table_describe");
 /// 查询知识库项
 pub const KB_LIST_ITEM_TOOL: &str = concat!("inner", "A-_-A", "kb_list_item");
 /// 内置markdown转其他文件格式工具名称
@@ -20,13 +22,15 @@ impl DefaultMcpServer {
     pub(crate) fn is_default_mcp(tool_name: &str) -> bool {
         tool_name == KB_DOC_SEARCH_TOOL
             || tool_name == KB_TABLE_SEARCH_TOOL
+            || tool_name == KB_TABLE_DESCRIBE_TOOL
             || tool_name == KB_LIST_ITEM_TOOL
     }
     pub(crate) fn new(tool_name: &str) -> anyhow::Result<Self> {
         match tool_name {
-            KB_DOC_SEARCH_TOOL | KB_TABLE_SEARCH_TOOL | KB_LIST_ITEM_TOOL => {
-                Ok(DefaultMcpServer::KbMcp(KbMcp))
-            }
+            KB_DOC_SEARCH_TOOL
+            | KB_TABLE_SEARCH_TOOL
+            | KB_TABLE_DESCRIBE_TOOL
+            | KB_LIST_ITEM_TOOL => Ok(DefaultMcpServer::KbMcp(KbMcp)),
             _ => {
                 anyhow::bail!("未知的工具名称：{}", tool_name);
             }
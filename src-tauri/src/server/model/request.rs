@@ -23,7 +23,7 @@ pub struct ModelAddReq {
     pub base_url: String,
     pub api_key: Option<String>,
     pub max_token: Option<i32>,
-    /// 适用的任务类型：1文本生成 2视觉问答
+    /// 适用的任务类型：1文本生成 2视觉问答 3文本嵌入
     pub task_type: i8,
 }
 
@@ -39,6 +39,6 @@ pub struct ModelUpdateReq {
     pub api_key: Option<String>,
     pub max_token: Option<i32>,
     pub status: Option<i8>,
-    /// 适用的任务类型：1文本生成 2视觉问答
+    /// 适用的任务类型：1文本生成 2视觉问答 3文本嵌入
     pub task_type: Option<i8>,
 }
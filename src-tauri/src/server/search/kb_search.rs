@@ -1,29 +1,26 @@
-use crate::constant;
-use crate::db::model::knowledge_base::KnowledgeBase;
+use crate::db::model::knowledge_base::{image_table_name, KnowledgeBase};
 use crate::db::model::knowledge_base_import_record::{
-    KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder,
+    batch_record_id, KnowledgeBaseImportRecord, KnowledgeBaseImportRecordBuilder,
 };
 use crate::db::Pool;
-use crate::server::search::request::SearchReq;
+use crate::server::kb::{embedder, reranker};
+use crate::server::search::request::{KbSearchMode, SearchReq};
 use crate::server::search::response::{KbSearchItemRes, KbSearchRes, SearchRes};
-use embedding::{Embedding, EmbeddingInput, Embeddings};
+use crate::utils::file_util;
+use embedding::TextEmbeddingModel;
 use engine::{Engine, SearchRequestBuilder};
 use rbs::value;
 use std::collections::HashMap;
 
+/// 以文搜图返回的图片数量上限
+const IMAGE_SEARCH_LIMIT: usize = 10;
+
 pub(crate) async fn search(req: &SearchReq) -> anyhow::Result<KbSearchRes> {
     let kw = req.kw.as_str();
     if kw.is_empty() {
         return Ok(KbSearchRes::default());
     }
 
-    let vector = Embeddings::embedding(EmbeddingInput::Text(format!(
-        "{}{}",
-        constant::TEXT_SEARCH_INSTRUCTION,
-        kw
-    )))
-    .unwrap();
-
     // 获取所有知识库表名
     let kbs = KnowledgeBase::select_all(Pool::get()?).await?;
     let table_names = kbs
@@ -45,18 +42,60 @@ pub(crate) async fn search(req: &SearchReq) -> anyhow::Result<KbSearchRes> {
         return Ok(KbSearchRes::default());
     }
 
-    let mut items = Vec::new();
+    // 各知识库的检索结果
+    let mut lists = Vec::new();
+
+    // 各知识库可能使用不同的嵌入模型，检索向量按模型缓存
+    let mut vectors: HashMap<String, Vec<f32>> = HashMap::new();
 
     for table_name in table_names.iter() {
-        let list = Engine::search_data(
+        let kb = kb_map.get(table_name).unwrap();
+        // 单个知识库的模型不可用或检索失败时跳过该知识库，不影响其他知识库的结果
+        let model = match embedder::resolve_model(&kb.get_embedding_config().model).await {
+            Ok(model) => model,
+            Err(e) => {
+                log::error!(
+                    "[kb] Failed to resolve embedding model of {}, reason: {}",
+                    table_name,
+                    e
+                );
+                continue;
+            }
+        };
+        let vector = match vectors.get(&model.id()) {
+            Some(vector) => vector.clone(),
+            None => match embedder::embed_query_with_model(&model, kw).await {
+                Ok(vector) => {
+                    vectors.insert(model.id(), vector.clone());
+                    vector
+                }
+                Err(e) => {
+                    log::error!(
+                        "[kb] Failed to embed query for {}, reason: {}",
+                        table_name,
+                        e
+                    );
+                    continue;
+                }
+            },
+        };
+        let list = match Engine::search_data(
             SearchRequestBuilder::default()
                 .table_name(table_name.clone())
-                .vector(Some(vector.clone()))
+                .vector(Some(vector))
+                .filter(req.filter.clone())
                 .limit(Some(20))
                 .build()
                 .unwrap(),
         )
-        .await?;
+        .await
+        {
+            Ok(list) => list,
+            Err(e) => {
+                log::error!("[kb] Failed to search {}, reason: {}", table_name, e);
+                continue;
+            }
+        };
         let list = list
             .into_iter()
             .map(|item| KbSearchItemRes {
@@ -64,19 +103,46 @@ pub(crate) async fn search(req: &SearchReq) -> anyhow::Result<KbSearchRes> {
                 score: item.score,
                 ref_kb: kb_map.get(table_name).unwrap().clone(),
                 ref_import_record: KnowledgeBaseImportRecordBuilder::default()
-                    .id(Some(item.batch_id.parse::<i64>().unwrap()))
+                    .id(batch_record_id(&item.batch_id))
                     .build()
                     .unwrap(),
+                image_path: None,
+                rerank_score: None,
             })
             .collect::<Vec<_>>();
-        items.extend(list);
+        let mut list = if kb.get_config().is_rerank {
+            rerank(kb, kw, list).await
+        } else {
+            list
+        };
+        // 同一知识库的结果分数可比较，重排过的结果使用重排分数
+        list.sort_by(|a, b| {
+            let score = |item: &KbSearchItemRes| item.rerank_score.or(item.score);
+            score(b).partial_cmp(&score(a)).unwrap()
+        });
+        lists.push(list);
     }
+    // 不同知识库的分数（向量相似度或不同重排模型的分数）不可比较，按排名交替合并
+    let mut items = interleave(lists);
+
+    let mut images = match req.mode {
+        KbSearchMode::Text => vec![],
+        // 以文搜图失败时（如CLIP模型不可用）只返回文本结果
+        KbSearchMode::Mixed => search_images(req, &kbs).await.unwrap_or_else(|e| {
+            log::error!("[kb] Failed to search images, reason: {}", e);
+            vec![]
+        }),
+    };
 
     // 查询出处
     let references = KnowledgeBaseImportRecord::select_by_map(
         Pool::get()?,
         value! {
-            "id": &items.iter().map(|item| &item.ref_import_record.id).collect::<Vec<_>>(),
+            "id": &items
+                .iter()
+                .chain(&images)
+                .map(|item| &item.ref_import_record.id)
+                .collect::<Vec<_>>(),
         },
     )
     .await?
@@ -84,19 +150,99 @@ pub(crate) async fn search(req: &SearchReq) -> anyhow::Result<KbSearchRes> {
     .map(|item| (item.id.unwrap(), item))
     .collect::<HashMap<i64, KnowledgeBaseImportRecord>>();
 
-    let mut items = items
-        .into_iter()
-        .map(|mut item| {
-            item.ref_import_record = references
-                .get(&item.ref_import_record.id.unwrap())
-                .unwrap()
-                .clone();
-            item
-        })
-        .collect::<Vec<_>>();
+    for item in items.iter_mut().chain(images.iter_mut()) {
+        item.ref_import_record = references
+            .get(&item.ref_import_record.id.unwrap())
+            .unwrap()
+            .clone();
+    }
+
+    images.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    images.truncate(IMAGE_SEARCH_LIMIT);
+
+    Ok(KbSearchRes { items, images })
+}
 
-    // 排序
-    items.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+/// 使用知识库的重排模型对检索结果重排，丢弃低于最低分数的结果，重排失败时保持原样
+async fn rerank(kb: &KnowledgeBase, kw: &str, list: Vec<KbSearchItemRes>) -> Vec<KbSearchItemRes> {
+    let contents = list.iter().map(|item| item.content.clone()).collect();
+    match reranker::rerank(&kb.get_config(), kw, contents).await {
+        Ok(scores) => scores
+            .into_iter()
+            .map(|score| KbSearchItemRes {
+                rerank_score: Some(score.score),
+                ..list[score.index].clone()
+            })
+            .collect(),
+        Err(e) => {
+            log::error!("Rerank error: {}", e);
+            list
+        }
+    }
+}
+
+/// 以文搜图，使用CLIP文本模型将检索文本转换为向量后检索各知识库的图片表
+async fn search_images(
+    req: &SearchReq,
+    kbs: &[KnowledgeBase],
+) -> anyhow::Result<Vec<KbSearchItemRes>> {
+    let vector = embedder::embed(&TextEmbeddingModel::text_to_image(), vec![req.kw.clone()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    let mut images = vec![];
+    for kb in kbs {
+        let Some(table_name) = &kb.table_name else {
+            continue;
+        };
+        let image_table_name = image_table_name(table_name);
+        if !Engine::has_table(&image_table_name).await? {
+            continue;
+        }
+        let list = Engine::search_data(
+            SearchRequestBuilder::default()
+                .table_name(image_table_name)
+                .vector(Some(vector.clone()))
+                .filter(req.filter.clone())
+                .limit(Some(IMAGE_SEARCH_LIMIT))
+                .build()?,
+        )
+        .await?;
+        images.extend(list.into_iter().map(|item| {
+            KbSearchItemRes {
+                image_path: file_util::kb_ref_file_path(table_name, &item.content),
+                content: item.content,
+                score: item.score,
+                ref_kb: kb.clone(),
+                ref_import_record: KnowledgeBaseImportRecordBuilder::default()
+                    .id(batch_record_id(&item.batch_id))
+                    .build()
+                    .unwrap(),
+                rerank_score: None,
+            }
+        }));
+    }
+    Ok(images)
+}
+
+/// 按排名交替合并多个已排序的列表：先取各列表的第一个，再取各列表的第二个，依此类推
+fn interleave<T>(lists: Vec<Vec<T>>) -> Vec<T> {
+    let mut iters = lists.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+    let mut items = Vec::new();
+    loop {
+        let len = items.len();
+        items.extend(iters.iter_mut().filter_map(Iterator::next));
+        if items.len() == len {
+            return items;
+        }
+    }
+}
 
-    Ok(KbSearchRes { items })
+#[test]
+fn test_interleave() {
+    assert_eq!(
+        interleave(vec![vec![1, 2, 3], vec![], vec![4], vec![5, 6]]),
+        vec![1, 4, 5, 2, 6, 3]
+    );
+    assert_eq!(interleave::<i32>(vec![]), Vec::<i32>::new());
 }
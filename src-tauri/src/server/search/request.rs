@@ -1,4 +1,5 @@
 use derive_builder::Builder;
+use engine::MetadataFilter;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Builder, Default)]
@@ -7,4 +8,20 @@ use serde::{Deserialize, Serialize};
 pub(crate) struct SearchReq {
     // 搜索关键字
     pub(crate) kw: String,
+    /// 知识库检索的元数据过滤条件
+    pub(crate) filter: Option<MetadataFilter>,
+    /// 知识库检索方式
+    #[serde(default)]
+    pub(crate) mode: KbSearchMode,
+}
+
+/// 知识库检索方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum KbSearchMode {
+    /// 仅检索文本分段
+    #[default]
+    Text,
+    /// 检索文本分段，同时以文搜图，返回与检索文本匹配的图片
+    Mixed,
 }
@@ -20,6 +20,8 @@ pub(crate) struct SearchRes {
 #[builder(default)]
 pub(crate) struct KbSearchRes {
     pub(crate) items: Vec<KbSearchItemRes>,
+    /// 以文搜图匹配的图片，仅混合检索时返回
+    pub(crate) images: Vec<KbSearchItemRes>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
 #[serde(rename_all = "camelCase")]
@@ -29,6 +31,10 @@ pub(crate) struct KbSearchItemRes {
     pub(crate) score: Option<f32>,
     pub(crate) ref_kb: KnowledgeBase,
     pub(crate) ref_import_record: KnowledgeBaseImportRecord,
+    /// 图片的本地路径，仅图片检索结果有值
+    pub(crate) image_path: Option<String>,
+    /// 重排分数，知识库开启重排时有值
+    pub(crate) rerank_score: Option<f32>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
 #[serde(rename_all = "camelCase")]
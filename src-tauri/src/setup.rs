@@ -197,6 +197,7 @@ async fn init() -> anyhow::Result<()> {
     db::init().await;
     // 初始化向量库
     engine::init().await;
+    server::kb::init().await;
     // 初始化嵌入模型
     embedding::init().await;
     // 初始化doc-to-pdf
@@ -205,6 +206,8 @@ async fn init() -> anyhow::Result<()> {
     mcp::init();
     // 启动定时任务
     task::start().await?;
+    // 启动导入队列
+    server::kb::start_import_queue().await;
 
     // 可以异步执行的初始化
     tauri::async_runtime::spawn(async move {
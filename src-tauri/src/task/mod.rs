@@ -9,6 +9,12 @@ pub async fn start() -> anyhow::Result<()> {
     })?;
     sched.add(print_pool_status).await?;
 
+    // 同步知识库监听的文件夹
+    let sync_watch_folders = Job::new_async("every 5 minutes", |_, _| {
+        Box::pin(server::kb::sync_all_watch_folders())
+    })?;
+    sched.add(sync_watch_folders).await?;
+
     sched.start().await?;
 
     Ok(())
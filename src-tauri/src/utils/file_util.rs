@@ -4,7 +4,10 @@ use crate::utils::file_util;
 use anyhow::bail;
 use common::{app_dir, data_dir, file_dir};
 use nanoid::nanoid;
+use sha2::{Digest, Sha256};
+use std::fs::File;
 use std::path::Path;
+use std::time::UNIX_EPOCH;
 
 /// 生成保存文件名
 ///
@@ -66,6 +69,19 @@ pub(crate) fn generate_unique_file_path(dir: &str, file_name: &str) -> String {
     }
 }
 
+/// 计算文件内容的SHA-256，返回十六进制字符串
+pub(crate) fn file_hash(path: impl AsRef<Path>) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 文件的修改时间，毫秒时间戳
+pub(crate) fn file_mtime(path: impl AsRef<Path>) -> anyhow::Result<i64> {
+    let modified = path.as_ref().metadata()?.modified()?;
+    Ok(modified.duration_since(UNIX_EPOCH)?.as_millis() as i64)
+}
+
 pub fn make_download_file(file_name: &str) -> String {
     format!("/file/download/{}", file_name)
 }
@@ -96,6 +112,43 @@ pub fn make_kb_ref_file(
     let download_file = format!("/file/kb/ref/{}", save_file_name);
     Ok((save_file_name, save_file, download_file))
 }
+/// 由引用文件的下载地址得到文件路径，下载地址不属于该向量表时返回None
+pub fn kb_ref_file_path(table_name: &str, download_file: &str) -> Option<String> {
+    let save_file_name = download_file.strip_prefix("/file/kb/ref/")?;
+    let uni_dir = save_file_name
+        .strip_prefix(&format!("{}-", table_name))?
+        .get(..10)?;
+    let path = data_dir!(
+        "database",
+        format!("{}.lance", table_name),
+        "refs",
+        uni_dir,
+        save_file_name
+    );
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// 将引用文件复制到另一个向量表的引用目录，返回新的文件路径及下载地址
+pub fn copy_kb_ref_file(
+    from_table_name: &str,
+    to_table_name: &str,
+    kb_import_record_id: i64,
+    download_file: &str,
+) -> anyhow::Result<(String, String)> {
+    let Some(path) = kb_ref_file_path(from_table_name, download_file) else {
+        bail!("引用文件不属于该知识库：{}", download_file);
+    };
+    // 保存的文件名为`表名-目录_原文件名`，目录固定为10位
+    let file_name = download_file
+        .strip_prefix(&format!("/file/kb/ref/{}-", from_table_name))
+        .and_then(|name| name.get(11..))
+        .unwrap_or_default();
+    let (_, save_file, download_file) =
+        make_kb_ref_file(to_table_name, kb_import_record_id, file_name)?;
+    std::fs::copy(path, &save_file)?;
+    Ok((save_file, download_file))
+}
+
 fn make_kb_ref_uni_dir(kb_import_record_id: i64) -> String {
     let uni_dir = kb_import_record_id.to_string();
     // uni_dir 固定为10位，不足的前补零，超过保留后10位。
@@ -128,3 +181,26 @@ pub async fn upload(path: &str) -> anyhow::Result<String> {
 
     Ok(res.data.unwrap_or_default())
 }
+
+#[test]
+fn test_kb_ref_file_path() {
+    let download_file = "/file/kb/ref/kb_1-0000000012_a.png";
+    let path = data_dir!(
+        "database",
+        "kb_1.lance",
+        "refs",
+        "0000000012",
+        "kb_1-0000000012_a.png"
+    );
+    assert_eq!(
+        kb_ref_file_path("kb_1", download_file),
+        Some(path.to_string_lossy().into_owned())
+    );
+    // 不属于该向量表的下载地址
+    assert_eq!(kb_ref_file_path("kb_2", download_file), None);
+    assert_eq!(
+        kb_ref_file_path("kb_1", "/file/download/kb_1-0000000012_a.png"),
+        None
+    );
+    assert_eq!(kb_ref_file_path("kb_1", "/file/kb/ref/kb_1-12"), None);
+}
@@ -100,12 +100,22 @@ export default {
     '解析中': 'Parsing',
     '成功': 'Success',
     '失败': 'Failed',
+    '已取消': 'Cancelled',
+    '重新导入': 'Re-import',
+    '读取文件': 'Reading file',
+    '渲染页面': 'Rendering pages',
+    '识别文字': 'Recognizing text',
+    '生成向量': 'Embedding',
+    '写入数据': 'Writing',
+    '失败阶段': 'Failed stage',
     '删除': 'Delete',
     '确认从知识库中删除该文件': 'Are you sure you want to delete this file from the knowledge base?',
     '输入关键词搜索知识库/本地文件/网页': 'Search for knowledge bases/local files/web pages using keywords',
     '知识库': 'Knowledge Base',
     '本地文件': 'Local File',
     '匹配度': 'Score',
+    '相关图片': 'Related images',
+    '重排分数': 'Rerank score',
     '打开': 'Open',
     '位置': 'Location',
     '添加': 'Add',
@@ -100,12 +100,22 @@ export default {
     '解析中': '解析中',
     '成功': '成功',
     '失败': '失败',
+    '已取消': '已取消',
+    '重新导入': '重新导入',
+    '读取文件': '读取文件',
+    '渲染页面': '渲染页面',
+    '识别文字': '识别文字',
+    '生成向量': '生成向量',
+    '写入数据': '写入数据',
+    '失败阶段': '失败阶段',
     '删除': '删除',
     '确认从知识库中删除该文件': '确认从知识库中删除该文件？',
     '输入关键词搜索知识库/本地文件/网页': '输入关键词搜索知识库/本地文件/网页',
     '知识库': '知识库',
     '本地文件': '本地文件',
     '匹配度': '匹配度',
+    '相关图片': '相关图片',
+    '重排分数': '重排分数',
     '打开': '打开',
     '位置': '位置',
     '添加': '添加',